@@ -0,0 +1,60 @@
+//! Compares `common_base` against the full `diff_and_common_with_options` extraction.
+//!
+//! Run with `cargo bench --bench common_base`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use yabe::diff::{common_base, diff_and_common_with_options};
+use yabe::options::DiffOptions;
+use yaml_rust2::yaml::{Hash, Yaml};
+
+const FILES: usize = 40;
+const ITERATIONS: u32 = 20;
+
+/// Builds a nested document where most leaves are shared and some differ per file.
+fn build_doc(file: usize, depth: usize, width: usize) -> Yaml {
+    let mut hash = Hash::new();
+    for key in 0..width {
+        let value = if depth == 0 {
+            if key % 4 == 0 {
+                Yaml::Integer((file * 100 + key) as i64)
+            } else {
+                Yaml::String(format!("shared-{}", key))
+            }
+        } else {
+            build_doc(file, depth - 1, width)
+        };
+        hash.insert(Yaml::String(format!("key{}", key)), value);
+    }
+    Yaml::Hash(hash)
+}
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let docs: Vec<Yaml> = (0..FILES).map(|file| build_doc(file, 3, 8)).collect();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+    let options = DiffOptions::default();
+
+    let full = time(|| {
+        black_box(diff_and_common_with_options(black_box(&objs), &options));
+    });
+    let base_only = time(|| {
+        black_box(common_base(black_box(&objs), &options));
+    });
+
+    println!("files: {}, leaves per file: {}", FILES, 8usize.pow(4));
+    println!("diff_and_common_with_options: {:?}", full);
+    println!("common_base:                  {:?}", base_only);
+    println!(
+        "speedup:                      {:.2}x",
+        full.as_secs_f64() / base_only.as_secs_f64()
+    );
+}
@@ -0,0 +1,100 @@
+//! Compares `compute_diff_with_options` with and without `fingerprint_subtrees`.
+//!
+//! Run with `cargo bench --bench fingerprint_subtrees`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use yabe::diff::compute_diff_with_options;
+use yabe::options::DiffOptions;
+use yaml_rust2::yaml::{Hash, Yaml};
+
+const ITERATIONS: u32 = 20;
+
+/// Builds a nested map of shared leaves.
+fn shared_section(depth: usize, width: usize) -> Yaml {
+    let mut hash = Hash::new();
+    for key in 0..width {
+        let value = if depth == 0 {
+            Yaml::String(format!("shared-value-{}", key))
+        } else {
+            shared_section(depth - 1, width)
+        };
+        hash.insert(Yaml::String(format!("key{}", key)), value);
+    }
+    Yaml::Hash(hash)
+}
+
+/// Builds a document of shared sections at every level, differing per environment in one leaf
+/// at the bottom: the case fingerprints are for.
+fn nested_doc(env: usize, levels: usize) -> Yaml {
+    let mut hash = Hash::new();
+    for key in 0..4 {
+        hash.insert(Yaml::String(format!("section{}", key)), shared_section(2, 6));
+    }
+    let nested = if levels == 0 {
+        Yaml::String(format!("env-{}", env))
+    } else {
+        nested_doc(env, levels - 1)
+    };
+    hash.insert(Yaml::String("nested".to_string()), nested);
+    Yaml::Hash(hash)
+}
+
+/// Builds a nested document differing per environment in one leaf of every map at the bottom
+/// level: the case against fingerprints, as no subtree is shared whole.
+fn scattered_doc(env: usize, depth: usize, width: usize) -> Yaml {
+    let mut hash = Hash::new();
+    for key in 0..width {
+        let value = match depth {
+            0 if key == width - 1 => Yaml::String(format!("env-{}", env)),
+            0 => Yaml::String(format!("shared-value-{}", key)),
+            _ => scattered_doc(env, depth - 1, width),
+        };
+        hash.insert(Yaml::String(format!("key{}", key)), value);
+    }
+    Yaml::Hash(hash)
+}
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn compare(label: &str, env: &Yaml, base: &Yaml) {
+    let plain = DiffOptions::default();
+    let fingerprinted = DiffOptions {
+        fingerprint_subtrees: true,
+        ..DiffOptions::default()
+    };
+    assert_eq!(
+        compute_diff_with_options(env, base, &plain),
+        compute_diff_with_options(env, base, &fingerprinted)
+    );
+
+    let without = time(|| {
+        black_box(compute_diff_with_options(black_box(env), base, &plain));
+    });
+    let with = time(|| {
+        black_box(compute_diff_with_options(black_box(env), base, &fingerprinted));
+    });
+    println!(
+        "{:<24} without: {:>12?}  with: {:>12?}  speedup: {:.2}x",
+        label,
+        without,
+        with,
+        without.as_secs_f64() / with.as_secs_f64()
+    );
+}
+
+fn main() {
+    for levels in [2, 4, 8, 16] {
+        compare(&format!("nested, {} levels", levels), &nested_doc(1, levels), &nested_doc(0, levels));
+    }
+    for depth in [3, 5] {
+        compare(&format!("scattered, {} levels", depth + 1), &scattered_doc(1, depth, 6), &scattered_doc(0, depth, 6));
+    }
+}
@@ -0,0 +1,280 @@
+//! Array strategies beyond positional comparison and strategic merge.
+//!
+//! Append-unique treats an array as a base list plus per-file extras: the base holds the
+//! elements every file has, each diff holds only the elements its file adds, and merging
+//! appends the extras that are not already present. Elements are compared with
+//! [`deep_equal`] and duplicates collapse, so reconstructed arrays never repeat an element.
+//! The base keeps the order of the first file and extras keep their file's order, appended
+//! after the base; a file whose base elements do not come first is therefore reordered.
+//!
+//! Prefix treats an array as a shared leading run plus per-file trailing elements: the base
+//! holds the longest prefix every file's array starts with, each diff holds the elements its
+//! file has after that prefix, and merging appends them. Duplicates are kept. When the arrays
+//! share no prefix, nothing is hoisted and every file keeps its whole array, so nothing is
+//! appended to a base that does not exist.
+//!
+//! Ordered-set treats an array as unique elements whose order matters, such as a priority list
+//! of tolerations or rules. The base holds the elements held by at least a quorum of the files,
+//! in the order a quorum of them agree on; each diff is an edit script (see [`crate::lcs`])
+//! inserting and deleting elements of the base, so files holding the base elements in another
+//! order, or lacking some, are expressed too. The base is chosen in two steps:
+//!
+//! 1. The elements a quorum of the arrays hold are ordered by their mean rank among those
+//!    elements in the arrays holding them, ties by first appearance.
+//! 2. In that order, an element is kept when, for every element kept before it, a quorum of
+//!    the arrays hold both with the kept one first.
+//!
+//! So `[a, b]`, `[b, a]` and `[b, a, c]` have the base `[b, a]` at a majority quorum, and the
+//! first file's diff moves `a` before `b`. Duplicate elements break the assumption of an
+//! ordered set, so arrays holding any are compared positionally: [`duplicated_arrays`] finds
+//! them to reject the inputs beforehand.
+//!
+//! Positional diffs mark unchanged elements with `null`. For output, such an array can be
+//! written as an indexed slots patch instead, listing only the changed elements by index along
+//! with the array's length:
+//!
+//! ```yaml
+//! args:
+//!   $slots: {1: --verbose}
+//!   $length: 3
+//! ```
+//!
+//! Only arrays of the diff against a base array of the same length are written that way, and
+//! merging reads slots patches only when [`crate::options::DiffOptions::slots_patches`] is set.
+//! Merging a slots patch onto an array of that length keeps the unlisted elements and merges
+//! each listed one onto the element at its index. Onto anything else, e.g. no array or one of
+//! another length, the patch stands for the array of its listed elements, with `null` at the
+//! unlisted indices before the last one: `$length` is not trusted to size an array.
+
+use yaml_rust2::yaml::{Array, Hash, Yaml};
+
+use crate::deep_equal::deep_equal;
+use crate::path::{format_path, key_segment, Segment};
+
+fn contains(items: &[&Yaml], item: &Yaml) -> bool {
+    items.iter().any(|existing| deep_equal(existing, item))
+}
+
+/// Returns the elements in order of first appearance, dropping duplicates.
+pub fn unique_elements(items: &[Yaml]) -> Vec<&Yaml> {
+    let mut unique: Vec<&Yaml> = Vec::new();
+    for item in items {
+        if !contains(&unique, item) {
+            unique.push(item);
+        }
+    }
+    unique
+}
+
+/// Returns the elements of `items` that are not in `base`, without duplicates.
+pub fn extra_elements<'a>(items: &'a [Yaml], base: &[&Yaml]) -> Vec<&'a Yaml> {
+    unique_elements(items)
+        .into_iter()
+        .filter(|item| !contains(base, item))
+        .collect()
+}
+
+/// Returns the elements present in every array, in order of the first one.
+pub fn common_elements<'a>(arrays: &[&'a Array]) -> Vec<&'a Yaml> {
+    let Some((first, rest)) = arrays.split_first() else {
+        return vec![];
+    };
+    unique_elements(first)
+        .into_iter()
+        .filter(|item| rest.iter().all(|array| array.iter().any(|other| deep_equal(item, other))))
+        .collect()
+}
+
+/// Checks whether an array holds an element more than once.
+pub fn has_duplicates(items: &[Yaml]) -> bool {
+    unique_elements(items).len() < items.len()
+}
+
+/// Returns the ordered-set base of arrays of unique elements (see the [module
+/// documentation](self)): elements at least `quorum_count` of the arrays hold, in an order at
+/// least that many agree on.
+///
+/// ```
+/// use yabe::arrays::ordered_common_elements;
+/// use yabe::yaml;
+///
+/// let arrays = [yaml!("[a, b]"), yaml!("[b, a]"), yaml!("[b, a, c]")];
+/// let arrays: Vec<_> = arrays.iter().map(|array| array.as_vec().unwrap()).collect();
+/// assert_eq!(ordered_common_elements(&arrays, 2), [&yaml!("b"), &yaml!("a")]);
+/// assert_eq!(ordered_common_elements(&arrays, 3).len(), 1);
+/// ```
+pub fn ordered_common_elements<'a>(arrays: &[&'a Array], quorum_count: usize) -> Vec<&'a Yaml> {
+    let mut elements: Vec<&Yaml> = Vec::new();
+    for array in arrays {
+        for item in array.iter() {
+            if !contains(&elements, item) {
+                elements.push(item);
+            }
+        }
+    }
+    let positions: Vec<Vec<Option<usize>>> = elements
+        .iter()
+        .map(|item| arrays.iter().map(|array| array.iter().position(|other| deep_equal(item, other))).collect())
+        .collect();
+    let held = |element: usize| positions[element].iter().flatten().count();
+    let mut candidates: Vec<usize> = (0..elements.len()).filter(|&element| held(element) >= quorum_count.max(1)).collect();
+
+    // The rank of an element in an array is its position among the candidates there
+    let rank = |element: usize, array: usize| {
+        let position = positions[element][array]?;
+        Some(candidates.iter().filter(|&&other| positions[other][array].is_some_and(|p| p < position)).count())
+    };
+    let mean_ranks: Vec<f64> = candidates
+        .iter()
+        .map(|&element| {
+            let ranks: Vec<usize> = (0..arrays.len()).filter_map(|array| rank(element, array)).collect();
+            ranks.iter().sum::<usize>() as f64 / ranks.len() as f64
+        })
+        .collect();
+    let mut order: Vec<usize> = (0..candidates.len()).collect();
+    order.sort_by(|&a, &b| mean_ranks[a].total_cmp(&mean_ranks[b]));
+    candidates = order.into_iter().map(|index| candidates[index]).collect();
+
+    let in_order = |first: usize, second: usize| {
+        positions[first]
+            .iter()
+            .zip(&positions[second])
+            .filter(|(first, second)| matches!((first, second), (Some(first), Some(second)) if first < second))
+            .count()
+    };
+    let mut kept: Vec<usize> = Vec::new();
+    for element in candidates {
+        if kept.iter().all(|&before| in_order(before, element) >= quorum_count) {
+            kept.push(element);
+        }
+    }
+    kept.into_iter().map(|element| elements[element]).collect()
+}
+
+/// Lists the paths (see [`crate::path::format_path`]) of the arrays of a document holding an
+/// element more than once, in document order; arrays inside such an array are not looked at.
+///
+/// ```
+/// use yabe::arrays::duplicated_arrays;
+/// use yabe::yaml;
+///
+/// let doc = yaml!("tolerations: [{key: a}, {key: b}, {key: a}]\nhosts: [x, y]");
+/// assert_eq!(duplicated_arrays(&doc), ["tolerations"]);
+/// ```
+pub fn duplicated_arrays(doc: &Yaml) -> Vec<String> {
+    let mut found = Vec::new();
+    collect_duplicated(doc, &mut Vec::new(), &mut found);
+    found
+}
+
+fn collect_duplicated(value: &Yaml, path: &mut Vec<Segment>, found: &mut Vec<String>) {
+    match value {
+        Yaml::Hash(hash) => {
+            for (key, child) in hash {
+                path.push(Segment::Key(key_segment(key)));
+                collect_duplicated(child, path, found);
+                path.pop();
+            }
+        }
+        Yaml::Array(items) if has_duplicates(items) => found.push(format_path(path)),
+        Yaml::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                path.push(Segment::Index(index));
+                collect_duplicated(child, path, found);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Appends the elements of `extra` that are not already in `base`.
+pub fn append_unique(base: &[Yaml], extra: &[Yaml]) -> Array {
+    let mut merged: Array = unique_elements(base).into_iter().cloned().collect();
+    for item in extra {
+        if !merged.iter().any(|existing| deep_equal(existing, item)) {
+            merged.push(item.clone());
+        }
+    }
+    merged
+}
+
+/// Returns the length of the longest prefix all arrays share.
+pub fn common_prefix_len(arrays: &[&Array]) -> usize {
+    let Some((first, rest)) = arrays.split_first() else {
+        return 0;
+    };
+    first
+        .iter()
+        .enumerate()
+        .take_while(|(index, item)| {
+            rest.iter()
+                .all(|array| array.get(*index).is_some_and(|other| deep_equal(item, other)))
+        })
+        .count()
+}
+
+/// Returns the elements of `items` after `prefix`, or `None` if `items` does not start with it.
+pub fn trailing_elements<'a>(items: &'a [Yaml], prefix: &[Yaml]) -> Option<&'a [Yaml]> {
+    if items.len() < prefix.len()
+        || !items.iter().zip(prefix).all(|(item, base)| deep_equal(item, base))
+    {
+        return None;
+    }
+    Some(&items[prefix.len()..])
+}
+
+/// Key holding the changed elements of a slots patch.
+pub const SLOTS_DIRECTIVE: &str = "$slots";
+/// Key holding the array length of a slots patch.
+pub const LENGTH_DIRECTIVE: &str = "$length";
+
+/// Builds the slots patch of a positional diff: every non-`null` element by index, and the
+/// length. Returns `None` for an array without `null` elements, which is written as it is.
+pub fn slots_patch(items: &[Yaml]) -> Option<Yaml> {
+    if !items.iter().any(Yaml::is_null) {
+        return None;
+    }
+    let slots: Hash = items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| !item.is_null())
+        .map(|(index, item)| (Yaml::Integer(index as i64), item.clone()))
+        .collect();
+    let mut patch = Hash::new();
+    patch.insert(Yaml::String(SLOTS_DIRECTIVE.to_string()), Yaml::Hash(slots));
+    patch.insert(Yaml::String(LENGTH_DIRECTIVE.to_string()), Yaml::Integer(items.len() as i64));
+    Some(Yaml::Hash(patch))
+}
+
+/// Checks if a value is a slots patch.
+pub fn is_slots_patch(value: &Yaml) -> bool {
+    matches!(value, Yaml::Hash(hash) if hash.len() == 2)
+        && value[SLOTS_DIRECTIVE].as_hash().is_some_and(|slots| slots.keys().all(|key| key.as_i64().is_some()))
+        && value[LENGTH_DIRECTIVE].as_i64().is_some_and(|length| length >= 0)
+}
+
+/// Applies a slots patch (see [`is_slots_patch`]) onto `base`.
+///
+/// `merge_element(base_item, patch_item)` merges a listed element; when the patch stands for the
+/// whole array, its base item is `null`. Listed indices past the length are ignored. Onto a
+/// value that is not an array of the patch's length, the array ends at the last listed index.
+pub fn apply_slots_patch(base: &Yaml, patch: &Yaml, merge_element: impl Fn(&Yaml, &Yaml) -> Yaml) -> Array {
+    let length = patch[LENGTH_DIRECTIVE].as_i64().unwrap_or(0);
+    let mut merged: Array = match base {
+        Yaml::Array(items) if items.len() as i64 == length => items.clone(),
+        _ => {
+            let listed = patch[SLOTS_DIRECTIVE].as_hash().into_iter().flat_map(|slots| slots.keys());
+            let end = listed.filter_map(Yaml::as_i64).filter(|index| *index < length).map(|index| index + 1).max();
+            vec![Yaml::Null; end.unwrap_or(0).max(0) as usize]
+        }
+    };
+    if let Some(slots) = patch[SLOTS_DIRECTIVE].as_hash() {
+        for (index, item) in slots {
+            if let Some(slot) = index.as_i64().and_then(|index| merged.get_mut(usize::try_from(index).ok()?)) {
+                *slot = merge_element(slot, item);
+            }
+        }
+    }
+    merged
+}
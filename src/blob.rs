@@ -0,0 +1,103 @@
+//! Handling of large opaque strings such as base64-encoded certificates and keys.
+//!
+//! With a threshold set, strings of at least that many bytes are blobs: they are compared by
+//! length and [`crate::fingerprint`] instead of byte by byte, and reports show a short
+//! fingerprint instead of the whole value. Stored values (base and diffs) always keep the
+//! full string; only what is logged or reported is abbreviated.
+
+use std::borrow::Cow;
+
+use yaml_rust2::yaml::{Hash, Yaml};
+
+use crate::deep_equal::deep_equal;
+use crate::fingerprint::{fingerprint, fingerprint_hex};
+
+/// Returns the string if `value` is a blob under `threshold`.
+pub fn as_blob(value: &Yaml, threshold: Option<usize>) -> Option<&str> {
+    match (value, threshold) {
+        (Yaml::String(s), Some(threshold)) if s.len() >= threshold => Some(s),
+        _ => None,
+    }
+}
+
+/// Checks two values for deep equality, comparing blobs by length and fingerprint.
+///
+/// Without a threshold this is [`deep_equal`].
+pub fn blob_equal(a: &Yaml, b: &Yaml, threshold: Option<usize>) -> bool {
+    if threshold.is_none() {
+        return deep_equal(a, b);
+    }
+    match (a, b) {
+        (Yaml::String(a_str), Yaml::String(b_str))
+            if as_blob(a, threshold).is_some() || as_blob(b, threshold).is_some() =>
+        {
+            a_str.len() == b_str.len() && fingerprint(a) == fingerprint(b)
+        }
+        (Yaml::Array(a_vec), Yaml::Array(b_vec)) => {
+            a_vec.len() == b_vec.len()
+                && a_vec
+                    .iter()
+                    .zip(b_vec)
+                    .all(|(a_item, b_item)| blob_equal(a_item, b_item, threshold))
+        }
+        (Yaml::Hash(a_hash), Yaml::Hash(b_hash)) => {
+            a_hash.len() == b_hash.len()
+                && a_hash.iter().all(|(a_key, a_value)| {
+                    b_hash
+                        .get(a_key)
+                        .is_some_and(|b_value| blob_equal(a_value, b_value, threshold))
+                })
+        }
+        _ => deep_equal(a, b),
+    }
+}
+
+/// Formats the placeholder shown in place of a blob, e.g. `<blob 4096 bytes, fnv 1a2b…>`.
+pub fn blob_summary(blob: &str) -> String {
+    format!(
+        "<blob {} bytes, fnv {}>",
+        blob.len(),
+        fingerprint_hex(&Yaml::String(blob.to_string()))
+    )
+}
+
+/// Returns a copy of `value` with every blob replaced by its summary, for reports.
+///
+/// The value is borrowed unchanged when it contains no blobs.
+pub fn abbreviate(value: &Yaml, threshold: Option<usize>) -> Cow<'_, Yaml> {
+    if threshold.is_none() {
+        return Cow::Borrowed(value);
+    }
+    match value {
+        Yaml::String(s) if as_blob(value, threshold).is_some() => {
+            Cow::Owned(Yaml::String(blob_summary(s)))
+        }
+        Yaml::Array(items) => {
+            let abbreviated: Vec<Cow<Yaml>> =
+                items.iter().map(|item| abbreviate(item, threshold)).collect();
+            if abbreviated.iter().all(|item| matches!(item, Cow::Borrowed(_))) {
+                Cow::Borrowed(value)
+            } else {
+                Cow::Owned(Yaml::Array(
+                    abbreviated.into_iter().map(Cow::into_owned).collect(),
+                ))
+            }
+        }
+        Yaml::Hash(hash) => {
+            let abbreviated: Vec<(&Yaml, Cow<Yaml>)> = hash
+                .iter()
+                .map(|(key, child)| (key, abbreviate(child, threshold)))
+                .collect();
+            if abbreviated.iter().all(|(_, child)| matches!(child, Cow::Borrowed(_))) {
+                Cow::Borrowed(value)
+            } else {
+                let mut result = Hash::new();
+                for (key, child) in abbreviated {
+                    result.insert(key.clone(), child.into_owned());
+                }
+                Cow::Owned(Yaml::Hash(result))
+            }
+        }
+        _ => Cow::Borrowed(value),
+    }
+}
@@ -0,0 +1,123 @@
+//! Building [`Yaml`] values in code, for tests and programmatic construction.
+//!
+//! [`IntoYaml`] converts Rust values into YAML: strings into strings (never parsed, so `"true"`
+//! stays a string), integers, floats, booleans, `None` into `null`, and arrays and vectors into
+//! YAML arrays. [`map`] and [`array`] build collections from them, and the [`crate::yaml!`]
+//! macro wraps both:
+//!
+//! ```
+//! use yabe::{yaml, Yaml};
+//!
+//! let doc = yaml!({
+//!     "image" => yaml!({ "tag" => "v1" }),
+//!     "ports" => [80, 443],
+//!     "debug" => false,
+//!     "sidecar" => None::<Yaml>,
+//! });
+//! assert_eq!(doc, yaml!("image: {tag: v1}\nports: [80, 443]\ndebug: false\nsidecar: ~"));
+//! ```
+
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::Yaml;
+
+/// Conversion of a Rust value into a [`Yaml`] value.
+pub trait IntoYaml {
+    fn into_yaml(self) -> Yaml;
+}
+
+impl IntoYaml for Yaml {
+    fn into_yaml(self) -> Yaml {
+        self
+    }
+}
+
+impl IntoYaml for &Yaml {
+    fn into_yaml(self) -> Yaml {
+        self.clone()
+    }
+}
+
+impl IntoYaml for &str {
+    fn into_yaml(self) -> Yaml {
+        Yaml::String(self.to_string())
+    }
+}
+
+impl IntoYaml for String {
+    fn into_yaml(self) -> Yaml {
+        Yaml::String(self)
+    }
+}
+
+impl IntoYaml for bool {
+    fn into_yaml(self) -> Yaml {
+        Yaml::Boolean(self)
+    }
+}
+
+macro_rules! integer_into_yaml {
+    ($($int:ty),*) => {
+        $(
+            impl IntoYaml for $int {
+                fn into_yaml(self) -> Yaml {
+                    Yaml::Integer(i64::from(self))
+                }
+            }
+        )*
+    };
+}
+
+integer_into_yaml!(i8, i16, i32, i64, u8, u16, u32);
+
+impl IntoYaml for f64 {
+    /// Renders the float the way YAML writes it, e.g. `.inf` for infinity.
+    fn into_yaml(self) -> Yaml {
+        let text = if self.is_nan() {
+            ".nan".to_string()
+        } else if self.is_infinite() {
+            if self > 0.0 { ".inf" } else { "-.inf" }.to_string()
+        } else {
+            format!("{:?}", self)
+        };
+        Yaml::Real(text)
+    }
+}
+
+impl<T: IntoYaml> IntoYaml for Option<T> {
+    fn into_yaml(self) -> Yaml {
+        self.map_or(Yaml::Null, IntoYaml::into_yaml)
+    }
+}
+
+impl<T: IntoYaml> IntoYaml for Vec<T> {
+    fn into_yaml(self) -> Yaml {
+        array(self)
+    }
+}
+
+impl<T: IntoYaml, const N: usize> IntoYaml for [T; N] {
+    fn into_yaml(self) -> Yaml {
+        array(self)
+    }
+}
+
+/// Builds a map from its entries, in order; a later entry replaces an earlier one with the
+/// same key, keeping its position.
+pub fn map<K: IntoYaml, V: IntoYaml>(entries: impl IntoIterator<Item = (K, V)>) -> Yaml {
+    let mut hash = Hash::new();
+    for (key, value) in entries {
+        let key = key.into_yaml();
+        match hash.get_mut(&key) {
+            Some(existing) => *existing = value.into_yaml(),
+            None => {
+                hash.insert(key, value.into_yaml());
+            }
+        }
+    }
+    Yaml::Hash(hash)
+}
+
+/// Builds an array from its items, in order.
+pub fn array<T: IntoYaml>(items: impl IntoIterator<Item = T>) -> Yaml {
+    Yaml::Array(items.into_iter().map(IntoYaml::into_yaml).collect())
+}
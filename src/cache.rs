@@ -0,0 +1,122 @@
+//! On-disk cache of intermediate results, keyed by digests of everything they depend on.
+//!
+//! Entries are content-addressed: a key is made of all inputs of a computation (file contents,
+//! options and the yabe version), and an entry is a `<digest>.yaml` file named after the key's
+//! SHA-256 digest, holding the value as YAML. The digest is collision-resistant, so only the
+//! digest is stored: a changed input yields a different key, and a stale entry is never read.
+//! An entry that cannot be read or parsed counts as a miss. Values round-trip unchanged, so a
+//! cached run produces the same output as a cold one.
+//!
+//! The directory grows by an entry for every input, and set of inputs, computed with different
+//! contents or options. [`Cache::prune`] caps its size, removing the least recently used entries
+//! first; reading an entry counts as a use. It can also be deleted at any time.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use sha2::{Digest, Sha256};
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::output::to_yaml_string;
+
+const ENTRY_EXTENSION: &str = "yaml";
+
+/// A cache directory.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// Opens the cache in `dir`, creating the directory if needed.
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Cache> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Cache {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(format!("{}.{}", key.name, ENTRY_EXTENSION))
+    }
+
+    /// Returns the value stored under `key`, if any, and marks the entry as used.
+    pub fn get(&self, key: &CacheKey) -> Option<Yaml> {
+        let path = self.entry_path(key);
+        let content = fs::read_to_string(&path).ok()?;
+        let value = YamlLoader::load_from_str(&content).ok()?.into_iter().next()?;
+        // A failed update only makes the entry look older to pruning
+        if let Ok(file) = fs::File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(value)
+    }
+
+    /// Stores `value` under `key`.
+    ///
+    /// The entry is written to a temporary file first, so concurrent runs never read a
+    /// partially written entry.
+    pub fn put(&self, key: &CacheKey, value: &Yaml) -> io::Result<()> {
+        let value = to_yaml_string(value)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        let temp = self
+            .dir
+            .join(format!("{}.{}.tmp", key.name, std::process::id()));
+        fs::write(&temp, value)?;
+        fs::rename(&temp, self.entry_path(key))
+    }
+
+    /// Removes the least recently used entries until the entries take at most `max_bytes`,
+    /// returning how many were removed. Other files in the directory are left alone.
+    pub fn prune(&self, max_bytes: u64) -> io::Result<usize> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != ENTRY_EXTENSION) {
+                continue;
+            }
+            let metadata = fs::metadata(&path)?;
+            entries.push((metadata.modified()?, metadata.len(), path));
+        }
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort();
+        let mut removed = 0;
+        for (_, len, path) in entries {
+            if total <= max_bytes {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= len;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+/// A cache key: the SHA-256 digest of the parts a result depends on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    name: String,
+}
+
+impl CacheKey {
+    /// Returns the digest naming the key's entry, as 64 hexadecimal digits.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Computes a cache key from the parts a result depends on and the yabe version.
+pub fn cache_key(parts: &[&[u8]]) -> CacheKey {
+    let mut hasher = Sha256::new();
+    for part in [env!("CARGO_PKG_VERSION").as_bytes()].iter().chain(parts) {
+        // Length prefixes keep ("ab", "c") and ("a", "bc") apart
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    CacheKey {
+        name: hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect(),
+    }
+}
@@ -0,0 +1,226 @@
+//! Self-checks run over extraction results.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use yaml_rust2::Yaml;
+
+use crate::deep_equal::deep_equal;
+use crate::diff::{compute_diff_with_options, LeafCounting};
+use crate::fingerprint::fingerprint;
+use crate::input::is_empty_document;
+use crate::normalize::normalize;
+use crate::options::DiffOptions;
+use crate::path::{join_path, key_segment};
+use crate::report::override_coverage_with;
+
+/// Finds paths whose value appears verbatim in every file's diff.
+///
+/// Such a value should have been hoisted into the base, so a non-empty result points at a
+/// quorum edge case or a hoisting bug. Paths are dotted (see [`crate::path`]); the empty path
+/// means the diffs are identical as a whole. Nothing is reported for fewer than two files.
+pub fn duplicated_diff_paths(diffs: &[Option<&Yaml>]) -> Vec<String> {
+    let mut found = Vec::new();
+    if diffs.len() < 2 {
+        return found;
+    }
+    let Some(values) = diffs.iter().copied().collect::<Option<Vec<&Yaml>>>() else {
+        return found;
+    };
+    collect_duplicates(&values, &mut Vec::new(), &mut found);
+    found
+}
+
+fn collect_duplicates(values: &[&Yaml], path: &mut Vec<String>, found: &mut Vec<String>) {
+    let first = values[0];
+    if values[1..].iter().all(|value| deep_equal(first, value)) {
+        found.push(join_path(path));
+        return;
+    }
+
+    let Some(first_hash) = first.as_hash() else {
+        return;
+    };
+    for key in first_hash.keys() {
+        let at_key: Option<Vec<&Yaml>> = values
+            .iter()
+            .map(|value| value.as_hash().and_then(|h| h.get(key)))
+            .collect();
+        if let Some(at_key) = at_key {
+            path.push(key_segment(key));
+            collect_duplicates(&at_key, path, found);
+            path.pop();
+        }
+    }
+}
+
+/// Finds the values of a file that the base already sets to the same value.
+///
+/// A file is minimal when it equals its own diff against the base (see
+/// [`compute_diff_with_options`]); the paths reported are those the diff leaves out. The empty
+/// path means the whole file is already in the base. Paths refer to the file after the
+/// preprocessing passes of `options`, e.g. with dotted keys expanded.
+pub fn redundant_paths(file: &Yaml, base: &Yaml, options: &DiffOptions) -> Vec<String> {
+    let mut found = Vec::new();
+    if is_empty_document(file) {
+        return found;
+    }
+    let file = normalize(file, options);
+    match compute_diff_with_options(&file, base, options) {
+        Some(diff) => collect_redundant(&file, &diff, &mut Vec::new(), &mut found),
+        None => found.push(String::new()),
+    }
+    found
+}
+
+fn collect_redundant(file: &Yaml, diff: &Yaml, path: &mut Vec<String>, found: &mut Vec<String>) {
+    let (Some(file_hash), Some(diff_hash)) = (file.as_hash(), diff.as_hash()) else {
+        return;
+    };
+    for (key, file_value) in file_hash {
+        path.push(key_segment(key));
+        match diff_hash.get(key) {
+            Some(diff_value) => collect_redundant(file_value, diff_value, path, found),
+            None => found.push(join_path(path)),
+        }
+        path.pop();
+    }
+}
+
+/// Finds the inputs that look like an extracted base passed among the files it was extracted
+/// from: every value they set, every other input sets the same, and some other input sets more.
+///
+/// Such an input skews the quorum, as its values count once more. Empty inputs are skipped, and
+/// nothing is reported for fewer than three non-empty inputs, as one file extending another is
+/// normal then.
+pub fn base_like_inputs(docs: &[&Yaml], options: &DiffOptions) -> Vec<usize> {
+    let present: Vec<(usize, &Yaml)> =
+        docs.iter().copied().enumerate().filter(|(_, doc)| !is_empty_document(doc)).collect();
+    if present.len() < 3 {
+        return vec![];
+    }
+    present
+        .iter()
+        .filter(|(index, doc)| {
+            let others = || present.iter().filter(|(other, _)| other != index);
+            matches!(doc, Yaml::Hash(_))
+                && others().all(|(_, other)| compute_diff_with_options(doc, other, options).is_none())
+                && others().any(|(_, other)| compute_diff_with_options(other, doc, options).is_some())
+        })
+        .map(|(index, _)| *index)
+        .collect()
+}
+
+/// What to do with inputs equal to another input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateInputs {
+    /// Warn about each pair of duplicates.
+    Warn,
+    /// Fail the run when there are duplicates.
+    Error,
+}
+
+impl FromStr for DuplicateInputs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "warn" => Ok(DuplicateInputs::Warn),
+            "error" => Ok(DuplicateInputs::Error),
+            _ => Err(format!("unknown duplicate input handling '{}', expected one of: warn, error", s)),
+        }
+    }
+}
+
+impl fmt::Display for DuplicateInputs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DuplicateInputs::Warn => "warn",
+            DuplicateInputs::Error => "error",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Finds the pairs of inputs that are deeply equal, usually a copy-pasted file never edited.
+///
+/// Inputs are bucketed by [`fingerprint`], so only inputs with equal fingerprints are compared.
+/// Each pair `(i, j)` has `i < j`, and pairs are in input order; three equal inputs make three
+/// pairs. Empty inputs are skipped.
+pub fn duplicate_inputs(docs: &[&Yaml]) -> Vec<(usize, usize)> {
+    let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut pairs = Vec::new();
+    for (index, doc) in docs.iter().enumerate() {
+        if is_empty_document(doc) {
+            continue;
+        }
+        let bucket = buckets.entry(fingerprint(doc)).or_default();
+        for &other in bucket.iter() {
+            if deep_equal(docs[other], doc) {
+                pairs.push((other, index));
+            }
+        }
+        bucket.push(index);
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+/// What to do when the base covers too little of the inputs, see [`mean_base_coverage`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnrelatedInputs {
+    /// Do not check.
+    Ignore,
+    /// Warn, or fail the run in strict mode.
+    #[default]
+    Warn,
+    /// Fail the run.
+    Error,
+}
+
+impl FromStr for UnrelatedInputs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ignore" => Ok(UnrelatedInputs::Ignore),
+            "warn" => Ok(UnrelatedInputs::Warn),
+            "error" => Ok(UnrelatedInputs::Error),
+            _ => Err(format!("unknown unrelated input handling '{}', expected one of: ignore, warn, error", s)),
+        }
+    }
+}
+
+impl fmt::Display for UnrelatedInputs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            UnrelatedInputs::Ignore => "ignore",
+            UnrelatedInputs::Warn => "warn",
+            UnrelatedInputs::Error => "error",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Returns the mean [`crate::report::override_coverage`] of the inputs: the fraction of the
+/// average input's leaves that come from the base.
+///
+/// A low value means the base holds little of what the inputs set, as when a glob picks up
+/// unrelated configuration files. `diffs` are the inputs' diffs against the base, in order.
+/// Empty inputs are skipped, and nothing is returned for fewer than two non-empty inputs.
+pub fn mean_base_coverage(docs: &[&Yaml], diffs: &[Option<&Yaml>]) -> Option<f64> {
+    mean_base_coverage_with(docs, diffs, LeafCounting::WholeArrays)
+}
+
+/// Returns the mean coverage like [`mean_base_coverage`], leaves counted as `counting` says
+/// (see [`override_coverage_with`]).
+pub fn mean_base_coverage_with(docs: &[&Yaml], diffs: &[Option<&Yaml>], counting: LeafCounting) -> Option<f64> {
+    let coverage: Vec<f64> = docs
+        .iter()
+        .zip(diffs)
+        .filter(|(doc, _)| !is_empty_document(doc))
+        .map(|(doc, diff)| override_coverage_with(doc, *diff, counting))
+        .collect();
+    (coverage.len() >= 2).then(|| coverage.iter().sum::<f64>() / coverage.len() as f64)
+}
@@ -0,0 +1,224 @@
+//! The modes reporting on the inputs instead of extracting a base: `--preview-renames`,
+//! `--schema`, `--union-schema`, `--two-phase`, `--template` and `--chain`.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::io::Write;
+
+use log::info;
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::Yaml;
+use yabe::comments::{DocComment, DocComments};
+use yabe::diff::overlay_chain;
+use yabe::normalize::{alias_renames, expand_dotted_keys};
+use yabe::options::KeyAliases;
+use yabe::output::{write_yaml, write_yaml_with_comments};
+use yabe::report::rename_report;
+use yabe::schema::{coverage, schema_diff, Coverage, Deviation};
+use yabe::skeleton::{template, two_phase, Template, TwoPhase};
+
+use super::write::{print_stream, write_diffs};
+use super::{check_empty_inputs, Context};
+
+/// A dry run of the alias pass, to check the aliases before migrating anything.
+pub fn preview_renames(context: &Context, aliases: &KeyAliases) -> Result<(), Box<dyn Error>> {
+    let renames: Vec<_> = context
+        .inputs
+        .docs()?
+        .iter()
+        .map(|doc| match context.options.expand_dotted_keys {
+            true => alias_renames(&expand_dotted_keys(doc), aliases),
+            false => alias_renames(doc, aliases),
+        })
+        .collect();
+    print!("{}", rename_report(&context.inputs.filenames, &renames, aliases.on_conflict, context.args.rename_format));
+    info!("Program completed successfully.");
+    Ok(())
+}
+
+/// Runs the requested schema, template or chain mode on the inputs but the empty ones.
+pub fn run(context: &Context) -> Result<(), Box<dyn Error>> {
+    let Context { args, options, inputs, bases, output, .. } = context;
+    let all_refs: Vec<&Yaml> = inputs.docs()?.iter().collect();
+    let empty = check_empty_inputs(&inputs.filenames, &all_refs, args.empty_inputs)?;
+    let (filenames, docs): (Vec<String>, Vec<&Yaml>) = inputs
+        .filenames
+        .iter()
+        .cloned()
+        .zip(all_refs)
+        .filter(|(filename, _)| !empty.contains(filename))
+        .unzip();
+
+    if args.schema {
+        info!("Comparing the structure of {} files.", docs.len());
+        let (schema, deviations) = schema_diff(&docs, options.quorum);
+        print_schema_report(schema.as_ref(), &deviations, &filenames)?;
+        info!("Program completed successfully.");
+        return Ok(());
+    }
+
+    if args.union_schema {
+        info!("Checking {} files against the union of their keys.", docs.len());
+        let (union, coverage) = coverage(&docs);
+        print_coverage_report(union.as_ref(), &coverage, &filenames)?;
+        info!("Program completed successfully.");
+        return Ok(());
+    }
+
+    if args.two_phase {
+        info!("Extracting the structure, then the values, of {} files.", docs.len());
+        let result = two_phase(&docs, options);
+        info!(
+            "Values: {} leaves of the structure diverge and hold a placeholder.",
+            result.divergent.len()
+        );
+        print_two_phase_report(&result, &filenames)?;
+        info!("Program completed successfully.");
+        return Ok(());
+    }
+
+    if args.template {
+        info!("Building a template of every key of {} files.", docs.len());
+        let result = template(&docs, options);
+        info!("Template: {} keys are optional.", result.optional.len());
+        print_template(&result)?;
+        info!("Program completed successfully.");
+        return Ok(());
+    }
+
+    // The read-only base, or else the first input, is the root of the chain
+    let levels: Vec<&Yaml> = bases.read_only.iter().chain(docs).collect();
+    let chain_filenames = if bases.read_only.is_some() {
+        &filenames[..]
+    } else {
+        &filenames[1.min(filenames.len())..]
+    };
+    info!("Computing overlay chain over {} levels.", levels.len());
+    let chain_diffs: Vec<Option<Cow<Yaml>>> = overlay_chain(&levels, options)
+        .into_iter()
+        .skip(1)
+        .map(|diff| diff.map(Cow::Owned))
+        .collect();
+    if args.stdout {
+        print_stream(None, None, &output.base_docs, &chain_diffs, chain_filenames, &output.config, output.style)?;
+    } else {
+        write_diffs(&chain_diffs, chain_filenames, args.inplace, &args.out_folder, output)?;
+    }
+    info!("Program completed successfully.");
+    Ok(())
+}
+
+/// Prints the common schema and each file's structural deviations as a YAML stream.
+///
+/// Each file's document maps deviating paths to a description; a schema-equal file is an empty
+/// document.
+fn print_schema_report(
+    schema: Option<&Yaml>,
+    deviations: &[Vec<Deviation>],
+    input_filenames: &[String],
+) -> Result<(), Box<dyn Error>> {
+    let mut out = std::io::stdout().lock();
+    if let Some(schema_yaml) = schema {
+        writeln!(out, "--- # schema")?;
+        write_yaml(schema_yaml, &mut out)?;
+    }
+    for (filename, file_deviations) in input_filenames.iter().zip(deviations) {
+        writeln!(out, "--- # {}", filename)?;
+        if !file_deviations.is_empty() {
+            let mut report = Hash::new();
+            for deviation in file_deviations {
+                report.insert(
+                    Yaml::String(deviation.path.clone()),
+                    Yaml::String(deviation.kind.to_string()),
+                );
+            }
+            write_yaml(&Yaml::Hash(report), &mut out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints the union schema and, for each file, the union paths it misses and the values it
+/// overrides, as a YAML stream.
+fn print_coverage_report(
+    union: Option<&Yaml>,
+    coverage: &[Coverage],
+    input_filenames: &[String],
+) -> Result<(), Box<dyn Error>> {
+    let mut out = std::io::stdout().lock();
+    if let Some(union_yaml) = union {
+        writeln!(out, "--- # union")?;
+        write_yaml(union_yaml, &mut out)?;
+    }
+    for (filename, file_coverage) in input_filenames.iter().zip(coverage) {
+        writeln!(out, "--- # {}", filename)?;
+        let mut report = Hash::new();
+        if !file_coverage.missing.is_empty() {
+            let missing = file_coverage
+                .missing
+                .iter()
+                .map(|(path, expected)| (Yaml::String(path.clone()), Yaml::String(expected.clone())))
+                .collect();
+            report.insert(Yaml::String("missing".to_string()), Yaml::Hash(missing));
+        }
+        if !file_coverage.overrides.is_empty() {
+            let overrides = file_coverage
+                .overrides
+                .iter()
+                .map(|(path, value)| (Yaml::String(path.clone()), value.clone()))
+                .collect();
+            report.insert(Yaml::String("overrides".to_string()), Yaml::Hash(overrides));
+        }
+        if !report.is_empty() {
+            write_yaml(&Yaml::Hash(report), &mut out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints a template, with a comment above each optional key naming how many files set it.
+fn print_template(result: &Template) -> Result<(), Box<dyn Error>> {
+    let Some(template) = &result.template else {
+        return Ok(());
+    };
+    let entries = result
+        .optional
+        .iter()
+        .map(|(keys, set, holding)| DocComment {
+            path: keys.join("."),
+            keys: keys.clone(),
+            text: format!("optional: set by {} of {} files", set, holding),
+        })
+        .collect();
+    write_yaml_with_comments(template, &DocComments { entries }, &mut std::io::stdout().lock())?;
+    Ok(())
+}
+
+/// Prints the structure, the skeleton and the divergent values of a two-phase extraction as a
+/// YAML stream; each divergent path maps the files that have it to their value.
+fn print_two_phase_report(result: &TwoPhase, input_filenames: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut out = std::io::stdout().lock();
+    if let (Some(structure), Some(skeleton)) = (&result.structure, &result.skeleton) {
+        writeln!(out, "--- # structure")?;
+        write_yaml(structure, &mut out)?;
+        writeln!(out, "--- # skeleton")?;
+        write_yaml(skeleton, &mut out)?;
+    }
+    if !result.divergent.is_empty() {
+        let divergent = result
+            .divergent
+            .iter()
+            .map(|(path, values)| {
+                let values = input_filenames
+                    .iter()
+                    .zip(values)
+                    .filter_map(|(filename, value)| Some((Yaml::String(filename.clone()), value.clone()?)))
+                    .collect();
+                (Yaml::String(path.clone()), Yaml::Hash(values))
+            })
+            .collect();
+        writeln!(out, "--- # divergent")?;
+        write_yaml(&Yaml::Hash(divergent), &mut out)?;
+    }
+    Ok(())
+}
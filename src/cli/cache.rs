@@ -0,0 +1,144 @@
+//! The cache of the default mode: each input's diff against the read-only base, and the base
+//! and diffs extracted from them.
+
+use std::borrow::Cow;
+use std::error::Error;
+
+use log::{debug, warn};
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::Yaml;
+use yabe::cache::{cache_key, Cache, CacheKey};
+use yabe::diff::{compute_diff_with_options, diff_and_common_timed, diff_and_common_with_options, KeyTimes};
+use yabe::input::parse_document;
+use yabe::merge::merge_yaml_with_options;
+use yabe::options::DiffOptions;
+use yabe::result::DiffResult;
+
+use super::{Bases, Inputs};
+use crate::Args;
+
+/// Opens the cache, pruned to its size cap, unless it is disabled or the mode is not cached.
+pub fn open(args: &Args) -> Option<Cache> {
+    // The cache only covers the default mode, whose results depend on nothing but the inputs
+    if args.no_cache || args.schema || args.union_schema || args.two_phase || args.template || args.chain || args.report_defaults || args.check || args.preview_renames {
+        return None;
+    }
+    match Cache::open(&args.cache_dir) {
+        Ok(cache) => {
+            match cache.prune(args.cache_max_size * 1024 * 1024) {
+                Ok(0) => {}
+                Ok(removed) => debug!("Pruned {} cache entries from {}", removed, args.cache_dir),
+                Err(e) => warn!("Failed to prune the cache in {}: {}", args.cache_dir, e),
+            }
+            Some(cache)
+        }
+        Err(e) => {
+            warn!("Cache disabled: cannot open {}: {}", args.cache_dir, e);
+            None
+        }
+    }
+}
+
+/// Wraps an optional value as a cache entry: `{value: ...}`, or `{}` for nothing.
+fn to_cache_entry(value: Option<&Yaml>) -> Yaml {
+    let mut entry = Hash::new();
+    if let Some(value) = value {
+        entry.insert(Yaml::String("value".to_string()), value.clone());
+    }
+    Yaml::Hash(entry)
+}
+
+/// Unwraps a cache entry written by [`to_cache_entry`]; `None` if it is malformed.
+fn from_cache_entry(entry: &Yaml) -> Option<Option<Yaml>> {
+    let hash = entry.as_hash()?;
+    match hash.get(&Yaml::String("value".to_string())) {
+        Some(value) if hash.len() == 1 => Some(Some(value.clone())),
+        None if hash.is_empty() => Some(None),
+        _ => None,
+    }
+}
+
+/// Stores a cache entry; a failed write only costs the next run a recomputation.
+fn store(cache: &Cache, key: &CacheKey, entry: &Yaml) {
+    if let Err(e) = cache.put(key, entry) {
+        warn!("Failed to write cache entry {}: {}", key.name(), e);
+    }
+}
+
+/// Computes each input's diff against the read-only base, reusing the cached diffs of unchanged
+/// inputs without parsing them.
+pub fn cached_input_diffs(
+    cache: &Cache,
+    inputs: &Inputs,
+    bases: &Bases,
+    options: &DiffOptions,
+) -> Result<Vec<Yaml>, Box<dyn Error>> {
+    let context = bases.context(options);
+    let mut diffs = Vec::new();
+    for (filename, content) in inputs.filenames.iter().zip(&inputs.contents) {
+        let key = cache_key(&[b"input-diff", content.as_bytes(), context.as_bytes()]);
+        let diff = match cache.get(&key).as_ref().and_then(from_cache_entry).flatten() {
+            Some(diff) => {
+                debug!("Using cached diff for {}", filename);
+                diff
+            }
+            None => {
+                let doc = parse_document(content)?;
+                let merged = match bases.existing {
+                    Some(ref base) => merge_yaml_with_options(base, &doc, options),
+                    None => Cow::Borrowed(&doc),
+                };
+                let diff = match bases.read_only {
+                    Some(ref helm) => compute_diff_with_options(merged.as_ref(), helm, options)
+                        .map(Cow::into_owned)
+                        .unwrap_or_else(|| Yaml::Hash(Hash::new())),
+                    None => merged.into_owned(),
+                };
+                store(cache, &key, &to_cache_entry(Some(&diff)));
+                diff
+            }
+        };
+        diffs.push(diff);
+    }
+    Ok(diffs)
+}
+
+/// Extracts the base and per-file diffs, reusing the cached result when no input changed.
+///
+/// `diffs` are those of the inputs against the read-only base. A miss is timed by top-level key
+/// when `timed` is set; a hit has no key times.
+pub fn cached_extraction<'a>(
+    cache: &Cache,
+    inputs: &Inputs,
+    bases: &Bases,
+    diffs: &'a [&'a Yaml],
+    options: &DiffOptions,
+    timed: bool,
+) -> (DiffResult<'a>, KeyTimes) {
+    let context = bases.context(options);
+    let parts: Vec<&[u8]> = [b"extraction".as_slice(), context.as_bytes()]
+        .into_iter()
+        .chain(inputs.contents.iter().map(|content| content.as_bytes()))
+        .collect();
+    let key = cache_key(&parts);
+    if let Some(Yaml::Array(entries)) = cache.get(&key) {
+        let entries: Option<Vec<Option<Yaml>>> = entries.iter().map(from_cache_entry).collect();
+        if let Some(mut entries) = entries.filter(|entries| entries.len() == diffs.len() + 1) {
+            debug!("Using cached base and diffs.");
+            let base = entries.remove(0).map(Cow::Owned);
+            return (DiffResult::new(base, entries.into_iter().map(|diff| diff.map(Cow::Owned)).collect()), vec![]);
+        }
+    }
+
+    let (result, key_times) = if timed {
+        diff_and_common_timed(diffs, options)
+    } else {
+        (diff_and_common_with_options(diffs, options), vec![])
+    };
+    let entries = std::iter::once(result.base.as_deref())
+        .chain(result.diffs.iter().map(|diff| diff.as_deref()))
+        .map(to_cache_entry)
+        .collect();
+    store(cache, &key, &Yaml::Array(entries));
+    (result, key_times)
+}
@@ -0,0 +1,273 @@
+//! `--check`, and the checks of the default mode: on the inputs before the extraction, and on
+//! its base and diffs after it.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::io::Write;
+
+use log::{error, info, warn};
+use yaml_rust2::Yaml;
+use yabe::arrays::duplicated_arrays;
+use yabe::check::{
+    base_like_inputs, duplicate_inputs, duplicated_diff_paths, mean_base_coverage_with, redundant_paths, DuplicateInputs,
+    UnrelatedInputs,
+};
+use yabe::denylist::denied_values;
+use yabe::diff::compute_diff_with_options;
+use yabe::ignore::strip_ignored;
+use yabe::input::is_empty_document;
+use yabe::merge::apply_diff;
+use yabe::normalize::{alias_conflicts, expand_dotted_keys, normalize_all};
+use yabe::options::{AliasConflict, ArrayStrategy, DiffOptions};
+use yabe::report::{empty_base_summary, explain_empty_base, fan_out_paths};
+use yabe::schema::{collection_scalar_conflicts, type_conflicts};
+
+use super::{Context, MergedInputs, EXIT_NO_BASE, EXIT_NOT_MINIMAL};
+
+/// Lists the values of the inputs that are not minimal, exiting with [`EXIT_NOT_MINIMAL`] if
+/// there are any.
+pub fn run(context: &Context) -> Result<(), Box<dyn Error>> {
+    let Context { options, inputs, bases, .. } = context;
+    let problems = check_minimal(
+        &inputs.filenames,
+        inputs.docs()?,
+        bases.read_only.as_ref(),
+        bases.existing.as_ref(),
+        options,
+    )?;
+    if problems > 0 {
+        error!("{} value(s) are not minimal; re-run without --check to fix them.", problems);
+        std::process::exit(EXIT_NOT_MINIMAL);
+    }
+    info!("All {} files are minimal.", inputs.filenames.len());
+    Ok(())
+}
+
+/// Prints every value that is already in the base or identical in every file, one per line,
+/// returning how many there are.
+///
+/// The base is the existing base on top of the read-only base. Empty files are skipped.
+fn check_minimal(
+    input_filenames: &[String],
+    docs: &[Yaml],
+    read_only_base: Option<&Yaml>,
+    existing_base: Option<&Yaml>,
+    options: &DiffOptions,
+) -> Result<usize, Box<dyn Error>> {
+    let base = apply_diff(read_only_base, existing_base, options).unwrap_or(Yaml::Null);
+    let display = |path: &str| if path.is_empty() { "(whole file)".to_string() } else { path.to_string() };
+    let mut out = std::io::stdout().lock();
+    let mut problems = 0;
+    for (filename, doc) in input_filenames.iter().zip(docs) {
+        for path in redundant_paths(doc, &base, options) {
+            writeln!(out, "{}: {}: already set to the same value in the base", filename, display(&path))?;
+            problems += 1;
+        }
+    }
+    let present: Vec<Option<&Yaml>> =
+        docs.iter().filter(|doc| !is_empty_document(doc)).map(Some).collect();
+    for path in duplicated_diff_paths(&present) {
+        writeln!(out, "{}: identical in every file; move it to the base", display(&path))?;
+        problems += 1;
+    }
+    Ok(problems)
+}
+
+/// Checks the inputs, as diffed against the read-only base, before the extraction.
+pub fn inputs(context: &Context, diffs_refs: &[&Yaml]) -> Result<(), Box<dyn Error>> {
+    let Context { args, options, .. } = context;
+    let input_filenames = &context.inputs.filenames;
+
+    // Intentional duplicates exist, so the check is opt-in
+    if let Some(mode) = args.duplicate_inputs {
+        let pairs = duplicate_inputs(diffs_refs);
+        for &(i, j) in &pairs {
+            match mode {
+                DuplicateInputs::Warn => warn!("{} and {} are identical.", input_filenames[i], input_filenames[j]),
+                DuplicateInputs::Error => error!("{} and {} are identical.", input_filenames[i], input_filenames[j]),
+            }
+        }
+        if mode == DuplicateInputs::Error && !pairs.is_empty() {
+            return Err(format!("{} pair(s) of identical inputs", pairs.len()).into());
+        }
+    }
+
+    // A file setting both an old key and its new name keeps one of them
+    if let Some(ref aliases) = options.key_aliases {
+        let mut conflicts = 0;
+        for (filename, doc) in input_filenames.iter().zip(diffs_refs) {
+            let doc = match options.expand_dotted_keys {
+                true => Cow::Owned(expand_dotted_keys(doc)),
+                false => Cow::Borrowed(*doc),
+            };
+            for (old, new) in alias_conflicts(&doc, aliases) {
+                conflicts += 1;
+                match aliases.on_conflict {
+                    AliasConflict::Fail => error!("{} sets both {} and its new name {}.", filename, old, new),
+                    AliasConflict::PreferNew => warn!("{} sets both {} and its new name {}; keeping {}.", filename, old, new, new),
+                    AliasConflict::PreferOld => warn!("{} sets both {} and its new name {}; keeping {}.", filename, old, new, old),
+                }
+            }
+        }
+        if aliases.on_conflict == AliasConflict::Fail && conflicts > 0 {
+            return Err(format!("{} key alias conflict(s)", conflicts).into());
+        }
+    }
+
+    // An extracted base passed among the inputs counts its values once more
+    let base_like = base_like_inputs(diffs_refs, options);
+    for &i in &base_like {
+        warn!(
+            "{} only sets values every other input sets the same; if it is an extracted base, leave it out with --exclude.",
+            input_filenames[i]
+        );
+    }
+    if args.strict && !base_like.is_empty() {
+        return Err(format!("{} input(s) look like an extracted base", base_like.len()).into());
+    }
+
+    // Values of different types at a path stay in every diff, which strict types rejects
+    if let Some(mode) = args.strict_types {
+        let normalized = normalize_all(diffs_refs, options);
+        let normalized_refs: Vec<&Yaml> = normalized.iter().map(|doc| doc.as_ref()).collect();
+        let conflicts = type_conflicts(&normalized_refs, mode);
+        for conflict in &conflicts {
+            error!("Type conflict at {}", conflict.describe(input_filenames));
+        }
+        if !conflicts.is_empty() {
+            return Err(format!("{} type conflict(s)", conflicts.len()).into());
+        }
+    }
+
+    // A scalar where other files have a map or an array is almost always a mistake
+    if args.strict_collection_scalar {
+        let normalized = normalize_all(diffs_refs, options);
+        let normalized_refs: Vec<&Yaml> = normalized.iter().map(|doc| doc.as_ref()).collect();
+        let conflicts = collection_scalar_conflicts(&normalized_refs);
+        for conflict in &conflicts {
+            error!("Scalar and collection at {}", conflict.describe(input_filenames));
+        }
+        if !conflicts.is_empty() {
+            return Err(format!("{} collection-scalar conflict(s)", conflicts.len()).into());
+        }
+    }
+
+    // Ordered sets hold each element once; duplicates would silently be compared positionally
+    if options.array_strategy == ArrayStrategy::OrderedSet {
+        let normalized = normalize_all(diffs_refs, options);
+        let mut duplicated = 0;
+        for (doc, filename) in normalized.iter().zip(input_filenames) {
+            for path in duplicated_arrays(doc) {
+                error!("{}: the array at {} holds duplicate elements, which ordered sets cannot", filename, path);
+                duplicated += 1;
+            }
+        }
+        if duplicated > 0 {
+            return Err(format!("{} array(s) with duplicate elements", duplicated).into());
+        }
+    }
+    Ok(())
+}
+
+/// Checks the extracted base and per-file diffs, exiting with [`EXIT_NO_BASE`] if
+/// `--require-base` is set and there is no base.
+pub fn extraction(
+    context: &Context,
+    merged: &MergedInputs,
+    diffs_refs: &[&Yaml],
+    base: Option<&Yaml>,
+    per_file_diffs: &[Option<Cow<Yaml>>],
+) -> Result<(), Box<dyn Error>> {
+    let Context { args, options, bases, .. } = context;
+
+    // A near-empty base usually means the inputs have little to do with each other
+    if args.unrelated_inputs != UnrelatedInputs::Ignore {
+        let coverage = match bases.read_only {
+            // What the inputs share with the read-only base is covered too, so they are measured
+            // whole, against the read-only base and the base together
+            Some(ref helm) => {
+                let full_base = apply_diff(Some(helm), base, options).unwrap_or(Yaml::Null);
+                let inputs: Vec<&Yaml> = merged.get()?.iter().map(|obj| obj.as_ref()).collect();
+                let full_diffs: Vec<Option<Cow<Yaml>>> =
+                    inputs.iter().map(|obj| compute_diff_with_options(obj, &full_base, options)).collect();
+                let diff_values: Vec<Option<&Yaml>> = full_diffs.iter().map(|d| d.as_deref()).collect();
+                mean_base_coverage_with(&inputs, &diff_values, args.leaf_counting)
+            }
+            None => {
+                let diff_values: Vec<Option<&Yaml>> = per_file_diffs.iter().map(|d| d.as_deref()).collect();
+                mean_base_coverage_with(diffs_refs, &diff_values, args.leaf_counting)
+            }
+        };
+        if let Some(coverage) = coverage.filter(|c| *c < args.min_base_coverage) {
+            let message = format!(
+                "The base covers {:.1}% of the average input's leaves (less than {:.1}%); the inputs may be unrelated files. Check the input files, or lower --min-base-coverage.",
+                100.0 * coverage,
+                100.0 * args.min_base_coverage
+            );
+            if args.unrelated_inputs == UnrelatedInputs::Error || args.strict {
+                error!("{}", message);
+                return Err("the inputs may be unrelated".into());
+            }
+            warn!("{}", message);
+        }
+    }
+
+    // A denied value in the base is set the same in several environments
+    if let (Some(denylist), Some(base_yaml)) = (&context.denylist, base) {
+        let denied = denied_values(base_yaml, denylist);
+        for (path, rule) in &denied {
+            error!("The base holds a denied value at {} (matching {}); it is shared between environments.", path, rule);
+        }
+        if !denied.is_empty() {
+            return Err(format!("{} denied value(s) in the base", denied.len()).into());
+        }
+    }
+
+    // Values identical in every diff should have been hoisted into the base
+    // Ignored key paths are meant to stay in every diff
+    let checked_diffs: Vec<Option<Cow<Yaml>>> = per_file_diffs
+        .iter()
+        .map(|d| d.as_deref().map(|d| strip_ignored(d, &options.ignored_paths)))
+        .map(|d| d.filter(|d| !matches!(d.as_ref(), Yaml::Hash(hash) if hash.is_empty())))
+        .collect();
+    let diff_values: Vec<Option<&Yaml>> = checked_diffs.iter().map(|d| d.as_deref()).collect();
+    let duplicated = duplicated_diff_paths(&diff_values);
+    for path in &duplicated {
+        warn!("Value at '{}' is identical in every diff and could be hoisted into the base.", path);
+    }
+    if args.strict && !duplicated.is_empty() {
+        return Err(format!("{} value(s) duplicated across all diffs", duplicated.len()).into());
+    }
+
+    // Paths where nearly every file differs are poor candidates for central management
+    if !args.no_fan_out_warning {
+        let present: Vec<Cow<Yaml>> = diffs_refs
+            .iter()
+            .filter(|doc| !is_empty_document(doc))
+            .map(|doc| strip_ignored(doc, &options.ignored_paths))
+            .collect();
+        let present: Vec<&Yaml> = present.iter().map(|doc| doc.as_ref()).collect();
+        for (path, distinct) in fan_out_paths(&present, args.max_fan_out) {
+            warn!(
+                "'{}' has {} distinct values across the inputs (more than {}); it may be better kept out of the base.",
+                path, distinct, args.max_fan_out
+            );
+        }
+    }
+
+    if (args.explain_no_base || args.require_base) && base.is_none() {
+        for line in empty_base_summary(&explain_empty_base(diffs_refs, options)) {
+            warn!("{}", line);
+        }
+    }
+
+    if args.require_base && base.is_none() {
+        error!(
+            "No common base found among {} input files; try lowering the quorum (currently {}%) or check the input set.",
+            context.inputs.filenames.len(),
+            args.quorum
+        );
+        std::process::exit(EXIT_NO_BASE);
+    }
+    Ok(())
+}
@@ -0,0 +1,20 @@
+//! `--compare`: how the files of two directories differ.
+
+use std::error::Error;
+use std::path::Path;
+
+use log::info;
+use yabe::compare::{compare_dirs_with_lines, comparison_report};
+use yabe::options::DiffOptions;
+use yabe::output::write_yaml;
+
+use crate::Args;
+
+/// Compares the files of the two directories and prints the report.
+pub fn run(dirs: &[String], args: &Args, options: &DiffOptions) -> Result<(), Box<dyn Error>> {
+    info!("Comparing {} with {}", dirs[0], dirs[1]);
+    let comparisons =
+        compare_dirs_with_lines(Path::new(&dirs[0]), Path::new(&dirs[1]), options, args.compare_context, args.compare_lines)?;
+    write_yaml(&comparison_report(&comparisons), &mut std::io::stdout().lock())?;
+    Ok(())
+}
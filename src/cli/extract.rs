@@ -0,0 +1,257 @@
+//! The default mode: extracting the common base of the inputs, and every file's diff from it.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs;
+use std::time::Instant;
+
+use log::{info, warn};
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::Yaml;
+use yabe::cache::Cache;
+use yabe::diff::{compute_diff_with_options, diff_and_common_timed, diff_and_common_with_options};
+use yabe::graph::{render_graph, GraphOptions};
+use yabe::group::{split_into_groups, Grouping, Groups};
+use yabe::merge::apply_diff;
+use yabe::output::{index_unchanged_slots, UnchangedSlots};
+use yabe::partition::partition_inputs;
+use yabe::preview::previews;
+use yabe::report::{default_paths, override_coverage_with, override_sizes_with, size_report};
+use yabe::stats::run_stats_with;
+use yabe::timing::{timed, Timings};
+
+use super::cache::{cached_extraction, cached_input_diffs};
+use super::minimize::minimize_excluded;
+use super::write::{print_partitions, print_stream, write_diffs, write_doc, write_partitions, write_previews};
+use super::{check, check_empty_inputs, write, Context, MergedInputs};
+use crate::MAX_TIMED_SUBTREES;
+
+/// Extracts the base and per-file diffs of the inputs and writes them, with the reports the
+/// arguments ask for.
+pub fn run(context: &Context, cache: Option<&Cache>, mut timings: Option<Timings>) -> Result<(), Box<dyn Error>> {
+    let Context { args, options, inputs, bases, output, .. } = context;
+    let input_filenames = &inputs.filenames;
+    let merged = MergedInputs::new(inputs, bases.existing.as_ref(), options);
+
+    if let (true, Some(helm)) = (args.report_defaults, &bases.read_only) {
+        for (filename, obj) in input_filenames.iter().zip(merged.get()?) {
+            let defaults = default_paths(obj, helm);
+            if defaults.is_empty() {
+                info!("{} overrides every read-only base key.", filename);
+            } else {
+                info!(
+                    "{} leaves {} read-only base key(s) at default: {}",
+                    filename,
+                    defaults.len(),
+                    defaults.join(", ")
+                );
+            }
+        }
+    }
+
+    // Compute diffs between each merged object and read-only base
+    let diffs: Vec<_> = if let Some(cache) = cache {
+        // Inputs are only parsed on a cache miss, where they are also diffed against the read-only base
+        let cached = timed(&mut timings, "input diffs (cached)", || cached_input_diffs(cache, inputs, bases, options))?;
+        cached.into_iter().map(Cow::Owned).collect()
+    } else if let Some(ref helm) = bases.read_only {
+        info!("Computing diffs between merged files and helm values.");
+        let merged_objs = merged.get()?;
+        timed(&mut timings, "read-only base diffs", || {
+            merged_objs
+                .iter()
+                .map(|obj| {
+                    // An input identical to the read-only base still takes part, unlike an empty one
+                    compute_diff_with_options(obj.as_ref(), helm, options)
+                        .unwrap_or_else(|| Cow::Owned(Yaml::Hash(Hash::new())))
+                })
+                .collect()
+        })
+    } else {
+        // No read-only base provided values; use merged_objs as diffs
+        merged.get()?.to_vec()
+    };
+
+    // Empty inputs are `null` all the way through and are left out of the extraction
+    let diffs_refs: Vec<&Yaml> = diffs.iter().map(|cow| cow.as_ref()).collect();
+    check_empty_inputs(input_filenames, &diffs_refs, args.empty_inputs)?;
+    check::inputs(context, &diffs_refs)?;
+
+    // Heterogeneous inputs, such as rendered manifests, get a base per partition instead
+    if !args.partition_by.is_empty() {
+        let partitioned = partition_inputs(&diffs_refs, &args.partition_by, options)?;
+        for &i in &partitioned.unpartitioned {
+            info!("{} is in no partition of {}; it is left whole.", input_filenames[i], args.partition_by.join(", "));
+        }
+        let per_file_diffs: Vec<Option<Cow<Yaml>>> = partitioned.diffs.iter().map(|d| d.as_ref().map(Cow::Borrowed)).collect();
+        if args.stdout {
+            print_partitions(&partitioned, input_filenames, &output.config)?;
+            print_stream(None, None, &output.base_docs, &per_file_diffs, input_filenames, &output.config, output.style)?;
+        } else {
+            write_partitions(&partitioned, input_filenames, &args.out_folder, &output.config)?;
+            write_diffs(&per_file_diffs, input_filenames, args.inplace, &args.out_folder, output)?;
+        }
+        info!("Program completed successfully.");
+        return Ok(());
+    }
+
+    // Now compute common base and per-file diffs among the diffs
+    info!(
+        "Computing common base and per-file diffs among the diffs with quorum {}%.",
+        args.quorum
+    );
+    let started = timings.is_some().then(Instant::now);
+    let (result, key_times) = match cache {
+        Some(cache) => cached_extraction(cache, inputs, bases, &diffs_refs, options, timings.is_some()),
+        None if timings.is_some() => diff_and_common_timed(&diffs_refs, options),
+        None => (diff_and_common_with_options(&diffs_refs, options), vec![]),
+    };
+    if let (Some(timings), Some(started)) = (timings.as_mut(), started) {
+        timings.record("extraction", started.elapsed()).subtrees = key_times;
+    }
+    let (base, per_file_diffs) = result.into_parts();
+    check::extraction(context, &merged, &diffs_refs, base.as_deref(), &per_file_diffs)?;
+
+    // Inputs sharing more than the global base get a group base in between
+    let groups = args.groups.map(|max_groups| {
+        let grouping = Grouping {
+            max_groups,
+            max_distance: args.group_max_distance,
+            min_size: args.group_min_size,
+            max_bases: args.max_group_bases,
+        };
+        let diff_values: Vec<Option<&Yaml>> = per_file_diffs.iter().map(|d| d.as_deref()).collect();
+        let groups = timed(&mut timings, "grouping", || split_into_groups(&diffs_refs, &diff_values, &grouping, options));
+        info!("Grouped inputs into {} group(s) with a base of their own.", groups.groups.len());
+        groups
+    });
+    let per_file_diffs: Vec<Option<Cow<Yaml>>> = match groups {
+        Some(ref groups) => groups.diffs.iter().map(|d| d.as_ref().map(Cow::Borrowed)).collect(),
+        None => per_file_diffs,
+    };
+
+    // A grouped input's group base is part of its overrides
+    let overrides: Vec<Option<Yaml>> = per_file_diffs
+        .iter()
+        .enumerate()
+        .map(|(i, diff)| {
+            let group_base = groups.as_ref().and_then(|groups| groups.group_of(i)).map(|group| &group.base);
+            apply_diff(group_base, diff.as_deref(), options)
+        })
+        .collect();
+    let diff_values: Vec<Option<&Yaml>> = overrides.iter().map(Option::as_ref).collect();
+    report(context, &diffs_refs, base.as_deref(), groups.as_ref(), &diff_values)?;
+
+    let excluded_diffs = if args.minimize_excluded {
+        let full_base = apply_diff(bases.read_only.as_ref(), base.as_deref(), options);
+        minimize_excluded(&inputs.excluded_filenames, bases.existing.as_ref(), full_base.as_ref(), options)?
+    } else {
+        Vec::new()
+    };
+
+    // Unchanged positional slots are left out of the diffs instead of written as null, against
+    // what each diff applies to; the base's own nulls are values
+    let (per_file_diffs, excluded_diffs) = if args.unchanged_slots == UnchangedSlots::Indexed {
+        let full_base = apply_diff(bases.read_only.as_ref(), base.as_deref(), options);
+        let indexed = |diff: &Option<Cow<Yaml>>, onto: Option<&Yaml>| {
+            diff.as_deref().map(|diff| Cow::Owned(index_unchanged_slots(diff, onto)))
+        };
+        let per_file_diffs = per_file_diffs
+            .iter()
+            .enumerate()
+            .map(|(i, diff)| {
+                let group_base = groups.as_ref().and_then(|groups| groups.group_of(i)).map(|group| &group.base);
+                indexed(diff, apply_diff(full_base.as_ref(), group_base, options).as_ref())
+            })
+            .collect();
+        (per_file_diffs, excluded_diffs.iter().map(|diff| indexed(diff, full_base.as_ref())).collect())
+    } else {
+        (per_file_diffs, excluded_diffs)
+    };
+
+    if let Some(ref base_yaml) = base {
+        for path in output.base_docs.unmatched(base_yaml) {
+            info!("Documented path {} is not in the base; its comment is not written.", path);
+        }
+    }
+
+    #[cfg(feature = "codegen")]
+    if let Some(ref path) = args.rust_structs {
+        // The read-only base's defaults are part of every configuration
+        let full_base = apply_diff(bases.read_only.as_ref(), base.as_deref(), options);
+        let diffs: Vec<Option<&Yaml>> = per_file_diffs.iter().map(|diff| diff.as_deref()).collect();
+        let code = yabe::codegen::rust_structs(full_base.as_ref(), &diffs, options, "Values")
+            .map_err(|e| format!("cannot generate Rust structs: {}", e))?;
+        fs::write(path, code)?;
+        info!("Rust structs written to {}", path);
+    }
+
+    timed(&mut timings, "serialization", || {
+        write::results(context, base.as_deref(), groups.as_ref(), &per_file_diffs, &excluded_diffs)
+    })?;
+
+    if let Some(ref timings) = timings {
+        eprint!("{}", timings.report(MAX_TIMED_SUBTREES));
+    }
+    info!("Program completed successfully.");
+    Ok(())
+}
+
+/// Reports on each file's overrides: the files exceeding `--max-overrides`, the size report,
+/// the run statistics, the graph and the previews.
+fn report(
+    context: &Context,
+    diffs_refs: &[&Yaml],
+    base: Option<&Yaml>,
+    groups: Option<&Groups>,
+    diff_values: &[Option<&Yaml>],
+) -> Result<(), Box<dyn Error>> {
+    let Context { args, options, inputs, bases, output, .. } = context;
+    let input_filenames = &inputs.filenames;
+
+    // Files far from the base are the first candidates for cleanup
+    let sizes = override_sizes_with(diff_values, args.leaf_counting);
+    if let Some(max_leaves) = args.max_overrides {
+        let exceeding: Vec<&(usize, usize)> = sizes.iter().filter(|(_, leaves)| *leaves > max_leaves).collect();
+        for (i, leaves) in &exceeding {
+            warn!(
+                "{} overrides {} leaves on top of the base (more than {}).",
+                input_filenames[*i], leaves, max_leaves
+            );
+        }
+        if args.strict && !exceeding.is_empty() {
+            return Err(format!("{} file(s) exceed --max-overrides", exceeding.len()).into());
+        }
+    }
+    if let Some(ref report_path) = args.size_report {
+        info!("Writing override size report to {}", report_path);
+        let coverage: Vec<f64> = diffs_refs
+            .iter()
+            .zip(diff_values)
+            .map(|(obj, diff)| override_coverage_with(obj, *diff, args.leaf_counting))
+            .collect();
+        let report = size_report(input_filenames, &sizes, &coverage, args.max_overrides);
+        write_doc(report_path, &report, &Yaml::Null)?;
+    }
+    if let Some(ref stats_path) = args.stats {
+        info!("Writing run statistics to {}", stats_path);
+        let group_count = groups.map_or(0, |groups| groups.groups.len());
+        fs::write(stats_path, run_stats_with(diffs_refs, base, diff_values, group_count, args.leaf_counting).to_json())?;
+    }
+
+    if let Some(ref graph_path) = args.graph {
+        let graph_options = GraphOptions {
+            format: args.graph_format,
+            max_depth: args.graph_depth,
+            subtrees: args.graph_subtrees.clone(),
+        };
+        info!("Writing {} graph to {}", graph_options.format, graph_path);
+        fs::write(graph_path, render_graph(base, diff_values, input_filenames, &graph_options))?;
+    }
+
+    if let Some(ref preview_dir) = args.preview_dir {
+        let previews = previews(bases.read_only.as_ref(), base, diff_values, diffs_refs, options);
+        write_previews(preview_dir, args.preview_changed_only, &previews, input_filenames, &output.config)?;
+    }
+    Ok(())
+}
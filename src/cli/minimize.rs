@@ -0,0 +1,94 @@
+//! `--minimize`: promoting what overlays share into their base, and leaving only what each one
+//! changes; and `--minimize-excluded`, diffing the excluded inputs against the extracted base.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use log::{debug, info};
+use yaml_rust2::Yaml;
+use yabe::deep_equal::deep_equal;
+use yabe::diff::compute_diff_with_options;
+use yabe::input::{is_empty_document, parse_document};
+use yabe::merge::merge_yaml_with_options;
+use yabe::minimize::{minimize_overlays, read_overlays, MinimizeStages};
+use yabe::options::DiffOptions;
+
+use super::write::write_doc;
+use crate::Args;
+
+/// Minimizes the overlays of a directory against a base file, writing them in place or to the
+/// output folder.
+pub fn run(paths: &[String], args: &Args, options: &DiffOptions, config: &Yaml) -> Result<(), Box<dyn Error>> {
+    let (base_path, overlay_dir) = (&paths[0], Path::new(&paths[1]));
+    info!("Minimizing the overlays in {} against {}", paths[1], base_path);
+    let base = parse_document(&fs::read_to_string(base_path)?)?;
+    let (names, docs): (Vec<String>, Vec<Yaml>) = read_overlays(overlay_dir)?.into_iter().unzip();
+    let stages = MinimizeStages {
+        promote: !args.no_promote,
+        minimize: !args.no_minimize,
+    };
+    let refs: Vec<&Yaml> = docs.iter().collect();
+    let minimized = minimize_overlays(&base, &refs, &stages, options);
+
+    // In place, only what changed is rewritten, so a second run touches nothing
+    let (base_target, overlay_dir_target) = if args.inplace {
+        (base_path.as_str(), overlay_dir)
+    } else {
+        (args.base_out_path.as_str(), Path::new(&args.out_folder))
+    };
+    if minimized.promoted.is_some() || !args.inplace {
+        info!("Writing base YAML to {}", base_target);
+        write_doc(base_target, &minimized.base, config)?;
+    } else {
+        info!("Nothing to promote into {}.", base_path);
+    }
+    for ((name, doc), overlay) in names.iter().zip(&docs).zip(&minimized.overlays) {
+        let unchanged = match overlay {
+            Some(overlay) => deep_equal(overlay, doc),
+            None => is_empty_document(doc),
+        };
+        if args.inplace && unchanged {
+            debug!("{} is already minimal.", name);
+            continue;
+        }
+        let target = overlay_dir_target.join(name);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match overlay {
+            Some(overlay) => write_doc(&target, overlay, config)?,
+            None => fs::write(&target, "")?,
+        }
+        info!("Overlay {} written to {}", name, target.display());
+    }
+    info!("Program completed successfully.");
+    Ok(())
+}
+
+/// Diffs each excluded input, merged with the existing base if any, against the extracted base
+/// on top of the read-only base.
+pub fn minimize_excluded(
+    excluded_filenames: &[String],
+    existing_base: Option<&Yaml>,
+    full_base: Option<&Yaml>,
+    options: &DiffOptions,
+) -> Result<Vec<Option<Cow<'static, Yaml>>>, Box<dyn Error>> {
+    let mut diffs = Vec::new();
+    for filename in excluded_filenames {
+        info!("Reading excluded input file: {}", filename);
+        let doc = parse_document(&fs::read_to_string(filename)?)?;
+        let merged = match existing_base {
+            Some(base) => merge_yaml_with_options(base, &doc, options).into_owned(),
+            None => doc,
+        };
+        let diff = match full_base {
+            _ if is_empty_document(&merged) => None,
+            Some(base) => compute_diff_with_options(&merged, base, options).map(Cow::into_owned),
+            None => Some(merged),
+        };
+        diffs.push(diff.map(Cow::Owned));
+    }
+    Ok(diffs)
+}
@@ -0,0 +1,194 @@
+//! The modes of the command line tool, run by `main` once the arguments are read and checked.
+//!
+//! [`setup`] turns the arguments into options and reads the files every mode needs. The modes
+//! that leave the extraction aside each have a module: [`compare`], [`minimize`], [`analysis`]
+//! (the schema, template and chain modes) and [`check`], which also holds the checks of the
+//! default mode. That mode, [`extract`], reads and stores its results with [`cache`] and writes
+//! them with [`write`].
+
+pub mod analysis;
+pub mod cache;
+pub mod check;
+pub mod compare;
+pub mod extract;
+pub mod minimize;
+pub mod setup;
+pub mod write;
+
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::error::Error;
+use std::path::Path;
+
+use log::{info, warn};
+use yaml_rust2::Yaml;
+use yabe::comments::DocComments;
+use yabe::denylist::Denylist;
+use yabe::directives::Directives;
+use yabe::input::{is_empty_document, parse_document, read_source, EmptyInputs};
+use yabe::merge::merge_yaml_with_options;
+use yabe::options::DiffOptions;
+use yabe::output::OutputStyle;
+use yabe::timing::{timed, Timings};
+
+use crate::Args;
+
+/// Exit code used when `--require-base` is set and no base was extracted.
+pub const EXIT_NO_BASE: i32 = 2;
+/// Exit code of `--check` when a file is not minimal.
+pub const EXIT_NOT_MINIMAL: i32 = 1;
+
+/// The input files of a run: their names, and their contents once read.
+#[derive(Default)]
+pub struct Inputs {
+    /// The inputs taking part in the extraction, as named on the command line.
+    pub filenames: Vec<String>,
+    /// The inputs left out of the extraction, which `--minimize-excluded` diffs against its base.
+    pub excluded_filenames: Vec<String>,
+    /// The contents of [`Inputs::filenames`], once read.
+    pub contents: Vec<String>,
+    docs: OnceCell<Vec<Yaml>>,
+}
+
+impl Inputs {
+    /// Reads the contents of the inputs taking part in the extraction.
+    pub fn read(&mut self, timings: &mut Option<Timings>) -> Result<(), Box<dyn Error>> {
+        let (filenames, contents) = (&self.filenames, &mut self.contents);
+        timed(timings, "reading", || -> Result<(), Box<dyn Error>> {
+            for filename in filenames {
+                info!("Reading input file: {}", filename);
+                contents.push(read_source(Path::new(filename))?);
+            }
+            Ok(())
+        })
+    }
+
+    /// Parses the inputs on first use, returning the same documents on every later call, so
+    /// whichever mode reads them has them and a cached run may parse none.
+    ///
+    /// A file without documents is an empty input, like a `null` document.
+    pub fn docs(&self) -> Result<&[Yaml], Box<dyn Error>> {
+        if let Some(docs) = self.docs.get() {
+            return Ok(docs);
+        }
+        let docs = self.contents.iter().map(|content| parse_document(content)).collect::<Result<Vec<_>, _>>()?;
+        Ok(self.docs.get_or_init(|| docs))
+    }
+}
+
+/// The read-only base and the existing base, with the sources they were read from.
+#[derive(Default)]
+pub struct Bases {
+    /// The read-only base: Helm chart values, or a chart's defaults.
+    pub read_only: Option<Yaml>,
+    pub read_only_content: Option<String>,
+    /// The existing base every input is merged onto before diffing.
+    pub existing: Option<Yaml>,
+    pub existing_content: Option<String>,
+}
+
+impl Bases {
+    /// Describes everything but the inputs that their diffs depend on, for cache keys.
+    pub fn context(&self, options: &DiffOptions) -> String {
+        format!("{:?}\n{:?}\n{:?}", options, self.read_only_content, self.existing_content)
+    }
+}
+
+/// Everything the modes read: the arguments, with the options and files they set up.
+pub struct Context<'a> {
+    pub args: &'a Args,
+    pub options: DiffOptions,
+    pub inputs: Inputs,
+    pub bases: Bases,
+    pub output: Output,
+    /// Values the base must not hold.
+    pub denylist: Option<Denylist>,
+}
+
+/// How and where the results are written.
+pub struct Output {
+    /// The sort configuration, `null` for none.
+    pub config: Yaml,
+    /// Comments written above the base's keys.
+    pub base_docs: DocComments,
+    pub style: OutputStyle,
+    /// Directives written around every file, those of the first input with
+    /// `--preserve-directives`.
+    pub directives: Directives,
+    /// The file whose diff alone is printed, as named among the inputs.
+    pub stdout_diff: Option<String>,
+    /// The subchart of every input, to write an umbrella chart's values.
+    #[cfg(feature = "helm")]
+    pub umbrella: Option<yabe::helm::Umbrella>,
+}
+
+/// Applies the empty input policy, returning the names of the empty inputs.
+///
+/// With [`EmptyInputs::Error`] any empty input fails the run.
+pub fn check_empty_inputs(
+    input_filenames: &[String],
+    docs: &[&Yaml],
+    policy: EmptyInputs,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let empty: Vec<String> = input_filenames
+        .iter()
+        .zip(docs)
+        .filter(|(_, doc)| is_empty_document(doc))
+        .map(|(filename, _)| filename.clone())
+        .collect();
+    if empty.is_empty() {
+        return Ok(empty);
+    }
+    match policy {
+        EmptyInputs::Error => Err(format!("empty input file(s): {}", empty.join(", ")).into()),
+        EmptyInputs::Ignore => {
+            for filename in &empty {
+                warn!("{} is empty; ignoring it.", filename);
+            }
+            Ok(empty)
+        }
+    }
+}
+
+/// Each input merged onto the existing base, if any, on first use, the same documents on every
+/// later use.
+pub struct MergedInputs<'a> {
+    inputs: &'a Inputs,
+    existing_base: Option<&'a Yaml>,
+    options: &'a DiffOptions,
+    merged: OnceCell<Vec<Cow<'a, Yaml>>>,
+}
+
+impl<'a> MergedInputs<'a> {
+    pub fn new(inputs: &'a Inputs, existing_base: Option<&'a Yaml>, options: &'a DiffOptions) -> Self {
+        MergedInputs { inputs, existing_base, options, merged: OnceCell::new() }
+    }
+
+    /// Returns the merged inputs, parsing and merging them on first use.
+    pub fn get(&self) -> Result<&[Cow<'a, Yaml>], Box<dyn Error>> {
+        let docs = self.inputs.docs()?;
+        Ok(self.merged.get_or_init(|| match self.existing_base {
+            Some(base) => self
+                .inputs
+                .filenames
+                .iter()
+                .zip(docs)
+                .map(|(filename, obj)| {
+                    let merged = merge_yaml_with_options(base, obj, self.options);
+                    info!("Merged base with input file: {}", filename);
+                    merged
+                })
+                .collect(),
+            // No existing base; use objs as merged_objs
+            None => docs.iter().map(Cow::Borrowed).collect(),
+        }))
+    }
+}
+
+/// Returns the file name of a path without its extension, or `fallback`.
+pub fn file_stem<'a>(path: &'a str, fallback: &'a str) -> &'a str {
+    Path::new(path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(fallback)
+}
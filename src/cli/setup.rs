@@ -0,0 +1,282 @@
+//! Turning the command line arguments into options, and reading the files every mode needs.
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use log::{info, warn};
+use yaml_rust2::{Yaml, YamlLoader};
+use yabe::comments::DocComments;
+use yabe::denylist::Denylist;
+use yabe::directives::{disagreeing_versions, scan_directives, Directives};
+use yabe::ignore::{parse_ignore_file, IgnoreRules, KeyPattern, IGNORE_FILE};
+use yabe::input::{expand_manifest_args, parse_document, partition_excluded, read_source};
+use yabe::options::{
+    ArrayStrategy, AtomicPath, Bucketing, DiffOptions, ExcludedKeys, KeyAliases, StringCoercion, ValueBuckets,
+};
+use yabe::output::{OutputStyle, UnchangedSlots};
+use yabe::sorter::{order_template, unlisted_keys};
+
+use super::{Bases, Inputs, Output};
+use crate::Args;
+
+/// Reads the rules of the ignore file: the given one, or `.yabeignore` if it exists.
+pub fn read_ignore_rules(path: Option<&str>, disabled: bool) -> Result<IgnoreRules, Box<dyn Error>> {
+    let path = match path {
+        _ if disabled => return Ok(IgnoreRules::default()),
+        Some(path) => path,
+        None if Path::new(IGNORE_FILE).exists() => IGNORE_FILE,
+        None => return Ok(IgnoreRules::default()),
+    };
+    info!("Reading ignore file: {}", path);
+    let source = read_source(Path::new(path))?;
+    parse_ignore_file(&source).map_err(|message| format!("{}: {}", path, message).into())
+}
+
+/// Lists the input files, without those the ignore file leaves out and split into those taking
+/// part in the extraction and those `--exclude` leaves out of it.
+pub fn input_files(args: &Args, ignore_rules: &IgnoreRules) -> Result<Inputs, Box<dyn Error>> {
+    let (input_filenames, excluded_filenames, unmatched) =
+        partition_excluded(expand_manifest_args(&args.input_files)?, &args.exclude);
+    for path in &unmatched {
+        warn!("--exclude {} matches no input file.", path);
+    }
+    let (input_filenames, ignored): (Vec<String>, Vec<String>) =
+        input_filenames.into_iter().partition(|filename| !ignore_rules.ignores_file(filename));
+    let (excluded_filenames, ignored_excluded): (Vec<String>, Vec<String>) =
+        excluded_filenames.into_iter().partition(|filename| !ignore_rules.ignores_file(filename));
+    for filename in ignored.iter().chain(&ignored_excluded) {
+        info!("Ignoring {}, which matches the ignore file.", filename);
+    }
+    for filename in &excluded_filenames {
+        info!("Excluding {} from the extraction.", filename);
+    }
+    if input_filenames.is_empty() && args.compare.is_none() && args.minimize.is_none() {
+        return Err("every input file is excluded".into());
+    }
+    Ok(Inputs {
+        filenames: input_filenames,
+        excluded_filenames,
+        ..Inputs::default()
+    })
+}
+
+/// Returns the file whose diff `--stdout-diff` prints, as named among the inputs.
+pub fn stdout_diff(args: &Args, inputs: &Inputs) -> Result<Option<String>, Box<dyn Error>> {
+    let Some(ref name) = args.stdout_diff else {
+        return Ok(None);
+    };
+    let excluded = inputs.excluded_filenames.iter().filter(|_| args.minimize_excluded);
+    let found = inputs.filenames.iter().chain(excluded).find(|filename| same_file_name(filename, name));
+    Ok(Some(found.ok_or_else(|| format!("--stdout-diff {}: not among the input files", name))?.clone()))
+}
+
+/// Builds the diff options, failing on malformed patterns and on options that do not go
+/// together.
+pub fn diff_options(args: &Args, ignore_rules: &IgnoreRules) -> Result<DiffOptions, Box<dyn Error>> {
+    // The ignore file's key paths and the command line's both apply
+    let mut ignored_paths = ignore_rules.key_paths.clone();
+    for path in &args.ignore_keys {
+        ignored_paths.push(KeyPattern::new(path).map_err(|message| format!("--ignore-key: {}", message))?);
+    }
+    let atomic_paths = args
+        .atomic_keys
+        .iter()
+        .map(|path| AtomicPath::new(path).map_err(|message| format!("--atomic-key: {}", message)))
+        .collect::<Result<Vec<AtomicPath>, String>>()?;
+
+    let key_aliases = match args.key_aliases {
+        Some(ref path) => {
+            info!("Reading key aliases: {}", path);
+            let doc = parse_document(&fs::read_to_string(path)?)?;
+            Some(KeyAliases::from_yaml(&doc, args.alias_conflict).map_err(|message| format!("{}: {}", path, message))?)
+        }
+        None => None,
+    };
+
+    let excluded_keys = if args.exclude_key_patterns.is_empty() {
+        None
+    } else {
+        Some(ExcludedKeys::new(&args.exclude_key_patterns).map_err(|message| format!("--exclude-key-pattern: {}", message))?)
+    };
+
+    let value_buckets = args
+        .buckets
+        .iter()
+        .map(|bucket| parse_bucket(bucket).map_err(|message| format!("--bucket {}: {}", bucket, message)))
+        .collect::<Result<Vec<ValueBuckets>, String>>()?;
+
+    if args.group_min_size < 2 {
+        return Err(format!("--group-min-size {}: a group has at least 2 files", args.group_min_size).into());
+    }
+    if !(0.0..=1.0).contains(&args.min_base_coverage) {
+        return Err(format!("--min-base-coverage {}: expected a fraction between 0 and 1", args.min_base_coverage).into());
+    }
+
+    let options = DiffOptions {
+        quorum: (args.quorum as f64) / 100.0,
+        null_handling: args.null_handling,
+        array_strategy: args.array_strategy.clone(),
+        expand_dotted_keys: args.expand_dotted_keys,
+        blob_threshold: args.blob_threshold,
+        base_mode: args.base_mode,
+        minority_keys: args.minority_keys,
+        leaf_transform: None,
+        hoist_scalars_only: args.hoist_scalars_only,
+        min_presence: args.min_presence,
+        null_as_empty: args.null_as_empty,
+        coercion: (!args.coerce_strings.is_empty()).then(|| StringCoercion {
+            types: args.coerce_strings.clone(),
+            form: args.coerce_form,
+            paths: args.coerce_paths.clone(),
+        }),
+        excluded_keys,
+        ignored_paths,
+        atomic_paths,
+        key_aliases,
+        value_buckets,
+        order_insensitive: args.order_insensitive,
+        fingerprint_subtrees: args.fingerprint_subtrees,
+        slots_patches: args.unchanged_slots == UnchangedSlots::Indexed,
+    };
+    if options.order_insensitive {
+        warn!("Ignoring key and element order: the base and diffs are written sorted, and merging them cannot restore the original order.");
+    }
+    // Elements of the additive strategies are never unchanged slots, even when null
+    if args.unchanged_slots == UnchangedSlots::Indexed
+        && matches!(options.array_strategy, ArrayStrategy::AppendUnique | ArrayStrategy::Prefix)
+    {
+        return Err(format!("--unchanged-slots indexed does not apply to the {} array strategy", options.array_strategy).into());
+    }
+    // Helm replaces arrays as a whole, so only positional array diffs are values it can read
+    #[cfg(feature = "helm")]
+    if args.umbrella.is_some() && options.array_strategy != ArrayStrategy::Positional {
+        return Err(format!("--umbrella does not apply to the {} array strategy", options.array_strategy).into());
+    }
+    Ok(options)
+}
+
+/// Parses a `--bucket PATH=STEP` argument into rounding buckets.
+fn parse_bucket(arg: &str) -> Result<ValueBuckets, String> {
+    let (path, step) = arg.rsplit_once('=').ok_or("expected PATH=STEP")?;
+    let step: f64 = step.parse().map_err(|_| format!("step '{}' is not a number", step))?;
+    if step.is_nan() || step <= 0.0 || step.is_infinite() {
+        return Err(format!("step {} is not positive", step));
+    }
+    ValueBuckets::new(path, Bucketing::round(step))
+}
+
+/// Checks whether two paths name the same file as given, with or without a leading `./`.
+fn same_file_name(a: &str, b: &str) -> bool {
+    a.strip_prefix("./").unwrap_or(a) == b.strip_prefix("./").unwrap_or(b)
+}
+
+/// Reads how the results are written: the sort configuration with the key order template, the
+/// base documentation and the umbrella mapping. The directives are those of no input yet.
+pub fn output(args: &Args, stdout_diff: Option<String>) -> Result<Output, Box<dyn Error>> {
+    let mut config = if !args.sort_config_path.is_empty() {
+        info!("Reading sort configuration file: {}", args.sort_config_path);
+        let content = fs::read_to_string(&args.sort_config_path);
+        if let Ok(content) = content {
+            parse_document(&content)?
+        } else {
+            warn!("Failed to read sort configuration file: {}", args.sort_config_path);
+            Yaml::Null
+        }
+    } else {
+        Yaml::Null
+    };
+    if let Some(ref template_path) = args.order_template {
+        info!("Reading key order template: {}", template_path);
+        let template = parse_document(&fs::read_to_string(template_path)?)?;
+        let mut hash = config.into_hash().unwrap_or_default();
+        hash.insert(Yaml::String("orderTemplate".to_string()), template);
+        config = Yaml::Hash(hash);
+    }
+    order_template(&config)?;
+    unlisted_keys(&config)?;
+    let base_docs = match args.base_docs {
+        Some(ref docs_path) => {
+            info!("Reading base documentation: {}", docs_path);
+            DocComments::from_yaml(&parse_document(&fs::read_to_string(docs_path)?)?)
+                .map_err(|e| format!("{}: {}", docs_path, e))?
+        }
+        None => DocComments::default(),
+    };
+
+    #[cfg(feature = "helm")]
+    let umbrella = match args.umbrella {
+        Some(ref path) => {
+            info!("Reading umbrella mapping: {}", path);
+            Some(yabe::helm::Umbrella::from_yaml(&parse_document(&fs::read_to_string(path)?)?).map_err(|e| format!("{}: {}", path, e))?)
+        }
+        None => None,
+    };
+
+    Ok(Output {
+        config,
+        base_docs,
+        style: OutputStyle { flow_width: args.flow_width, reals: args.real_format },
+        directives: Directives::default(),
+        stdout_diff,
+        #[cfg(feature = "helm")]
+        umbrella,
+    })
+}
+
+/// Reads the denylist of values the base must not hold, if any.
+pub fn read_denylist(args: &Args) -> Result<Option<Denylist>, Box<dyn Error>> {
+    let Some(ref path) = args.denylist else {
+        return Ok(None);
+    };
+    info!("Reading denylist: {}", path);
+    Ok(Some(Denylist::from_yaml(&parse_document(&fs::read_to_string(path)?)?).map_err(|e| format!("{}: {}", path, e))?))
+}
+
+/// Reads the read-only base, from a values file or a chart's defaults, and the existing base.
+pub fn read_bases(args: &Args) -> Result<Bases, Box<dyn Error>> {
+    let mut bases = Bases::default();
+    if let Some(ref read_only_base) = args.read_only_base {
+        info!("Reading helm values file: {}", read_only_base);
+        let content = read_source(Path::new(read_only_base))?;
+        bases.read_only = YamlLoader::load_from_str(&content)?.into_iter().next();
+        bases.read_only_content = Some(content);
+    }
+    #[cfg(feature = "helm")]
+    if let Some(ref chart) = args.chart {
+        info!("Reading helm chart defaults: {}", chart);
+        let doc = yabe::helm::chart_defaults(Path::new(chart))?;
+        bases.read_only_content = Some(yabe::output::to_yaml_string(&doc)?);
+        bases.read_only = Some(doc);
+    }
+
+    // Read and parse the existing base file if provided
+    if let Some(ref base_path) = args.base {
+        info!("Reading existing base YAML file: {}", base_path);
+        let content = read_source(Path::new(base_path))?;
+        bases.existing = YamlLoader::load_from_str(&content)?.into_iter().next();
+        bases.existing_content = Some(content);
+    }
+    Ok(bases)
+}
+
+/// Returns the directives the files are written with: those of the first input with
+/// `--preserve-directives`, warning about inputs of another YAML version.
+pub fn directives(args: &Args, inputs: &Inputs) -> Directives {
+    if !args.preserve_directives {
+        return Directives::default();
+    }
+    let all: Vec<Directives> = inputs.contents.iter().map(|content| scan_directives(content)).collect();
+    let filenames = &inputs.filenames;
+    for index in disagreeing_versions(&all) {
+        warn!(
+            "{} has YAML version {}, not {} as {}; writing the version of {}.",
+            filenames[index],
+            all[index].version.as_deref().unwrap_or("unset"),
+            all[0].version.as_deref().unwrap_or("unset"),
+            filenames[0],
+            filenames[0]
+        );
+    }
+    all.into_iter().next().unwrap_or_default()
+}
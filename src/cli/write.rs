@@ -0,0 +1,370 @@
+//! Writing the results: the base, group and partition bases and every file's diff, to files or
+//! as a stream to stdout.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use log::{info, warn};
+use yaml_rust2::Yaml;
+use yabe::comments::DocComments;
+use yabe::directives::Directives;
+use yabe::env_file::{env_vars, NonScalar};
+use yabe::group::Groups;
+use yabe::output::{write_yaml, write_yaml_with_style, OutputStyle};
+use yabe::partition::Partitioned;
+use yabe::result::DiffResult;
+use yabe::set_args::set_args;
+use yabe::sorter::sort_yaml;
+
+use super::{file_stem, Context, Output};
+
+/// Writes the results of the default mode as the arguments ask: one file's diff, the umbrella
+/// chart's values, a stream to stdout, or the base, groups and diffs as files.
+pub fn results(
+    context: &Context,
+    base: Option<&Yaml>,
+    groups: Option<&Groups>,
+    per_file_diffs: &[Option<Cow<Yaml>>],
+    excluded_diffs: &[Option<Cow<Yaml>>],
+) -> Result<(), Box<dyn Error>> {
+    let Context { args, inputs, output, .. } = context;
+    let (input_filenames, excluded_filenames) = (&inputs.filenames, &inputs.excluded_filenames);
+    let (config, style, out_folder) = (&output.config, output.style, &args.out_folder);
+    if let Some(ref name) = output.stdout_diff {
+        let diffs = per_file_diffs.iter().chain(excluded_diffs).map(|diff| diff.as_deref().map(Cow::Borrowed));
+        let named = DiffResult::new(None, diffs.collect()).with_names(input_filenames.iter().chain(excluded_filenames).cloned());
+        if let Some(diff) = named.diff(name) {
+            write_yaml_with_style(&sort_for_output(diff, config), &DocComments::default(), style, &mut std::io::stdout().lock())?;
+        } else {
+            info!("No diff for {}; nothing to print.", name);
+        }
+        return Ok(());
+    }
+    #[cfg(feature = "helm")]
+    if let Some(ref umbrella) = output.umbrella {
+        let diff_values: Vec<Option<&Yaml>> = per_file_diffs.iter().map(|d| d.as_deref()).collect();
+        let values = yabe::helm::umbrella_values(base, &diff_values, input_filenames, umbrella, &context.options)?;
+        let values = sort_for_output(&values, config);
+        if args.stdout {
+            write_yaml_with_style(&values, &output.base_docs, style, &mut std::io::stdout().lock())?;
+        } else {
+            fs::create_dir_all(out_folder)?;
+            let values_path = Path::new(out_folder).join(yabe::helm::VALUES_FILE);
+            info!("Writing umbrella chart values to {}", values_path.display());
+            let mut file = BufWriter::new(fs::File::create(&values_path)?);
+            write_yaml_with_style(&values, &output.base_docs, style, &mut file)?;
+            file.flush()?;
+        }
+        return Ok(());
+    }
+    if args.stdout {
+        print_stream(base, groups, &output.base_docs, per_file_diffs, input_filenames, config, style)?;
+        print_stream(None, None, &output.base_docs, excluded_diffs, excluded_filenames, config, style)?;
+        return Ok(());
+    }
+
+    // Process the base YAML if it exists
+    if let Some(base_yaml) = base {
+        let base_out_path = &args.base_out_path;
+        info!("Writing base YAML to {}", base_out_path);
+        let mut file = BufWriter::new(fs::File::create(base_out_path)?);
+        output.directives.write_around(&mut file, |file| {
+            write_yaml_with_style(&sort_for_output(base_yaml, config), &output.base_docs, style, file)
+        })?;
+        file.flush()?;
+        info!("Base YAML written to {}", base_out_path);
+        if args.env_file {
+            let env_path = Path::new(base_out_path).with_extension("env");
+            write_env_file(base_yaml, "the base", &env_path, args.env_non_scalar)?;
+        }
+    } else {
+        info!("No base YAML to write.");
+    }
+
+    if let Some(groups) = groups {
+        write_groups(groups, input_filenames, out_folder, config)?;
+    }
+
+    if args.set_args {
+        write_set_args(per_file_diffs, input_filenames, out_folder)?;
+        write_set_args(excluded_diffs, excluded_filenames, out_folder)?;
+        return Ok(());
+    }
+    if args.env_file {
+        write_env_files(per_file_diffs, input_filenames, out_folder, args.env_non_scalar)?;
+        write_env_files(excluded_diffs, excluded_filenames, out_folder, args.env_non_scalar)?;
+        return Ok(());
+    }
+    write_diffs(per_file_diffs, input_filenames, args.inplace, out_folder, output)?;
+    write_diffs(excluded_diffs, excluded_filenames, args.inplace, out_folder, output)?;
+    Ok(())
+}
+
+/// Sorts a document with the sort configuration, if any.
+fn sort_for_output<'a>(doc: &'a Yaml, config: &Yaml) -> Cow<'a, Yaml> {
+    if *config != Yaml::Null {
+        sort_yaml(doc, config)
+    } else {
+        Cow::Borrowed(doc)
+    }
+}
+
+/// Writes a document to a file, sorted with the sort configuration, without rendering all of it
+/// in memory first.
+pub fn write_doc<P: AsRef<Path>>(path: P, doc: &Yaml, config: &Yaml) -> Result<(), Box<dyn Error>> {
+    write_styled_doc(path, doc, config, &Directives::default(), OutputStyle::default())
+}
+
+/// Writes a document to a file like [`write_doc`], laid out with a style, between the
+/// directives and markers.
+fn write_styled_doc<P: AsRef<Path>>(
+    path: P,
+    doc: &Yaml,
+    config: &Yaml,
+    directives: &Directives,
+    style: OutputStyle,
+) -> Result<(), Box<dyn Error>> {
+    let mut file = BufWriter::new(fs::File::create(path)?);
+    directives.write_around(&mut file, |file| {
+        write_yaml_with_style(&sort_for_output(doc, config), &DocComments::default(), style, file)
+    })?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Prints the base and each file's diff to stdout as a multi-document YAML stream.
+///
+/// Every document starts with a `--- # <name>` marker; a file without a diff is an empty document.
+/// With groups, each group base follows the base, and then a `--- # groups` document mapping
+/// each file to its group.
+pub fn print_stream(
+    base: Option<&Yaml>,
+    groups: Option<&Groups>,
+    base_docs: &DocComments,
+    per_file_diffs: &[Option<Cow<Yaml>>],
+    input_filenames: &[String],
+    config: &Yaml,
+    style: OutputStyle,
+) -> Result<(), Box<dyn Error>> {
+    let mut out = std::io::stdout().lock();
+    if let Some(base_yaml) = base {
+        writeln!(out, "--- # base")?;
+        write_yaml_with_style(&sort_for_output(base_yaml, config), base_docs, style, &mut out)?;
+    }
+    if let Some(groups) = groups {
+        for group in &groups.groups {
+            writeln!(out, "--- # {}", group.name)?;
+            write_yaml(&sort_for_output(&group.base, config), &mut out)?;
+        }
+        writeln!(out, "--- # groups")?;
+        write_yaml(&groups.assignment(input_filenames), &mut out)?;
+    }
+    for (filename, diff) in input_filenames.iter().zip(per_file_diffs) {
+        writeln!(out, "--- # {}", filename)?;
+        if let Some(diff_yaml) = diff {
+            write_yaml_with_style(&sort_for_output(diff_yaml, config), &DocComments::default(), style, &mut out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes each input's preview to `<preview_dir>/<name>_preview.yaml`, warning about previews
+/// that do not have the same effect as their input.
+///
+/// With `changed_only` only those are written, so a lossless extraction writes nothing.
+pub fn write_previews(
+    preview_dir: &str,
+    changed_only: bool,
+    previews: &[Option<(Yaml, bool)>],
+    input_filenames: &[String],
+    config: &Yaml,
+) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(preview_dir)?;
+    let mut changed = 0;
+    for (filename, preview) in input_filenames.iter().zip(previews) {
+        let Some((preview_yaml, same)) = preview else {
+            info!("No preview for empty input {}.", filename);
+            continue;
+        };
+        if !same {
+            changed += 1;
+            warn!("Preview of {} does not match the original input.", filename);
+        } else if changed_only {
+            continue;
+        }
+        let preview_filename =
+            format!("{}/{}_preview.yaml", preview_dir, file_stem(filename, "preview"));
+        write_doc(&preview_filename, preview_yaml, config)?;
+        info!("Preview of {} written to {}", filename, preview_filename);
+    }
+    if changed == 0 {
+        info!("All previews match their original inputs.");
+    }
+    Ok(())
+}
+
+/// Writes each group base to `<out_folder>/<group>.yaml` and the file-to-group assignment to
+/// `<out_folder>/groups.yaml`.
+fn write_groups(
+    groups: &Groups,
+    input_filenames: &[String],
+    out_folder: &str,
+    config: &Yaml,
+) -> Result<(), Box<dyn Error>> {
+    for group in &groups.groups {
+        let group_filename = format!("{}/{}.yaml", out_folder, group.name);
+        write_doc(&group_filename, &group.base, config)?;
+        info!("Base of {} ({} files) written to {}", group.name, group.members.len(), group_filename);
+    }
+    let assignment_filename = format!("{}/groups.yaml", out_folder);
+    write_doc(&assignment_filename, &groups.assignment(input_filenames), &Yaml::Null)?;
+    info!("Group assignment written to {}", assignment_filename);
+    Ok(())
+}
+
+/// Writes each partition base to `<out>/partitions/<name>.yaml` and the partition of every file
+/// to `<out>/partitions.yaml`.
+pub fn write_partitions(
+    partitioned: &Partitioned,
+    input_filenames: &[String],
+    out_folder: &str,
+    config: &Yaml,
+) -> Result<(), Box<dyn Error>> {
+    for partition in &partitioned.partitions {
+        let Some(ref base) = partition.base else {
+            info!("Partition {} ({} files) shares nothing.", partition.name, partition.members.len());
+            continue;
+        };
+        let partition_filename = Path::new(out_folder).join("partitions").join(format!("{}.yaml", partition.name));
+        if let Some(parent) = partition_filename.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_doc(&partition_filename, base, config)?;
+        info!(
+            "Base of partition {} ({} files) written to {}",
+            partition.name,
+            partition.members.len(),
+            partition_filename.display()
+        );
+    }
+    let assignment_filename = format!("{}/partitions.yaml", out_folder);
+    write_doc(&assignment_filename, &partitioned.assignment(input_filenames), &Yaml::Null)?;
+    info!("Partition assignment written to {}", assignment_filename);
+    Ok(())
+}
+
+/// Prints each partition base as a `--- # <name>` document, then a `--- # partitions` document
+/// mapping each file to its partition.
+pub fn print_partitions(partitioned: &Partitioned, input_filenames: &[String], config: &Yaml) -> Result<(), Box<dyn Error>> {
+    let mut out = std::io::stdout().lock();
+    for partition in &partitioned.partitions {
+        if let Some(ref base) = partition.base {
+            writeln!(out, "--- # {}", partition.name)?;
+            write_yaml(&sort_for_output(base, config), &mut out)?;
+        }
+    }
+    writeln!(out, "--- # partitions")?;
+    write_yaml(&partitioned.assignment(input_filenames), &mut out)?;
+    Ok(())
+}
+
+/// Writes each file's diff as Helm `--set` arguments to `<out_folder>/<name>.set`, one
+/// shell-quoted argument per line.
+fn write_set_args(
+    per_file_diffs: &[Option<Cow<Yaml>>],
+    input_filenames: &[String],
+    out_folder: &str,
+) -> Result<(), Box<dyn Error>> {
+    for (diff, filename) in per_file_diffs.iter().zip(input_filenames) {
+        let Some(diff) = diff else {
+            info!("No diff for {}; not writing a --set file.", filename);
+            continue;
+        };
+        let args = set_args(diff).map_err(|e| format!("{} cannot be written as --set arguments: {}", filename, e))?;
+        let lines: String = args.iter().map(|arg| format!("{}\n", arg)).collect();
+        let set_filename = format!("{}/{}.set", out_folder, file_stem(filename, "diff"));
+        fs::write(&set_filename, lines)?;
+        info!("--set arguments for {} written to {}", filename, set_filename);
+    }
+    Ok(())
+}
+
+/// Writes each file's diff as environment variables to `<out_folder>/<name>.env`.
+fn write_env_files(
+    per_file_diffs: &[Option<Cow<Yaml>>],
+    input_filenames: &[String],
+    out_folder: &str,
+    non_scalar: NonScalar,
+) -> Result<(), Box<dyn Error>> {
+    for (diff, filename) in per_file_diffs.iter().zip(input_filenames) {
+        let Some(diff) = diff else {
+            info!("No diff for {}; not writing an .env file.", filename);
+            continue;
+        };
+        let env_path = format!("{}/{}.env", out_folder, file_stem(filename, "diff"));
+        write_env_file(diff, filename, Path::new(&env_path), non_scalar)?;
+    }
+    Ok(())
+}
+
+/// Writes a document as environment variables to `path`, one `NAME=value` line each.
+fn write_env_file(doc: &Yaml, name: &str, path: &Path, non_scalar: NonScalar) -> Result<(), Box<dyn Error>> {
+    let vars = env_vars(doc, non_scalar).map_err(|e| format!("{} cannot be written as environment variables: {}", name, e))?;
+    let lines: String = vars.iter().map(|var| format!("{}\n", var)).collect();
+    fs::write(path, lines)?;
+    info!("Environment variables of {} written to {}", name, path.display());
+    Ok(())
+}
+
+/// Writes each file's diff, either back to the original file or to the output folder.
+pub fn write_diffs(
+    per_file_diffs: &[Option<Cow<Yaml>>],
+    input_filenames: &[String],
+    inplace: bool,
+    out_folder: &str,
+    output: &Output,
+) -> Result<(), Box<dyn Error>> {
+    let (config, directives, style) = (&output.config, &output.directives, output.style);
+    // Determine whether to write diffs to original files or new files
+    if inplace {
+        info!("Inplace mode enabled. Modifying original files.");
+        for (i, diff) in per_file_diffs.iter().enumerate() {
+            if let Some(diff_yaml) = diff {
+                info!("Writing diff back to original file: {}", input_filenames[i]);
+                write_styled_doc(&input_filenames[i], diff_yaml.as_ref(), config, directives, style)?;
+                info!(
+                    "Difference written back to original file {}",
+                    input_filenames[i]
+                );
+            } else {
+                // If there is no diff, remove the content of the file
+                info!("No diff for {}; clearing file content.", input_filenames[i]);
+                fs::write(&input_filenames[i], "")?;
+                info!(
+                    "No difference for {}; file content cleared.",
+                    input_filenames[i]
+                );
+            }
+        }
+    } else {
+        info!("Writing diffs to new files.");
+        for (i, diff) in per_file_diffs.iter().enumerate() {
+            if let Some(diff_yaml) = diff {
+                info!("Writing diff for {} to new file.", input_filenames[i]);
+                let diff_filename =
+                    format!("{}/{}_diff.yaml", out_folder, file_stem(&input_filenames[i], "diff"));
+                write_styled_doc(&diff_filename, diff_yaml.as_ref(), config, directives, style)?;
+                info!(
+                    "Difference for {} written to {}",
+                    input_filenames[i], diff_filename
+                );
+            } else {
+                info!("No diff for {}; not writing a diff file.", input_filenames[i]);
+            }
+        }
+    }
+    Ok(())
+}
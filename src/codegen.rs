@@ -0,0 +1,287 @@
+//! Rust types for the configuration: serde structs inferred from the base and the diffs.
+//!
+//! Every map becomes a struct and every other value the Rust type of its YAML type: `String`,
+//! `i64`, `f64` (for reals, and for integers and reals mixed), `bool`, and `Vec<_>` of the type
+//! of all the elements of an array. A path holding values of several types, or only `null`, is
+//! a `serde_yaml::Value`. The types of a path are those of every configuration there, so a
+//! value only some environments set is typed too.
+//!
+//! The configuration of each input is its diff applied onto the base. A field is required when
+//! every configuration with the map sets it to something other than `null`, whether from the
+//! base or from its own diff; a field only some configurations set is an `Option`, skipped when
+//! serializing if `None`. In arrays, a field of the elements is required when every element
+//! sets it.
+//!
+//! Structs are named after their key in PascalCase, prefixed with the name of the enclosing
+//! struct when that name is taken, and the elements of an array of maps after their key with
+//! `Item`. Fields are the keys in snake_case, with a `#[serde(rename)]` where that is not the
+//! key; keys made of the same characters but for punctuation get numbered fields. The code uses
+//! `serde` with its `derive` feature, and `serde_yaml` for untyped values; it is a scaffold to
+//! review, not a guarantee that every document of the environments deserializes.
+
+use hashlink::LinkedHashMap;
+use yaml_rust2::Yaml;
+
+use crate::input::is_empty_document;
+use crate::merge::apply_diff;
+use crate::options::DiffOptions;
+use crate::path::key_segment;
+
+/// Keywords that cannot be field names.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do", "dyn", "else",
+    "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match", "mod",
+    "move", "mut", "override", "priv", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true",
+    "try", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// The type inferred at a path.
+#[derive(Debug, Clone, PartialEq)]
+enum Shape {
+    /// Only `null` was seen.
+    Unknown,
+    Bool,
+    Int,
+    Real,
+    String,
+    Array(Box<Shape>),
+    Map(LinkedHashMap<String, (Shape, bool)>),
+    /// Values of several types.
+    Mixed,
+}
+
+/// Generates serde structs for the configuration an extraction describes, the root one named
+/// `root`.
+///
+/// The types are inferred from the configuration of each input, its diff applied onto the base
+/// (see [`crate::merge::apply_diff`]), which must be a map; without diffs, from the base alone.
+///
+/// ```
+/// use yabe::codegen::rust_structs;
+/// use yabe::{yaml, DiffOptions};
+///
+/// let base = yaml!("replicas: 1\nimage: {repository: nginx, tag: v1}");
+/// let prod = yaml!("replicas: 3\ntls: {secretName: prod-tls}");
+/// let code = rust_structs(Some(&base), &[Some(&prod), None], &DiffOptions::default(), "Values").unwrap();
+/// assert!(code.contains("pub struct Values {\n    pub replicas: i64,\n    pub image: Image,\n"));
+/// assert!(code.contains("    pub tls: Option<Tls>,\n"));
+/// assert!(code.contains("    #[serde(rename = \"secretName\")]\n    pub secret_name: String,\n"));
+/// ```
+pub fn rust_structs(base: Option<&Yaml>, diffs: &[Option<&Yaml>], options: &DiffOptions, root: &str) -> Result<String, String> {
+    let configs: Vec<Yaml> = if diffs.is_empty() {
+        base.into_iter().cloned().collect()
+    } else {
+        diffs.iter().map(|diff| apply_diff(base, *diff, options).unwrap_or(Yaml::Null)).collect()
+    };
+    let configs: Vec<&Yaml> = configs.iter().filter(|config| !is_empty_document(config)).collect();
+    if configs.iter().any(|config| config.as_hash().is_none()) {
+        return Err("only map configurations can be typed as structs".to_string());
+    }
+    let fields = match infer(&configs, base) {
+        Shape::Map(fields) => fields,
+        _ => LinkedHashMap::new(),
+    };
+
+    let mut structs = Vec::new();
+    let mut names = Vec::new();
+    let root = pascal_case(root);
+    names.push(root.clone());
+    write_struct(&root, &fields, &mut structs, &mut names);
+    let mut code = String::from("use serde::{Deserialize, Serialize};\n");
+    for definition in structs {
+        code.push('\n');
+        code.push_str(&definition);
+    }
+    Ok(code)
+}
+
+/// Infers the shape of the values seen at a path, keys in the order of the base value there
+/// first.
+fn infer(values: &[&Yaml], base: Option<&Yaml>) -> Shape {
+    let values: Vec<&Yaml> = values.iter().copied().filter(|value| !value.is_null()).collect();
+    match values.first() {
+        None => Shape::Unknown,
+        Some(Yaml::Hash(_)) if values.iter().all(|value| value.as_hash().is_some()) => infer_map(&values, base),
+        Some(Yaml::Array(_)) if values.iter().all(|value| value.is_array()) => {
+            let elements: Vec<&Yaml> = values.iter().flat_map(|value| value.as_vec().into_iter().flatten()).collect();
+            Shape::Array(Box::new(infer(&elements, None)))
+        }
+        Some(_) => values.iter().map(|value| scalar_shape(value)).reduce(merge_scalars).unwrap_or(Shape::Mixed),
+    }
+}
+
+/// Infers the fields of maps: a key is required when every map sets it.
+fn infer_map(maps: &[&Yaml], base: Option<&Yaml>) -> Shape {
+    let base = base.and_then(|base| base.as_hash());
+    let mut keys: LinkedHashMap<String, Vec<&Yaml>> = LinkedHashMap::new();
+    for key in base.into_iter().flat_map(|base| base.keys()) {
+        keys.insert(key_segment(key), Vec::new());
+    }
+    for map in maps {
+        for (key, value) in map.as_hash().into_iter().flatten() {
+            // Not through `entry`, which moves existing keys to the back
+            let key = key_segment(key);
+            match keys.get_mut(&key) {
+                Some(values) => values.push(value),
+                None => {
+                    keys.insert(key, vec![value]);
+                }
+            }
+        }
+    }
+    let fields = keys
+        .into_iter()
+        .map(|(key, values)| {
+            let set = values.iter().filter(|value| !value.is_null()).count();
+            let required = set == maps.len();
+            let base_value = base.and_then(|base| base.iter().find(|(k, _)| key_segment(k) == key)).map(|(_, v)| v);
+            (key, (infer(&values, base_value), required))
+        })
+        .collect();
+    Shape::Map(fields)
+}
+
+fn scalar_shape(value: &Yaml) -> Shape {
+    match value {
+        Yaml::Boolean(_) => Shape::Bool,
+        Yaml::Integer(_) => Shape::Int,
+        Yaml::Real(_) => Shape::Real,
+        Yaml::String(_) => Shape::String,
+        _ => Shape::Mixed,
+    }
+}
+
+fn merge_scalars(a: Shape, b: Shape) -> Shape {
+    match (a, b) {
+        (a, b) if a == b => a,
+        (Shape::Int, Shape::Real) | (Shape::Real, Shape::Int) => Shape::Real,
+        _ => Shape::Mixed,
+    }
+}
+
+/// Writes the struct of a map, followed by the structs of the maps it holds.
+fn write_struct(
+    name: &str,
+    fields: &LinkedHashMap<String, (Shape, bool)>,
+    structs: &mut Vec<String>,
+    names: &mut Vec<String>,
+) {
+    let index = structs.len();
+    structs.push(String::new());
+    let mut definition = format!(
+        "#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]\npub struct {} {{\n",
+        name
+    );
+    let mut field_names: Vec<String> = Vec::new();
+    for (key, (shape, required)) in fields {
+        let mut field = field_name(key);
+        if field_names.contains(&field) {
+            let base = field.clone();
+            let number = (2..).find(|n| !field_names.contains(&format!("{}_{}", base, n))).unwrap_or(2);
+            field = format!("{}_{}", base, number);
+        }
+        field_names.push(field.clone());
+        let rust_type = type_of(shape, key, name, structs, names);
+        let mut attributes = Vec::new();
+        if field != *key {
+            attributes.push(format!("rename = {:?}", key));
+        }
+        let rust_type = if *required {
+            rust_type
+        } else {
+            attributes.push("default, skip_serializing_if = \"Option::is_none\"".to_string());
+            format!("Option<{}>", rust_type)
+        };
+        if !attributes.is_empty() {
+            definition.push_str(&format!("    #[serde({})]\n", attributes.join(", ")));
+        }
+        definition.push_str(&format!("    pub {}: {},\n", field, rust_type));
+    }
+    definition.push_str("}\n");
+    structs[index] = definition;
+}
+
+/// Returns the Rust type of a shape, writing the structs it needs.
+fn type_of(shape: &Shape, key: &str, parent: &str, structs: &mut Vec<String>, names: &mut Vec<String>) -> String {
+    match shape {
+        Shape::Unknown | Shape::Mixed => "serde_yaml::Value".to_string(),
+        Shape::Bool => "bool".to_string(),
+        Shape::Int => "i64".to_string(),
+        Shape::Real => "f64".to_string(),
+        Shape::String => "String".to_string(),
+        Shape::Array(element) => {
+            let element_key = format!("{} item", key);
+            format!("Vec<{}>", type_of(element, &element_key, parent, structs, names))
+        }
+        Shape::Map(fields) => {
+            let name = struct_name(key, parent, names);
+            write_struct(&name, fields, structs, names);
+            name
+        }
+    }
+}
+
+/// Names the struct of a key, unique among the names given so far.
+fn struct_name(key: &str, parent: &str, names: &mut Vec<String>) -> String {
+    let own = pascal_case(key);
+    let mut name = own.clone();
+    if names.contains(&name) {
+        name = format!("{}{}", parent, own);
+    }
+    if names.contains(&name) {
+        let base = name.clone();
+        name = (2..).map(|n| format!("{}{}", base, n)).find(|name| !names.contains(name)).unwrap_or(base);
+    }
+    names.push(name.clone());
+    name
+}
+
+/// Splits a key into lowercase words at punctuation and at lowercase-to-uppercase changes.
+fn words(key: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut previous_lower = false;
+    for c in key.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            previous_lower = false;
+            continue;
+        }
+        if c.is_ascii_uppercase() && previous_lower {
+            words.push(std::mem::take(&mut word));
+        }
+        previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        word.push(c.to_ascii_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn pascal_case(key: &str) -> String {
+    let name: String = words(key)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map(|first| first.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+        })
+        .collect();
+    match name.chars().next() {
+        None => "Field".to_string(),
+        Some(first) if first.is_ascii_digit() => format!("T{}", name),
+        Some(_) => name,
+    }
+}
+
+fn field_name(key: &str) -> String {
+    let name = words(key).join("_");
+    match name.chars().next() {
+        None => "field".to_string(),
+        Some(first) if first.is_ascii_digit() => format!("_{}", name),
+        Some(_) if KEYWORDS.contains(&name.as_str()) => format!("{}_", name),
+        Some(_) => name,
+    }
+}
@@ -0,0 +1,109 @@
+//! Documentation of the base, read from a sidecar file and written as comments.
+//!
+//! The parser drops comments, so documentation written in the inputs is lost. Instead, a
+//! sidecar file maps key paths (see [`crate::path`]) to documentation strings:
+//!
+//! ```yaml
+//! replicas: Number of pods; the autoscaler overrides it in production.
+//! image.tag: |
+//!   Image tag deployed.
+//!   Bumped by the release pipeline.
+//! ```
+//!
+//! Each string is written as `#` comment lines right above its key when the base is written
+//! (see [`crate::output::write_yaml_with_comments`]), indented like the key. Comments are not
+//! data: reading the base back, e.g. with `--base` or for [`crate::merge::apply_diff`], gives
+//! the same document as without them, and they are written again on the next run from the
+//! sidecar. Paths can only name map keys, as comments are not attached to array elements; a
+//! path missing from the base is skipped.
+
+use yaml_rust2::Yaml;
+
+use crate::path::{key_segment, parse_path, Segment};
+
+/// The documentation of a key path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocComment {
+    /// The path as written in the sidecar file.
+    pub path: String,
+    /// The unescaped keys along the path.
+    pub keys: Vec<String>,
+    pub text: String,
+}
+
+/// The documentation of a sidecar file, in the file's order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocComments {
+    pub entries: Vec<DocComment>,
+}
+
+impl DocComments {
+    /// Reads a sidecar document: a map from key paths to strings.
+    pub fn from_yaml(doc: &Yaml) -> Result<Self, String> {
+        let hash = match doc {
+            Yaml::Hash(hash) => hash,
+            Yaml::Null => return Ok(DocComments::default()),
+            _ => return Err("the documentation must be a map from key paths to strings".to_string()),
+        };
+        let mut entries = Vec::new();
+        for (path, text) in hash {
+            let (Some(path), Some(text)) = (path.as_str(), text.as_str()) else {
+                return Err(format!("documentation entry {:?}: {:?} is not a string to a string", path, text));
+            };
+            entries.push(DocComment {
+                path: path.to_string(),
+                keys: parse_keys(path)?,
+                text: text.to_string(),
+            });
+        }
+        Ok(DocComments { entries })
+    }
+
+    /// Returns the documentation of a path, given as its keys.
+    pub fn get(&self, keys: &[String]) -> Option<&str> {
+        self.entries.iter().find(|entry| entry.keys == keys).map(|entry| entry.text.as_str())
+    }
+
+    /// Returns the documented paths that `doc` does not have.
+    pub fn unmatched(&self, doc: &Yaml) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|entry| {
+                let found = entry.keys.iter().try_fold(doc, |value, key| {
+                    value.as_hash()?.iter().find(|(k, _)| key_segment(k) == *key).map(|(_, v)| v)
+                });
+                found.is_none()
+            })
+            .map(|entry| entry.path.as_str())
+            .collect()
+    }
+}
+
+fn parse_keys(path: &str) -> Result<Vec<String>, String> {
+    if path.is_empty() {
+        return Err("empty documentation path".to_string());
+    }
+    parse_path(path)?
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Key(key) => Ok(key),
+            Segment::Index(_) => Err(format!("documentation path '{}' indexes an array", path)),
+        })
+        .collect()
+}
+
+/// Formats documentation as comment lines at an indentation, each ending with a newline.
+pub fn comment_lines(text: &str, indent: &str) -> String {
+    let mut lines = String::new();
+    for line in text.trim_end().lines() {
+        let line = line.replace('\r', " ");
+        lines.push_str(indent);
+        lines.push('#');
+        if !line.is_empty() {
+            lines.push(' ');
+            lines.push_str(line.trim_end());
+        }
+        lines.push('\n');
+    }
+    lines
+}
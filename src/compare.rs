@@ -0,0 +1,442 @@
+//! Before/after comparison of two directories of values files, for change review.
+//!
+//! Files are matched by their path relative to each directory. A `.yml` file matches a `.yaml`
+//! file of the same name on the other side, so renaming the extension is not an addition plus
+//! a removal. Each matched pair is compared with [`compute_diff_with_options`] in both
+//! directions: values the after file sets differently are its diff, and keys it no longer has
+//! are listed as removed.
+//!
+//! For review, a report can also show a changed file's unchanged keys near each change, like the
+//! context lines of a textual diff. Nearness follows the tree: a key is near a change when it is
+//! in the same map and at most N keys before or after the changed key in that map's order.
+//!
+//! A changed multiline string, such as a script in a block scalar, stays whole in the diff,
+//! which cannot patch part of a string. The report can show it line by line instead: a string
+//! leaf is multiline when the before or the after value contains a newline, and its lines,
+//! split at each `\n`, are aligned by [`crate::lcs`] into unified-diff hunks:
+//!
+//! ```text
+//! @@ -2,3 +2,3 @@
+//!  set -e
+//! -echo v1
+//! +echo v2
+//!  exit 0
+//! ```
+//!
+//! Hunk headers give the first line (counted from 1) and the number of lines on each side, and
+//! lines are prefixed with ` ` (unchanged), `-` (before only) or `+` (after only). A string
+//! ending with a newline has an empty last line, so a change of the final newline alone shows
+//! as an empty line removed or added.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::Yaml;
+
+use crate::deep_equal::deep_equal;
+use crate::diff::compute_diff_with_options;
+use crate::input::parse_document;
+use crate::lcs;
+use crate::options::DiffOptions;
+use crate::path::{join_path, key_segment, split_path};
+use crate::report::default_paths;
+
+/// What happened to a file between the two directories.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileChange {
+    /// Only the after directory has the file.
+    Added,
+    /// Only the before directory has the file.
+    Removed,
+    /// Both files hold the same values.
+    Unchanged,
+    /// The files differ.
+    Changed {
+        /// Values the after file sets that the before file does not, or sets differently.
+        diff: Option<Yaml>,
+        /// Paths the before file sets that the after file does not.
+        removed: Vec<String>,
+    },
+}
+
+impl FileChange {
+    fn status(&self) -> &'static str {
+        match self {
+            FileChange::Added => "added",
+            FileChange::Removed => "removed",
+            FileChange::Unchanged => "unchanged",
+            FileChange::Changed { .. } => "changed",
+        }
+    }
+
+    /// Returns the paths changed in the file: the leaves of the diff, then the removed paths.
+    ///
+    /// The diff is walked through maps; any other value is a leaf as a whole.
+    pub fn changed_paths(&self) -> Vec<String> {
+        let FileChange::Changed { diff, removed } = self else {
+            return vec![];
+        };
+        let mut leaves = Vec::new();
+        if let Some(diff) = diff {
+            collect_leaf_segments(diff, &mut Vec::new(), &mut leaves);
+        }
+        let mut paths: Vec<String> = leaves.iter().map(|path| join_path(path)).collect();
+        paths.extend(removed.iter().cloned());
+        paths
+    }
+}
+
+/// The comparison of one file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileComparison {
+    /// Path relative to the directories; `before -> after` when the extensions differ.
+    pub name: String,
+    pub change: FileChange,
+    /// Unchanged keys near the changes, as path and value; see [`context_keys`].
+    pub context: Vec<(String, Yaml)>,
+    /// Changed multiline strings, as path and hunk lines; see [`line_diffs`].
+    pub lines: Vec<(String, Vec<String>)>,
+}
+
+/// Compares two parsed documents, `null` standing for a file without documents.
+pub fn compare_docs(before: &Yaml, after: &Yaml, options: &DiffOptions) -> FileChange {
+    let diff = compute_diff_with_options(after, before, options).map(|diff| diff.into_owned());
+    let removed = default_paths(after, before);
+    if diff.is_none() && removed.is_empty() {
+        FileChange::Unchanged
+    } else {
+        FileChange::Changed { diff, removed }
+    }
+}
+
+/// Returns the unchanged keys near each change of a file, with their values, in order of the
+/// changes.
+///
+/// A key is near a change when it is in the same map, at most `lines` keys before or after the
+/// changed key in the map's key order: the after file's, or the before file's for a removed key.
+/// Keys that are changed themselves or hold a change are not context.
+pub fn context_keys(before: &Yaml, after: &Yaml, change: &FileChange, lines: usize) -> Vec<(String, Yaml)> {
+    let FileChange::Changed { diff, removed } = change else {
+        return vec![];
+    };
+    let mut changed = Vec::new();
+    if let Some(diff) = diff {
+        collect_leaf_segments(diff, &mut Vec::new(), &mut changed);
+    }
+    changed.extend(removed.iter().map(|path| split_path(path)));
+
+    let mut found: Vec<(Vec<String>, Yaml)> = Vec::new();
+    for path in changed.iter().filter(|path| lines > 0 && !path.is_empty()) {
+        let (key, parent) = path.split_last().unwrap();
+        let Some((hash, index)) = [after, before].into_iter().find_map(|doc| {
+            let hash = map_at(doc, parent)?;
+            let index = hash.keys().position(|k| key_segment(k) == *key)?;
+            Some((hash, index))
+        }) else {
+            continue;
+        };
+        let near = index.saturating_sub(lines)..=index + lines;
+        for (k, value) in hash.iter().enumerate().filter(|(i, _)| near.contains(i)).map(|(_, entry)| entry) {
+            let mut sibling = parent.to_vec();
+            sibling.push(key_segment(k));
+            let is_change = changed.iter().any(|path| path.starts_with(&sibling));
+            if !is_change && !found.iter().any(|(path, _)| *path == sibling) {
+                found.push((sibling, value.clone()));
+            }
+        }
+    }
+    found.into_iter().map(|(path, value)| (join_path(&path), value)).collect()
+}
+
+/// Returns the line-by-line changes of each changed multiline string of a file (see the [module
+/// documentation](self)), in order of the changes, with `context` unchanged lines around each
+/// change.
+///
+/// Only strings in maps are compared line by line: a string in an array is part of the array's
+/// change. A string longer than [`lcs::DEFAULT_MAX_LEN`] lines on either side is left out.
+///
+/// ```
+/// use yabe::compare::{compare_docs, line_diffs};
+/// use yabe::{yaml, DiffOptions};
+///
+/// let before = yaml!("script: \"set -e\\necho v1\\nexit 0\"");
+/// let after = yaml!("script: \"set -e\\necho v2\\nexit 0\"");
+/// let change = compare_docs(&before, &after, &DiffOptions::default());
+/// let lines = line_diffs(&before, &after, &change, 0);
+/// assert_eq!(lines, [("script".to_string(), vec!["@@ -2 +2 @@".to_string(), "-echo v1".to_string(), "+echo v2".to_string()])]);
+/// ```
+pub fn line_diffs(before: &Yaml, after: &Yaml, change: &FileChange, context: usize) -> Vec<(String, Vec<String>)> {
+    let FileChange::Changed { diff: Some(diff), .. } = change else {
+        return vec![];
+    };
+    let mut changed = Vec::new();
+    collect_leaf_segments(diff, &mut Vec::new(), &mut changed);
+    changed
+        .into_iter()
+        .filter_map(|path| {
+            let (key, parent) = path.split_last()?;
+            let string_at = |doc: &'_ Yaml| {
+                map_at(doc, parent)?.iter().find(|(k, _)| key_segment(k) == *key)?.1.as_str().map(str::to_string)
+            };
+            let (before, after) = (string_at(before)?, string_at(after)?);
+            if !before.contains('\n') && !after.contains('\n') {
+                return None;
+            }
+            let hunks = line_hunks(&before, &after, context)?;
+            Some((join_path(&path), hunks))
+        })
+        .collect()
+}
+
+/// A line of a line diff, as its index before and its index after; a missing index marks a
+/// removed or an added line.
+type LineOp = (Option<usize>, Option<usize>);
+
+/// Renders the unified-diff hunks between the lines of two strings; `None` if a side has more
+/// lines than the alignment handles.
+fn line_hunks(before: &str, after: &str, context: usize) -> Option<Vec<String>> {
+    let lines = |text: &str| text.split('\n').map(|line| Yaml::String(line.to_string())).collect::<Vec<_>>();
+    let (before, after) = (lines(before), lines(after));
+    if before.len().max(after.len()) > lcs::DEFAULT_MAX_LEN {
+        return None;
+    }
+    let text = |line: &Yaml| line.as_str().unwrap_or_default().to_string();
+
+    let mut ops: Vec<LineOp> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in lcs::matches(&before, &after, deep_equal).into_iter().chain([(before.len(), after.len())]) {
+        ops.extend((i..next_i).map(|i| (Some(i), None)));
+        ops.extend((j..next_j).map(|j| (None, Some(j))));
+        if next_i < before.len() {
+            ops.push((Some(next_i), Some(next_j)));
+        }
+        (i, j) = (next_i + 1, next_j + 1);
+    }
+
+    // Changes closer than twice the context share a hunk
+    let changes: Vec<usize> = (0..ops.len()).filter(|&k| ops[k].0.is_none() || ops[k].1.is_none()).collect();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &k in &changes {
+        let (start, end) = (k.saturating_sub(context), (k + context + 1).min(ops.len()));
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    let mut hunks = Vec::new();
+    for (start, end) in ranges {
+        // The lines of a side before the hunk, and in it
+        let side = |index: fn(&LineOp) -> Option<usize>| {
+            let first = ops[..start].iter().filter_map(index).count();
+            match ops[start..end].iter().filter_map(index).count() {
+                0 => format!("{},0", first),
+                1 => format!("{}", first + 1),
+                count => format!("{},{}", first + 1, count),
+            }
+        };
+        hunks.push(format!("@@ -{} +{} @@", side(|op| op.0), side(|op| op.1)));
+        for op in &ops[start..end] {
+            hunks.push(match op {
+                (Some(i), Some(_)) => format!(" {}", text(&before[*i])),
+                (Some(i), None) => format!("-{}", text(&before[*i])),
+                (None, Some(j)) => format!("+{}", text(&after[*j])),
+                (None, None) => unreachable!("every line is on a side"),
+            });
+        }
+    }
+    Some(hunks)
+}
+
+/// Compares the values files (`.yaml` and `.yml`, in subdirectories too) of two directories,
+/// in order of their relative paths.
+pub fn compare_dirs(before: &Path, after: &Path, options: &DiffOptions) -> io::Result<Vec<FileComparison>> {
+    compare_dirs_with_context(before, after, options, 0)
+}
+
+/// Same as [`compare_dirs`], with the [`context_keys`] of each changed file within `lines` keys
+/// of a change.
+pub fn compare_dirs_with_context(
+    before: &Path,
+    after: &Path,
+    options: &DiffOptions,
+    lines: usize,
+) -> io::Result<Vec<FileComparison>> {
+    compare_dirs_with_lines(before, after, options, lines, None)
+}
+
+/// Same as [`compare_dirs_with_context`], with the [`line_diffs`] of each changed file when
+/// `line_context` is set, with that many unchanged lines around each change.
+pub fn compare_dirs_with_lines(
+    before: &Path,
+    after: &Path,
+    options: &DiffOptions,
+    lines: usize,
+    line_context: Option<usize>,
+) -> io::Result<Vec<FileComparison>> {
+    let before_files = values_files(before)?;
+    let after_files = values_files(after)?;
+
+    let mut stems: Vec<&String> = before_files.keys().chain(after_files.keys()).collect();
+    stems.sort();
+    stems.dedup();
+
+    let mut comparisons = Vec::new();
+    for stem in stems {
+        let comparison = match (before_files.get(stem), after_files.get(stem)) {
+            (Some(before_name), Some(after_name)) => {
+                let before_doc = read_doc(&before.join(before_name))?;
+                let after_doc = read_doc(&after.join(after_name))?;
+                let name = if before_name == after_name {
+                    after_name.clone()
+                } else {
+                    format!("{} -> {}", before_name, after_name)
+                };
+                let change = compare_docs(&before_doc, &after_doc, options);
+                FileComparison {
+                    name,
+                    context: context_keys(&before_doc, &after_doc, &change, lines),
+                    lines: line_context.map_or(vec![], |context| line_diffs(&before_doc, &after_doc, &change, context)),
+                    change,
+                }
+            }
+            (None, Some(after_name)) => FileComparison {
+                name: after_name.clone(),
+                change: FileChange::Added,
+                context: vec![],
+                lines: vec![],
+            },
+            (Some(before_name), None) => FileComparison {
+                name: before_name.clone(),
+                change: FileChange::Removed,
+                context: vec![],
+                lines: vec![],
+            },
+            (None, None) => unreachable!("every stem comes from one of the directories"),
+        };
+        comparisons.push(comparison);
+    }
+    Ok(comparisons)
+}
+
+/// Renders comparisons as a report: a `files` map of name to status, diff, removed paths,
+/// context and line-by-line changes, and a `summary` with the number of files per status and the number of files each path
+/// changed in, most changed first.
+pub fn comparison_report(comparisons: &[FileComparison]) -> Yaml {
+    let key = |name: &str| Yaml::String(name.to_string());
+    let mut files = Hash::new();
+    let mut counts: BTreeMap<&str, i64> = BTreeMap::new();
+    let mut path_counts: BTreeMap<String, i64> = BTreeMap::new();
+
+    for comparison in comparisons {
+        let mut entry = Hash::new();
+        entry.insert(key("status"), key(comparison.change.status()));
+        if let FileChange::Changed { diff, removed } = &comparison.change {
+            if let Some(diff) = diff {
+                entry.insert(key("diff"), diff.clone());
+            }
+            if !removed.is_empty() {
+                entry.insert(key("removed"), Yaml::Array(removed.iter().map(|path| key(path)).collect()));
+            }
+        }
+        if !comparison.context.is_empty() {
+            let context = comparison.context.iter().map(|(path, value)| (key(path), value.clone())).collect();
+            entry.insert(key("context"), Yaml::Hash(context));
+        }
+        if !comparison.lines.is_empty() {
+            let lines = comparison
+                .lines
+                .iter()
+                .map(|(path, hunks)| (key(path), Yaml::Array(hunks.iter().map(|line| key(line)).collect())))
+                .collect();
+            entry.insert(key("lines"), Yaml::Hash(lines));
+        }
+        files.insert(key(&comparison.name), Yaml::Hash(entry));
+        *counts.entry(comparison.change.status()).or_default() += 1;
+        for path in comparison.change.changed_paths() {
+            *path_counts.entry(path).or_default() += 1;
+        }
+    }
+
+    let mut summary = Hash::new();
+    for status in ["added", "removed", "changed", "unchanged"] {
+        summary.insert(key(status), Yaml::Integer(counts.get(status).copied().unwrap_or(0)));
+    }
+    let mut paths: Vec<(String, i64)> = path_counts.into_iter().collect();
+    paths.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    if !paths.is_empty() {
+        summary.insert(
+            key("paths"),
+            Yaml::Hash(paths.into_iter().map(|(path, count)| (key(&path), Yaml::Integer(count))).collect()),
+        );
+    }
+
+    let mut report = Hash::new();
+    report.insert(key("files"), Yaml::Hash(files));
+    report.insert(key("summary"), Yaml::Hash(summary));
+    Yaml::Hash(report)
+}
+
+fn collect_leaf_segments(value: &Yaml, path: &mut Vec<String>, found: &mut Vec<Vec<String>>) {
+    match value {
+        Yaml::Hash(hash) if !hash.is_empty() => {
+            for (key, child) in hash {
+                path.push(key_segment(key));
+                collect_leaf_segments(child, path, found);
+                path.pop();
+            }
+        }
+        _ => found.push(path.clone()),
+    }
+}
+
+/// Returns the map at a path of segments, walking through maps only.
+fn map_at<'a>(doc: &'a Yaml, path: &[String]) -> Option<&'a Hash> {
+    path.iter().try_fold(doc.as_hash()?, |hash, segment| {
+        hash.iter().find(|(k, _)| key_segment(k) == *segment)?.1.as_hash()
+    })
+}
+
+/// Maps the relative path without extension of every values file in `dir` to its relative path.
+///
+/// Should a directory hold both `x.yaml` and `x.yml`, `x.yaml` is used.
+pub(crate) fn values_files(dir: &Path) -> io::Result<BTreeMap<String, String>> {
+    if !dir.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} is not a directory", dir.display()),
+        ));
+    }
+    let mut files = BTreeMap::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        for entry in fs::read_dir(dir.join(&relative))? {
+            let entry = entry?;
+            let relative = relative.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                pending.push(relative);
+                continue;
+            }
+            let extension = relative.extension().and_then(|ext| ext.to_str());
+            if !matches!(extension, Some("yaml" | "yml")) {
+                continue;
+            }
+            let name = relative.to_string_lossy().into_owned();
+            let stem = relative.with_extension("").to_string_lossy().into_owned();
+            if extension == Some("yaml") || !files.contains_key(&stem) {
+                files.insert(stem, name);
+            }
+        }
+    }
+    Ok(files)
+}
+
+pub(crate) fn read_doc(path: &Path) -> io::Result<Yaml> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    parse_document(&content)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+}
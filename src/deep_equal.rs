@@ -1,5 +1,7 @@
 use yaml_rust2::Yaml;
 
+use crate::normalize::sort_canonically;
+
 /// Recursively checks if two Yaml values are deeply equal.
 pub fn deep_equal(a: &Yaml, b: &Yaml) -> bool {
     match (a, b) {
@@ -19,11 +21,28 @@ pub fn deep_equal(a: &Yaml, b: &Yaml) -> bool {
                 false
             } else {
                 a_hash.iter().all(|(a_key, a_value)| {
-                    b_hash.get(a_key).map_or(false, |b_value| deep_equal(a_value, b_value))
+                    b_hash.get(a_key).is_some_and(|b_value| deep_equal(a_value, b_value))
                 })
             }
         }
         (Yaml::Null, Yaml::Null) => true,
         _ => false,
     }
-}
\ No newline at end of file
+}
+
+/// Checks if two Yaml values are equal regardless of the order of array elements, arrays being
+/// compared as multisets; map key order is ignored as by [`deep_equal`].
+///
+/// ```
+/// use yabe::deep_equal::{deep_equal, deep_equal_unordered};
+/// use yabe::yaml;
+///
+/// let a = yaml!("ports: [{name: http, port: 80}, {port: 443, name: https}]");
+/// let b = yaml!("ports: [{name: https, port: 443}, {name: http, port: 80}]");
+/// assert!(!deep_equal(&a, &b));
+/// assert!(deep_equal_unordered(&a, &b));
+/// assert!(!deep_equal_unordered(&yaml!("[1, 1, 2]"), &yaml!("[1, 2, 2]")));
+/// ```
+pub fn deep_equal_unordered(a: &Yaml, b: &Yaml) -> bool {
+    deep_equal(&sort_canonically(a), &sort_canonically(b))
+}
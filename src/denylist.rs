@@ -0,0 +1,141 @@
+//! Values that must never end up in the base, such as secrets and credentials.
+//!
+//! A value every environment shares is hoisted into the base, so a secret in the base was set
+//! the same in several environments: it leaked across them. A denylist file lists what must
+//! stay per environment, as exact values and as regular expressions:
+//!
+//! ```yaml
+//! values:
+//!   - hunter2
+//! patterns:
+//!   - 'AKIA[0-9A-Z]{16}'
+//!   - '-----BEGIN [A-Z ]*PRIVATE KEY-----'
+//! ```
+//!
+//! Both are compared with the scalar leaves of the base as written in YAML, so the value `5432`
+//! matches the number `5432` as well as the string `"5432"`. A pattern matches anywhere in a
+//! leaf; anchor it with `^` and `$` to match whole leaves. `null` leaves never match. Reports
+//! name the rule a leaf matched, never the leaf itself, so they do not leak it again.
+
+use regex::Regex;
+use yaml_rust2::Yaml;
+
+use crate::path::{format_path, key_segment, Segment};
+
+/// The values and patterns of a denylist file.
+#[derive(Debug, Clone, Default)]
+pub struct Denylist {
+    /// The denied values, as scalar text.
+    pub values: Vec<String>,
+    /// The denied patterns.
+    pub patterns: Vec<Regex>,
+}
+
+impl Denylist {
+    /// Reads a denylist document: a map of `values` and `patterns` lists, both optional.
+    pub fn from_yaml(doc: &Yaml) -> Result<Self, String> {
+        let hash = match doc {
+            Yaml::Hash(hash) => hash,
+            Yaml::Null => return Ok(Denylist::default()),
+            _ => return Err("the denylist must be a map of values and patterns".to_string()),
+        };
+        let mut denylist = Denylist::default();
+        for (key, entries) in hash {
+            let entries = match entries {
+                Yaml::Array(entries) => entries.as_slice(),
+                Yaml::Null => &[],
+                _ => return Err(format!("denylist {}: expected a list", key_segment(key))),
+            };
+            match key.as_str() {
+                Some("values") => {
+                    for entry in entries {
+                        let value = scalar_text(entry).ok_or_else(|| format!("denied value {:?} is not a scalar", entry))?;
+                        denylist.values.push(value);
+                    }
+                }
+                Some("patterns") => {
+                    for entry in entries {
+                        let pattern = entry.as_str().ok_or_else(|| format!("denied pattern {:?} is not a string", entry))?;
+                        denylist.patterns.push(Regex::new(pattern).map_err(|e| format!("denied pattern '{}': {}", pattern, e))?);
+                    }
+                }
+                _ => return Err(format!("unknown denylist key '{}', expected values or patterns", key_segment(key))),
+            }
+        }
+        Ok(denylist)
+    }
+
+    /// Returns whether the denylist denies nothing.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty() && self.patterns.is_empty()
+    }
+
+    /// Describes the first rule a leaf matches, if any: `value #<n>` (counted from 1) or
+    /// `pattern '<pattern>'`.
+    pub fn matching_rule(&self, leaf: &Yaml) -> Option<String> {
+        let text = scalar_text(leaf)?;
+        if let Some(index) = self.values.iter().position(|value| *value == text) {
+            return Some(format!("value #{}", index + 1));
+        }
+        self.patterns
+            .iter()
+            .find(|pattern| pattern.is_match(&text))
+            .map(|pattern| format!("pattern '{}'", pattern.as_str()))
+    }
+}
+
+/// Returns the path of each leaf of the base the denylist denies, with the rule it matched, in
+/// the order of the base.
+///
+/// ```
+/// use yabe::denylist::{denied_values, Denylist};
+/// use yabe::yaml;
+///
+/// let denylist = Denylist::from_yaml(&yaml!("values: [hunter2]\npatterns: ['^AKIA']")).unwrap();
+/// let base = yaml!("db: {password: hunter2, port: 5432}\naws: {key: AKIAEXAMPLE}");
+/// assert_eq!(
+///     denied_values(&base, &denylist),
+///     [("db.password".to_string(), "value #1".to_string()), ("aws.key".to_string(), "pattern '^AKIA'".to_string())]
+/// );
+/// ```
+pub fn denied_values(base: &Yaml, denylist: &Denylist) -> Vec<(String, String)> {
+    let mut denied = Vec::new();
+    if !denylist.is_empty() {
+        collect_denied(base, denylist, &mut Vec::new(), &mut denied);
+    }
+    denied
+}
+
+fn collect_denied(value: &Yaml, denylist: &Denylist, path: &mut Vec<Segment>, denied: &mut Vec<(String, String)>) {
+    match value {
+        Yaml::Hash(hash) => {
+            for (key, child) in hash {
+                path.push(Segment::Key(key_segment(key)));
+                collect_denied(child, denylist, path, denied);
+                path.pop();
+            }
+        }
+        Yaml::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                path.push(Segment::Index(index));
+                collect_denied(child, denylist, path, denied);
+                path.pop();
+            }
+        }
+        leaf => {
+            if let Some(rule) = denylist.matching_rule(leaf) {
+                denied.push((format_path(path), rule));
+            }
+        }
+    }
+}
+
+/// The text of a non-null scalar as written in YAML.
+fn scalar_text(value: &Yaml) -> Option<String> {
+    match value {
+        Yaml::String(s) | Yaml::Real(s) => Some(s.clone()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
@@ -1,21 +1,117 @@
 use std::borrow::Cow;
-use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::BuildHasherDefault;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 use log::debug;
 use yaml_rust2::yaml::{Hash, Yaml};
-use crate::deep_equal::deep_equal;
+use crate::arrays;
+use crate::blob::{abbreviate, blob_equal};
+use crate::fingerprint::SubtreeFingerprints;
+use crate::ignore::keep_out_of_base;
+use crate::input::is_empty_document;
+use crate::lcs;
+use crate::merge::merge_yaml_with_options;
+use crate::normalize::{needs_normalization, normalize, normalize_all};
+use crate::options::{ArrayStrategy, BaseMode, Bucketing, DiffOptions, MinorityKeys, NullHandling};
+use crate::path::key_segment;
+use crate::result::{DiffResult, DiffsByName};
+use crate::schema::{type_conflicts, type_name, StrictTypes, TypeConflict};
+use crate::strategic_merge::{self, ElementKey};
 
 /// Recursively computes the difference between an override YAML object and the helm values YAML object.
+///
+/// The diff keeps what `obj` changes or adds on top of `helm`; `None` means nothing.
+///
+/// ```
+/// use yabe::{compute_diff, yaml};
+///
+/// let helm = yaml!("replicas: 1\nimage:\n  repository: nginx\n  tag: v1");
+/// let env = yaml!("replicas: 1\nimage:\n  repository: nginx\n  tag: v2\nregion: eu");
+///
+/// let diff = compute_diff(&env, &helm).unwrap();
+/// assert_eq!(diff["image"], yaml!("tag: v2"));
+/// assert_eq!(diff["region"].as_str(), Some("eu"));
+/// assert!(diff["replicas"].is_badvalue());
+///
+/// assert!(compute_diff(&helm, &helm).is_none());
+/// ```
 pub fn compute_diff<'a>(obj: &'a Yaml, helm: &'a Yaml) -> Option<Cow<'a, Yaml>> {
-    if deep_equal(obj, helm) {
+    compute_diff_with_options(obj, helm, &DiffOptions::default())
+}
+
+/// Same as [`compute_diff`], honouring the given options.
+pub fn compute_diff_with_options<'a>(
+    obj: &'a Yaml,
+    helm: &'a Yaml,
+    options: &DiffOptions,
+) -> Option<Cow<'a, Yaml>> {
+    // Paths are only tracked for the atomic paths that need them
+    let mut path = Vec::new();
+    let path = (!options.atomic_paths.is_empty()).then_some(&mut path);
+    if needs_normalization(options) {
+        let obj = normalize(obj, options);
+        let helm = normalize(helm, options);
+        let fingerprints = options.fingerprint_subtrees.then(|| SubtreeFingerprints::new(&[&obj, &helm]));
+        return diff_at(&obj, &helm, options, path, fingerprints.as_ref()).map(|diff| Cow::Owned(diff.into_owned()));
+    }
+    let fingerprints = options.fingerprint_subtrees.then(|| SubtreeFingerprints::new(&[obj, helm]));
+    diff_at(obj, helm, options, path, fingerprints.as_ref())
+}
+
+/// Recursively diffs two already normalized values.
+fn diff_value<'a>(obj: &'a Yaml, helm: &'a Yaml, options: &DiffOptions) -> Option<Cow<'a, Yaml>> {
+    diff_at(obj, helm, options, None, None)
+}
+
+/// Same as [`diff_value`], for the values at `path` if it is tracked, so that maps at an atomic
+/// path are diffed as a whole. Values whose `fingerprints` differ are known to differ without
+/// being compared; equal fingerprints are still confirmed by comparing the values.
+fn diff_at<'a>(
+    obj: &'a Yaml,
+    helm: &'a Yaml,
+    options: &DiffOptions,
+    mut path: Option<&mut Vec<String>>,
+    fingerprints: Option<&SubtreeFingerprints>,
+) -> Option<Cow<'a, Yaml>> {
+    let known_different = fingerprints.is_some_and(|fingerprints| fingerprints.differ(obj, helm));
+    if !known_different && blob_equal(obj, helm, options.blob_threshold) {
         None
     } else {
         match (obj, helm) {
+            (Yaml::Hash(_), Yaml::Hash(_)) if path.as_deref().is_some_and(|path| options.is_atomic(path)) => {
+                Some(Cow::Owned(replacing_diff(obj, helm)))
+            }
             (Yaml::Hash(obj_hash), Yaml::Hash(helm_hash)) => {
                 let mut diff_hash = Hash::new();
                 for (key, obj_value) in obj_hash {
-                    let helm_value = helm_hash.get(key).unwrap_or(&Yaml::Null);
-                    if let Some(diff_value) = compute_diff(obj_value, helm_value) {
+                    let helm_value = helm_hash.get(key);
+                    if let Some(path) = path.as_deref_mut() {
+                        path.push(key_segment(key));
+                    }
+                    let diff_value = match (obj_value, options.null_handling) {
+                        (Yaml::Null, NullHandling::Inherit) => None,
+                        (Yaml::Null, NullHandling::Value) if helm_value.is_none() => {
+                            Some(Cow::Borrowed(obj_value))
+                        }
+                        (Yaml::Null, _) if options.null_as_empty => {
+                            let helm_value = helm_value.unwrap_or(&Yaml::Null);
+                            let obj_value = empty_like(helm_value).unwrap_or(obj_value);
+                            diff_at(obj_value, helm_value, options, path.as_deref_mut(), fingerprints)
+                        }
+                        _ => {
+                            let helm_value = helm_value.unwrap_or(&Yaml::Null);
+                            diff_at(obj_value, helm_value, options, path.as_deref_mut(), fingerprints)
+                        }
+                    };
+                    if let Some(path) = path.as_deref_mut() {
+                        path.pop();
+                    }
+                    if let Some(diff_value) = diff_value {
                         diff_hash.insert(key.clone(), diff_value.into_owned());
                     }
                 }
@@ -26,6 +122,50 @@ pub fn compute_diff<'a>(obj: &'a Yaml, helm: &'a Yaml) -> Option<Cow<'a, Yaml>>
                 }
             }
             (Yaml::Array(obj_array), Yaml::Array(helm_array)) => {
+                if let Some(merge_key) = options.merge_key() {
+                    if let Some(diff) = compute_keyed_diff(obj, obj_array, helm_array, merge_key, options) {
+                        return diff;
+                    }
+                }
+                if options.array_strategy == ArrayStrategy::AppendUnique {
+                    // Base elements are always inherited; only additions are kept
+                    let base_items: Vec<&Yaml> = helm_array.iter().collect();
+                    let extras = arrays::extra_elements(obj_array, &base_items);
+                    return if extras.is_empty() {
+                        None
+                    } else {
+                        Some(Cow::Owned(Yaml::Array(extras.into_iter().cloned().collect())))
+                    };
+                }
+                if options.array_strategy == ArrayStrategy::Prefix {
+                    // Only what follows the base is kept; other arrays are kept whole
+                    return match arrays::trailing_elements(obj_array, helm_array) {
+                        Some([]) => None,
+                        Some(tail) => Some(Cow::Owned(Yaml::Array(tail.to_vec()))),
+                        None => Some(Cow::Borrowed(obj)),
+                    };
+                }
+                if options.array_strategy == ArrayStrategy::OrderedSet
+                    && obj_array.len().max(helm_array.len()) <= lcs::DEFAULT_MAX_LEN
+                    && !arrays::has_duplicates(obj_array)
+                    && !arrays::has_duplicates(helm_array)
+                {
+                    // Elements are inserted and deleted whole, never changed
+                    let eq = |a: &Yaml, b: &Yaml| blob_equal(a, b, options.blob_threshold);
+                    return lcs::edit_script(helm_array, obj_array, eq, |_, _| None).map(Cow::Owned);
+                }
+                if let Some(max_len) = options.lcs_max_len() {
+                    if obj_array.len() <= max_len && helm_array.len() <= max_len {
+                        let eq = |a: &Yaml, b: &Yaml| blob_equal(a, b, options.blob_threshold);
+                        // A changed element keeps its diff only if merging it back is exact
+                        let diff_element = |item: &Yaml, base_item: &Yaml| {
+                            let diff = diff_value(item, base_item, options)?.into_owned();
+                            let merged = merge_yaml_with_options(base_item, &diff, options);
+                            eq(&merged, item).then_some(diff)
+                        };
+                        return lcs::edit_script(helm_array, obj_array, eq, diff_element).map(Cow::Owned);
+                    }
+                }
                 if obj_array.len() != helm_array.len() {
                     Some(Cow::Borrowed(obj))
                 } else {
@@ -34,7 +174,7 @@ pub fn compute_diff<'a>(obj: &'a Yaml, helm: &'a Yaml) -> Option<Cow<'a, Yaml>>
                         .iter()
                         .zip(helm_array.iter())
                         .map(|(obj_item, helm_item)| {
-                            if let Some(diff_item) = compute_diff(obj_item, helm_item) {
+                            if let Some(diff_item) = diff_at(obj_item, helm_item, options, None, fingerprints) {
                                 has_diff = true;
                                 diff_item.into_owned()
                             } else {
@@ -55,11 +195,830 @@ pub fn compute_diff<'a>(obj: &'a Yaml, helm: &'a Yaml) -> Option<Cow<'a, Yaml>>
     }
 }
 
+/// Hasher of the extraction's maps and sets: SipHash with fixed keys, unlike the per-instance
+/// random keys of `HashMap::new`, so iterating them visits entries in the same order in every
+/// run. Key order in the base still follows that iteration rather than the inputs, so whether
+/// output is ordered as the inputs depends on the ordered-output work, not on this.
+type FixedState = BuildHasherDefault<DefaultHasher>;
+
+/// Base and per-file diffs produced by the extraction.
+type Extraction<'a> = (Option<Cow<'a, Yaml>>, Vec<Option<Cow<'a, Yaml>>>);
+
+/// Time spent extracting each top-level key, see [`diff_and_common_timed`].
+pub type KeyTimes = Vec<(String, Duration)>;
+
+/// Computes an overlay chain over ordered levels, e.g. base → staging → staging-eu.
+///
+/// Each level is diffed against the merged result of all preceding levels (what
+/// [`crate::merge::merge_all`] returns for them), so it only keeps what it overrides on top of
+/// its immediate parent. Levels may be full documents or overlays.
+/// The first level is the root of the chain and is returned unchanged; a level that adds
+/// nothing to its parent yields `None`.
+pub fn overlay_chain(levels: &[&Yaml], options: &DiffOptions) -> Vec<Option<Yaml>> {
+    let Some((root, rest)) = levels.split_first() else {
+        return vec![];
+    };
+
+    let mut result = vec![Some((*root).clone())];
+    let mut parent = (*root).clone();
+    for level in rest {
+        let effective = merge_yaml_with_options(&parent, level, options).into_owned();
+        result.push(compute_diff_with_options(&effective, &parent, options).map(Cow::into_owned));
+        parent = effective;
+    }
+    result
+}
+
+/// Diffs two arrays keyed by `merge_key` as a strategic merge patch.
+///
+/// Returns `None` if either array is not keyed, so the caller falls back to positional diffing.
+fn compute_keyed_diff<'a>(
+    obj: &'a Yaml,
+    obj_array: &'a [Yaml],
+    helm_array: &'a [Yaml],
+    merge_key: &str,
+    options: &DiffOptions,
+) -> Option<Option<Cow<'a, Yaml>>> {
+    let obj_keys = strategic_merge::element_keys(obj_array, merge_key)?;
+    let helm_keys = strategic_merge::element_keys(helm_array, merge_key)?;
+
+    let mut patch = Vec::new();
+    for (obj_item, key) in obj_array.iter().zip(&obj_keys) {
+        match helm_keys.iter().position(|helm_key| helm_key == key) {
+            Some(index) => {
+                if let Some(diff) = diff_value(obj_item, &helm_array[index], options) {
+                    patch.push(strategic_merge::with_merge_key(diff.into_owned(), merge_key, key));
+                }
+            }
+            None => patch.push(obj_item.clone()),
+        }
+    }
+    for key in helm_keys.iter().filter(|key| !obj_keys.contains(key)) {
+        patch.push(strategic_merge::delete_element(merge_key, key));
+    }
+
+    if patch.is_empty() {
+        debug!(
+            "Keyed arrays only differ in order; {:?}",
+            abbreviate(obj, options.blob_threshold)
+        );
+        Some(None)
+    } else {
+        Some(Some(Cow::Owned(Yaml::Array(patch))))
+    }
+}
+
+/// Extracts base and diffs for scalars counted by bucket for the quorum (see [`Bucketing`]).
+///
+/// The largest bucket meeting the quorum wins, ties going to the bucket seen first, and the
+/// base is the value most of its files set, ties going to the first of them.
+fn bucketed_diff_and_common<'a>(
+    objs: &[&'a Yaml],
+    bucketing: &Bucketing,
+    quorum_count: usize,
+    options: &DiffOptions,
+    with_diffs: bool,
+) -> Extraction<'a> {
+    // A value without a bucket is a bucket of its own, apart from the buckets proper
+    let buckets: Vec<(bool, Yaml)> = objs
+        .iter()
+        .map(|obj| match bucketing.bucket(obj) {
+            Some(bucket) => (true, bucket),
+            None => (false, (*obj).clone()),
+        })
+        .collect();
+    let count = |bucket: &(bool, Yaml)| buckets.iter().filter(|other| *other == bucket).count();
+    let winner = buckets
+        .iter()
+        .enumerate()
+        .filter(|(i, bucket)| !buckets[..*i].contains(bucket) && count(bucket) >= quorum_count)
+        .fold(None, |best: Option<(&(bool, Yaml), usize)>, (_, bucket)| match best {
+            Some(best) if best.1 >= count(bucket) => Some(best),
+            _ => Some((bucket, count(bucket))),
+        });
+    let Some((winner, _)) = winner else {
+        debug!("No bucket meets the quorum; including all values in diffs.");
+        return (None, whole_diffs(objs, with_diffs));
+    };
+
+    let members: Vec<&'a Yaml> = objs.iter().zip(&buckets).filter(|(_, bucket)| *bucket == winner).map(|(obj, _)| *obj).collect();
+    let base_val = equal_counts(&members, options)
+        .into_iter()
+        .fold(None, |best: Option<(&'a Yaml, usize)>, (value, count)| match best {
+            Some(best) if best.1 >= count => Some(best),
+            _ => Some((value, count)),
+        })
+        .map(|(value, _)| value)
+        .expect("the winning bucket has members");
+    debug!(
+        "Base value determined by bucket quorum: {:?}",
+        abbreviate(base_val, options.blob_threshold)
+    );
+    if !with_diffs {
+        return (Some(Cow::Borrowed(base_val)), vec![]);
+    }
+    let diffs = objs
+        .iter()
+        .map(|obj| {
+            if blob_equal(obj, base_val, options.blob_threshold) {
+                None
+            } else {
+                Some(Cow::Borrowed(*obj))
+            }
+        })
+        .collect();
+    (Some(Cow::Borrowed(base_val)), diffs)
+}
+
+/// Extracts base and diffs for arrays keyed by `merge_key`, matching elements by key.
+///
+/// Returns `None` if any of the arrays is not keyed.
+fn keyed_diff_and_common<'a>(
+    objs: &[&'a Yaml],
+    merge_key: &str,
+    options: &DiffOptions,
+    with_diffs: bool,
+) -> Option<Extraction<'a>> {
+    let mut per_file_keys = Vec::with_capacity(objs.len());
+    for obj in objs {
+        per_file_keys.push(strategic_merge::element_keys(obj.as_vec()?, merge_key)?);
+    }
+
+    // Collect all merge keys in first-seen order
+    let mut all_keys: Vec<&ElementKey> = Vec::new();
+    for keys in &per_file_keys {
+        for key in keys {
+            if !all_keys.contains(&key) {
+                all_keys.push(key);
+            }
+        }
+    }
+
+    let mut base_items = Vec::new();
+    let mut diffs: Vec<Vec<Yaml>> = vec![Vec::new(); objs.len()];
+    for key in all_keys {
+        let values_at_key: Vec<&Yaml> = objs
+            .iter()
+            .zip(&per_file_keys)
+            .map(|(obj, keys)| match keys.iter().position(|k| k == key) {
+                Some(index) => &obj.as_vec().unwrap()[index],
+                None => &Yaml::Null,
+            })
+            .collect();
+
+        let (sub_base, sub_diffs) = extract(&values_at_key, options, with_diffs, None, None);
+        let base_includes_key = sub_base.is_some();
+        if let Some(sub_base_val) = sub_base {
+            base_items.push(sub_base_val.into_owned());
+        }
+
+        for (i, sub_diff) in sub_diffs.into_iter().enumerate() {
+            match sub_diff {
+                Some(diff) if diff.is_null() && base_includes_key => {
+                    diffs[i].push(strategic_merge::delete_element(merge_key, key));
+                }
+                Some(diff) if diff.is_null() => {}
+                Some(diff) => {
+                    diffs[i].push(strategic_merge::with_merge_key(diff.into_owned(), merge_key, key));
+                }
+                None => {}
+            }
+        }
+    }
+
+    let base = if base_items.is_empty() {
+        None
+    } else {
+        Some(Cow::Owned(Yaml::Array(base_items)))
+    };
+    if !with_diffs {
+        return Some((base, vec![]));
+    }
+    let diffs = diffs
+        .into_iter()
+        .map(|items| {
+            if items.is_empty() {
+                None
+            } else {
+                Some(Cow::Owned(Yaml::Array(items)))
+            }
+        })
+        .collect();
+    Some((base, diffs))
+}
+
+/// Extracts the elements shared by every array as base; each diff keeps the elements its file adds.
+fn append_unique_diff_and_common<'a>(objs: &[&'a Yaml], with_diffs: bool) -> Extraction<'a> {
+    let all_arrays: Vec<&Vec<Yaml>> = objs.iter().filter_map(|obj| obj.as_vec()).collect();
+    let common = arrays::common_elements(&all_arrays);
+    let base = if common.is_empty() {
+        None
+    } else {
+        Some(Cow::Owned(Yaml::Array(common.iter().map(|item| (*item).clone()).collect())))
+    };
+    if !with_diffs {
+        return (base, vec![]);
+    }
+
+    let diffs = all_arrays
+        .iter()
+        .map(|array| {
+            let extras = arrays::extra_elements(array, &common);
+            if extras.is_empty() {
+                None
+            } else {
+                Some(Cow::Owned(Yaml::Array(extras.into_iter().cloned().collect())))
+            }
+        })
+        .collect();
+    (base, diffs)
+}
+
+/// Extracts the prefix shared by every array as base; each diff keeps the elements after it.
+///
+/// Without a shared prefix there is no base and every file keeps its whole array.
+fn prefix_diff_and_common<'a>(objs: &[&'a Yaml], with_diffs: bool) -> Extraction<'a> {
+    let all_arrays: Vec<&Vec<Yaml>> = objs.iter().filter_map(|obj| obj.as_vec()).collect();
+    let prefix_len = arrays::common_prefix_len(&all_arrays);
+    if prefix_len == 0 {
+        return (None, whole_diffs(objs, with_diffs));
+    }
+    let base = Some(Cow::Owned(Yaml::Array(all_arrays[0][..prefix_len].to_vec())));
+    if !with_diffs {
+        return (base, vec![]);
+    }
+
+    let diffs = all_arrays
+        .iter()
+        .map(|array| {
+            if array.len() == prefix_len {
+                None
+            } else {
+                Some(Cow::Owned(Yaml::Array(array[prefix_len..].to_vec())))
+            }
+        })
+        .collect();
+    (base, diffs)
+}
+
+/// Extracts a subsequence common to every array as base; each diff is an edit script onto it.
+///
+/// The subsequence is folded pairwise from the first array, so it is common to all but not
+/// necessarily the longest possible. Without one there is no base and every file keeps its
+/// whole array. Returns `None` if an array is longer than `max_len`.
+fn lcs_diff_and_common<'a>(
+    objs: &[&'a Yaml],
+    max_len: usize,
+    options: &DiffOptions,
+    with_diffs: bool,
+) -> Option<Extraction<'a>> {
+    let all_arrays: Vec<&Vec<Yaml>> = objs.iter().filter_map(|obj| obj.as_vec()).collect();
+    if all_arrays.iter().any(|array| array.len() > max_len) {
+        return None;
+    }
+    let eq = |a: &Yaml, b: &Yaml| blob_equal(a, b, options.blob_threshold);
+    let common = all_arrays[1..]
+        .iter()
+        .fold(all_arrays[0].clone(), |common, array| lcs::common_subsequence(&common, array, eq));
+    if common.is_empty() {
+        return Some((None, whole_diffs(objs, with_diffs)));
+    }
+    if !with_diffs {
+        return Some((Some(Cow::Owned(Yaml::Array(common))), vec![]));
+    }
+
+    // The base is a subsequence of every array, so each script only inserts
+    let diffs = all_arrays
+        .iter()
+        .map(|array| lcs::edit_script(&common, array, eq, |_, _| None).map(Cow::Owned))
+        .collect();
+    Some((Some(Cow::Owned(Yaml::Array(common))), diffs))
+}
+
+/// Extracts the ordered-set base of arrays of unique elements (see [`crate::arrays`]); each
+/// diff is an edit script onto it.
+///
+/// Without a base every file keeps its whole array. Returns `None` if an array holds duplicate
+/// elements or is longer than [`lcs::DEFAULT_MAX_LEN`].
+fn ordered_set_diff_and_common<'a>(
+    objs: &[&'a Yaml],
+    quorum_count: usize,
+    options: &DiffOptions,
+    with_diffs: bool,
+) -> Option<Extraction<'a>> {
+    let all_arrays: Vec<&Vec<Yaml>> = objs.iter().filter_map(|obj| obj.as_vec()).collect();
+    if all_arrays.iter().any(|array| array.len() > lcs::DEFAULT_MAX_LEN || arrays::has_duplicates(array)) {
+        return None;
+    }
+    let common: Vec<Yaml> = arrays::ordered_common_elements(&all_arrays, quorum_count).into_iter().cloned().collect();
+    if common.is_empty() {
+        return Some((None, whole_diffs(objs, with_diffs)));
+    }
+    if !with_diffs {
+        return Some((Some(Cow::Owned(Yaml::Array(common))), vec![]));
+    }
+
+    let eq = |a: &Yaml, b: &Yaml| blob_equal(a, b, options.blob_threshold);
+    let diffs = all_arrays
+        .iter()
+        .map(|array| lcs::edit_script(&common, array, eq, |_, _| None).map(Cow::Owned))
+        .collect();
+    Some((Some(Cow::Owned(Yaml::Array(common))), diffs))
+}
+
 /// Recursively computes the common base and differences among multiple Yaml objects.
-pub fn diff_and_common_multiple<'a>(
+///
+/// A value becomes base when at least `quorum` (0.0-1.0) of the objects agree on it; each diff
+/// keeps what its object needs on top of the base, `None` meaning nothing.
+///
+/// ```
+/// use yabe::{diff_and_common_multiple, yaml};
+///
+/// let dev = yaml!("region: eu\nreplicas: 1");
+/// let staging = yaml!("region: eu\nreplicas: 2");
+/// let prod = yaml!("region: us\nreplicas: 2");
+/// let objs = [&dev, &staging, &prod];
+///
+/// let result = diff_and_common_multiple(&objs, 0.51);
+/// let base = result.base.as_deref().unwrap();
+/// assert_eq!(base["region"].as_str(), Some("eu"));
+/// assert_eq!(base["replicas"].as_i64(), Some(2));
+/// assert_eq!(result.diffs[0].as_deref(), Some(&yaml!("replicas: 1")));
+/// assert!(result.diffs[1].is_none());
+/// assert_eq!(result.diffs[2].as_deref(), Some(&yaml!("region: us")));
+///
+/// // Naming the diffs after their inputs makes the result easy to inspect
+/// let result = result.with_names(["dev", "staging", "prod"]);
+/// assert_eq!(result.diff("prod"), Some(&yaml!("region: us")));
+/// assert_eq!(result.diff_count(), 2);
+/// println!("{}", result);
+/// ```
+pub fn diff_and_common_multiple<'a>(objs: &'a [&'a Yaml], quorum: f64) -> DiffResult<'a> {
+    let options = DiffOptions {
+        quorum,
+        ..DiffOptions::default()
+    };
+    diff_and_common_with_options(objs, &options)
+}
+
+/// Same as [`diff_and_common_multiple`], honouring the given options.
+///
+/// ```
+/// use yabe::{diff_and_common_with_options, yaml, DiffOptions, NullHandling};
+///
+/// let a = yaml!("sidecar: ~\nport: 80");
+/// let b = yaml!("sidecar: {image: envoy}\nport: 80");
+/// let objs = [&a, &b];
+///
+/// // An explicit `null` falls back to the base instead of conflicting with it
+/// let options = DiffOptions { quorum: 1.0, null_handling: NullHandling::Inherit, ..DiffOptions::default() };
+/// let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+/// assert_eq!(base.unwrap()["sidecar"], yaml!("image: envoy"));
+/// assert!(diffs.iter().all(Option::is_none));
+/// ```
+///
+/// Empty documents (`null`, see [`crate::input::is_empty_document`]) are left out: they count
+/// neither towards the quorum nor as a type conflict, and their diff is `None`. If every
+/// document is empty, the base is `null`.
+pub fn diff_and_common_with_options<'a>(objs: &'a [&'a Yaml], options: &DiffOptions) -> DiffResult<'a> {
+    extract_result(objs, options, None)
+}
+
+/// Same as [`diff_and_common_with_options`], also returning the time spent extracting each
+/// top-level key, subtree included, in the order the keys were processed.
+///
+/// The preprocessing passes of `options` are not attributed to any key; nor is the
+/// [`BaseMode::Representative`] choice of a base. Without a top-level map there are no keys.
+pub fn diff_and_common_timed<'a>(objs: &'a [&'a Yaml], options: &DiffOptions) -> (DiffResult<'a>, KeyTimes) {
+    let mut key_times = Vec::new();
+    let result = extract_result(objs, options, Some(&mut key_times));
+    (result, key_times)
+}
+
+fn extract_result<'a>(objs: &'a [&'a Yaml], options: &DiffOptions, key_times: Option<&mut KeyTimes>) -> DiffResult<'a> {
+    if needs_normalization(options) {
+        let normalized = normalize_all(objs, options);
+        let refs: Vec<&Yaml> = normalized.iter().map(|obj| obj.as_ref()).collect();
+        let result = DiffResult::from(extract_base(&refs, options, true, key_times));
+        return keep_out_of_base(&refs, result, &options.ignored_paths).into_owned();
+    }
+    keep_out_of_base(objs, extract_base(objs, options, true, key_times).into(), &options.ignored_paths)
+}
+
+/// Same as [`diff_and_common_with_options`], but fails when the inputs hold values of different
+/// types at a path (see [`type_conflicts`]), which extraction would otherwise silently leave in
+/// every file's diff.
+///
+/// With [`StrictTypes::FailFast`] the error holds the first conflict; with
+/// [`StrictTypes::CollectAll`] it holds every one, so they can all be fixed at once. Conflicts
+/// are looked for after the preprocessing passes of `options`.
+///
+/// ```
+/// use yabe::diff::diff_and_common_strict;
+/// use yabe::schema::StrictTypes;
+/// use yabe::{yaml, DiffOptions};
+///
+/// let a = yaml!("port: 80\nresources: 512Mi");
+/// let b = yaml!("port: '80'\nresources: {limits: {memory: 512Mi}}");
+/// let objs = [&a, &b];
+///
+/// let conflicts = diff_and_common_strict(&objs, &DiffOptions::default(), StrictTypes::CollectAll).unwrap_err();
+/// let paths: Vec<&str> = conflicts.iter().map(|c| c.path.as_str()).collect();
+/// assert_eq!(paths, ["port", "resources"]);
+/// assert_eq!(diff_and_common_strict(&objs, &DiffOptions::default(), StrictTypes::FailFast).unwrap_err().len(), 1);
+/// ```
+pub fn diff_and_common_strict<'a>(
     objs: &'a [&'a Yaml],
-    quorum: f64,
-) -> (Option<Cow<'a, Yaml>>, Vec<Option<Cow<'a, Yaml>>>) {
+    options: &DiffOptions,
+    mode: StrictTypes,
+) -> Result<DiffResult<'a>, Vec<TypeConflict>> {
+    let normalized = normalize_all(objs, options);
+    let refs: Vec<&Yaml> = normalized.iter().map(|obj| obj.as_ref()).collect();
+    let conflicts = type_conflicts(&refs, mode);
+    if conflicts.is_empty() {
+        Ok(diff_and_common_with_options(objs, options))
+    } else {
+        Err(conflicts)
+    }
+}
+
+/// Same as [`diff_and_common_with_options`] over named inputs, returning the diffs keyed by
+/// name in input order.
+///
+/// ```
+/// use yabe::diff::diff_and_common_named;
+/// use yabe::{yaml, DiffOptions};
+///
+/// let dev = yaml!("image: nginx\nreplicas: 1");
+/// let prod = yaml!("image: nginx\nreplicas: 3");
+/// let inputs = [("dev.yaml".to_string(), &dev), ("prod.yaml".to_string(), &prod)];
+///
+/// let (base, diffs) = diff_and_common_named(&inputs, &DiffOptions::default());
+/// assert_eq!(base.unwrap()["image"].as_str(), Some("nginx"));
+/// assert_eq!(diffs.keys().collect::<Vec<_>>(), ["dev.yaml", "prod.yaml"]);
+/// assert_eq!(diffs["prod.yaml"].as_deref(), Some(&yaml!("replicas: 3")));
+/// ```
+///
+/// # Panics
+///
+/// Panics if two inputs have the same name.
+pub fn diff_and_common_named(
+    inputs: &[(String, &Yaml)],
+    options: &DiffOptions,
+) -> (Option<Cow<'static, Yaml>>, DiffsByName<'static>) {
+    let objs: Vec<&Yaml> = inputs.iter().map(|(_, obj)| *obj).collect();
+    diff_and_common_with_options(&objs, options)
+        .into_owned()
+        .with_names(inputs.iter().map(|(name, _)| name.as_str()))
+        .into_named_parts()
+}
+
+/// Same as [`diff_and_common_with_options`], but only the inputs for whose index `contributes`
+/// returns `true` make up the base; every input, contributing or not, gets its diff against it.
+///
+/// The quorum applies within the contributors: with a quorum of 0.51 and three contributors, a
+/// value two of them set the same is hoisted, however many other inputs there are. Contributors
+/// get the diffs they would get if they were the only inputs. The others are diffed against the
+/// base as with [`compute_diff_with_options`]; with no base, an input's diff is all of it. Empty
+/// documents get no diff either way.
+///
+/// ```
+/// use yabe::diff::diff_and_common_from;
+/// use yabe::{yaml, DiffOptions};
+///
+/// let eu_dev = yaml!("region: eu\nreplicas: 1");
+/// let eu_prod = yaml!("region: eu\nreplicas: 3");
+/// let us_prod = yaml!("region: us\nreplicas: 3");
+/// let objs = [&eu_dev, &eu_prod, &us_prod];
+///
+/// // Only the EU files make up the base; the US file is diffed against it
+/// let options = DiffOptions { quorum: 1.0, ..DiffOptions::default() };
+/// let result = diff_and_common_from(&objs, |index| index < 2, &options);
+/// assert_eq!(result.base.as_deref(), Some(&yaml!("region: eu")));
+/// assert_eq!(result.diffs[0].as_deref(), Some(&yaml!("replicas: 1")));
+/// assert_eq!(result.diffs[2].as_deref(), Some(&yaml!("region: us\nreplicas: 3")));
+/// ```
+pub fn diff_and_common_from(
+    objs: &[&Yaml],
+    contributes: impl Fn(usize) -> bool,
+    options: &DiffOptions,
+) -> DiffResult<'static> {
+    let contributors: Vec<&Yaml> =
+        objs.iter().enumerate().filter(|(index, _)| contributes(*index)).map(|(_, obj)| *obj).collect();
+    let (base, contributor_diffs) = diff_and_common_with_options(&contributors, options).into_owned().into_parts();
+
+    let mut contributor_diffs = contributor_diffs.into_iter();
+    let diffs = objs
+        .iter()
+        .enumerate()
+        .map(|(index, obj)| {
+            if contributes(index) {
+                contributor_diffs.next().flatten()
+            } else if is_empty_document(obj) {
+                None
+            } else {
+                let base = base.as_deref().unwrap_or(&Yaml::Null);
+                compute_diff_with_options(obj, base, options).map(|diff| Cow::Owned(diff.into_owned()))
+            }
+        })
+        .collect();
+    DiffResult::new(base, diffs)
+}
+
+/// Computes only the common base among multiple Yaml objects, skipping the per-file diffs.
+///
+/// The result is the same as the base returned by [`diff_and_common_with_options`].
+pub fn common_base(objs: &[&Yaml], options: &DiffOptions) -> Option<Yaml> {
+    if needs_normalization(options) {
+        let normalized = normalize_all(objs, options);
+        let refs: Vec<&Yaml> = normalized.iter().map(|obj| obj.as_ref()).collect();
+        let base = DiffResult::new(extract_base(&refs, options, false, None).0, vec![]);
+        return keep_out_of_base(&refs, base, &options.ignored_paths).base.map(Cow::into_owned);
+    }
+    let base = DiffResult::new(extract_base(objs, options, false, None).0, vec![]);
+    keep_out_of_base(objs, base, &options.ignored_paths).base.map(Cow::into_owned)
+}
+
+/// Runs the extraction at the top level, applying the [`BaseMode`].
+fn extract_base<'a>(
+    objs: &[&'a Yaml],
+    options: &DiffOptions,
+    with_diffs: bool,
+    key_times: Option<&mut KeyTimes>,
+) -> Extraction<'a> {
+    // Empty documents contribute nothing: they are left out of the quorum and get no diff
+    let present: Vec<&'a Yaml> =
+        objs.iter().copied().filter(|obj| !is_empty_document(obj)).collect();
+    if !present.is_empty() && present.len() < objs.len() {
+        debug!("Ignoring {} empty documents.", objs.len() - present.len());
+        let (base, present_diffs) = extract_base(&present, options, with_diffs, key_times);
+        if !with_diffs {
+            return (base, vec![]);
+        }
+        let mut present_diffs = present_diffs.into_iter();
+        let diffs = objs
+            .iter()
+            .map(|obj| {
+                if is_empty_document(obj) {
+                    None
+                } else {
+                    present_diffs.next().flatten()
+                }
+            })
+            .collect();
+        return (base, diffs);
+    }
+
+    // Paths are only tracked for the bucketing and atomic paths that need them
+    let mut path = Vec::new();
+    let path = (!options.value_buckets.is_empty() || !options.atomic_paths.is_empty()).then_some(&mut path);
+    let (base, diffs) = extract(objs, options, with_diffs, key_times, path);
+    if options.base_mode == BaseMode::Synthetic {
+        return (base, diffs);
+    }
+    let Some(synthetic) = base else {
+        return (None, diffs);
+    };
+
+    // The first input of minimal distance to the synthetic base wins
+    let (index, representative) = objs
+        .iter()
+        .copied()
+        .enumerate()
+        .min_by_key(|(_, obj)| distance(obj, &synthetic, options))
+        .expect("a base implies at least one input");
+    debug!("Input {} of {} is the representative base.", index + 1, objs.len());
+    if !with_diffs {
+        return (Some(Cow::Borrowed(representative)), vec![]);
+    }
+    let diffs = objs
+        .iter()
+        .map(|obj| rebase(obj, representative, options, &mut Vec::new()).map(Cow::Owned))
+        .collect();
+    (Some(Cow::Borrowed(representative)), diffs)
+}
+
+/// Counts the leaves by which two values differ; see [`BaseMode`].
+pub fn distance(a: &Yaml, b: &Yaml, options: &DiffOptions) -> usize {
+    match (a, b) {
+        (Yaml::Hash(a_hash), Yaml::Hash(b_hash)) => {
+            let changed: usize = a_hash
+                .iter()
+                .map(|(key, a_value)| match b_hash.get(key) {
+                    Some(b_value) => distance(a_value, b_value, options),
+                    None => count_leaves(a_value),
+                })
+                .sum();
+            let missing: usize = b_hash
+                .iter()
+                .filter(|(key, _)| !a_hash.contains_key(key))
+                .map(|(_, b_value)| count_leaves(b_value))
+                .sum();
+            changed + missing
+        }
+        _ if blob_equal(a, b, options.blob_threshold) => 0,
+        _ => 1,
+    }
+}
+
+/// How [`count_leaves_with`] counts the leaves of arrays, for the metrics built on it (override
+/// sizes, coverage and run statistics).
+///
+/// Positional diffs hold `null` in the slots of unchanged elements. Neither policy counts these
+/// placeholders as overrides: a whole array is one leaf however many it holds, and walking into
+/// arrays skips them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LeafCounting {
+    /// An array is one leaf, whatever it holds.
+    #[default]
+    WholeArrays,
+    /// Non-empty arrays are walked into like maps, and their `null` elements count 0; an
+    /// element changed to `null` cannot be told from an unchanged one.
+    Elements,
+}
+
+impl FromStr for LeafCounting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "whole-arrays" => Ok(LeafCounting::WholeArrays),
+            "elements" => Ok(LeafCounting::Elements),
+            _ => Err(format!("unknown leaf counting '{}', expected one of: whole-arrays, elements", s)),
+        }
+    }
+}
+
+impl fmt::Display for LeafCounting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LeafCounting::WholeArrays => "whole-arrays",
+            LeafCounting::Elements => "elements",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Counts the leaves of a value: non-empty maps are walked into, anything else is one leaf.
+pub fn count_leaves(value: &Yaml) -> usize {
+    count_leaves_with(value, LeafCounting::WholeArrays)
+}
+
+/// Counts the leaves of a value like [`count_leaves`], arrays counted as `counting` says.
+///
+/// ```
+/// use yabe::diff::{count_leaves_with, LeafCounting};
+/// use yabe::yaml;
+///
+/// let diff = yaml!("args: [~, --verbose, ~]\nreplicas: 3");
+/// assert_eq!(count_leaves_with(&diff, LeafCounting::WholeArrays), 2);
+/// assert_eq!(count_leaves_with(&diff, LeafCounting::Elements), 2);
+/// assert_eq!(count_leaves_with(&yaml!("args: [-v, --verbose]"), LeafCounting::Elements), 2);
+/// ```
+pub fn count_leaves_with(value: &Yaml, counting: LeafCounting) -> usize {
+    match value {
+        Yaml::Hash(hash) if !hash.is_empty() => hash.values().map(|value| count_leaves_with(value, counting)).sum(),
+        Yaml::Array(items) if !items.is_empty() && counting == LeafCounting::Elements => items
+            .iter()
+            .filter(|item| !item.is_null())
+            .map(|item| count_leaves_with(item, counting))
+            .sum(),
+        _ => 1,
+    }
+}
+
+/// Diffs an input against a representative base, with `null` for keys the input lacks.
+fn rebase(obj: &Yaml, representative: &Yaml, options: &DiffOptions, path: &mut Vec<String>) -> Option<Yaml> {
+    let (Yaml::Hash(obj_hash), Yaml::Hash(rep_hash)) = (obj, representative) else {
+        return diff_value(obj, representative, options).map(Cow::into_owned);
+    };
+    if options.is_atomic(path) {
+        return (!blob_equal(obj, representative, options.blob_threshold)).then(|| replacing_diff(obj, representative));
+    }
+    let mut diff = Hash::new();
+    for (key, obj_value) in obj_hash {
+        if obj_value.is_null() && options.null_handling == NullHandling::Inherit {
+            continue;
+        }
+        path.push(key_segment(key));
+        let value_diff = match rep_hash.get(key) {
+            Some(rep_value) => rebase(obj_value, rep_value, options, path),
+            None => diff_value(obj_value, &Yaml::Null, options).map(Cow::into_owned),
+        };
+        path.pop();
+        if let Some(value_diff) = value_diff {
+            diff.insert(key.clone(), value_diff);
+        }
+    }
+    for key in rep_hash.keys().filter(|key| !obj_hash.contains_key(key)) {
+        diff.insert(key.clone(), Yaml::Null);
+    }
+    if diff.is_empty() {
+        None
+    } else {
+        Some(Yaml::Hash(diff))
+    }
+}
+
+/// Diffs a value that replaces `base` as a whole: the value itself, with `null` for every key
+/// of the maps of `base` that it lacks, so that merging it onto `base` gives it back.
+fn replacing_diff(obj: &Yaml, base: &Yaml) -> Yaml {
+    let (Yaml::Hash(obj_hash), Yaml::Hash(base_hash)) = (obj, base) else {
+        return obj.clone();
+    };
+    let mut diff: Hash = obj_hash
+        .iter()
+        .map(|(key, value)| match base_hash.get(key) {
+            Some(base_value) => (key.clone(), replacing_diff(value, base_value)),
+            None => (key.clone(), value.clone()),
+        })
+        .collect();
+    for key in base_hash.keys().filter(|key| !obj_hash.contains_key(key)) {
+        diff.insert(key.clone(), Yaml::Null);
+    }
+    Yaml::Hash(diff)
+}
+
+/// Returns an empty map or array if `value` is one, see [`DiffOptions::null_as_empty`].
+fn empty_like(value: &Yaml) -> Option<&'static Yaml> {
+    static EMPTY_HASH: OnceLock<Yaml> = OnceLock::new();
+    static EMPTY_ARRAY: OnceLock<Yaml> = OnceLock::new();
+    match value {
+        Yaml::Hash(_) => Some(EMPTY_HASH.get_or_init(|| Yaml::Hash(Hash::new()))),
+        Yaml::Array(_) => Some(EMPTY_ARRAY.get_or_init(|| Yaml::Array(vec![]))),
+        _ => None,
+    }
+}
+
+/// Returns the empty collection matching `values` if they are all maps or all arrays.
+fn common_empty<'v>(mut values: impl Iterator<Item = &'v Yaml>) -> Option<&'static Yaml> {
+    let first = values.next()?;
+    let empty = empty_like(first)?;
+    values.all(|value| type_name(value) == type_name(first)).then_some(empty)
+}
+
+/// Extracts maps at an atomic path: the first map a quorum of files set the same, compared as a
+/// whole, is the base, and every other file keeps its whole map in its diff.
+fn atomic_diff_and_common<'a>(
+    objs: &[&'a Yaml],
+    quorum_count: usize,
+    options: &DiffOptions,
+    with_diffs: bool,
+) -> Extraction<'a> {
+    let counts = equal_counts(objs, options);
+    let Some(&(base_value, _)) = counts.iter().find(|(_, count)| *count >= quorum_count) else {
+        debug!("No map meets the quorum; including all maps in diffs.");
+        return (None, whole_diffs(objs, with_diffs));
+    };
+    if !with_diffs {
+        return (Some(Cow::Borrowed(base_value)), vec![]);
+    }
+    let diffs = objs
+        .iter()
+        .map(|obj| {
+            (!blob_equal(obj, base_value, options.blob_threshold)).then(|| Cow::Owned(replacing_diff(obj, base_value)))
+        })
+        .collect();
+    (Some(Cow::Borrowed(base_value)), diffs)
+}
+
+/// Counts the objects equal to each other, as diffs compare them: each distinct value, the first
+/// object holding it standing for the others, with the number of objects equal to it, in input
+/// order.
+///
+/// Values are grouped by [`blob_equal`] rather than hashed, so counting agrees with comparing:
+/// maps differing only in key order are counted together, at any depth.
+fn equal_counts<'a>(objs: &[&'a Yaml], options: &DiffOptions) -> Vec<(&'a Yaml, usize)> {
+    let mut counts: Vec<(&Yaml, usize)> = Vec::new();
+    for obj in objs {
+        match counts.iter_mut().find(|(value, _)| blob_equal(value, obj, options.blob_threshold)) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((obj, 1)),
+        }
+    }
+    counts
+}
+
+/// Returns every object as its own diff, or nothing when diffs are not wanted.
+fn whole_diffs<'a>(objs: &[&'a Yaml], with_diffs: bool) -> Vec<Option<Cow<'a, Yaml>>> {
+    if with_diffs {
+        objs.iter().map(|obj| Some(Cow::Borrowed(*obj))).collect()
+    } else {
+        vec![]
+    }
+}
+
+/// Recursively extracts the base and, if `with_diffs` is set, the per-file diffs.
+///
+/// With `key_times`, the time spent on each key of a top-level map is recorded in it.
+fn extract<'a>(
+    objs: &[&'a Yaml],
+    options: &DiffOptions,
+    with_diffs: bool,
+    mut key_times: Option<&mut KeyTimes>,
+    mut path: Option<&mut Vec<String>>,
+) -> Extraction<'a> {
+    let quorum = options.quorum;
     debug!(
         "diff_and_common_multiple called with {} objects and quorum {}%.",
         objs.len(),
@@ -75,20 +1034,11 @@ pub fn diff_and_common_multiple<'a>(
     let quorum_count = (quorum * total_files as f64).ceil() as usize;
 
     // Collect types of each object and check for type differences in a single pass
-    let mut type_set = HashSet::new();
+    let mut type_set: HashSet<&str, FixedState> = HashSet::default();
     let mut obj_type = "";
 
     for obj in objs {
-        let obj_type_str = match obj {
-            Yaml::Null => "null",
-            Yaml::Boolean(_) => "bool",
-            Yaml::Integer(_) => "int",
-            Yaml::Real(_) => "real",
-            Yaml::String(_) => "string",
-            Yaml::Array(_) => "array",
-            Yaml::Hash(_) => "hash",
-            _ => "unknown",
-        };
+        let obj_type_str = type_name(obj);
         type_set.insert(obj_type_str);
         if obj_type.is_empty() {
             obj_type = obj_type_str;
@@ -98,37 +1048,89 @@ pub fn diff_and_common_multiple<'a>(
     // If types differ, include them in diffs
     if type_set.len() > 1 {
         debug!("Types differ. Including entire values in diffs.");
-        return (
-            None,
-            objs.iter().map(|obj| Some(Cow::Borrowed(*obj))).collect(),
-        );
+        return (None, whole_diffs(objs, with_diffs));
     }
 
-    // Handle primitive types and arrays as atomic units
-    if obj_type != "hash" {
-        debug!("Handling primitive types or arrays as atomic units.");
+    // Shared arrays stay duplicated in every diff when only scalars are hoisted
+    if obj_type == "array" && options.hoist_scalars_only {
+        debug!("Only hoisting scalars; including arrays in diffs.");
+        return (None, whole_diffs(objs, with_diffs));
+    }
 
-        // Collect occurrences of unique values using deep comparison
-        let mut occurrences: HashMap<&Yaml, usize> = HashMap::new();
-        for obj in objs {
-            *occurrences.entry(*obj).or_insert(0) += 1;
+    // Arrays keyed by the strategic merge key are matched element by element
+    if obj_type == "array" {
+        if let Some(merge_key) = options.merge_key() {
+            if let Some(result) = keyed_diff_and_common(objs, merge_key, options, with_diffs) {
+                debug!("Handled arrays keyed by '{}'.", merge_key);
+                return result;
+            }
         }
+    }
 
-        // Find the value(s) that meet the quorum
-        let base_value = occurrences.iter().find_map(|(val, &count)| {
-            if count >= quorum_count {
-                Some(*val)
-            } else {
-                None
+    if obj_type == "array" && options.array_strategy == ArrayStrategy::AppendUnique {
+        debug!("Handling arrays as base list plus per-file extras.");
+        return append_unique_diff_and_common(objs, with_diffs);
+    }
+
+    if obj_type == "array" && options.array_strategy == ArrayStrategy::Prefix {
+        debug!("Handling arrays as a shared prefix plus per-file trailing elements.");
+        return prefix_diff_and_common(objs, with_diffs);
+    }
+
+    if obj_type == "array" && options.array_strategy == ArrayStrategy::OrderedSet {
+        if let Some(result) = ordered_set_diff_and_common(objs, quorum_count, options, with_diffs) {
+            debug!("Handled arrays as ordered sets.");
+            return result;
+        }
+    }
+
+    if obj_type == "array" {
+        if let Some(max_len) = options.lcs_max_len() {
+            if let Some(result) = lcs_diff_and_common(objs, max_len, options, with_diffs) {
+                debug!("Aligned arrays by their longest common subsequence.");
+                return result;
             }
-        });
+        }
+    }
+
+    // Scalars at a bucketed path are counted by bucket
+    if obj_type != "hash" && obj_type != "array" {
+        if let Some(bucketing) = path.as_deref().and_then(|path| options.bucketing_at(path)) {
+            return bucketed_diff_and_common(objs, bucketing, quorum_count, options, with_diffs);
+        }
+    }
+
+    // Maps at an atomic path are hoisted or kept whole
+    if obj_type == "hash" && path.as_deref().is_some_and(|path| options.is_atomic(path)) {
+        if options.hoist_scalars_only {
+            debug!("Only hoisting scalars; including atomic maps in diffs.");
+            return (None, whole_diffs(objs, with_diffs));
+        }
+        debug!("Handling maps at an atomic path as a whole.");
+        return atomic_diff_and_common(objs, quorum_count, options, with_diffs);
+    }
+
+    // Handle primitive types and arrays as atomic units
+    if obj_type != "hash" {
+        debug!("Handling primitive types or arrays as atomic units.");
+
+        // Find the first value, in input order, that meets the quorum
+        let base_value = equal_counts(objs, options)
+            .into_iter()
+            .find_map(|(val, count)| if count >= quorum_count { Some(val) } else { None });
 
         if let Some(base_val) = base_value {
-            debug!("Base value determined by quorum: {:?}", base_val);
+            debug!(
+                "Base value determined by quorum: {:?}",
+                abbreviate(base_val, options.blob_threshold)
+            );
+            if !with_diffs {
+                return (Some(Cow::Borrowed(base_val)), vec![]);
+            }
             let diffs = objs
                 .iter()
                 .map(|obj| {
-                    if deep_equal(obj, base_val) {
+                    if blob_equal(obj, base_val, options.blob_threshold) {
                         None
                     } else {
                         Some(Cow::Borrowed(*obj))
@@ -139,10 +1141,7 @@ pub fn diff_and_common_multiple<'a>(
         } else {
             // No value meets the quorum; include all values in diffs
             debug!("No value meets the quorum; including all values in diffs.");
-            return (
-                None,
-                objs.iter().map(|obj| Some(Cow::Borrowed(*obj))).collect(),
-            );
+            return (None, whole_diffs(objs, with_diffs));
         }
     }
 
@@ -150,7 +1149,7 @@ pub fn diff_and_common_multiple<'a>(
     if obj_type == "hash" {
         debug!("Handling hashes (maps).");
         // Collect all unique keys
-        let mut all_keys = HashSet::new();
+        let mut all_keys: HashSet<&Yaml, FixedState> = HashSet::default();
         for obj in objs {
             if let Yaml::Hash(ref h) = obj {
                 all_keys.extend(h.keys());
@@ -159,28 +1158,96 @@ pub fn diff_and_common_multiple<'a>(
 
         // Initialize base hash and diffs
         let mut base_hash = Hash::new();
-        let mut diffs: Vec<Hash> = vec![Hash::new(); objs.len()];
+        let diff_count = if with_diffs { objs.len() } else { 0 };
+        let mut diffs: Vec<Hash> = vec![Hash::new(); diff_count];
         let mut has_base = false;
-        let mut has_diffs = vec![false; objs.len()];
+        let mut has_diffs = vec![false; diff_count];
 
         // Iterate over all keys
         for key in &all_keys {
             debug!("Processing key: {:?}", key);
+            let started = key_times.is_some().then(Instant::now);
 
-            // Collect values at current key from all objects
-            let values_at_key: Vec<&Yaml> = objs
+            // Collect values at current key from all objects; `None` marks a missing key
+            let slots: Vec<Option<&Yaml>> = objs
                 .iter()
                 .map(|obj| {
                     if let Yaml::Hash(ref h) = obj {
-                        h.get(*key).unwrap_or(&Yaml::Null)
+                        h.get(*key)
                     } else {
-                        &Yaml::Null
+                        None
                     }
                 })
                 .collect();
+            let is_explicit_null: Vec<bool> = slots
+                .iter()
+                .map(|slot| matches!(slot, Some(Yaml::Null)))
+                .collect();
 
-            // Recursively process the values at this key
-            let (sub_base, sub_diffs) = diff_and_common_multiple(&values_at_key, quorum);
+            // Files with an inherited null have no opinion on this key
+            let participants: Vec<usize> = (0..objs.len())
+                .filter(|&i| {
+                    !(options.null_handling == NullHandling::Inherit && is_explicit_null[i])
+                })
+                .collect();
+            if participants.is_empty() {
+                debug!("Only inherited nulls at key {:?}; skipping.", key);
+                continue;
+            }
+
+            // A key only some files set may be extracted among those files alone
+            let (present, absent): (Vec<usize>, Vec<usize>) =
+                participants.iter().partition(|&&i| slots[i].is_some());
+            let among_present = !absent.is_empty()
+                && match options.minority_keys {
+                    MinorityKeys::KeepInDiff => false,
+                    MinorityKeys::Hoist => true,
+                    MinorityKeys::HoistIfAllPresentAgree => present.windows(2).all(|pair| {
+                        blob_equal(slots[pair[0]].unwrap(), slots[pair[1]].unwrap(), options.blob_threshold)
+                    }),
+                };
+            let (participants, absent) = if among_present {
+                debug!("Key {:?} is missing from {} files; extracting it among the others.", key, absent.len());
+                (present, absent)
+            } else {
+                (participants, vec![])
+            };
+
+            let values_at_key: Vec<&Yaml> = participants
+                .iter()
+                .map(|&i| slots[i].unwrap_or(&Yaml::Null))
+                .collect();
+
+            // An explicit null may stand for the empty collection the other files have
+            let values_at_key = if options.null_as_empty {
+                let empty = common_empty(values_at_key.iter().copied().filter(|value| !value.is_null()));
+                participants
+                    .iter()
+                    .zip(values_at_key)
+                    .map(|(&i, value)| match (slots[i], empty) {
+                        (Some(Yaml::Null), Some(empty)) => empty,
+                        _ => value,
+                    })
+                    .collect()
+            } else {
+                values_at_key
+            };
+
+            // Recursively process the values at this key, unless too few files set it
+            let presence = slots.iter().filter(|slot| matches!(slot, Some(value) if !value.is_null())).count();
+            let (sub_base, sub_diffs) = if presence < options.min_presence {
+                debug!("Key {:?} is set by {} files, fewer than {}; keeping it in diffs.", key, presence, options.min_presence);
+                (None, whole_diffs(&values_at_key, with_diffs))
+            } else {
+                if let Some(path) = path.as_deref_mut() {
+                    path.push(key_segment(key));
+                }
+                let extraction = extract(&values_at_key, options, with_diffs, None, path.as_deref_mut());
+                if let Some(path) = path.as_deref_mut() {
+                    path.pop();
+                }
+                extraction
+            };
 
             if let Some(ref sub_base_val) = sub_base {
                 // Base value meets quorum
@@ -190,14 +1257,27 @@ pub fn diff_and_common_multiple<'a>(
 
             let base_includes_key = sub_base.is_some();
 
-            for (i, sub_diff) in sub_diffs.into_iter().enumerate() {
+            // Files lacking a hoisted key remove it again
+            if base_includes_key && with_diffs {
+                for &i in &absent {
+                    diffs[i].insert((*key).clone(), Yaml::Null);
+                    has_diffs[i] = true;
+                }
+            }
+
+            for (&i, sub_diff) in participants.iter().zip(sub_diffs) {
                 if let Some(sub_diff_val) = sub_diff {
-                    if !sub_diff_val.is_null() || base_includes_key {
+                    let keep_null = options.null_handling == NullHandling::Value
+                        && is_explicit_null[i];
+                    if !sub_diff_val.is_null() || base_includes_key || keep_null {
                         diffs[i].insert((*key).clone(), sub_diff_val.into_owned());
                         has_diffs[i] = true;
                     }
                 }
             }
+            if let (Some(key_times), Some(started)) = (key_times.as_deref_mut(), started) {
+                key_times.push((key_segment(key), started.elapsed()));
+            }
         }
 
         // Prepare base and diffs for return
@@ -223,8 +1303,5 @@ pub fn diff_and_common_multiple<'a>(
 
     // Should not reach here; treat as diffs
     debug!("Unhandled object type. Including entire values in diffs.");
-    (
-        None,
-        objs.iter().map(|obj| Some(Cow::Borrowed(*obj))).collect(),
-    )
+    (None, whole_diffs(objs, with_diffs))
 }
\ No newline at end of file
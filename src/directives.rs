@@ -0,0 +1,128 @@
+//! Document-level directives and markers, kept from the inputs for the files written.
+//!
+//! The parser drops everything around a document's content, so the files yabe writes start
+//! without a directive or a `---` marker. Linters of some environments require them; when
+//! asked, the base and diff files are written with the ones of the first input:
+//!
+//! * the `%YAML` version directive, written with the `---` marker it requires;
+//! * the `---` document start marker;
+//! * the `...` document end marker.
+//!
+//! Only the first document of an input is looked at, as only that one is read. Other
+//! directives, like `%TAG`, are not kept: the tags they define are not kept either, so they
+//! would name handles the output no longer uses. Comments around the markers are not kept.
+
+use std::io;
+
+/// The directives and markers of a document, see the [module documentation](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Directives {
+    /// The version of the `%YAML` directive, e.g. `1.2`.
+    pub version: Option<String>,
+    /// Whether the document starts with `---`.
+    pub start_marker: bool,
+    /// Whether the document ends with `...`.
+    pub end_marker: bool,
+}
+
+impl Directives {
+    /// Returns whether there is nothing to write.
+    pub fn is_empty(&self) -> bool {
+        *self == Directives::default()
+    }
+
+    /// The lines written before the content: the version directive, then the start marker.
+    pub fn header(&self) -> String {
+        match &self.version {
+            Some(version) => format!("%YAML {}\n---\n", version),
+            None if self.start_marker => "---\n".to_string(),
+            None => String::new(),
+        }
+    }
+
+    /// The line written after the content: the end marker.
+    pub fn footer(&self) -> &'static str {
+        if self.end_marker {
+            "...\n"
+        } else {
+            ""
+        }
+    }
+
+    /// Writes a document's content, as rendered by `write`, between the header and the footer.
+    pub fn write_around<W: io::Write>(
+        &self,
+        writer: &mut W,
+        write: impl FnOnce(&mut W) -> io::Result<()>,
+    ) -> io::Result<()> {
+        writer.write_all(self.header().as_bytes())?;
+        write(writer)?;
+        writer.write_all(self.footer().as_bytes())
+    }
+}
+
+/// Reads the directives and markers of the first document of a YAML source.
+///
+/// ```
+/// use yabe::directives::scan_directives;
+///
+/// let directives = scan_directives("# values\n%YAML 1.2\n---\nreplicas: 2\n...\n");
+/// assert_eq!(directives.version.as_deref(), Some("1.2"));
+/// assert!(directives.start_marker && directives.end_marker);
+/// assert_eq!(directives.header(), "%YAML 1.2\n---\n");
+/// ```
+pub fn scan_directives(source: &str) -> Directives {
+    let mut directives = Directives::default();
+    let mut lines = source.lines();
+    // The prologue: directives, comments and blank lines, up to the start marker or the content
+    for line in lines.by_ref() {
+        if let Some(directive) = line.strip_prefix('%') {
+            let mut words = directive.split_whitespace();
+            if words.next() == Some("YAML") {
+                directives.version = words.next().map(str::to_string);
+            }
+        } else if is_marker(line, "---") {
+            directives.start_marker = true;
+            break;
+        } else if !is_blank_or_comment(line) {
+            break;
+        }
+    }
+    // The content ends at the end marker or at the start of the next document
+    for line in lines {
+        if is_marker(line, "...") {
+            directives.end_marker = true;
+            break;
+        }
+        if is_marker(line, "---") {
+            break;
+        }
+    }
+    directives
+}
+
+/// Returns the indices of the inputs whose `%YAML` version is not the first input's, an input
+/// without the directive included.
+pub fn disagreeing_versions(directives: &[Directives]) -> Vec<usize> {
+    let Some(first) = directives.first() else {
+        return vec![];
+    };
+    directives
+        .iter()
+        .enumerate()
+        .filter(|(_, other)| other.version != first.version)
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Checks whether a line is a document marker: at the start of the line, followed by nothing,
+/// a space or a tab.
+fn is_marker(line: &str, marker: &str) -> bool {
+    line.strip_prefix(marker)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+}
+
+fn is_blank_or_comment(line: &str) -> bool {
+    let line = line.trim_start();
+    line.is_empty() || line.starts_with('#')
+}
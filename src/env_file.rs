@@ -0,0 +1,161 @@
+//! Diffs as environment variable files (`.env`), for applications configured through their
+//! environment.
+//!
+//! Each leaf of a diff, as flattened by [`crate::flatten::flatten`], becomes one `NAME=value`
+//! line:
+//!
+//! * The name joins the path's keys and array indices with `_`, uppercased, with every character
+//!   other than an ASCII letter, a digit or `_` replaced by `_`: `image.tag` is `IMAGE_TAG`,
+//!   `ports[0].name` is `PORTS_0_NAME` and `app.kubernetes.io/name` is `APP_KUBERNETES_IO_NAME`.
+//!   A name starting with a digit is prefixed with `_`. Paths written the same way, like `a.b`
+//!   and `a_b`, cannot both be set.
+//! * A value made only of ASCII letters, digits and `_-./:@%+,` is written as is; any other
+//!   value, an empty one included, is double-quoted, with `\`, `"`, `$` and `` ` `` escaped by a
+//!   backslash and line breaks written `\n` and `\r`. Numbers and booleans are written as in the
+//!   YAML, and `null` as an empty value, as an environment variable cannot be unset by a file.
+//! * Empty maps and arrays have no value of their own; see [`NonScalar`].
+//!
+//! Lines are sorted by name. A diff that is not a map has no path to name.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use yaml_rust2::Yaml;
+
+use crate::flatten::flatten;
+use crate::path::{parse_path, Segment};
+
+/// How leaves that are not scalars, empty maps and arrays, are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonScalar {
+    /// Fail: the variable could not be told apart from an empty string.
+    #[default]
+    Error,
+    /// Write them as JSON, `{}` and `[]`.
+    Json,
+}
+
+impl FromStr for NonScalar {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(NonScalar::Error),
+            "json" => Ok(NonScalar::Json),
+            _ => Err(format!("unknown non-scalar mode '{}', expected one of: error, json", s)),
+        }
+    }
+}
+
+impl fmt::Display for NonScalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            NonScalar::Error => "error",
+            NonScalar::Json => "json",
+        };
+        f.write_str(name)
+    }
+}
+
+/// An environment variable, its value not yet quoted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvVar {
+    pub name: String,
+    pub value: String,
+}
+
+impl EnvVar {
+    /// The variable as a `.env` line, without the line break.
+    pub fn to_line(&self) -> String {
+        format!("{}={}", self.name, quote(&self.value))
+    }
+}
+
+impl fmt::Display for EnvVar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_line())
+    }
+}
+
+/// Renders a diff as the environment variables of its leaves, sorted by name.
+///
+/// ```
+/// use yabe::env_file::{env_vars, NonScalar};
+/// use yabe::yaml;
+///
+/// let diff = yaml!("image:\n  tag: v2\nports: [80, 443]\ngreeting: hello world\ndebug: ~");
+/// let lines: Vec<String> = env_vars(&diff, NonScalar::Error).unwrap().iter().map(|var| var.to_string()).collect();
+/// assert_eq!(lines, ["DEBUG=\"\"", "GREETING=\"hello world\"", "IMAGE_TAG=v2", "PORTS_0=80", "PORTS_1=443"]);
+/// ```
+pub fn env_vars(diff: &Yaml, non_scalar: NonScalar) -> Result<Vec<EnvVar>, String> {
+    match diff {
+        Yaml::Hash(hash) if hash.is_empty() => return Ok(vec![]),
+        Yaml::Hash(_) => {}
+        _ => return Err("only a map diff can be written as environment variables".to_string()),
+    }
+    let mut vars: BTreeMap<String, (String, String)> = BTreeMap::new();
+    for (path, leaf) in flatten(diff)? {
+        let name = env_name(&parse_path(&path)?);
+        let value = match leaf {
+            Yaml::String(s) => s,
+            Yaml::Integer(i) => i.to_string(),
+            Yaml::Real(text) => text,
+            Yaml::Boolean(b) => b.to_string(),
+            Yaml::Null => String::new(),
+            Yaml::Hash(_) | Yaml::Array(_) => match non_scalar {
+                NonScalar::Json if leaf.is_array() => "[]".to_string(),
+                NonScalar::Json => "{}".to_string(),
+                NonScalar::Error => return Err(format!("'{}' is an empty map or array, which has no value", path)),
+            },
+            other => return Err(format!("'{}' holds {:?}, which cannot be written", path, other)),
+        };
+        if let Some((other, _)) = vars.get(&name) {
+            return Err(format!("'{}' and '{}' are both written as {}", other, path, name));
+        }
+        vars.insert(name, (path, value));
+    }
+    Ok(vars.into_iter().map(|(name, (_, value))| EnvVar { name, value }).collect())
+}
+
+/// Names the variable of a path.
+pub fn env_name(path: &[Segment]) -> String {
+    let segments: Vec<String> = path
+        .iter()
+        .map(|segment| match segment {
+            Segment::Key(key) => key
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+                .collect(),
+            Segment::Index(index) => index.to_string(),
+        })
+        .collect();
+    let name = segments.join("_");
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+fn quote(value: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_-./:@%+,".contains(c);
+    if !value.is_empty() && value.chars().all(safe) {
+        return value.to_string();
+    }
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' | '"' | '$' | '`' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,52 @@
+//! Errors of the file-based entry points, such as [`crate::input::diff_files`].
+//!
+//! The functions on [`yaml_rust2::Yaml`] values cannot fail; errors only come from reading and
+//! parsing inputs, and always name the file they come from.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use yaml_rust2::ScanError;
+
+/// An input that could not be read or parsed.
+#[derive(Debug)]
+pub enum DiffError {
+    /// The file could not be read.
+    Read { path: PathBuf, source: io::Error },
+    /// The file is not valid YAML.
+    Parse { path: PathBuf, source: ScanError },
+}
+
+impl DiffError {
+    /// The file the error comes from.
+    pub fn path(&self) -> &Path {
+        match self {
+            DiffError::Read { path, .. } | DiffError::Parse { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for DiffError {
+    /// Writes `<file>: <error>`, with the line and column of a parse error as
+    /// `<file>:<line>:<column>: <error>`, both counted from 1.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiffError::Read { path, source } => write!(f, "{}: {}", path.display(), source),
+            DiffError::Parse { path, source } => {
+                let marker = source.marker();
+                write!(f, "{}:{}:{}: {}", path.display(), marker.line(), marker.col() + 1, source.info())
+            }
+        }
+    }
+}
+
+impl Error for DiffError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DiffError::Read { source, .. } => Some(source),
+            DiffError::Parse { source, .. } => Some(source),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Canonical fingerprints of YAML values.
+//!
+//! Fingerprints are consistent with [`crate::deep_equal::deep_equal`]: deeply equal values
+//! always have equal fingerprints. Map key order is ignored, array order is not. The hash
+//! (64-bit FNV-1a) is fixed, so fingerprints are stable across runs and builds, which makes
+//! them suitable as cache keys.
+
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+
+use yaml_rust2::Yaml;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+#[derive(Debug)]
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(FNV_OFFSET)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+}
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv::new()
+    }
+}
+
+/// Lets the subtree fingerprints hash node addresses with FNV, much cheaper than SipHash.
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        Fnv::write(self, bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Computes the fingerprint of a value.
+pub fn fingerprint(value: &Yaml) -> u64 {
+    combine(value, fingerprint)
+}
+
+/// Computes the fingerprint of a value from the fingerprints `child` gives its items, keys and
+/// values.
+fn combine(value: &Yaml, mut child: impl FnMut(&Yaml) -> u64) -> u64 {
+    let mut hasher = Fnv::new();
+    match value {
+        Yaml::Real(s) => {
+            hasher.write(b"r");
+            hasher.write(s.as_bytes());
+        }
+        Yaml::Integer(i) => {
+            hasher.write(b"i");
+            hasher.write(&i.to_le_bytes());
+        }
+        Yaml::String(s) => {
+            hasher.write(b"s");
+            hasher.write(s.as_bytes());
+        }
+        Yaml::Boolean(b) => hasher.write(if *b { b"t" } else { b"f" }),
+        Yaml::Array(items) => {
+            hasher.write(b"a");
+            hasher.write_u64(items.len() as u64);
+            for item in items {
+                hasher.write_u64(child(item));
+            }
+        }
+        Yaml::Hash(hash) => {
+            // Entries are combined with a commutative sum so key order does not matter
+            let entries = hash.iter().fold(0u64, |acc, (key, value)| {
+                let mut entry = Fnv::new();
+                entry.write_u64(child(key));
+                entry.write_u64(child(value));
+                acc.wrapping_add(entry.0)
+            });
+            hasher.write(b"h");
+            hasher.write_u64(hash.len() as u64);
+            hasher.write_u64(entries);
+        }
+        Yaml::Null => hasher.write(b"n"),
+        Yaml::Alias(index) => {
+            hasher.write(b"*");
+            hasher.write_u64(*index as u64);
+        }
+        Yaml::BadValue => hasher.write(b"!"),
+    }
+    hasher.0
+}
+
+/// The fingerprints of every subtree of some values, computed in one pass.
+///
+/// Fingerprinting each subtree on its own hashes the nodes below it again at every level; this
+/// hashes each node once. Only maps and arrays are kept, identified by address, so lookups only
+/// find the maps and arrays of the values it was built from, while they are borrowed.
+///
+/// ```
+/// use yabe::fingerprint::{fingerprint, SubtreeFingerprints};
+/// use yabe::yaml;
+///
+/// let doc = yaml!("image: {repository: nginx, tag: v1}");
+/// let fingerprints = SubtreeFingerprints::new(&[&doc]);
+/// assert_eq!(fingerprints.get(&doc["image"]), Some(fingerprint(&doc["image"])));
+/// assert_eq!(fingerprints.get(&doc["image"]["tag"]), None);
+/// assert_eq!(fingerprints.get(&yaml!("{repository: nginx, tag: v1}")), None);
+/// ```
+#[derive(Debug, Default)]
+pub struct SubtreeFingerprints(HashMap<*const Yaml, u64, BuildHasherDefault<Fnv>>);
+
+impl SubtreeFingerprints {
+    /// Fingerprints the subtrees of `roots`.
+    pub fn new(roots: &[&Yaml]) -> Self {
+        let mut fingerprints = SubtreeFingerprints::default();
+        for root in roots {
+            fingerprints.record(root);
+        }
+        fingerprints
+    }
+
+    fn record(&mut self, value: &Yaml) -> u64 {
+        if !matches!(value, Yaml::Array(_) | Yaml::Hash(_)) {
+            // Scalars are cheaper to compare than to look up
+            return fingerprint(value);
+        }
+        let fingerprint = combine(value, |child| self.record(child));
+        self.0.insert(value, fingerprint);
+        fingerprint
+    }
+
+    /// Returns the fingerprint of a map or array of the roots, `None` for other values.
+    pub fn get(&self, value: &Yaml) -> Option<u64> {
+        self.0.get(&(value as *const Yaml)).copied()
+    }
+
+    /// Checks whether two subtrees of the roots are known to differ: their fingerprints differ,
+    /// so they are not deeply equal. Equal fingerprints prove nothing, colliding values included.
+    pub fn differ(&self, a: &Yaml, b: &Yaml) -> bool {
+        matches!((self.get(a), self.get(b)), (Some(a), Some(b)) if a != b)
+    }
+}
+
+/// Computes the fingerprint of a value as a 16-digit hex string.
+pub fn fingerprint_hex(value: &Yaml) -> String {
+    format!("{:016x}", fingerprint(value))
+}
+
+/// Computes the fingerprint of raw bytes, such as a file's content.
+pub fn fingerprint_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv::new();
+    hasher.write(bytes);
+    hasher.0
+}
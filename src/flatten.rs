@@ -0,0 +1,116 @@
+//! Conversion between nested YAML and flat maps of leaf path → scalar.
+//!
+//! Paths use the encoding of [`crate::path`]: `spec.containers[0].image`, with `\.`, `\[` and
+//! `\\` escaping literal characters in keys. Leaves are scalars, `null`, and empty maps or
+//! arrays (so they survive the round trip). Map keys are stringified, so `1: a` and `"1": a`
+//! in the same map collide and are rejected.
+
+use std::collections::BTreeMap;
+
+use yaml_rust2::yaml::{Hash, Yaml};
+
+use crate::path::{format_path, key_segment, parse_path, Segment};
+
+/// Flattens a document into a map of leaf path → leaf value.
+///
+/// A scalar document becomes a single entry under the empty path.
+pub fn flatten(doc: &Yaml) -> Result<BTreeMap<String, Yaml>, String> {
+    let mut flat = BTreeMap::new();
+    flatten_into(doc, &mut Vec::new(), &mut flat)?;
+    Ok(flat)
+}
+
+fn flatten_into(
+    value: &Yaml,
+    path: &mut Vec<Segment>,
+    flat: &mut BTreeMap<String, Yaml>,
+) -> Result<(), String> {
+    match value {
+        Yaml::Hash(hash) if !hash.is_empty() => {
+            for (key, child) in hash {
+                path.push(Segment::Key(key_segment(key)));
+                flatten_into(child, path, flat)?;
+                path.pop();
+            }
+        }
+        Yaml::Array(items) if !items.is_empty() => {
+            for (index, child) in items.iter().enumerate() {
+                path.push(Segment::Index(index));
+                flatten_into(child, path, flat)?;
+                path.pop();
+            }
+        }
+        leaf => {
+            let key = format_path(path);
+            if flat.insert(key.clone(), leaf.clone()).is_some() {
+                return Err(format!("keys collide at path '{}'", key));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rebuilds a nested document from a flat map produced by [`flatten`].
+///
+/// Missing array indices are filled with `null`. A path that is both a leaf and a parent,
+/// or used both as a map and as an array, is an error.
+pub fn unflatten(flat: &BTreeMap<String, Yaml>) -> Result<Yaml, String> {
+    // `BadValue` marks slots that have not been set yet
+    let mut root = Yaml::BadValue;
+    for (path, value) in flat {
+        let segments = parse_path(path)?;
+        insert(&mut root, &segments, value.clone(), path)?;
+    }
+    if root.is_badvalue() {
+        return Ok(Yaml::Hash(Hash::new()));
+    }
+    fill_unset(&mut root);
+    Ok(root)
+}
+
+fn insert(slot: &mut Yaml, segments: &[Segment], value: Yaml, path: &str) -> Result<(), String> {
+    let Some((first, rest)) = segments.split_first() else {
+        if !slot.is_badvalue() {
+            return Err(format!("path '{}' is set more than once", path));
+        }
+        *slot = value;
+        return Ok(());
+    };
+
+    match first {
+        Segment::Key(key) => {
+            if slot.is_badvalue() {
+                *slot = Yaml::Hash(Hash::new());
+            }
+            let Yaml::Hash(hash) = slot else {
+                return Err(format!("path '{}' conflicts with a non-map value", path));
+            };
+            let child = hash
+                .entry(Yaml::String(key.clone()))
+                .or_insert(Yaml::BadValue);
+            insert(child, rest, value, path)
+        }
+        Segment::Index(index) => {
+            if slot.is_badvalue() {
+                *slot = Yaml::Array(vec![]);
+            }
+            let Yaml::Array(items) = slot else {
+                return Err(format!("path '{}' conflicts with a non-array value", path));
+            };
+            if items.len() <= *index {
+                items.resize(index + 1, Yaml::BadValue);
+            }
+            insert(&mut items[*index], rest, value, path)
+        }
+    }
+}
+
+/// Replaces the slots that were never set with `null`.
+fn fill_unset(value: &mut Yaml) {
+    match value {
+        Yaml::BadValue => *value = Yaml::Null,
+        Yaml::Hash(hash) => hash.values_mut().for_each(fill_unset),
+        Yaml::Array(items) => items.iter_mut().for_each(fill_unset),
+        _ => {}
+    }
+}
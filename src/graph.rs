@@ -0,0 +1,266 @@
+//! Diagrams of the base and the overrides of each file, as Mermaid or Graphviz DOT text.
+//!
+//! The base is drawn as a tree of its keys rooted at a `base` node, walking into maps; scalars,
+//! arrays and empty maps are leaves labelled with their value. Each file's overrides branch off
+//! the deepest base map they are below, as separate nodes on an edge labelled with the file
+//! name. Shared nodes and overrides are styled in two colors.
+//!
+//! Large configurations make large graphs. A depth limit stops the tree at that many keys: a
+//! map there is drawn as one node, and a file's overrides below it are merged into one node
+//! counting them. Subtree filters restrict the graph to the given paths and their ancestors.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use yaml_rust2::Yaml;
+
+use crate::path::{join_path, key_segment, split_path};
+
+/// Fill colors of base and override nodes.
+const SHARED_COLOR: &str = "#dbeafe";
+const OVERRIDE_COLOR: &str = "#fee2e2";
+
+/// Text format of a diagram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GraphFormat {
+    /// A Mermaid flowchart.
+    #[default]
+    Mermaid,
+    /// A Graphviz digraph.
+    Dot,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            "dot" => Ok(GraphFormat::Dot),
+            _ => Err(format!("unknown graph format '{}', expected one of: mermaid, dot", s)),
+        }
+    }
+}
+
+impl fmt::Display for GraphFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            GraphFormat::Mermaid => "mermaid",
+            GraphFormat::Dot => "dot",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What to draw and how.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphOptions {
+    pub format: GraphFormat,
+    /// Number of key levels drawn below the root; `None` draws everything.
+    pub max_depth: Option<usize>,
+    /// Dotted paths (see [`crate::path`]) of the subtrees to draw; empty draws everything.
+    pub subtrees: Vec<String>,
+}
+
+/// Renders the base and the overrides each named file applies on top of it.
+pub fn render_graph(
+    base: Option<&Yaml>,
+    overrides: &[Option<&Yaml>],
+    names: &[String],
+    options: &GraphOptions,
+) -> String {
+    let subtrees: Vec<Vec<String>> = options.subtrees.iter().map(|path| split_path(path)).collect();
+    let mut graph = Graph {
+        format: options.format,
+        max_depth: options.max_depth.unwrap_or(usize::MAX),
+        subtrees,
+        lines: Vec::new(),
+        maps: HashMap::new(),
+        next_id: 0,
+    };
+    let root = graph.node("base", SHARED_COLOR);
+    graph.maps.insert(Vec::new(), root.clone());
+    if let Some(base) = base {
+        graph.add_base(base, &mut Vec::new(), &root);
+    }
+    for (index, diff) in overrides.iter().enumerate() {
+        if let Some(diff) = diff {
+            let name = names.get(index).cloned().unwrap_or_else(|| index.to_string());
+            graph.add_overrides(diff, &name);
+        }
+    }
+    graph.finish()
+}
+
+struct Graph {
+    format: GraphFormat,
+    max_depth: usize,
+    subtrees: Vec<Vec<String>>,
+    lines: Vec<String>,
+    /// Node ids of the base maps drawn, by path.
+    maps: HashMap<Vec<String>, String>,
+    next_id: usize,
+}
+
+impl Graph {
+    /// Checks if a path is inside one of the subtrees, or leads to one.
+    fn selected(&self, path: &[String]) -> bool {
+        self.subtrees.is_empty()
+            || self
+                .subtrees
+                .iter()
+                .any(|subtree| path.starts_with(subtree) || subtree.starts_with(path))
+    }
+
+    fn node(&mut self, label: &str, color: &str) -> String {
+        let id = format!("n{}", self.next_id);
+        self.next_id += 1;
+        let line = match self.format {
+            GraphFormat::Mermaid => {
+                let class = if color == SHARED_COLOR { "shared" } else { "override" };
+                format!("  {}[\"{}\"]:::{}", id, label.replace('"', "#quot;"), class)
+            }
+            GraphFormat::Dot => format!("  {} [label=\"{}\", fillcolor=\"{}\"];", id, dot_escape(label), color),
+        };
+        self.lines.push(line);
+        id
+    }
+
+    fn edge(&mut self, from: &str, to: &str, label: Option<&str>) {
+        let line = match (self.format, label) {
+            (GraphFormat::Mermaid, None) => format!("  {} --> {}", from, to),
+            (GraphFormat::Mermaid, Some(label)) => {
+                format!("  {} -->|\"{}\"| {}", from, label.replace('"', "#quot;"), to)
+            }
+            (GraphFormat::Dot, None) => format!("  {} -> {};", from, to),
+            (GraphFormat::Dot, Some(label)) => format!("  {} -> {} [label=\"{}\"];", from, to, dot_escape(label)),
+        };
+        self.lines.push(line);
+    }
+
+    fn add_base(&mut self, value: &Yaml, path: &mut Vec<String>, parent: &str) {
+        let Yaml::Hash(hash) = value else {
+            return;
+        };
+        for (key, child) in hash {
+            path.push(key_segment(key));
+            if self.selected(path) {
+                let walk = matches!(child, Yaml::Hash(h) if !h.is_empty()) && path.len() < self.max_depth;
+                let label = if walk {
+                    key_segment(key)
+                } else {
+                    format!("{} = {}", key_segment(key), summary(child))
+                };
+                let id = self.node(&label, SHARED_COLOR);
+                self.edge(parent, &id, None);
+                if walk {
+                    self.maps.insert(path.clone(), id.clone());
+                    self.add_base(child, path, &id);
+                }
+            }
+            path.pop();
+        }
+    }
+
+    fn add_overrides(&mut self, diff: &Yaml, name: &str) {
+        let mut leaves = Vec::new();
+        collect_leaves(diff, &mut Vec::new(), &mut leaves);
+
+        // Leaves below the depth limit are merged per visible path
+        let mut visible: Vec<(Vec<String>, Vec<&Yaml>, bool)> = Vec::new();
+        for (path, value) in leaves {
+            if !self.selected(&path) {
+                continue;
+            }
+            let cut = path[..path.len().min(self.max_depth.max(1))].to_vec();
+            let truncated = cut.len() < path.len();
+            match visible.iter_mut().find(|(p, _, _)| *p == cut) {
+                Some((_, values, _)) => values.push(value),
+                None => visible.push((cut, vec![value], truncated)),
+            }
+        }
+
+        for (path, values, truncated) in visible {
+            let attach = (0..path.len())
+                .rev()
+                .find(|&len| self.maps.contains_key(&path[..len]))
+                .unwrap_or(0);
+            let rest = join_path(&path[attach..]);
+            let label = match values.as_slice() {
+                [value] if !truncated => format!("{} = {}", rest, summary(value)),
+                _ => format!("{}: {}", rest, count(values.len(), "override")),
+            };
+            let parent = self.maps[&path[..attach]].clone();
+            let id = self.node(&label, OVERRIDE_COLOR);
+            self.edge(&parent, &id, Some(name));
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut out = String::new();
+        match self.format {
+            GraphFormat::Mermaid => {
+                out.push_str("graph LR\n");
+                out.push_str(&format!("  classDef shared fill:{}\n", SHARED_COLOR));
+                out.push_str(&format!("  classDef override fill:{}\n", OVERRIDE_COLOR));
+            }
+            GraphFormat::Dot => {
+                out.push_str("digraph yabe {\n  rankdir=LR;\n  node [shape=box, style=filled];\n");
+            }
+        }
+        for line in self.lines {
+            out.push_str(&line);
+            out.push('\n');
+        }
+        if self.format == GraphFormat::Dot {
+            out.push_str("}\n");
+        }
+        out
+    }
+}
+
+/// Collects the leaves of a value with their paths, walking into non-empty maps.
+fn collect_leaves<'a>(value: &'a Yaml, path: &mut Vec<String>, found: &mut Vec<(Vec<String>, &'a Yaml)>) {
+    match value {
+        Yaml::Hash(hash) if !hash.is_empty() => {
+            for (key, child) in hash {
+                path.push(key_segment(key));
+                collect_leaves(child, path, found);
+                path.pop();
+            }
+        }
+        _ => found.push((path.clone(), value)),
+    }
+}
+
+/// Short rendering of a value for a label.
+fn summary(value: &Yaml) -> String {
+    const MAX_CHARS: usize = 40;
+    let text = match value {
+        Yaml::String(s) => s.clone(),
+        Yaml::Null => "~".to_string(),
+        Yaml::Array(items) => format!("[{}]", count(items.len(), "item")),
+        Yaml::Hash(hash) if hash.is_empty() => "{}".to_string(),
+        Yaml::Hash(hash) => format!("{{{}}}", count(hash.len(), "key")),
+        other => key_segment(other),
+    };
+    let text = text.replace('\n', " ");
+    if text.chars().count() > MAX_CHARS {
+        format!("{}…", text.chars().take(MAX_CHARS).collect::<String>())
+    } else {
+        text
+    }
+}
+
+fn count(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
+
+fn dot_escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,232 @@
+//! Grouping of inputs that share more than the global base.
+//!
+//! Inputs are clustered bottom-up with complete linkage: every input starts as a group of its
+//! own and the two closest groups are merged, the distance between two groups being the largest
+//! [`distance`] between their members. This takes O(n³) distance lookups for n inputs, which is
+//! negligible next to extraction for the handful of environments a chart usually has.
+//!
+//! Each group of at least [`Grouping::min_size`] inputs then gets a base of its own, extracted
+//! from its members' diffs against the global base. The result is a small hierarchy: the global
+//! base, a group base on top of it, and each member's diff on top of its group base. An input is
+//! rebuilt by merging the three in that order. Smaller groups, and groups whose members share
+//! nothing beyond the global base, are dropped; their inputs keep their diff against the global
+//! base.
+//!
+//! With more group bases than [`Grouping::max_bases`], the smallest groups are merged back into
+//! the global base until the limit holds: the group with the fewest members goes first, of two
+//! as large the one whose base holds fewer leaves (it saves its members the least), then the one
+//! listed last. Its members' diffs are against the global base again, so each grows by about
+//! the leaves of the dropped group base; the other groups are kept as they are.
+//!
+//! The other way round, [`consolidate_bases`] folds several bases back into one, such as
+//! per-region bases that turned out to share most of their values.
+
+use yaml_rust2::yaml::{Hash, Yaml};
+
+use crate::diff::{count_leaves, diff_and_common_with_options, distance};
+use crate::input::is_empty_document;
+use crate::options::DiffOptions;
+use crate::result::DiffResult;
+
+/// How inputs are clustered into groups.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grouping {
+    /// Upper bound on the number of groups, counting inputs left on their own.
+    pub max_groups: usize,
+    /// Groups within this many differing leaves of each other are merged even once there are
+    /// at most `max_groups`, making groups coarser.
+    pub max_distance: Option<usize>,
+    /// Minimum number of members of a group with a base of its own; groups of a single member
+    /// never get one.
+    pub min_size: usize,
+    /// Upper bound on the number of group bases, the smallest groups being merged back into the
+    /// global base beyond it (see the [module documentation](self)).
+    pub max_bases: Option<usize>,
+}
+
+impl Grouping {
+    /// Clusters into at most `max_groups` groups, with a base for every group of two or more
+    /// inputs.
+    pub fn new(max_groups: usize) -> Self {
+        Grouping {
+            max_groups,
+            max_distance: None,
+            min_size: 2,
+            max_bases: None,
+        }
+    }
+}
+
+/// A group of inputs and what they share beyond the global base.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    /// Name of the group, `group-<n>` counting from 1.
+    pub name: String,
+    /// Indices of the member inputs, in input order.
+    pub members: Vec<usize>,
+    /// What the members share on top of the global base.
+    pub base: Yaml,
+}
+
+/// Group bases and the per-input diffs on top of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Groups {
+    pub groups: Vec<Group>,
+    /// Each input's diff against its group base, or against the global base if it has no group.
+    pub diffs: Vec<Option<Yaml>>,
+}
+
+impl Groups {
+    /// Returns the group of the `index`-th input, if any.
+    pub fn group_of(&self, index: usize) -> Option<&Group> {
+        self.groups.iter().find(|group| group.members.contains(&index))
+    }
+
+    /// Maps each input name to the name of its group, or `null` if it has none.
+    pub fn assignment(&self, names: &[String]) -> Yaml {
+        let mut assignment = Hash::new();
+        for (index, name) in names.iter().enumerate() {
+            let group = match self.group_of(index) {
+                Some(group) => Yaml::String(group.name.clone()),
+                None => Yaml::Null,
+            };
+            assignment.insert(Yaml::String(name.clone()), group);
+        }
+        Yaml::Hash(assignment)
+    }
+
+    /// Consolidates the group bases with [`consolidate_bases`], naming the residuals after
+    /// their groups.
+    ///
+    /// The consolidated base holds what the groups share on top of the global base, so it fits
+    /// between the two: an input is rebuilt from the global base, the consolidated base, its
+    /// group's residual and its own diff. Inputs without a group do not see it.
+    pub fn consolidated_bases(&self, options: &DiffOptions) -> DiffResult<'static> {
+        let bases: Vec<&Yaml> = self.groups.iter().map(|group| &group.base).collect();
+        consolidate_bases(&bases, options).with_names(self.groups.iter().map(|group| group.name.clone()))
+    }
+}
+
+/// Clusters the inputs, returning the indices of each cluster's members in input order.
+///
+/// Clusters are ordered by their first member. Empty inputs are left out.
+pub fn cluster(objs: &[&Yaml], grouping: &Grouping, options: &DiffOptions) -> Vec<Vec<usize>> {
+    let present: Vec<usize> = (0..objs.len()).filter(|&i| !is_empty_document(objs[i])).collect();
+    let mut distances = vec![vec![0; objs.len()]; objs.len()];
+    for (n, &i) in present.iter().enumerate() {
+        for &j in &present[n + 1..] {
+            let d = distance(objs[i], objs[j], options);
+            distances[i][j] = d;
+            distances[j][i] = d;
+        }
+    }
+    let linkage = |a: &[usize], b: &[usize]| {
+        a.iter()
+            .flat_map(|&i| b.iter().map(move |&j| (i, j)))
+            .map(|(i, j)| distances[i][j])
+            .max()
+            .unwrap_or(0)
+    };
+
+    let mut clusters: Vec<Vec<usize>> = present.into_iter().map(|i| vec![i]).collect();
+    while clusters.len() > 1 {
+        // Ties go to the pair listed first
+        let mut closest = (usize::MAX, 0, 0);
+        for a in 0..clusters.len() {
+            for b in a + 1..clusters.len() {
+                let d = linkage(&clusters[a], &clusters[b]);
+                if d < closest.0 {
+                    closest = (d, a, b);
+                }
+            }
+        }
+        let (d, a, b) = closest;
+        let within_distance = grouping.max_distance.is_some_and(|max| d <= max);
+        if clusters.len() <= grouping.max_groups && !within_distance {
+            break;
+        }
+        let merged = clusters.remove(b);
+        clusters[a].extend(merged);
+        clusters[a].sort_unstable();
+    }
+    clusters
+}
+
+/// Clusters the inputs and extracts a base per group from their diffs against the global base.
+///
+/// `diffs` are the results of [`diff_and_common_with_options`] on `objs`. A diff that is
+/// `None`, because the input equals the global base, takes part as an empty map.
+pub fn split_into_groups(
+    objs: &[&Yaml],
+    diffs: &[Option<&Yaml>],
+    grouping: &Grouping,
+    options: &DiffOptions,
+) -> Groups {
+    let mut result = Groups {
+        groups: vec![],
+        diffs: diffs.iter().map(|diff| diff.cloned()).collect(),
+    };
+    let nothing = Yaml::Hash(Hash::new());
+    let mut candidates = Vec::new();
+    for members in cluster(objs, grouping, options) {
+        if members.len() < grouping.min_size.max(2) {
+            continue;
+        }
+        let member_diffs: Vec<&Yaml> = members.iter().map(|&i| diffs[i].unwrap_or(&nothing)).collect();
+        let (base, group_diffs) = diff_and_common_with_options(&member_diffs, options).into_owned().into_parts();
+        let base = match base {
+            Some(base) if !matches!(&*base, Yaml::Hash(hash) if hash.is_empty()) => base.into_owned(),
+            _ => continue,
+        };
+        candidates.push((members, base, group_diffs));
+    }
+    if let Some(max_bases) = grouping.max_bases {
+        while candidates.len() > max_bases {
+            // Ties go to the group listed last
+            let smallest = (0..candidates.len())
+                .rev()
+                .min_by_key(|&i| (candidates[i].0.len(), count_leaves(&candidates[i].1)))
+                .unwrap_or(0);
+            candidates.remove(smallest);
+        }
+    }
+    for (members, base, group_diffs) in candidates {
+        for (&i, diff) in members.iter().zip(group_diffs) {
+            result.diffs[i] = diff.map(|diff| diff.into_owned());
+        }
+        result.groups.push(Group {
+            name: format!("group-{}", result.groups.len() + 1),
+            members,
+            base,
+        });
+    }
+    result
+}
+
+/// Merges several bases into one canonical base, returning it with the residual each original
+/// base needs on top of it: extraction run one level up, treating the bases as inputs.
+///
+/// Merging a base's residual onto the consolidated base (see [`crate::merge::apply_diff`])
+/// recovers it; the files diffed against that base keep their diffs unchanged. The quorum
+/// decides what the consolidated base holds, as with inputs. The recovery is exact unless
+/// minority keys are hoisted, in which case a base lacking a hoisted key gets it as `null`.
+///
+/// ```
+/// use yabe::deep_equal::deep_equal;
+/// use yabe::group::consolidate_bases;
+/// use yabe::merge::apply_diff;
+/// use yabe::{yaml, DiffOptions};
+///
+/// let eu = yaml!("image: nginx\nreplicas: 3\nregion: eu");
+/// let us = yaml!("image: nginx\nreplicas: 3\nregion: us");
+/// let options = DiffOptions { quorum: 1.0, ..DiffOptions::default() };
+/// let result = consolidate_bases(&[&eu, &us], &options).with_names(["eu", "us"]);
+///
+/// assert!(deep_equal(result.base.as_deref().unwrap(), &yaml!("image: nginx\nreplicas: 3")));
+/// assert_eq!(result.diff("us"), Some(&yaml!("region: us")));
+/// let recovered = apply_diff(result.base.as_deref(), result.diff("us"), &options);
+/// assert!(deep_equal(&recovered.unwrap(), &us));
+/// ```
+pub fn consolidate_bases(bases: &[&Yaml], options: &DiffOptions) -> DiffResult<'static> {
+    diff_and_common_with_options(bases, options).into_owned()
+}
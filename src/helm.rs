@@ -0,0 +1,389 @@
+//! Helm charts: the default values of a chart directory as a read-only base.
+//!
+//! A chart's defaults are its `values.yaml` coalesced over the defaults of its subcharts, the
+//! way Helm computes them before rendering. A subchart's values are scoped under its name in
+//! the parent, or under its alias when the dependency has one (`redis.auth.enabled` configures
+//! the `redis` subchart's `auth.enabled`); a subchart listed several times under different
+//! aliases is scoped under each. The subchart's `global` section is lifted into the parent's
+//! top-level `global` instead, as globals are shared by the whole chart tree. Parent values
+//! win over subchart defaults, and a parent `null` removes the subchart default.
+//!
+//! Only unpacked subcharts in `charts/<dir>` are read; packaged `.tgz` dependencies are skipped
+//! with a warning, so their defaults are not part of the base.
+//!
+//! The other way round, [`umbrella_values`] assembles an extraction into the values of an
+//! umbrella chart, given the subchart each input configures (see [`Umbrella`]):
+//!
+//! - the base stays at the top level;
+//! - each input's diff is nested under its subchart's key, except for `global` and the keys
+//!   listed as `top_level`, which Helm reads at the top level: those are merged onto the base
+//!   with [`crate::merge::apply_diff`], so they override it for every subchart;
+//! - two inputs setting a top-level path to different values are an error, since a single
+//!   values file can only hold one, and so is a subchart key the top level already has;
+//! - so is a top-level path that only some of the inputs set: the others would get the override
+//!   in place of their base value;
+//! - every input configures exactly one subchart, and a subchart at most one input; an input
+//!   without a diff adds nothing.
+//!
+//! Helm reads the file as plain values and replaces arrays as a whole, so only diffs of the
+//! positional array strategy can be assembled: the edit scripts, keyed element lists and
+//! appended elements of the other strategies only mean something to yabe's own merging.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::Yaml;
+
+use crate::deep_equal::deep_equal;
+use crate::input::parse_document;
+use crate::merge::apply_diff;
+use crate::options::{ArrayStrategy, DiffOptions};
+use crate::path::{join_path, key_segment};
+
+/// File holding a chart's metadata.
+pub const CHART_FILE: &str = "Chart.yaml";
+
+/// File holding a chart's default values.
+pub const VALUES_FILE: &str = "values.yaml";
+
+/// Key of the values shared by a chart and all of its subcharts.
+pub const GLOBAL_KEY: &str = "global";
+
+/// Returns the default values of the chart in `dir`, including those of its subcharts.
+///
+/// A directory without `Chart.yaml` is an error; a chart without `values.yaml` has no values of
+/// its own.
+pub fn chart_defaults(dir: &Path) -> io::Result<Yaml> {
+    let chart = read_yaml(&dir.join(CHART_FILE))?;
+    if chart["name"].as_str().is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: chart has no name", dir.join(CHART_FILE).display()),
+        ));
+    }
+    let values_path = dir.join(VALUES_FILE);
+    let mut values = if values_path.is_file() {
+        read_yaml(&values_path)?
+    } else {
+        Yaml::Null
+    };
+    if !matches!(values, Yaml::Hash(_) | Yaml::Null) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: values are not a map", values_path.display()),
+        ));
+    }
+
+    for (subchart_dir, name) in subcharts(dir)? {
+        let mut defaults = chart_defaults(&subchart_dir)?;
+        let globals = match &mut defaults {
+            Yaml::Hash(hash) => hash.remove(&key(GLOBAL_KEY)),
+            _ => None,
+        };
+        for scope in scopes(&chart, &name).iter().filter(|_| !defaults.is_null()) {
+            values = coalesce(&values, &scoped(scope, defaults.clone()));
+        }
+        if let Some(globals) = globals {
+            values = coalesce(&values, &scoped(GLOBAL_KEY, globals));
+        }
+    }
+    Ok(values)
+}
+
+/// The subchart each input configures, for [`umbrella_values`].
+///
+/// Read from a map of input names to subchart keys, the names as the inputs were given and the
+/// keys the subchart's name or alias in the umbrella chart:
+///
+/// ```yaml
+/// subcharts:
+///   envs/api.yaml: api
+///   envs/worker.yaml: worker
+/// top_level: [ingress]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Umbrella {
+    /// Input names and their subchart keys, in the file's order.
+    pub subcharts: Vec<(String, String)>,
+    /// Keys of the diffs kept at the top level besides [`GLOBAL_KEY`].
+    pub top_level: Vec<String>,
+}
+
+impl Umbrella {
+    /// Reads an umbrella document: a `subcharts` map and an optional `top_level` list.
+    pub fn from_yaml(doc: &Yaml) -> Result<Self, String> {
+        let Yaml::Hash(hash) = doc else {
+            return Err("the umbrella mapping must be a map of subcharts and top_level keys".to_string());
+        };
+        let mut umbrella = Umbrella::default();
+        for (section, entries) in hash {
+            match (section.as_str(), entries) {
+                (Some("subcharts"), Yaml::Hash(subcharts)) => {
+                    for (input, subchart) in subcharts {
+                        let (Some(input), Some(subchart)) = (input.as_str(), subchart.as_str()) else {
+                            return Err(format!("subchart entry {:?}: {:?} is not an input name to a key", input, subchart));
+                        };
+                        if let Some((other, _)) = umbrella.subcharts.iter().find(|(_, seen)| seen == subchart) {
+                            return Err(format!("subchart '{}' is configured by both {} and {}", subchart, other, input));
+                        }
+                        umbrella.subcharts.push((input.to_string(), subchart.to_string()));
+                    }
+                }
+                (Some("top_level"), Yaml::Array(keys)) => {
+                    for top_key in keys {
+                        let top_key = top_key.as_str().ok_or_else(|| format!("top_level key {:?} is not a string", top_key))?;
+                        umbrella.top_level.push(top_key.to_string());
+                    }
+                }
+                (Some("subcharts"), _) => return Err("umbrella subcharts: expected a map".to_string()),
+                (Some("top_level"), _) => return Err("umbrella top_level: expected a list".to_string()),
+                _ => {
+                    return Err(format!(
+                        "unknown umbrella key '{}', expected subcharts or top_level",
+                        key_segment(section)
+                    ))
+                }
+            }
+        }
+        Ok(umbrella)
+    }
+
+    /// Returns the subchart key of an input.
+    pub fn subchart_of(&self, input: &str) -> Option<&str> {
+        self.subcharts.iter().find(|(name, _)| name == input).map(|(_, subchart)| subchart.as_str())
+    }
+
+    /// Returns whether a key of the diffs stays at the top level.
+    pub fn is_top_level(&self, name: &str) -> bool {
+        name == GLOBAL_KEY || self.top_level.iter().any(|top_key| top_key == name)
+    }
+}
+
+/// Assembles the base and the diffs of the named inputs into the values of an umbrella chart,
+/// following the rules of the [module documentation](self).
+///
+/// ```
+/// use yabe::helm::{umbrella_values, Umbrella};
+/// use yabe::{yaml, DiffOptions};
+///
+/// let umbrella = Umbrella::from_yaml(&yaml!("subcharts: {api.yaml: api, worker.yaml: worker}")).unwrap();
+/// let base = yaml!("image: {registry: ghcr.io}");
+/// let api = yaml!("replicas: 3\nglobal: {domain: example.com}");
+/// let worker = yaml!("global: {domain: example.com}");
+/// let names = ["api.yaml".to_string(), "worker.yaml".to_string()];
+/// let diffs = [Some(&api), Some(&worker)];
+/// let values = umbrella_values(Some(&base), &diffs, &names, &umbrella, &DiffOptions::default()).unwrap();
+/// assert_eq!(values, yaml!("image: {registry: ghcr.io}\nglobal: {domain: example.com}\napi: {replicas: 3}"));
+/// ```
+pub fn umbrella_values(
+    base: Option<&Yaml>,
+    diffs: &[Option<&Yaml>],
+    names: &[String],
+    umbrella: &Umbrella,
+    options: &DiffOptions,
+) -> Result<Yaml, String> {
+    if options.array_strategy != ArrayStrategy::Positional {
+        return Err(format!("Helm cannot read the array diffs of the {} array strategy", options.array_strategy));
+    }
+    if options.slots_patches {
+        return Err("Helm cannot read slots patches of unchanged array elements".to_string());
+    }
+    if let Some((input, _)) = umbrella.subcharts.iter().find(|(input, _)| !names.contains(input)) {
+        return Err(format!("the umbrella mapping names {}, which is not an input", input));
+    }
+    let mut top_level = Hash::new();
+    let mut nested: Vec<(&str, Yaml)> = Vec::new();
+    let mut input_diffs: Vec<(&String, Option<&Hash>)> = Vec::new();
+    for (name, diff) in names.iter().zip(diffs) {
+        let subchart = umbrella
+            .subchart_of(name)
+            .ok_or_else(|| format!("{} has no subchart in the umbrella mapping", name))?;
+        let Some(diff) = diff else {
+            input_diffs.push((name, None));
+            continue;
+        };
+        let Yaml::Hash(diff) = diff else {
+            return Err(format!("the diff of {} is not a map", name));
+        };
+        input_diffs.push((name, Some(diff)));
+        let mut own = Hash::new();
+        for (diff_key, value) in diff {
+            let mut path = vec![key_segment(diff_key)];
+            if umbrella.is_top_level(&path[0]) {
+                merge_top_level(&mut top_level, diff_key.clone(), value, &mut path, name)?;
+            } else {
+                own.insert(diff_key.clone(), value.clone());
+            }
+        }
+        if !own.is_empty() {
+            nested.push((subchart, Yaml::Hash(own)));
+        }
+    }
+
+    for (name, diff) in input_diffs {
+        if let Some(path) = unset_top_level(&top_level, diff) {
+            return Err(format!(
+                "{} keeps the base value of the top-level {}, which another input overrides for every subchart",
+                name,
+                join_path(&path)
+            ));
+        }
+    }
+
+    let top_level = (!top_level.is_empty()).then_some(Yaml::Hash(top_level));
+    let mut values = match apply_diff(base, top_level.as_ref(), options) {
+        Some(Yaml::Hash(values)) => values,
+        Some(Yaml::Null) | None => Hash::new(),
+        Some(_) => return Err("the base is not a map".to_string()),
+    };
+    for (subchart, value) in nested {
+        if values.contains_key(&key(subchart)) {
+            return Err(format!("subchart key '{}' is also a top-level key of the values", subchart));
+        }
+        values.insert(key(subchart), value);
+    }
+    Ok(Yaml::Hash(values))
+}
+
+/// Merges one input's top-level value into those of the inputs before it, failing where they
+/// set a leaf differently.
+fn merge_top_level(into: &mut Hash, top_key: Yaml, value: &Yaml, path: &mut Vec<String>, name: &str) -> Result<(), String> {
+    match (into.get_mut(&top_key), value) {
+        (None, _) => {
+            into.insert(top_key, value.clone());
+        }
+        (Some(Yaml::Hash(existing)), Yaml::Hash(hash)) => {
+            for (child_key, child) in hash {
+                path.push(key_segment(child_key));
+                merge_top_level(existing, child_key.clone(), child, path, name)?;
+                path.pop();
+            }
+        }
+        (Some(existing), _) if deep_equal(existing, value) => {}
+        (Some(_), _) => {
+            return Err(format!(
+                "{} sets the top-level {} differently from an input before it",
+                name,
+                join_path(path)
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Returns the first path of the merged top-level values that an input's diff does not set.
+fn unset_top_level(top_level: &Hash, diff: Option<&Hash>) -> Option<Vec<String>> {
+    for (key, value) in top_level {
+        let set = diff.and_then(|diff| diff.get(key));
+        let unset = match (value, set) {
+            (_, None) => Some(vec![]),
+            (Yaml::Hash(value), Some(Yaml::Hash(set))) if !value.is_empty() => unset_top_level(value, Some(set)),
+            _ => None,
+        };
+        if let Some(mut path) = unset {
+            path.insert(0, key_segment(key));
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Merges `defaults` under `values`: values win, maps are merged and a `null` value removes the
+/// default.
+pub fn coalesce(values: &Yaml, defaults: &Yaml) -> Yaml {
+    match (values, defaults) {
+        (Yaml::Hash(values_hash), Yaml::Hash(defaults_hash)) => {
+            let mut merged = Hash::new();
+            for (k, default) in defaults_hash {
+                match values_hash.get(k) {
+                    Some(Yaml::Null) => {}
+                    Some(value) => {
+                        merged.insert(k.clone(), coalesce(value, default));
+                    }
+                    None => {
+                        merged.insert(k.clone(), default.clone());
+                    }
+                }
+            }
+            for (k, value) in values_hash {
+                if !defaults_hash.contains_key(k) {
+                    merged.insert(k.clone(), value.clone());
+                }
+            }
+            Yaml::Hash(merged)
+        }
+        (Yaml::Null, defaults) => defaults.clone(),
+        (values, _) => values.clone(),
+    }
+}
+
+/// Lists the unpacked subcharts of the chart in `dir` with their chart names, sorted by
+/// directory.
+fn subcharts(dir: &Path) -> io::Result<Vec<(PathBuf, String)>> {
+    let charts_dir = dir.join("charts");
+    if !charts_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut entries: Vec<_> = fs::read_dir(&charts_dir)?.collect::<io::Result<_>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut found = Vec::new();
+    for entry in entries {
+        let path = entry.path();
+        if path.join(CHART_FILE).is_file() {
+            let name = read_yaml(&path.join(CHART_FILE))?["name"].as_str().map(str::to_string);
+            match name {
+                Some(name) => found.push((path, name)),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{}: chart has no name", path.join(CHART_FILE).display()),
+                    ))
+                }
+            }
+        } else if path.extension().is_some_and(|ext| ext == "tgz") {
+            warn!(
+                "Skipping packaged subchart {}; unpack it to include its defaults.",
+                path.display()
+            );
+        }
+    }
+    Ok(found)
+}
+
+/// Keys a subchart's values are scoped under: the aliases of its dependency entries, or its
+/// name.
+fn scopes(chart: &Yaml, name: &str) -> Vec<String> {
+    let mut scopes: Vec<String> = chart["dependencies"]
+        .as_vec()
+        .into_iter()
+        .flatten()
+        .filter(|dependency| dependency["name"].as_str() == Some(name))
+        .map(|dependency| dependency["alias"].as_str().unwrap_or(name).to_string())
+        .collect();
+    if scopes.is_empty() {
+        scopes.push(name.to_string());
+    }
+    scopes.dedup();
+    scopes
+}
+
+fn key(name: &str) -> Yaml {
+    Yaml::String(name.to_string())
+}
+
+fn scoped(scope: &str, value: Yaml) -> Yaml {
+    let mut hash = Hash::new();
+    hash.insert(key(scope), value);
+    Yaml::Hash(hash)
+}
+
+fn read_yaml(path: &Path) -> io::Result<Yaml> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    parse_document(&content)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+}
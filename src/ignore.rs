@@ -0,0 +1,254 @@
+//! Exclusions read from a `.yabeignore` file, so that they live in the repository instead of on
+//! the command line.
+//!
+//! The file holds one pattern per line; blank lines and lines starting with `#` are skipped.
+//!
+//! * A line starting with `key:` holds a key path (see [`crate::path`]) kept out of the base:
+//!   every input keeps its own value there, in its diff. A `*` segment matches any one key, so
+//!   `key: metadata.annotations.*` keeps each annotation out of the base.
+//! * Any other line is a glob of input files left out altogether, as if they had not been given.
+//!   `*` matches any characters but `/`, `?` one of them, and a `**` segment any number of
+//!   directories. A glob with a `/` is matched against the whole path as given (without a
+//!   leading `./`); one without is matched against each component, so `generated` leaves out the
+//!   files below any `generated` directory, like `.gitignore` does. Negations (`!`) are not
+//!   supported.
+
+use std::borrow::Cow;
+
+use yaml_rust2::yaml::{Hash, Yaml};
+
+use crate::input::is_empty_document;
+use crate::path::{key_segment, parse_path, Segment};
+use crate::result::DiffResult;
+
+/// Name of the ignore file, read from the working directory.
+pub const IGNORE_FILE: &str = ".yabeignore";
+
+const KEY_PREFIX: &str = "key:";
+
+/// The patterns of an ignore file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreRules {
+    /// Globs of input files to leave out.
+    pub file_globs: Vec<String>,
+    /// Key paths to keep out of the base.
+    pub key_paths: Vec<KeyPattern>,
+}
+
+impl IgnoreRules {
+    /// Checks whether an input file is left out.
+    pub fn ignores_file(&self, path: &str) -> bool {
+        self.file_globs.iter().any(|glob| matches_file_glob(glob, path))
+    }
+}
+
+/// Parses an ignore file, failing with the line number of a malformed line.
+pub fn parse_ignore_file(source: &str) -> Result<IgnoreRules, String> {
+    let mut rules = IgnoreRules::default();
+    for (number, line) in source.lines().enumerate() {
+        let line = line.trim();
+        let fail = |message: String| format!("line {}: {}", number + 1, message);
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        } else if let Some(path) = line.strip_prefix(KEY_PREFIX) {
+            let path = path.trim();
+            rules.key_paths.push(KeyPattern::new(path).map_err(fail)?);
+        } else if line.starts_with('!') {
+            return Err(fail(format!("negated pattern '{}' is not supported", line)));
+        } else {
+            rules.file_globs.push(line.to_string());
+        }
+    }
+    Ok(rules)
+}
+
+/// A key path pattern: a non-empty path of map keys, in which a `*` segment matches any one key.
+/// It cannot index arrays, which only enter the base whole.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPattern {
+    /// The pattern as given.
+    pub path: String,
+    keys: Vec<String>,
+}
+
+impl KeyPattern {
+    /// Fails unless the pattern is a non-empty path of map keys.
+    pub fn new(path: &str) -> Result<Self, String> {
+        if path.is_empty() {
+            return Err("empty key path".to_string());
+        }
+        let keys = parse_path(path)?
+            .into_iter()
+            .map(|segment| match segment {
+                Segment::Key(key) => Ok(key),
+                Segment::Index(_) => Err(format!("key path '{}' indexes an array", path)),
+            })
+            .collect::<Result<Vec<String>, String>>()?;
+        Ok(KeyPattern {
+            path: path.to_string(),
+            keys,
+        })
+    }
+
+    /// Checks whether the pattern matches a path, given as its keys.
+    pub fn matches(&self, path: &[String]) -> bool {
+        self.keys.len() == path.len() && self.keys.iter().zip(path).all(|(pattern, key)| pattern == "*" || pattern == key)
+    }
+}
+
+/// Checks whether a file path matches a glob (see the module documentation).
+pub fn matches_file_glob(glob: &str, path: &str) -> bool {
+    let path: Vec<Vec<char>> = path
+        .strip_prefix("./")
+        .unwrap_or(path)
+        .split('/')
+        .map(|segment| segment.chars().collect())
+        .collect();
+    let glob = glob.strip_prefix("./").unwrap_or(glob).trim_start_matches('/');
+    let glob: Vec<Vec<char>> = glob.split('/').map(|segment| segment.chars().collect()).collect();
+    if glob.len() == 1 {
+        path.iter().any(|segment| matches_segment(&glob[0], segment))
+    } else {
+        matches_segments(&glob, &path)
+    }
+}
+
+fn matches_segments(glob: &[Vec<char>], path: &[Vec<char>]) -> bool {
+    match glob.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if *first == ['*', '*'] => {
+            (0..=path.len()).any(|skipped| matches_segments(rest, &path[skipped..]))
+        }
+        Some((first, rest)) => path
+            .split_first()
+            .is_some_and(|(segment, path)| matches_segment(first, segment) && matches_segments(rest, path)),
+    }
+}
+
+fn matches_segment(glob: &[char], text: &[char]) -> bool {
+    match glob.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skipped| matches_segment(rest, &text[skipped..])),
+        Some(('?', rest)) => !text.is_empty() && matches_segment(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && matches_segment(rest, &text[1..]),
+    }
+}
+
+/// Keeps the key paths matching `patterns` out of an extraction result's base.
+///
+/// They are removed from the base, and each input's value there goes into its diff instead, so
+/// merging the diffs onto the base still rebuilds `objs`, the inputs of the extraction. Maps
+/// left empty in the base by the removal are removed as well.
+pub fn keep_out_of_base<'a>(objs: &[&Yaml], mut result: DiffResult<'a>, patterns: &[KeyPattern]) -> DiffResult<'a> {
+    for pattern in patterns {
+        let paths = match result.base.as_deref() {
+            Some(base) => matching_paths(base, &pattern.keys),
+            None => continue,
+        };
+        for path in paths {
+            if let Some(base) = result.base.as_mut() {
+                remove_at(base.to_mut(), &path);
+            }
+            for (obj, diff) in objs.iter().zip(result.diffs.iter_mut()) {
+                if is_empty_document(obj) {
+                    continue;
+                }
+                match lookup(obj, &path) {
+                    Some(value) => {
+                        let diff = diff.get_or_insert_with(|| Cow::Owned(Yaml::Hash(Hash::new())));
+                        set_at(diff.to_mut(), &path, value.clone());
+                    }
+                    None => {
+                        if let Some(current) = diff.as_mut() {
+                            remove_at(current.to_mut(), &path);
+                            if matches!(current.as_ref(), Yaml::Hash(hash) if hash.is_empty()) {
+                                *diff = None;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Removes the key paths matching `patterns` from a document, e.g. to leave them out of checks
+/// about what the base could hold. Maps left empty by the removal are removed as well.
+pub fn strip_ignored<'a>(doc: &'a Yaml, patterns: &[KeyPattern]) -> Cow<'a, Yaml> {
+    let mut doc = Cow::Borrowed(doc);
+    for pattern in patterns {
+        for path in matching_paths(&doc, &pattern.keys) {
+            remove_at(doc.to_mut(), &path);
+        }
+    }
+    doc
+}
+
+/// Returns the key paths of `value` matching a pattern, as the keys along them.
+fn matching_paths(value: &Yaml, pattern: &[String]) -> Vec<Vec<Yaml>> {
+    let Some((first, rest)) = pattern.split_first() else {
+        return vec![vec![]];
+    };
+    let Yaml::Hash(hash) = value else {
+        return vec![];
+    };
+    let mut paths = Vec::new();
+    for (key, child) in hash {
+        if first != "*" && key_segment(key) != *first {
+            continue;
+        }
+        for mut path in matching_paths(child, rest) {
+            path.insert(0, key.clone());
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+fn lookup<'y>(value: &'y Yaml, path: &[Yaml]) -> Option<&'y Yaml> {
+    path.iter().try_fold(value, |value, key| value.as_hash()?.get(key))
+}
+
+/// Removes the value at a path, and the maps it leaves empty below the top level.
+fn remove_at(value: &mut Yaml, path: &[Yaml]) {
+    let Some((key, rest)) = path.split_first() else {
+        return;
+    };
+    let Yaml::Hash(hash) = value else {
+        return;
+    };
+    if rest.is_empty() {
+        hash.remove(key);
+        return;
+    }
+    if let Some(child) = hash.get_mut(key) {
+        let was_empty = matches!(child, Yaml::Hash(child) if child.is_empty());
+        remove_at(child, rest);
+        if !was_empty && matches!(child, Yaml::Hash(child) if child.is_empty()) {
+            hash.remove(key);
+        }
+    }
+}
+
+/// Sets the value at a path, creating the maps on the way; a value that is not a map on the way
+/// already holds the whole input value there, so it is left alone.
+fn set_at(value: &mut Yaml, path: &[Yaml], new_value: Yaml) {
+    let Some((key, rest)) = path.split_first() else {
+        return;
+    };
+    let Yaml::Hash(hash) = value else {
+        return;
+    };
+    if rest.is_empty() {
+        match hash.get_mut(key) {
+            Some(existing) => *existing = new_value,
+            None => {
+                hash.insert(key.clone(), new_value);
+            }
+        }
+        return;
+    }
+    let child = hash.entry(key.clone()).or_insert_with(|| Yaml::Hash(Hash::new()));
+    set_at(child, rest, new_value);
+}
@@ -0,0 +1,202 @@
+//! Input layer: resolving the files to read, and reading them.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use yaml_rust2::{ScanError, Yaml, YamlLoader};
+
+use crate::diff::diff_and_common_with_options;
+use crate::error::DiffError;
+use crate::options::DiffOptions;
+use crate::result::DiffResult;
+
+/// Expands `@manifest` arguments into the files listed in the manifest, preserving order.
+///
+/// A manifest lists one path per line; blank lines and lines starting with `#` are skipped.
+/// Relative paths are resolved against the manifest's directory. Other arguments are kept as-is.
+/// A listed file that does not exist is an error naming the manifest line.
+pub fn expand_manifest_args(args: &[String]) -> io::Result<Vec<String>> {
+    let mut files = Vec::new();
+    for arg in args {
+        match arg.strip_prefix('@') {
+            Some(manifest) => files.extend(read_manifest(Path::new(manifest))?),
+            None => files.push(arg.clone()),
+        }
+    }
+    Ok(files)
+}
+
+/// Reads the paths listed in a manifest file.
+pub fn read_manifest(manifest: &Path) -> io::Result<Vec<String>> {
+    let content = read_source(manifest).map_err(|e| {
+        io::Error::new(e.kind(), format!("{}: {}", manifest.display(), e))
+    })?;
+    let dir = manifest.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut files = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let path = dir.join(line);
+        if !path.is_file() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "{}:{}: listed file {} does not exist",
+                    manifest.display(),
+                    number + 1,
+                    path.display()
+                ),
+            ));
+        }
+        files.push(path.to_string_lossy().into_owned());
+    }
+    Ok(files)
+}
+
+/// Splits input files into those taking part and those excluded, keeping their order.
+///
+/// A file is excluded when it is the same file as one of `excluded`: the same path, or the same
+/// file once both are resolved. Returns the files taking part, the excluded files and the
+/// exclusions that matched no file.
+pub fn partition_excluded(files: Vec<String>, excluded: &[String]) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let resolve = |path: &str| fs::canonicalize(path).unwrap_or_else(|_| Path::new(path).to_path_buf());
+    let excluded_paths: Vec<_> = excluded.iter().map(|path| resolve(path)).collect();
+    let mut matched = vec![false; excluded.len()];
+    let (mut taking_part, mut left_out) = (Vec::new(), Vec::new());
+    for file in files {
+        let path = resolve(&file);
+        match excluded_paths.iter().position(|excluded| *excluded == path) {
+            Some(index) => {
+                matched[index] = true;
+                left_out.push(file);
+            }
+            None => taking_part.push(file),
+        }
+    }
+    let unmatched = excluded
+        .iter()
+        .zip(matched)
+        .filter(|(_, matched)| !matched)
+        .map(|(path, _)| path.clone())
+        .collect();
+    (taking_part, left_out, unmatched)
+}
+
+/// Strips a leading UTF-8 byte order mark and turns CRLF and lone CR line endings into LF, as
+/// written by some Windows editors.
+///
+/// The parser reads every line ending as a line break, block scalars included, but takes a byte
+/// order mark for part of the first key; the comments and directives read from a source keep a
+/// trailing CR. Sources are normalized before anything reads them, so that a file saved on
+/// Windows is the same input as the same file saved elsewhere. A CR escaped in a double-quoted
+/// scalar (`"\r"`) is not a line ending and is kept.
+///
+/// ```
+/// use yabe::input::normalize_source;
+///
+/// assert_eq!(normalize_source("\u{feff}a: 1\r\nb: |\r\n  x\r\n"), "a: 1\nb: |\n  x\n");
+/// assert_eq!(normalize_source("a: 1\rb: 2\r\n"), "a: 1\nb: 2\n");
+/// ```
+pub fn normalize_source(source: &str) -> Cow<'_, str> {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    if source.contains('\r') {
+        Cow::Owned(source.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(source)
+    }
+}
+
+/// Reads a text file, normalized with [`normalize_source`].
+pub fn read_source(path: &Path) -> io::Result<String> {
+    let source = fs::read_to_string(path)?;
+    Ok(match normalize_source(&source) {
+        Cow::Borrowed(normalized) if normalized.len() == source.len() => source,
+        normalized => normalized.into_owned(),
+    })
+}
+
+/// Parses the first document of a YAML source, normalized with [`normalize_source`]; a source
+/// without documents is `null`.
+pub fn parse_document(source: &str) -> Result<Yaml, ScanError> {
+    Ok(YamlLoader::load_from_str(&normalize_source(source))?.into_iter().next().unwrap_or(Yaml::Null))
+}
+
+/// Reads and parses the first document of a YAML file; a file without documents is `null`.
+pub fn read_document(path: &Path) -> Result<Yaml, DiffError> {
+    let source = read_source(path).map_err(|source| DiffError::Read { path: path.to_path_buf(), source })?;
+    parse_document(&source).map_err(|source| DiffError::Parse { path: path.to_path_buf(), source })
+}
+
+/// Reads, parses and extracts the base of YAML files, with the diffs named after the paths as
+/// given.
+///
+/// This is [`crate::diff::diff_and_common_with_options`] for inputs on disk; the first file that
+/// cannot be read or parsed is the error, and nothing is extracted. Empty files are empty inputs.
+///
+/// ```no_run
+/// use std::path::Path;
+/// use yabe::{diff_files, DiffOptions};
+///
+/// let result = diff_files(&[Path::new("prod.yaml"), Path::new("staging.yaml")], &DiffOptions::default())?;
+/// if let Some(diff) = result.diff("prod.yaml") {
+///     println!("{:?}", diff);
+/// }
+/// # Ok::<(), yabe::DiffError>(())
+/// ```
+pub fn diff_files(paths: &[&Path], options: &DiffOptions) -> Result<DiffResult<'static>, DiffError> {
+    let docs = paths.iter().map(|path| read_document(path)).collect::<Result<Vec<Yaml>, DiffError>>()?;
+    let refs: Vec<&Yaml> = docs.iter().collect();
+    Ok(diff_and_common_with_options(&refs, options)
+        .into_owned()
+        .with_names(paths.iter().map(|path| path.display().to_string())))
+}
+
+/// Returns whether a parsed input is empty: a file without documents, or a `null` document.
+///
+/// Empty inputs contribute nothing to base extraction; see
+/// [`crate::diff::diff_and_common_with_options`].
+pub fn is_empty_document(doc: &Yaml) -> bool {
+    matches!(doc, Yaml::Null | Yaml::BadValue)
+}
+
+/// What to do with empty input files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyInputs {
+    /// Empty inputs are left out of the quorum and get no diff.
+    #[default]
+    Ignore,
+    /// Empty inputs are an error.
+    Error,
+}
+
+impl FromStr for EmptyInputs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ignore" => Ok(EmptyInputs::Ignore),
+            "error" => Ok(EmptyInputs::Error),
+            _ => Err(format!(
+                "unknown empty input handling '{}', expected one of: ignore, error",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for EmptyInputs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EmptyInputs::Ignore => "ignore",
+            EmptyInputs::Error => "error",
+        };
+        f.write_str(name)
+    }
+}
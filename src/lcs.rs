@@ -0,0 +1,191 @@
+//! Order-significant arrays aligned by a longest common subsequence.
+//!
+//! The alignment uses Myers' O((N+M)·D) algorithm, where N and M are the array lengths and D
+//! the number of inserted and removed elements; it keeps the frontier of every step, so memory
+//! grows as O(D²). Arrays longer than the strategy's cap are compared positionally instead.
+//!
+//! A diff against a base array is an edit script under the `$lcs` key:
+//!
+//! ```yaml
+//! args:
+//!   $lcs:
+//!     - {at: 1, delete: 1}
+//!     - {at: 3, insert: [--verbose]}
+//!     - {at: 4, change: [{port: 8080}]}
+//! ```
+//!
+//! `at` is an index into the base array. `delete` removes that many base elements, `insert`
+//! adds elements before the base element at `at`, and `change` applies one diff per element to
+//! the base elements starting at `at`. A hunk that removes as many elements as it adds is a
+//! `change` when every element's diff reproduces it, and a `delete` plus `insert` otherwise.
+//! Scripts are only understood when merging with the LCS or the ordered-set strategy.
+
+use yaml_rust2::yaml::{Array, Hash, Yaml};
+
+/// Key holding an edit script.
+pub const LCS_DIRECTIVE: &str = "$lcs";
+
+/// Default cap on the length of arrays that are aligned.
+pub const DEFAULT_MAX_LEN: usize = 1000;
+
+/// Returns the index pairs of a longest common subsequence of `a` and `b`, in order.
+pub fn matches(a: &[Yaml], b: &[Yaml], eq: impl Fn(&Yaml, &Yaml) -> bool) -> Vec<(usize, usize)> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let offset = n + m + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    // Forward pass: furthest reaching x on every diagonal k = x - y after d edits
+    'search: for d in 0..=(n + m) {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let at = |k: isize| v[(offset + k) as usize];
+            let mut x = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+                at(k + 1)
+            } else {
+                at(k - 1) + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && eq(&a[x as usize], &b[y as usize]) {
+                x += 1;
+                y += 1;
+            }
+            v[(offset + k) as usize] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    // Backward pass: follow the recorded frontiers, collecting the diagonal runs
+    let mut pairs = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, frontier) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| frontier[(k + d) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let (prev_x, prev_y) = if d == 0 {
+            (0, 0)
+        } else {
+            let prev_x = at(prev_k);
+            (prev_x, prev_x - prev_k)
+        };
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            pairs.push((x as usize, y as usize));
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    pairs.reverse();
+    pairs
+}
+
+/// Returns a longest common subsequence of `a` and `b`, taking elements from `a`.
+pub fn common_subsequence(a: &[Yaml], b: &[Yaml], eq: impl Fn(&Yaml, &Yaml) -> bool) -> Array {
+    matches(a, b, eq).into_iter().map(|(i, _)| a[i].clone()).collect()
+}
+
+fn key(name: &str) -> Yaml {
+    Yaml::String(name.to_string())
+}
+
+/// Builds the edit script turning `base` into `items`, or `None` if they are equal.
+///
+/// `diff_element(item, base_item)` computes the diff of a changed element, or `None` if the
+/// element has to be replaced whole.
+pub fn edit_script(
+    base: &[Yaml],
+    items: &[Yaml],
+    eq: impl Fn(&Yaml, &Yaml) -> bool,
+    diff_element: impl Fn(&Yaml, &Yaml) -> Option<Yaml>,
+) -> Option<Yaml> {
+    let mut hunks = Array::new();
+    let (mut base_index, mut item_index) = (0, 0);
+    let pairs = matches(base, items, eq);
+    for (next_base, next_item) in pairs.into_iter().chain([(base.len(), items.len())]) {
+        let removed = &base[base_index..next_base];
+        let added = &items[item_index..next_item];
+        if !removed.is_empty() || !added.is_empty() {
+            let mut hunk = Hash::new();
+            hunk.insert(key("at"), Yaml::Integer(base_index as i64));
+            let changes: Option<Array> = (removed.len() == added.len())
+                .then(|| {
+                    added
+                        .iter()
+                        .zip(removed)
+                        .map(|(item, base_item)| diff_element(item, base_item))
+                        .collect()
+                })
+                .flatten();
+            if let Some(changes) = changes {
+                hunk.insert(key("change"), Yaml::Array(changes));
+            } else {
+                if !removed.is_empty() {
+                    hunk.insert(key("delete"), Yaml::Integer(removed.len() as i64));
+                }
+                if !added.is_empty() {
+                    hunk.insert(key("insert"), Yaml::Array(added.to_vec()));
+                }
+            }
+            hunks.push(Yaml::Hash(hunk));
+        }
+        base_index = next_base + 1;
+        item_index = next_item + 1;
+    }
+    if hunks.is_empty() {
+        return None;
+    }
+    let mut script = Hash::new();
+    script.insert(key(LCS_DIRECTIVE), Yaml::Array(hunks));
+    Some(Yaml::Hash(script))
+}
+
+/// Checks if a value is an edit script.
+pub fn is_script(value: &Yaml) -> bool {
+    matches!(value, Yaml::Hash(hash) if hash.len() == 1 && value[LCS_DIRECTIVE].as_vec().is_some())
+}
+
+/// Replays an edit script on `base`; a value that is not a script (see [`is_script`]) has no
+/// hunks.
+///
+/// Hunks are applied in order of `at`; indices past the end of `base` are clamped.
+/// `merge_element(base_item, diff)` applies the diff of a changed element.
+pub fn apply_script(
+    base: &[Yaml],
+    script: &Yaml,
+    merge_element: impl Fn(&Yaml, &Yaml) -> Yaml,
+) -> Array {
+    let mut hunks: Vec<&Yaml> = script[LCS_DIRECTIVE].as_vec().into_iter().flatten().collect();
+    let index = |hunk: &Yaml, name: &str| hunk[name].as_i64().unwrap_or(0).clamp(0, base.len() as i64) as usize;
+    hunks.sort_by_key(|hunk| index(hunk, "at"));
+
+    let mut merged = Array::new();
+    let mut cursor = 0;
+    for hunk in hunks {
+        let at = index(hunk, "at").max(cursor);
+        merged.extend_from_slice(&base[cursor..at]);
+        cursor = at;
+        if let Some(changes) = hunk["change"].as_vec() {
+            for change in changes {
+                match base.get(cursor) {
+                    Some(base_item) => merged.push(merge_element(base_item, change)),
+                    None => merged.push(change.clone()),
+                }
+                cursor = (cursor + 1).min(base.len());
+            }
+        }
+        if let Some(inserted) = hunk["insert"].as_vec() {
+            merged.extend_from_slice(inserted);
+        }
+        cursor = (cursor + index(hunk, "delete")).min(base.len());
+    }
+    merged.extend_from_slice(&base[cursor..]);
+    merged
+}
@@ -1,6 +1,94 @@
+pub mod arrays;
+pub mod blob;
+pub mod builder;
+pub mod cache;
+pub mod check;
+#[cfg(feature = "codegen")]
+pub mod codegen;
+pub mod comments;
+pub mod compare;
 pub mod deep_equal;
+pub mod denylist;
 pub mod diff;
+pub mod directives;
+pub mod env_file;
+pub mod error;
+pub mod fingerprint;
+pub mod flatten;
+pub mod graph;
+pub mod group;
+#[cfg(feature = "helm")]
+pub mod helm;
+pub mod ignore;
+pub mod input;
+pub mod lcs;
 pub mod merge;
+pub mod minimize;
+pub mod normalize;
+pub mod options;
+pub mod output;
+pub mod partition;
+pub mod path;
+pub mod preview;
+pub mod report;
+pub mod result;
+pub mod schema;
+pub mod set_args;
+pub mod skeleton;
 pub mod sorter;
+pub mod stats;
+pub mod strategic_merge;
+pub mod timing;
+pub mod transform;
 
-pub use diff::{compute_diff, diff_and_common_multiple};
\ No newline at end of file
+pub use diff::{common_base, compute_diff, diff_and_common_multiple, diff_and_common_with_options};
+pub use error::DiffError;
+pub use input::diff_files;
+pub use options::{ArrayStrategy, BaseMode, DiffOptions, MinorityKeys, NullHandling};
+pub use result::{DiffResult, DiffsByName};
+pub use yaml_rust2::Yaml;
+
+/// Builds a [`Yaml`] value from YAML source, for examples and tests.
+///
+/// The first document is returned; an empty source is `null`. Panics if the source is not
+/// valid YAML.
+///
+/// ```
+/// use yabe::{yaml, Yaml};
+///
+/// let doc = yaml!("image:\n  tag: v1\nports: [80, 443]");
+/// assert_eq!(doc["image"]["tag"].as_str(), Some("v1"));
+/// assert_eq!(doc["ports"][1].as_i64(), Some(443));
+/// assert_eq!(yaml!(""), Yaml::Null);
+/// ```
+///
+/// Maps (`{ key => value, ... }`) and arrays (`[item, ...]`) can also be built from Rust
+/// values, converted with [`builder::IntoYaml`]; nested maps take a nested `yaml!`. Unlike YAML
+/// source, a string is never read as another type.
+///
+/// ```
+/// use yabe::{yaml, Yaml};
+///
+/// let name = "web";
+/// let doc = yaml!({ "name" => name, "replicas" => 3, "tag" => "1.10", "env" => yaml!({ "DEBUG" => "true" }) });
+/// assert_eq!(doc["tag"].as_str(), Some("1.10"));
+/// assert_eq!(doc["env"]["DEBUG"].as_str(), Some("true"));
+/// assert_eq!(yaml!([1, 2]), yaml!("[1, 2]"));
+/// assert_eq!(yaml!({}), yaml!("{}"));
+/// ```
+#[macro_export]
+macro_rules! yaml {
+    ({ $($key:expr => $value:expr),* $(,)? }) => {{
+        let entries: ::std::vec::Vec<($crate::Yaml, $crate::Yaml)> = ::std::vec![
+            $(($crate::builder::IntoYaml::into_yaml($key), $crate::builder::IntoYaml::into_yaml($value))),*
+        ];
+        $crate::builder::map(entries)
+    }};
+    ([ $($item:expr),* $(,)? ]) => {{
+        let items: ::std::vec::Vec<$crate::Yaml> = ::std::vec![$($crate::builder::IntoYaml::into_yaml($item)),*];
+        $crate::Yaml::Array(items)
+    }};
+    ($source:expr) => {
+        $crate::input::parse_document($source).expect("invalid YAML literal")
+    };
+}
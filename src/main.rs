@@ -1,31 +1,74 @@
-use std::borrow::Cow;
+mod cli;
+
 use std::error::Error;
-use std::fs;
-use std::path::Path;
 
 use clap::Parser;
-use log::{info, warn};
-use yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
-use yabe::diff::{compute_diff, diff_and_common_multiple};
-use yabe::merge::merge_yaml;
-use yabe::sorter::sort_yaml;
+use log::info;
+use yabe::check::{DuplicateInputs, UnrelatedInputs};
+use yabe::diff::LeafCounting;
+use yabe::env_file::NonScalar;
+use yabe::graph::GraphFormat;
+use yabe::input::EmptyInputs;
+use yabe::options::{AliasConflict, ArrayStrategy, BaseMode, CoercedForm, CoercedType, MinorityKeys, NullHandling};
+use yabe::output::{RealFormat, UnchangedSlots};
+use yabe::report::ReportFormat;
+use yabe::schema::StrictTypes;
+use yabe::timing::{timed, Timings};
+
+use cli::{analysis, cache, check, compare, extract, minimize, setup, Context};
+
+/// Number of the slowest top-level keys listed by `--timing`.
+const MAX_TIMED_SUBTREES: usize = 10;
 
 /// Command-line arguments
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Helm chart values file
-    #[arg(short = 'r', long = "read-base", value_name = "READ_BASE")]
+    #[arg(short = 'r', long = "read-base", value_name = "READ_BASE", group = "read_base_source")]
     read_only_base: Option<String>,
 
+    /// Helm chart directory whose default values, including its subcharts', are the read-only base
+    #[cfg(feature = "helm")]
+    #[arg(long = "chart", value_name = "CHART_DIR", group = "read_base_source")]
+    chart: Option<String>,
+
     /// Base YAML file to merge with input files
     #[arg(short = 'b', long = "base", value_name = "WRITE_BASE")]
     base: Option<String>,
 
-    /// Input YAML files
-    #[arg(required = true)]
+    /// Input YAML files; @<manifest> expands to the files listed in the manifest
+    #[arg(required_unless_present_any = ["compare", "minimize"])]
     input_files: Vec<String>,
 
+    /// Leave this input file out of the extraction, e.g. an extracted base passed among the inputs by mistake; may be repeated
+    #[arg(long = "exclude", value_name = "FILE")]
+    exclude: Vec<String>,
+
+    /// Also write the diff of each --exclude'd input against the extracted base
+    #[arg(long = "minimize-excluded", requires = "exclude", conflicts_with_all = ["schema", "chain", "check"])]
+    minimize_excluded: bool,
+
+    /// Keep this dotted key path out of the base, so that every file keeps its own value there; a `*` segment matches any key; may be repeated
+    #[arg(long = "ignore-key", value_name = "PATH")]
+    ignore_keys: Vec<String>,
+
+    /// Treat the map at this dotted key path as a single value, hoisted whole when a quorum sets the same map and kept whole in the diff otherwise; a `*` segment matches any key; may be repeated
+    #[arg(long = "atomic-key", value_name = "PATH")]
+    atomic_keys: Vec<String>,
+
+    /// Drop every key whose whole name matches this regular expression, at any depth, from the inputs before diffing, e.g. '.*checksum.*'; may be repeated
+    #[arg(long = "exclude-key-pattern", value_name = "REGEX")]
+    exclude_key_patterns: Vec<String>,
+
+    /// Ignore file listing globs of input files to leave out and `key:` paths to keep out of the base [default: ./.yabeignore, if it exists]
+    #[arg(long = "ignore-file", value_name = "FILE")]
+    ignore_file: Option<String>,
+
+    /// Do not read the ignore file
+    #[arg(long = "no-ignore-file", conflicts_with = "ignore_file")]
+    no_ignore_file: bool,
+
     /// Modify the original input files with diffs
     #[arg(short = 'i', long = "in-place")]
     inplace: bool,
@@ -38,6 +81,74 @@ struct Args {
     #[arg(long = "debug")]
     debug: bool,
 
+    /// Only log errors
+    #[arg(long = "quiet", conflicts_with = "debug")]
+    quiet: bool,
+
+    /// Print the base and diffs to stdout as a YAML stream instead of writing files
+    #[arg(long = "stdout", conflicts_with = "inplace")]
+    stdout: bool,
+
+    /// Write the base and diffs as the values of an umbrella chart to <OUT>/values.yaml (stdout with --stdout), each diff nested under the subchart this YAML file maps its input to
+    #[cfg(feature = "helm")]
+    #[arg(
+        long = "umbrella",
+        value_name = "FILE",
+        conflicts_with_all = ["inplace", "stdout_diff", "set_args", "env_file", "unchanged_slots", "groups", "exclude", "schema", "union_schema", "two_phase", "template", "chain", "partition_by", "check", "compare", "minimize"]
+    )]
+    umbrella: Option<String>,
+
+    /// Print only this input file's diff to stdout instead of writing files
+    #[arg(
+        long = "stdout-diff",
+        value_name = "FILE",
+        conflicts_with_all = ["inplace", "stdout", "set_args", "schema", "union_schema", "chain", "partition_by", "check", "compare", "minimize"]
+    )]
+    stdout_diff: Option<String>,
+
+    /// How to write unchanged elements of positional array diffs: null, or indexed (only the changed elements, by index)
+    #[arg(
+        long = "unchanged-slots",
+        default_value_t = UnchangedSlots::Null,
+        conflicts_with_all = ["schema", "chain", "groups", "partition_by"]
+    )]
+    unchanged_slots: UnchangedSlots,
+
+    /// Write the base and diff files with the `%YAML` version directive and the `---` and `...` document markers of the first input
+    #[arg(long = "preserve-directives")]
+    preserve_directives: bool,
+
+    /// Write the maps and arrays of the base and diffs whose flow form, e.g. `{cpu: 500m, memory: 1Gi}`, is at most this many characters long on one line in flow style
+    #[arg(long = "flow-width", value_name = "CHARS")]
+    flow_width: Option<usize>,
+
+    /// Write the reals of the base and diffs as read (preserve), or from their value with the fewest decimals reading back the same (shortest) or a fixed number of them (fixed:<DECIMALS>), optionally in scientific notation from a magnitude of 10^EXPONENT or below 10^-EXPONENT (e.g. shortest,sci:6)
+    #[arg(long = "real-format", value_name = "FORMAT", default_value_t = RealFormat::Preserve)]
+    real_format: RealFormat,
+
+    /// Write each file's diff as Helm --set arguments, one per line, to <OUT>/<name>.set instead of YAML
+    #[arg(
+        long = "set-args",
+        conflicts_with_all = ["inplace", "stdout", "unchanged_slots", "schema", "union_schema", "chain", "partition_by", "compare", "minimize"]
+    )]
+    set_args: bool,
+
+    /// Write each file's diff as environment variables, one NAME=value line per leaf, to <OUT>/<name>.env instead of YAML; the base is also written next to its YAML file with an .env extension
+    #[arg(
+        long = "env-file",
+        conflicts_with_all = ["inplace", "stdout", "stdout_diff", "set_args", "unchanged_slots", "schema", "union_schema", "two_phase", "template", "chain", "partition_by", "compare", "minimize"]
+    )]
+    env_file: bool,
+
+    /// How --env-file writes empty maps and arrays: error, or json ({} and [])
+    #[arg(long = "env-non-scalar", value_name = "MODE", default_value_t = NonScalar::Error, requires = "env_file")]
+    env_non_scalar: NonScalar,
+
+    /// Also write serde-annotated Rust structs for the configuration, inferred from the base and diffs, to this file (`codegen` feature)
+    #[cfg(feature = "codegen")]
+    #[arg(long = "rust-structs", value_name = "FILE")]
+    rust_structs: Option<String>,
+
     /// Quorum percentage (0-100)
     #[arg(short = 'q', long = "quorum", default_value_t = 51)]
     quorum: u8,
@@ -46,200 +157,350 @@ struct Args {
     #[arg(long = "base-out-path", default_value = "./base.yaml")]
     base_out_path: String,
 
+    /// Sidecar file mapping key paths of the base to documentation, written as comments above those keys
+    #[arg(long = "base-docs", value_name = "FILE")]
+    base_docs: Option<String>,
+
     /// Sort configuration file path
     #[arg(long = "sort-config-path", default_value = "./sort-config.yaml")]
     sort_config_path: String,
+
+    /// Key order template: a YAML document with keys in the canonical order, or a list of key paths; overrides orderTemplate in the sort configuration
+    #[arg(long = "order-template", value_name = "FILE")]
+    order_template: Option<String>,
+
+    /// Interpretation of explicit nulls: absent, inherit or value
+    #[arg(long = "null-handling", default_value_t = NullHandling::Absent)]
+    null_handling: NullHandling,
+
+    /// Array strategy: positional, append-unique, prefix, lcs[:<max_len>], ordered-set or strategic-merge[:<merge_key>[,<field>...]]
+    #[arg(long = "array-strategy", default_value_t = ArrayStrategy::Positional)]
+    array_strategy: ArrayStrategy,
+
+    /// Expand literal dotted keys (a.b.c: 1) into nested maps before diffing
+    #[arg(long = "expand-dotted-keys")]
+    expand_dotted_keys: bool,
+
+    /// YAML map of renamed keys, old dotted path to new one (e.g. ingress.hosts: ingress.hostnames); values at old paths are moved to the new ones before diffing
+    #[arg(long = "key-aliases", value_name = "FILE")]
+    key_aliases: Option<String>,
+
+    /// What to do with a file setting both paths of a key alias: prefer-new, prefer-old or fail
+    #[arg(long = "alias-conflict", default_value_t = AliasConflict::PreferNew, requires = "key_aliases")]
+    alias_conflict: AliasConflict,
+
+    /// Only print the key renames --key-aliases makes in each input, without extracting or writing anything
+    #[arg(
+        long = "preview-renames",
+        requires = "key_aliases",
+        conflicts_with_all = ["inplace", "stdout", "schema", "union_schema", "two_phase", "template", "chain", "check", "compare", "minimize"]
+    )]
+    preview_renames: bool,
+
+    /// Format of the --preview-renames report: text or json
+    #[arg(long = "rename-format", default_value_t = ReportFormat::Text, requires = "preview_renames")]
+    rename_format: ReportFormat,
+
+    /// Base to extract: synthetic (quorum winners per leaf) or representative (the input closest to them)
+    #[arg(long = "base-mode", default_value_t = BaseMode::Synthetic)]
+    base_mode: BaseMode,
+
+    /// Keys only some files set: keep-in-diff, hoist (quorum among the files setting them; the others get `key: null`) or hoist-if-all-present-agree
+    #[arg(long = "minority-keys", default_value_t = MinorityKeys::KeepInDiff)]
+    minority_keys: MinorityKeys,
+
+    /// Read an explicit null as an empty map or array where the other files have one
+    #[arg(long = "null-as-empty")]
+    null_as_empty: bool,
+
+    /// Read quoted values of these types (int, float, bool; comma-separated) and their unquoted equivalents as one value
+    #[arg(long = "coerce-strings", value_name = "TYPES", value_delimiter = ',')]
+    coerce_strings: Vec<CoercedType>,
+
+    /// Form of coerced values in the base and the diffs: typed or string
+    #[arg(long = "coerce-form", default_value_t = CoercedForm::Typed, requires = "coerce_strings")]
+    coerce_form: CoercedForm,
+
+    /// Only coerce values at or below this dotted key path; may be repeated
+    #[arg(long = "coerce-path", value_name = "PATH", requires = "coerce_strings")]
+    coerce_paths: Vec<String>,
+
+    /// Only hoist keys that at least this many files set to something other than null
+    #[arg(long = "min-presence", value_name = "FILES", default_value_t = 0)]
+    min_presence: usize,
+
+    /// Only hoist scalars into the base; arrays stay in every file, even when shared
+    #[arg(long = "hoist-scalars-only")]
+    hoist_scalars_only: bool,
+
+    /// Count numbers (or numbers with a unit, like 100m) at this dotted key path as one value for the quorum when they round to the same multiple of STEP, e.g. resources.*.cpu=50; may be repeated
+    #[arg(long = "bucket", value_name = "PATH=STEP")]
+    buckets: Vec<String>,
+
+    /// Ignore the order of map keys and array elements everywhere, comparing arrays as multisets; the output is written in a canonical order
+    #[arg(long = "order-insensitive")]
+    order_insensitive: bool,
+
+    /// Treat strings of at least this many bytes as blobs, compared by fingerprint and abbreviated in logs
+    #[arg(long = "blob-threshold", value_name = "BYTES")]
+    blob_threshold: Option<usize>,
+
+    /// Fingerprint every subtree of the inputs diffed against a base, skipping full comparisons of subtrees that differ; faster on large, deeply nested inputs
+    #[arg(long = "fingerprint-subtrees")]
+    fingerprint_subtrees: bool,
+
+    /// Compare the structure (keys and types) of the inputs instead of their values; prints the common schema and each file's deviations to stdout
+    #[arg(long = "schema", conflicts_with_all = ["chain", "inplace"])]
+    schema: bool,
+
+    /// Check the inputs for completeness against the union of their keys instead of extracting a base; prints the union schema and each file's missing keys and overrides to stdout
+    #[arg(long = "union-schema", conflicts_with_all = ["schema", "chain", "inplace"])]
+    union_schema: bool,
+
+    /// Extract the structure shared by the quorum first, then fill in the agreed values; prints the structure, the skeleton with a placeholder at each divergent leaf, and the divergent values to stdout
+    #[arg(long = "two-phase", conflicts_with_all = ["schema", "union_schema", "chain", "inplace"])]
+    two_phase: bool,
+
+    /// Print a starter file for a new environment with every key of the inputs: the base value where there is one, the empty value of the key's type otherwise, and a comment above each key only some inputs set
+    #[arg(long = "template", conflicts_with_all = ["schema", "union_schema", "two_phase", "chain", "inplace"])]
+    template: bool,
+
+    /// Fail instead of warning when a self-check finds a problem
+    #[arg(long = "strict")]
+    strict: bool,
+
+    /// Fail when the inputs hold values of different types at a path: fail-fast stops at the first conflict, collect-all reports them all
+    #[arg(long = "strict-types", value_name = "MODE")]
+    strict_types: Option<StrictTypes>,
+
+    /// Fail when some inputs hold a scalar and others a map or an array at the same path (resources: 512Mi against resources: {limits: ...}), tolerating other type differences
+    #[arg(long = "strict-collection-scalar")]
+    strict_collection_scalar: bool,
+
+    /// Treat the inputs as an ordered overlay chain, diffing each against all preceding ones
+    #[arg(long = "chain", conflicts_with = "base")]
+    chain: bool,
+
+    /// Report the read-only base keys each input leaves at their default
+    #[arg(long = "report-defaults", requires = "read_base_source")]
+    report_defaults: bool,
+
+    /// Exit with code 2 if no common base could be extracted
+    #[arg(long = "require-base")]
+    require_base: bool,
+
+    /// Explain, key by key, why no common base was extracted (done anyway with --require-base)
+    #[arg(long = "explain-no-base")]
+    explain_no_base: bool,
+
+    /// What to do with empty input files: ignore (leave them out of the quorum) or error
+    #[arg(long = "empty-inputs", default_value_t = EmptyInputs::Ignore)]
+    empty_inputs: EmptyInputs,
+
+    /// Check for input files equal to one another, usually copy-pasted without edits: warn, or error [default: no check]
+    #[arg(long = "duplicate-inputs", value_name = "MODE")]
+    duplicate_inputs: Option<DuplicateInputs>,
+
+    /// Fraction (0-1) of the average input's leaves the base must cover; below it, the inputs may be unrelated files
+    #[arg(long = "min-base-coverage", value_name = "FRACTION", default_value_t = 0.1)]
+    min_base_coverage: f64,
+
+    /// What to do when the base covers less than --min-base-coverage: ignore, warn (an error with --strict), or error
+    #[arg(long = "unrelated-inputs", value_name = "MODE", default_value_t = UnrelatedInputs::Warn)]
+    unrelated_inputs: UnrelatedInputs,
+
+    /// Fail when a value of the base matches one of the values or patterns of this denylist file, e.g. a secret that must not be shared between environments
+    #[arg(long = "denylist", value_name = "FILE")]
+    denylist: Option<String>,
+
+    /// Write each input's effective configuration after extraction (read-only base + base + diff) to this folder
+    #[arg(long = "preview-dir")]
+    preview_dir: Option<String>,
+
+    /// Only write the previews that do not match their original input
+    #[arg(long = "preview-changed-only", requires = "preview_dir")]
+    preview_changed_only: bool,
+
+    /// Warn about paths with more than this many distinct values across the inputs
+    #[arg(long = "max-fan-out", default_value_t = 10)]
+    max_fan_out: usize,
+
+    /// Do not warn about paths exceeding --max-fan-out
+    #[arg(long = "no-fan-out-warning")]
+    no_fan_out_warning: bool,
+
+    /// Write each file's override leaf count, largest first, and the fraction of its leaves coming from the base as YAML to this file
+    #[arg(long = "size-report", value_name = "FILE")]
+    size_report: Option<String>,
+
+    /// Warn about files overriding more than this many leaves on top of the base
+    #[arg(long = "max-overrides", value_name = "LEAVES")]
+    max_overrides: Option<usize>,
+
+    /// Also write statistics of the run (file count, base and override sizes, coverage, type conflicts) as JSON to this file, to track them over time
+    #[arg(long = "stats", value_name = "FILE")]
+    stats: Option<String>,
+
+    /// How --size-report, --max-overrides, --stats and --min-base-coverage count arrays: whole-arrays (one leaf each) or elements (walked into, the null slots of unchanged elements counting 0)
+    #[arg(long = "leaf-counting", value_name = "MODE", default_value_t = LeafCounting::WholeArrays)]
+    leaf_counting: LeafCounting,
+
+    /// Directory of the cache of per-file results, reused across runs; by default in the working directory. It grows by an entry for every changed input and set of options, up to --cache-max-size
+    #[arg(long = "cache-dir", default_value = "./.yabe-cache")]
+    cache_dir: String,
+
+    /// Size in MiB the cache is pruned to, least recently used entries first, before a cached run
+    #[arg(long = "cache-max-size", value_name = "MIB", default_value_t = 64)]
+    cache_max_size: u64,
+
+    /// Neither read nor write the cache
+    #[arg(long = "no-cache")]
+    no_cache: bool,
+
+    /// Print the wall-clock time spent in each phase of the run, the extraction broken down by top-level key, to stderr at the end
+    #[arg(long = "timing", conflicts_with_all = ["schema", "union_schema", "chain", "check", "compare", "minimize", "partition_by"])]
+    timing: bool,
+
+    /// Cluster the inputs into at most this many groups and extract a base per group on top of the global base
+    #[arg(long = "groups", value_name = "N", conflicts_with_all = ["schema", "chain"])]
+    groups: Option<usize>,
+
+    /// Also merge groups whose inputs differ by at most this many leaves, for coarser groups
+    #[arg(long = "group-max-distance", value_name = "LEAVES", requires = "groups")]
+    group_max_distance: Option<usize>,
+
+    /// Only give a base to groups of at least this many files (at least 2)
+    #[arg(long = "group-min-size", value_name = "FILES", default_value_t = 2, requires = "groups")]
+    group_min_size: usize,
+
+    /// Give at most this many groups a base, merging the smallest groups back into the global base beyond it
+    #[arg(long = "max-group-bases", value_name = "N", requires = "groups")]
+    max_group_bases: Option<usize>,
+
+    /// Partition the inputs by their values at these dotted paths (comma-separated, e.g. kind,metadata.name) and extract a base within each partition instead of across all inputs
+    #[arg(
+        long = "partition-by",
+        value_name = "PATHS",
+        value_delimiter = ',',
+        conflicts_with_all = ["schema", "chain", "groups", "check", "require_base", "explain_no_base", "graph", "preview_dir", "minimize_excluded", "size_report", "max_overrides", "stats"]
+    )]
+    partition_by: Vec<String>,
+
+    /// Verify that the inputs are already minimal against --base instead of writing anything; exits with code 1 and lists the offending paths if not
+    #[arg(
+        long = "check",
+        requires = "base",
+        conflicts_with_all = ["inplace", "stdout", "schema", "chain", "groups", "report_defaults"]
+    )]
+    check: bool,
+
+    /// Compare the values files of two directories for review instead of extracting a base; prints each file's changes and a summary to stdout
+    #[arg(
+        long = "compare",
+        num_args = 2,
+        value_names = ["BEFORE", "AFTER"],
+        conflicts_with_all = ["input_files", "read_base_source", "base", "inplace", "stdout", "schema", "chain", "groups", "check"]
+    )]
+    compare: Option<Vec<String>>,
+
+    /// Also report the unchanged keys at most this many keys before or after each change of the --compare report, in the same map
+    #[arg(long = "compare-context", value_name = "KEYS", default_value_t = 0, requires = "compare")]
+    compare_context: usize,
+
+    /// Also report each changed multiline string of the --compare report line by line, as unified-diff hunks with this many unchanged lines around each change
+    #[arg(long = "compare-lines", value_name = "LINES", requires = "compare")]
+    compare_lines: Option<usize>,
+
+    /// Promote the values every overlay in OVERLAY_DIR shares into BASE, then reduce each overlay to its difference from BASE; writes to --out and --base-out-path, or in place with -i
+    #[arg(
+        long = "minimize",
+        num_args = 2,
+        value_names = ["BASE", "OVERLAY_DIR"],
+        conflicts_with_all = ["input_files", "read_base_source", "base", "stdout", "schema", "chain", "groups", "check", "compare"]
+    )]
+    minimize: Option<Vec<String>>,
+
+    /// Do not promote shared overlay values into the base with --minimize
+    #[arg(long = "no-promote", requires = "minimize", conflicts_with = "no_minimize")]
+    no_promote: bool,
+
+    /// Do not reduce the overlays to their difference from the base with --minimize
+    #[arg(long = "no-minimize", requires = "minimize")]
+    no_minimize: bool,
+
+    /// Write a diagram of the base and each file's overrides to this file
+    #[arg(long = "graph", value_name = "FILE")]
+    graph: Option<String>,
+
+    /// Format of the --graph diagram: mermaid or dot
+    #[arg(long = "graph-format", default_value_t = GraphFormat::Mermaid, requires = "graph")]
+    graph_format: GraphFormat,
+
+    /// Only draw this many key levels of the --graph diagram
+    #[arg(long = "graph-depth", value_name = "LEVELS", requires = "graph")]
+    graph_depth: Option<usize>,
+
+    /// Only draw this subtree (a dotted key path) of the --graph diagram; may be repeated
+    #[arg(long = "graph-subtree", value_name = "PATH", requires = "graph")]
+    graph_subtrees: Vec<String>,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
 
-    if args.debug {
-        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+    // Diagnostics always go to stderr so stdout only ever carries YAML
+    if args.quiet {
+        env_logger::Builder::new()
+            .filter_level(log::LevelFilter::Error)
+            .target(env_logger::Target::Stderr)
+            .init();
+    } else if args.debug {
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug"))
+            .target(env_logger::Target::Stderr)
+            .init();
     } else {
-        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+            .target(env_logger::Target::Stderr)
+            .init();
     }
 
     info!("Starting the YAML diffing program.");
+    let mut timings = args.timing.then(Timings::default);
 
-    let input_filenames = args.input_files;
+    let ignore_rules = setup::read_ignore_rules(args.ignore_file.as_deref(), args.no_ignore_file)?;
+    let mut inputs = setup::input_files(&args, &ignore_rules)?;
+    let stdout_diff = setup::stdout_diff(&args, &inputs)?;
+    let options = setup::diff_options(&args, &ignore_rules)?;
+    let mut output = setup::output(&args, stdout_diff)?;
+    let denylist = setup::read_denylist(&args)?;
 
-    let quorum_percentage = (args.quorum as f64) / 100.0;
-
-    let base_out_path = args.base_out_path;
-
-    let out_folder = args.out_folder;
+    if let Some(ref dirs) = args.compare {
+        return compare::run(dirs, &args, &options);
+    }
+    if let Some(ref paths) = args.minimize {
+        return minimize::run(paths, &args, &options, &output.config);
+    }
 
-    let config = if !args.sort_config_path.is_empty() {
-        info!("Reading sort configuration file: {}", args.sort_config_path);
-        let content = fs::read_to_string(&args.sort_config_path);
-        if let Ok(content) = content {
-            YamlLoader::load_from_str(&content)?.into_iter().next().unwrap_or(Yaml::Null)
-        } else {
-            warn!("Failed to read sort configuration file: {}", args.sort_config_path);
-            Yaml::Null
-        }
-    } else {
-        Yaml::Null
-    };
+    let bases = setup::read_bases(&args)?;
+    let cache = cache::open(&args);
 
-    let read_only_base = if let Some(ref read_only_base) = args.read_only_base {
-        info!("Reading helm values file: {}", read_only_base);
-        let content = fs::read_to_string(read_only_base)?;
-        YamlLoader::load_from_str(&content)?.into_iter().next()
-    } else {
-        None
-    };
-
-    // Read and parse the existing base file if provided
-    let existing_base = if let Some(ref base_path) = args.base {
-        info!("Reading existing base YAML file: {}", base_path);
-        let content = fs::read_to_string(base_path)?;
-        YamlLoader::load_from_str(&content)?.into_iter().next()
-    } else {
-        None
-    };
-
-    // Read and parse each YAML input file into an object
-    let mut all_docs = Vec::new();
-    for filename in &input_filenames {
-        info!("Reading input file: {}", filename);
-        let content = fs::read_to_string(filename)?;
-        if let Some(doc) = YamlLoader::load_from_str(&content)?.into_iter().next() {
-            all_docs.push(doc);
-        } else {
-            warn!("No YAML documents in {}", filename);
-        }
+    // Read each YAML input file; with a cache, parsing is deferred to cache misses
+    inputs.read(&mut timings)?;
+    // The files written take the directives of the first input
+    output.directives = setup::directives(&args, &inputs);
+    if cache.is_none() {
+        timed(&mut timings, "parsing", || inputs.docs().map(|_| ()))?;
     }
+    let context = Context { args: &args, options, inputs, bases, output, denylist };
 
-    // Merge existing base with each input file if existing base is provided
-    let merged_objs: Vec<Cow<Yaml>> = if let Some(ref base) = existing_base {
-        input_filenames
-            .iter()
-            .zip(all_docs.iter())
-            .map(|(filename, obj)| {
-                let merged = merge_yaml(base, obj);
-                info!("Merged base with input file: {}", filename);
-                merged
-            })
-            .collect()
-    } else {
-        // No existing base; use objs as merged_objs
-        all_docs.iter().map(|doc| Cow::Borrowed(doc)).collect()
-    };
-
-    // Compute diffs between each merged object and read-only base
-    let diffs: Vec<_> = if let Some(ref helm) = read_only_base {
-        info!("Computing diffs between merged files and helm values.");
-        merged_objs
-            .iter()
-            .map(|obj| compute_diff(obj.as_ref(), helm).unwrap_or_else(|| Cow::Owned(Yaml::Null)))
-            .collect()
-    } else {
-        // No read-only base provided values; use merged_objs as diffs
-        merged_objs.clone()
-    };
-
-    // Now compute common base and per-file diffs among the diffs
-    let diffs_refs: Vec<&Yaml> = diffs.iter().map(|cow| cow.as_ref()).collect();
-    info!(
-        "Computing common base and per-file diffs among the diffs with quorum {}%.",
-        args.quorum
-    );
-    let (base, per_file_diffs) = diff_and_common_multiple(&diffs_refs, quorum_percentage);
-
-    // Process the base YAML if it exists
-    if let Some(base_yaml) = base {
-        let processed_yaml = if config != Yaml::Null {
-            sort_yaml(base_yaml.as_ref(), &config)
-        } else {
-            Cow::Borrowed(base_yaml.as_ref())
-        };
-
-        info!("Writing base YAML to {}", base_out_path);
-        let mut out_str = String::new();
-        {
-            let mut emitter = YamlEmitter::new(&mut out_str);
-            emitter.dump(&processed_yaml)?;
-        }
-        out_str = out_str.trim_start_matches("---\n").to_string();
-        out_str.push('\n');
-        fs::write(base_out_path.as_str(), out_str)?;
-        info!("Base YAML written to {}", base_out_path);
-    } else {
-        info!("No base YAML to write.");
+    if let (true, Some(aliases)) = (args.preview_renames, &context.options.key_aliases) {
+        return analysis::preview_renames(&context, aliases);
     }
-
-    // Determine whether to write diffs to original files or new files
-    if args.inplace {
-        info!("Inplace mode enabled. Modifying original files.");
-        for (i, diff) in per_file_diffs.iter().enumerate() {
-            if let Some(diff_yaml) = diff {
-                let processed_diff = if config != Yaml::Null {
-                    sort_yaml(diff_yaml.as_ref(), &config)
-                } else {
-                    Cow::Borrowed(diff_yaml.as_ref())
-                };
-
-                info!("Writing diff back to original file: {}", input_filenames[i]);
-                let mut out_str = String::new();
-                {
-                    let mut emitter = YamlEmitter::new(&mut out_str);
-                    emitter.dump(&processed_diff)?;
-                }
-                out_str = out_str.trim_start_matches("---\n").to_string();
-                out_str.push('\n');
-                fs::write(&input_filenames[i], out_str)?;
-                info!(
-                    "Difference written back to original file {}",
-                    input_filenames[i]
-                );
-            } else {
-                // If there is no diff, remove the content of the file
-                info!("No diff for {}; clearing file content.", input_filenames[i]);
-                fs::write(&input_filenames[i], "")?;
-                info!(
-                    "No difference for {}; file content cleared.",
-                    input_filenames[i]
-                );
-            }
-        }
-    } else {
-        info!("Writing diffs to new files.");
-        for (i, diff) in per_file_diffs.iter().enumerate() {
-            if let Some(diff_yaml) = diff {
-                let processed_diff = if config != Yaml::Null {
-                    sort_yaml(diff_yaml.as_ref(), &config)
-                } else {
-                    Cow::Borrowed(diff_yaml.as_ref())
-                };
-
-                info!("Writing diff for {} to new file.", input_filenames[i]);
-                let mut out_str = String::new();
-                {
-                    let mut emitter = YamlEmitter::new(&mut out_str);
-                    emitter.dump(&processed_diff)?;
-                }
-
-                let input_path = Path::new(&input_filenames[i]);
-                let file_stem = input_path
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("diff");
-                let diff_filename = format!("{}/{}_diff.yaml", out_folder, file_stem);
-                out_str = out_str.trim_start_matches("---\n").to_string();
-                out_str.push('\n');
-                fs::write(&diff_filename, out_str)?;
-                info!(
-                    "Difference for {} written to {}",
-                    input_filenames[i], diff_filename
-                );
-            } else {
-                info!("No diff for {}; not writing a diff file.", input_filenames[i]);
-            }
-        }
+    if args.schema || args.union_schema || args.two_phase || args.template || args.chain {
+        return analysis::run(&context);
     }
-
-    info!("Program completed successfully.");
-    Ok(())
-}
\ No newline at end of file
+    if args.check {
+        return check::run(&context);
+    }
+    extract::run(&context, cache.as_ref(), timings)
+}
@@ -1,21 +1,188 @@
 use std::borrow::Cow;
+use yaml_rust2::yaml::Hash;
 use yaml_rust2::Yaml;
 
+use crate::arrays;
+use crate::lcs;
+use crate::options::{ArrayStrategy, DiffOptions};
+use crate::path::{key_segment, parse_path, value_at, Segment};
+use crate::strategic_merge;
+
 /// Merges two YAML documents.
 pub fn merge_yaml<'a>(base: &'a Yaml, override_yaml: &'a Yaml) -> Cow<'a, Yaml> {
+    merge_yaml_with_options(base, override_yaml, &DiffOptions::default())
+}
+
+/// Same as [`merge_yaml`], honouring the array strategy of the given options.
+pub fn merge_yaml_with_options<'a>(
+    base: &'a Yaml,
+    override_yaml: &'a Yaml,
+    options: &DiffOptions,
+) -> Cow<'a, Yaml> {
     match (base, override_yaml) {
+        (_, Yaml::Hash(_)) if options.slots_patches && arrays::is_slots_patch(override_yaml) => {
+            let merged = arrays::apply_slots_patch(base, override_yaml, |base_item, item| {
+                merge_yaml_with_options(base_item, item, options).into_owned()
+            });
+            Cow::Owned(Yaml::Array(merged))
+        }
         (Yaml::Hash(base_hash), Yaml::Hash(override_hash)) => {
             let mut merged = base_hash.clone();
             for (key, override_value) in override_hash {
                 merged.entry(key.clone())
                     .and_modify(|base_value| {
-                        let merged_value = merge_yaml(base_value, override_value);
+                        let merged_value = merge_yaml_with_options(base_value, override_value, options);
                         *base_value = merged_value.into_owned();
                     })
-                    .or_insert_with(|| override_value.clone());
+                    .or_insert_with(|| resolve_slots_patches(override_value, options).into_owned());
             }
             Cow::Owned(Yaml::Hash(merged))
         }
-        (_, override_val) => Cow::Borrowed(override_val),
+        (Yaml::Array(base_array), Yaml::Hash(_))
+            if options.uses_edit_scripts() && lcs::is_script(override_yaml) =>
+        {
+            let merged = lcs::apply_script(base_array, override_yaml, |base_item, diff| {
+                merge_yaml_with_options(base_item, diff, options).into_owned()
+            });
+            Cow::Owned(Yaml::Array(merged))
+        }
+        (Yaml::Array(base_array), Yaml::Array(override_array))
+            if options.array_strategy == ArrayStrategy::AppendUnique =>
+        {
+            Cow::Owned(Yaml::Array(arrays::append_unique(base_array, override_array)))
+        }
+        (Yaml::Array(base_array), Yaml::Array(override_array))
+            if options.array_strategy == ArrayStrategy::Prefix =>
+        {
+            Cow::Owned(Yaml::Array(base_array.iter().chain(override_array).cloned().collect()))
+        }
+        (Yaml::Array(base_array), Yaml::Array(override_array)) => options
+            .merge_key()
+            .and_then(|merge_key| {
+                strategic_merge::merge_arrays(base_array, override_array, merge_key, |b, o| {
+                    merge_yaml_with_options(b, o, options)
+                })
+            })
+            .unwrap_or_else(|| resolve_slots_patches(override_yaml, options)),
+        (_, override_val) => resolve_slots_patches(override_val, options),
+    }
+}
+
+/// Turns the slots patches (see [`crate::arrays`]) in a value that replaces, rather than merges
+/// onto, a base value into the arrays they stand for, if the options read slots patches.
+fn resolve_slots_patches<'a>(value: &'a Yaml, options: &DiffOptions) -> Cow<'a, Yaml> {
+    if !options.slots_patches || !holds_slots_patch(value) {
+        return Cow::Borrowed(value);
+    }
+    match value {
+        _ if arrays::is_slots_patch(value) => Cow::Owned(Yaml::Array(arrays::apply_slots_patch(
+            &Yaml::Null,
+            value,
+            |_, item| resolve_slots_patches(item, options).into_owned(),
+        ))),
+        Yaml::Hash(hash) => Cow::Owned(Yaml::Hash(
+            hash.iter()
+                .map(|(key, child)| (key.clone(), resolve_slots_patches(child, options).into_owned()))
+                .collect(),
+        )),
+        Yaml::Array(items) => Cow::Owned(Yaml::Array(
+            items.iter().map(|item| resolve_slots_patches(item, options).into_owned()).collect(),
+        )),
+        other => Cow::Borrowed(other),
+    }
+}
+
+fn holds_slots_patch(value: &Yaml) -> bool {
+    match value {
+        _ if arrays::is_slots_patch(value) => true,
+        Yaml::Hash(hash) => hash.values().any(holds_slots_patch),
+        Yaml::Array(items) => items.iter().any(holds_slots_patch),
+        _ => false,
+    }
+}
+
+/// Merges documents in order, each one overriding the previous ones.
+///
+/// Returns `None` for an empty slice.
+pub fn merge_all(docs: &[&Yaml], options: &DiffOptions) -> Option<Yaml> {
+    let (first, rest) = docs.split_first()?;
+    Some(rest.iter().fold((*first).clone(), |merged, doc| {
+        merge_yaml_with_options(&merged, doc, options).into_owned()
+    }))
+}
+
+/// Applies a diff produced by extraction on top of its base, reconstructing what the diff was
+/// extracted from.
+///
+/// `None` stands for no base or no diff; the result is `None` only if both are.
+pub fn apply_diff(base: Option<&Yaml>, diff: Option<&Yaml>, options: &DiffOptions) -> Option<Yaml> {
+    match (base, diff) {
+        (Some(base), Some(diff)) => Some(merge_yaml_with_options(base, diff, options).into_owned()),
+        (Some(only), None) | (None, Some(only)) => Some(only.clone()),
+        (None, None) => None,
+    }
+}
+
+/// Applies only the part of a diff at `path` (see [`crate::path::parse_path`]) on top of its
+/// base, leaving the rest of the base as it is, e.g. to check one section of a diff at a time.
+///
+/// The part is merged onto the base's value at the path like [`apply_diff`] merges a whole
+/// diff, and maps the base lacks on the way are created. When the diff has nothing at the path,
+/// nothing is applied and the result is the base; the empty path applies the whole diff. Going
+/// through an array requires the base to have that array, with the element or up to the one
+/// before it. Such a mismatch, and a malformed path, are errors.
+pub fn apply_diff_at(
+    base: Option<&Yaml>,
+    diff: Option<&Yaml>,
+    path: &str,
+    options: &DiffOptions,
+) -> Result<Option<Yaml>, String> {
+    let segments = parse_path(path)?;
+    let Some(part) = diff.and_then(|diff| value_at(diff, &segments)) else {
+        return Ok(base.cloned());
+    };
+    let mut result = base.cloned().unwrap_or(Yaml::Null);
+    apply_at(&mut result, part, &segments, path, options)?;
+    Ok(Some(result))
+}
+
+fn apply_at(
+    slot: &mut Yaml,
+    part: &Yaml,
+    segments: &[Segment],
+    path: &str,
+    options: &DiffOptions,
+) -> Result<(), String> {
+    let Some((first, rest)) = segments.split_first() else {
+        *slot = merge_yaml_with_options(slot, part, options).into_owned();
+        return Ok(());
+    };
+    match first {
+        Segment::Key(key) => {
+            if !matches!(slot, Yaml::Hash(_)) {
+                *slot = Yaml::Hash(Hash::new());
+            }
+            let Yaml::Hash(hash) = slot else {
+                unreachable!("the slot was just made a map");
+            };
+            let existing = hash.keys().find(|k| key_segment(k) == *key).cloned();
+            let k = existing.unwrap_or_else(|| Yaml::String(key.clone()));
+            if !hash.contains_key(&k) {
+                hash.insert(k.clone(), Yaml::Null);
+            }
+            apply_at(hash.get_mut(&k).unwrap(), part, rest, path, options)
+        }
+        Segment::Index(index) => {
+            let Yaml::Array(items) = slot else {
+                return Err(format!("path '{}' goes through an array the base does not have", path));
+            };
+            if *index == items.len() {
+                items.push(Yaml::Null);
+            }
+            match items.get_mut(*index) {
+                Some(item) => apply_at(item, part, rest, path, options),
+                None => Err(format!("path '{}' goes past the end of a base array", path)),
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,110 @@
+//! Minimizing a directory of overlays against the base they are applied on, e.g. a chart's
+//! `values.yaml` and one values file per environment.
+//!
+//! Two stages run in order, each of which can be turned off:
+//!
+//! * Promotion: values every overlay sets, and sets the same, are merged into the base. The
+//!   quorum is always 100% and keys only some overlays set are never promoted, whatever the
+//!   options say, so the base keeps meaning "what every environment gets". It needs at least two
+//!   overlays; empty overlays take no part.
+//! * Minimization: each overlay is reduced to what its effective values (the original base with
+//!   the overlay applied) differ by from the new base.
+//!
+//! The effective values of every overlay are the same before and after, and running the stages
+//! again on their output promotes and removes nothing.
+
+use std::io;
+use std::path::Path;
+
+use yaml_rust2::Yaml;
+
+use crate::compare::{read_doc, values_files};
+use crate::diff::{common_base, compute_diff_with_options};
+use crate::input::is_empty_document;
+use crate::merge::{apply_diff, merge_yaml_with_options};
+use crate::options::{BaseMode, DiffOptions, MinorityKeys};
+
+/// Stages to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinimizeStages {
+    /// Promote the values shared by every overlay into the base.
+    pub promote: bool,
+    /// Reduce each overlay to its difference from the base.
+    pub minimize: bool,
+}
+
+impl Default for MinimizeStages {
+    fn default() -> Self {
+        MinimizeStages {
+            promote: true,
+            minimize: true,
+        }
+    }
+}
+
+/// The base and overlays after minimizing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Minimized {
+    /// The base, including the promoted values.
+    pub base: Yaml,
+    /// The values promoted into the base, if any.
+    pub promoted: Option<Yaml>,
+    /// Each overlay, in input order; `None` for an overlay left with nothing to set, or one that
+    /// was empty to begin with.
+    pub overlays: Vec<Option<Yaml>>,
+}
+
+/// Runs the stages on a base and its overlays.
+pub fn minimize_overlays(
+    base: &Yaml,
+    overlays: &[&Yaml],
+    stages: &MinimizeStages,
+    options: &DiffOptions,
+) -> Minimized {
+    let present: Vec<&Yaml> = overlays.iter().copied().filter(|doc| !is_empty_document(doc)).collect();
+    let promoted = if stages.promote && present.len() > 1 {
+        let unanimous = DiffOptions {
+            quorum: 1.0,
+            minority_keys: MinorityKeys::KeepInDiff,
+            min_presence: 0,
+            base_mode: BaseMode::Synthetic,
+            ..options.clone()
+        };
+        common_base(&present, &unanimous)
+    } else {
+        None
+    };
+    let new_base = apply_diff(Some(base), promoted.as_ref(), options).unwrap_or(Yaml::Null);
+
+    let minimized = overlays
+        .iter()
+        .map(|overlay| {
+            if is_empty_document(overlay) {
+                None
+            } else if stages.minimize {
+                let effective = merge_yaml_with_options(base, overlay, options);
+                compute_diff_with_options(effective.as_ref(), &new_base, options).map(|diff| diff.into_owned())
+            } else {
+                Some((*overlay).clone())
+            }
+        })
+        .collect();
+
+    Minimized {
+        base: new_base,
+        promoted,
+        overlays: minimized,
+    }
+}
+
+/// Reads the values files (`.yaml` and `.yml`, in subdirectories too) of a directory of
+/// overlays, returning their paths relative to it with their documents, in order of path.
+pub fn read_overlays(dir: &Path) -> io::Result<Vec<(String, Yaml)>> {
+    values_files(dir)?
+        .into_values()
+        .map(|name| {
+            let doc = read_doc(&dir.join(&name))?;
+            Ok((name, doc))
+        })
+        .collect()
+}
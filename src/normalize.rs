@@ -0,0 +1,402 @@
+//! Preprocessing passes applied to every input before diffing.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use yaml_rust2::yaml::{Hash, Yaml};
+
+use crate::merge::merge_yaml;
+use crate::options::{
+    AliasConflict, CoercedForm, CoercedType, DiffOptions, ExcludedKeys, KeyAliases, LeafTransform, StringCoercion,
+};
+use crate::path::{format_path, key_segment, parse_path, split_path, Segment};
+
+/// Checks if any preprocessing pass is enabled.
+pub fn needs_normalization(options: &DiffOptions) -> bool {
+    options.expand_dotted_keys
+        || options.excluded_keys.is_some()
+        || options.key_aliases.is_some()
+        || options.coercion.is_some()
+        || options.leaf_transform.is_some()
+        || options.order_insensitive
+}
+
+/// Applies the enabled preprocessing passes to a document.
+pub fn normalize<'a>(doc: &'a Yaml, options: &DiffOptions) -> Cow<'a, Yaml> {
+    let mut doc = Cow::Borrowed(doc);
+    if options.expand_dotted_keys {
+        doc = Cow::Owned(expand_dotted_keys(&doc));
+    }
+    if let Some(excluded) = &options.excluded_keys {
+        doc = Cow::Owned(drop_keys(&doc, excluded));
+    }
+    if let Some(aliases) = &options.key_aliases {
+        doc = Cow::Owned(rename_keys(&doc, aliases));
+    }
+    if let Some(coercion) = &options.coercion {
+        doc = Cow::Owned(coerce_scalars(&doc, coercion));
+    }
+    if let Some(transform) = &options.leaf_transform {
+        doc = Cow::Owned(transform_leaves(&doc, transform));
+    }
+    if options.order_insensitive {
+        doc = Cow::Owned(sort_canonically(&doc));
+    }
+    doc
+}
+
+/// Applies the enabled preprocessing passes to every document.
+pub fn normalize_all<'a>(docs: &[&'a Yaml], options: &DiffOptions) -> Vec<Cow<'a, Yaml>> {
+    docs.iter().map(|doc| normalize(doc, options)).collect()
+}
+
+/// Expands literal dotted keys into nested maps, like Helm's `--set a.b.c=1`.
+///
+/// `a.b.c: 1` becomes `a: {b: {c: 1}}`; an escaped dot (`a\.b`) stays part of the key.
+/// Keys with empty segments (`a..b`, `.a`) are left untouched. When an expanded key collides
+/// with an existing one, the maps are merged and the entry that comes later in the document wins.
+pub fn expand_dotted_keys(doc: &Yaml) -> Yaml {
+    match doc {
+        Yaml::Hash(hash) => {
+            let mut expanded = Hash::new();
+            for (key, value) in hash {
+                let value = expand_dotted_keys(value);
+                let (key, value) = match key.as_str() {
+                    Some(k) if k.contains('.') => nest(k, value),
+                    _ => (key.clone(), value),
+                };
+                match expanded.get_mut(&key) {
+                    Some(existing) => {
+                        let merged = merge_yaml(existing, &value).into_owned();
+                        *existing = merged;
+                    }
+                    None => {
+                        expanded.insert(key, value);
+                    }
+                }
+            }
+            Yaml::Hash(expanded)
+        }
+        Yaml::Array(items) => Yaml::Array(items.iter().map(expand_dotted_keys).collect()),
+        _ => doc.clone(),
+    }
+}
+
+/// Turns `a.b.c` and a value into the key `a` and the value `{b: {c: value}}`.
+fn nest(key: &str, value: Yaml) -> (Yaml, Yaml) {
+    let segments = split_path(key);
+    if segments.iter().any(|segment| segment.is_empty()) {
+        return (Yaml::String(key.to_string()), value);
+    }
+    let mut segments = segments.into_iter().rev();
+    let mut nested = value;
+    let mut head = segments.next().unwrap_or_default();
+    for segment in segments {
+        let mut hash = Hash::new();
+        hash.insert(Yaml::String(head), nested);
+        nested = Yaml::Hash(hash);
+        head = segment;
+    }
+    (Yaml::String(head), nested)
+}
+
+/// Removes every map key matching `excluded`, with its value, at any depth (see [`ExcludedKeys`]).
+///
+/// Maps left empty keep their key, as an empty map.
+pub fn drop_keys(doc: &Yaml, excluded: &ExcludedKeys) -> Yaml {
+    match doc {
+        Yaml::Hash(hash) => Yaml::Hash(
+            hash.iter()
+                .filter(|(key, _)| !excluded.matches(key))
+                .map(|(key, value)| (key.clone(), drop_keys(value, excluded)))
+                .collect(),
+        ),
+        Yaml::Array(items) => Yaml::Array(items.iter().map(|item| drop_keys(item, excluded)).collect()),
+        _ => doc.clone(),
+    }
+}
+
+/// Moves the values at the old paths of `aliases` to their new paths, see [`KeyAliases`].
+///
+/// A document setting both paths of an alias keeps one value according to the conflict
+/// policy; [`AliasConflict::Fail`] keeps the new one, as callers are expected to have refused
+/// the document with [`alias_conflicts`] already.
+pub fn rename_keys(doc: &Yaml, aliases: &KeyAliases) -> Yaml {
+    apply_aliases(doc, aliases).0
+}
+
+/// Returns the aliases whose old and new paths the document both sets, in order.
+///
+/// Earlier aliases are applied before a later one is checked, as [`rename_keys`] does. A new
+/// path under a value that is not a map counts as set.
+pub fn alias_conflicts<'a>(doc: &Yaml, aliases: &'a KeyAliases) -> Vec<&'a (String, String)> {
+    alias_renames(doc, aliases)
+        .into_iter()
+        .filter(|rename| rename.conflict)
+        .map(|rename| &aliases.aliases[rename.index])
+        .collect()
+}
+
+/// A move of a value from the old path of an alias to its new path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AliasRename<'a> {
+    /// Index of the alias among the [`KeyAliases`].
+    pub index: usize,
+    /// The old path, as written in the aliases.
+    pub old: &'a str,
+    /// The new path, as written in the aliases.
+    pub new: &'a str,
+    /// Whether the document set the new path too, the conflict policy deciding which value stays.
+    pub conflict: bool,
+}
+
+/// Lists the moves [`rename_keys`] makes in a document, in the order of the aliases, without
+/// renaming anything; an alias whose old path the document does not set makes none.
+///
+/// ```
+/// use yabe::normalize::alias_renames;
+/// use yabe::options::{AliasConflict, KeyAliases};
+/// use yabe::yaml;
+///
+/// let aliases = KeyAliases::from_yaml(&yaml!("ingress.hosts: ingress.hostnames\nimage: container.image"), AliasConflict::PreferNew).unwrap();
+/// let renames = alias_renames(&yaml!("image: nginx\ncontainer: {image: redis}"), &aliases);
+/// assert_eq!((renames.len(), renames[0].old, renames[0].new, renames[0].conflict), (1, "image", "container.image", true));
+/// ```
+pub fn alias_renames<'a>(doc: &Yaml, aliases: &'a KeyAliases) -> Vec<AliasRename<'a>> {
+    apply_aliases(doc, aliases).1
+}
+
+fn apply_aliases<'a>(doc: &Yaml, aliases: &'a KeyAliases) -> (Yaml, Vec<AliasRename<'a>>) {
+    let mut doc = doc.clone();
+    let mut renames = Vec::new();
+    for (index, (old_path, new_path)) in aliases.aliases.iter().enumerate() {
+        let (Some(old), Some(new)) = (key_path(old_path), key_path(new_path)) else {
+            continue;
+        };
+        if old == new || !occupied(&doc, &old) {
+            continue;
+        }
+        let conflict = occupied(&doc, &new);
+        let Some(value) = take_at(&mut doc, &old) else {
+            continue;
+        };
+        renames.push(AliasRename { index, old: old_path, new: new_path, conflict });
+        if !conflict || aliases.on_conflict == AliasConflict::PreferOld {
+            put_at(&mut doc, &new, value);
+        }
+    }
+    (doc, renames)
+}
+
+/// Parses a path of map keys; `None` if it is empty or indexes an array.
+fn key_path(path: &str) -> Option<Vec<String>> {
+    parse_path(path)
+        .ok()?
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Key(key) => Some(key),
+            Segment::Index(_) => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|keys| !keys.is_empty())
+}
+
+fn find_key<'h>(hash: &'h Hash, segment: &str) -> Option<&'h Yaml> {
+    hash.keys().find(|key| key_segment(key) == segment)
+}
+
+/// Checks whether a document sets a path, or holds something other than a map on the way.
+fn occupied(doc: &Yaml, path: &[String]) -> bool {
+    let Some((first, rest)) = path.split_first() else {
+        return true;
+    };
+    match doc {
+        Yaml::Hash(hash) => match find_key(hash, first) {
+            Some(key) => occupied(&hash[key], rest),
+            None => false,
+        },
+        _ => true,
+    }
+}
+
+/// Removes and returns the value at a path, removing the maps it leaves empty.
+fn take_at(doc: &mut Yaml, path: &[String]) -> Option<Yaml> {
+    let (first, rest) = path.split_first()?;
+    let Yaml::Hash(hash) = doc else {
+        return None;
+    };
+    let key = find_key(hash, first)?.clone();
+    if rest.is_empty() {
+        return hash.remove(&key);
+    }
+    let child = hash.get_mut(&key)?;
+    let value = take_at(child, rest)?;
+    if matches!(child, Yaml::Hash(child) if child.is_empty()) {
+        hash.remove(&key);
+    }
+    Some(value)
+}
+
+/// Sets the value at a path, creating maps on the way and replacing values that are not maps.
+fn put_at(doc: &mut Yaml, path: &[String], value: Yaml) {
+    let Some((first, rest)) = path.split_first() else {
+        *doc = value;
+        return;
+    };
+    if !matches!(doc, Yaml::Hash(_)) {
+        *doc = Yaml::Hash(Hash::new());
+    }
+    let Yaml::Hash(hash) = doc else {
+        return;
+    };
+    let key = find_key(hash, first).cloned().unwrap_or_else(|| Yaml::String(first.clone()));
+    let child = hash.entry(key).or_insert(Yaml::Null);
+    if rest.is_empty() {
+        *child = value;
+    } else {
+        put_at(child, rest, value);
+    }
+}
+
+/// Puts every coerced leaf of a document in the canonical form of `coercion`.
+pub fn coerce_scalars(doc: &Yaml, coercion: &StringCoercion) -> Yaml {
+    let coercion = coercion.clone();
+    let transform = LeafTransform::new(move |path, value| {
+        if coercion.applies_to(path) {
+            coerce_scalar(value, &coercion)
+        } else {
+            value.clone()
+        }
+    });
+    transform_leaves(doc, &transform)
+}
+
+/// Puts a scalar in the canonical form of `coercion`, if it is of one of its types.
+pub fn coerce_scalar(value: &Yaml, coercion: &StringCoercion) -> Yaml {
+    let is = |kind| coercion.types.contains(&kind);
+    match (coercion.form, value) {
+        (CoercedForm::Typed, Yaml::String(s)) => match s.as_str() {
+            "true" | "false" if is(CoercedType::Bool) => Yaml::Boolean(s == "true"),
+            _ if is(CoercedType::Int) && is_canonical_int(s) => s.parse().map_or_else(|_| value.clone(), Yaml::Integer),
+            _ if is(CoercedType::Float) && is_canonical_float(s) => Yaml::Real(s.clone()),
+            _ => value.clone(),
+        },
+        (CoercedForm::String, Yaml::Boolean(b)) if is(CoercedType::Bool) => Yaml::String(b.to_string()),
+        (CoercedForm::String, Yaml::Integer(i)) if is(CoercedType::Int) => Yaml::String(i.to_string()),
+        (CoercedForm::String, Yaml::Real(s)) if is(CoercedType::Float) && is_canonical_float(s) => {
+            Yaml::String(s.clone())
+        }
+        _ => value.clone(),
+    }
+}
+
+/// `0`, or digits without a leading zero, optionally after `-`.
+fn is_canonical_int(s: &str) -> bool {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    let nonzero = !digits.is_empty() && !digits.starts_with('0');
+    digits.bytes().all(|b| b.is_ascii_digit()) && (s == "0" || nonzero)
+}
+
+/// A canonical integer part, a dot and at least one digit.
+fn is_canonical_float(s: &str) -> bool {
+    match s.split_once('.') {
+        Some((int, frac)) => {
+            (is_canonical_int(int) || int == "-0") && !frac.is_empty() && frac.bytes().all(|b| b.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+/// Replaces every leaf with the result of `transform`, walking into maps and arrays.
+pub fn transform_leaves(doc: &Yaml, transform: &LeafTransform) -> Yaml {
+    transform_at(doc, transform, &mut Vec::new())
+}
+
+fn transform_at(value: &Yaml, transform: &LeafTransform, path: &mut Vec<Segment>) -> Yaml {
+    match value {
+        Yaml::Hash(hash) if !hash.is_empty() => Yaml::Hash(
+            hash.iter()
+                .map(|(key, child)| {
+                    path.push(Segment::Key(key_segment(key)));
+                    let child = transform_at(child, transform, path);
+                    path.pop();
+                    (key.clone(), child)
+                })
+                .collect(),
+        ),
+        Yaml::Array(items) if !items.is_empty() => Yaml::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    path.push(Segment::Index(index));
+                    let item = transform_at(item, transform, path);
+                    path.pop();
+                    item
+                })
+                .collect(),
+        ),
+        Yaml::Hash(_) | Yaml::Array(_) => value.clone(),
+        leaf => transform.apply(&format_path(path), leaf),
+    }
+}
+
+/// Sorts the keys of every map and the elements of every array in the [`canonical_cmp`] order.
+///
+/// Documents differing only in key and element order become equal, arrays being compared as
+/// multisets. The original order is lost.
+pub fn sort_canonically(doc: &Yaml) -> Yaml {
+    match doc {
+        Yaml::Hash(hash) => {
+            let mut entries: Vec<(Yaml, Yaml)> =
+                hash.iter().map(|(key, value)| (sort_canonically(key), sort_canonically(value))).collect();
+            entries.sort_by(|(a, _), (b, _)| canonical_cmp(a, b));
+            Yaml::Hash(entries.into_iter().collect())
+        }
+        Yaml::Array(items) => {
+            let mut items: Vec<Yaml> = items.iter().map(sort_canonically).collect();
+            items.sort_by(canonical_cmp);
+            Yaml::Array(items)
+        }
+        other => other.clone(),
+    }
+}
+
+/// A total order of values: by type (`null`, booleans, integers, reals, strings, arrays, maps),
+/// then by value. Reals are ordered by number, then by their text; arrays and maps are ordered
+/// element by element, maps by entries in their current order, so they should be sorted first.
+pub fn canonical_cmp(a: &Yaml, b: &Yaml) -> Ordering {
+    fn rank(value: &Yaml) -> u8 {
+        match value {
+            Yaml::Null => 0,
+            Yaml::Boolean(_) => 1,
+            Yaml::Integer(_) => 2,
+            Yaml::Real(_) => 3,
+            Yaml::String(_) => 4,
+            Yaml::Array(_) => 5,
+            Yaml::Hash(_) => 6,
+            Yaml::Alias(_) => 7,
+            Yaml::BadValue => 8,
+        }
+    }
+    match (a, b) {
+        (Yaml::Boolean(a), Yaml::Boolean(b)) => a.cmp(b),
+        (Yaml::Integer(a), Yaml::Integer(b)) => a.cmp(b),
+        (Yaml::Real(a_text), Yaml::Real(b_text)) => {
+            let number = |value: &Yaml| value.as_f64().unwrap_or(f64::NAN);
+            number(a).total_cmp(&number(b)).then_with(|| a_text.cmp(b_text))
+        }
+        (Yaml::String(a), Yaml::String(b)) => a.cmp(b),
+        (Yaml::Array(a), Yaml::Array(b)) => {
+            a.iter().zip(b).map(|(a, b)| canonical_cmp(a, b)).find(|o| o.is_ne()).unwrap_or_else(|| a.len().cmp(&b.len()))
+        }
+        (Yaml::Hash(a), Yaml::Hash(b)) => a
+            .iter()
+            .zip(b)
+            .map(|((a_key, a_value), (b_key, b_value))| canonical_cmp(a_key, b_key).then_with(|| canonical_cmp(a_value, b_value)))
+            .find(|o| o.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        (Yaml::Alias(a), Yaml::Alias(b)) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
@@ -0,0 +1,822 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use regex::Regex;
+use yaml_rust2::Yaml;
+
+use crate::ignore::KeyPattern;
+use crate::lcs::DEFAULT_MAX_LEN;
+use crate::path::{key_segment, parse_path, Segment};
+
+/// How an explicit `null` in an input is interpreted during quorum and diffing.
+///
+/// A key that is missing from a file is always treated the same way: it counts as a `null`
+/// for the quorum, so a key that is set in some files and missing in others is never hoisted
+/// (see [`MinorityKeys`] to change that). Only the *explicit* `key: null` case is configurable:
+///
+/// | Input            | `Absent` (default)           | `Inherit`                        | `Value`                          |
+/// |------------------|------------------------------|----------------------------------|----------------------------------|
+/// | key missing      | counts as `null`             | counts as `null`                 | counts as `null`                 |
+/// | `key: null`      | same as missing              | no opinion: excluded from quorum | a concrete value                 |
+/// | `null` in diff   | only if the base has the key | never                            | kept, even if the base lacks it  |
+///
+/// `Value` is the `distinguish_null` behaviour; the variants are mutually exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NullHandling {
+    /// An explicit `null` is indistinguishable from a missing key.
+    #[default]
+    Absent,
+    /// An explicit `null` means "fall back to base": it neither becomes base nor appears in the diff.
+    Inherit,
+    /// An explicit `null` is a value of its own and is preserved in the diffs.
+    Value,
+}
+
+impl FromStr for NullHandling {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "absent" => Ok(NullHandling::Absent),
+            "inherit" => Ok(NullHandling::Inherit),
+            "value" => Ok(NullHandling::Value),
+            _ => Err(format!(
+                "unknown null handling '{}', expected one of: absent, inherit, value",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for NullHandling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            NullHandling::Absent => "absent",
+            NullHandling::Inherit => "inherit",
+            NullHandling::Value => "value",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How arrays are compared, diffed and merged.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ArrayStrategy {
+    /// Arrays are compared element by element; unchanged slots are `null` in diffs.
+    #[default]
+    Positional,
+    /// Arrays of maps are matched by `merge_key`, like Kubernetes strategic merge patches.
+    ///
+    /// A `merge_key` of comma-separated fields (`name,protocol`) is a composite key. Arrays that
+    /// are not keyed by `merge_key` fall back to `Positional`.
+    /// See [`crate::strategic_merge`] for the supported directives.
+    StrategicMerge { merge_key: String },
+    /// Arrays are a base list plus per-file extras; diffs hold only added elements.
+    ///
+    /// See [`crate::arrays`] for ordering and deduplication.
+    AppendUnique,
+    /// Arrays are a shared prefix plus per-file trailing elements; diffs hold only the tail.
+    ///
+    /// See [`crate::arrays`] for arrays that share no prefix.
+    Prefix,
+    /// Arrays are aligned by a longest common subsequence; diffs are edit scripts.
+    ///
+    /// Arrays longer than `max_len` fall back to `Positional`.
+    /// See [`crate::lcs`] for the script format and complexity.
+    Lcs { max_len: usize },
+    /// Arrays are ordered sets of unique elements: the base is the elements a quorum of files
+    /// hold in the order a quorum of them agree on, and diffs are edit scripts inserting and
+    /// removing elements.
+    ///
+    /// Arrays with duplicate elements, or longer than [`DEFAULT_MAX_LEN`], fall back to
+    /// `Positional`. See [`crate::arrays`] for how the base is chosen and [`crate::lcs`] for the
+    /// script format.
+    OrderedSet,
+}
+
+impl FromStr for ArrayStrategy {
+    type Err = String;
+
+    /// Parses `positional`, `append-unique`, `prefix`, `lcs[:<max_len>]`, `ordered-set` or
+    /// `strategic-merge[:<merge_key>]`, the merge key defaulting to `name`; a composite merge key
+    /// separates its fields with commas.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "positional" => Ok(ArrayStrategy::Positional),
+            None if s == "append-unique" => Ok(ArrayStrategy::AppendUnique),
+            None if s == "prefix" => Ok(ArrayStrategy::Prefix),
+            None if s == "ordered-set" => Ok(ArrayStrategy::OrderedSet),
+            None if s == "strategic-merge" => Ok(ArrayStrategy::StrategicMerge {
+                merge_key: "name".to_string(),
+            }),
+            None if s == "lcs" => Ok(ArrayStrategy::Lcs {
+                max_len: DEFAULT_MAX_LEN,
+            }),
+            Some(("lcs", max_len)) => max_len
+                .parse()
+                .map(|max_len| ArrayStrategy::Lcs { max_len })
+                .map_err(|_| format!("invalid LCS length cap '{}', expected a number", max_len)),
+            Some(("strategic-merge", key)) if key.split(',').all(|field| !field.trim().is_empty()) => {
+                Ok(ArrayStrategy::StrategicMerge {
+                    merge_key: key.to_string(),
+                })
+            }
+            _ => Err(format!(
+                "unknown array strategy '{}', expected positional, append-unique, prefix, lcs[:<max_len>], ordered-set or strategic-merge[:<key>]",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ArrayStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArrayStrategy::Positional => f.write_str("positional"),
+            ArrayStrategy::StrategicMerge { merge_key } => write!(f, "strategic-merge:{}", merge_key),
+            ArrayStrategy::AppendUnique => f.write_str("append-unique"),
+            ArrayStrategy::Prefix => f.write_str("prefix"),
+            ArrayStrategy::Lcs { max_len } => write!(f, "lcs:{}", max_len),
+            ArrayStrategy::OrderedSet => f.write_str("ordered-set"),
+        }
+    }
+}
+
+/// How a key that some files set and others lack is extracted.
+///
+/// A missing key counts as `null`, so by default such a key conflicts with the files that set
+/// it and stays in their diffs however consistent its value is. The other policies extract the
+/// key among the files that set it and, when it reaches the base, give every file lacking it
+/// an explicit `key: null` in its diff, which Helm treats as removing the key. Files with an
+/// inherited `null` (see [`NullHandling::Inherit`]) set nothing and count as neither.
+///
+/// With `a.yaml` and `b.yaml` setting `tls: {enabled: true, port: 443}` and `tls: {enabled:
+/// true, port: 8443}`, and `c.yaml` without `tls`:
+///
+/// | Policy                    | base                  | `a` diff          | `b` diff           | `c` diff     |
+/// |---------------------------|-----------------------|-------------------|--------------------|--------------|
+/// | `KeepInDiff` (default)    | -                     | all of `tls`      | all of `tls`       | -            |
+/// | `Hoist`                   | `tls: {enabled: true}`| `tls: {port: 443}`| `tls: {port: 8443}`| `tls: null`  |
+/// | `HoistIfAllPresentAgree`  | -                     | all of `tls`      | all of `tls`       | -            |
+///
+/// `Hoist` applies the quorum among the files setting the key, so their common part is
+/// hoisted even if they disagree elsewhere. `HoistIfAllPresentAgree` hoists the key only if
+/// every file setting it has the same value: had `a` and `b` both used port 443, the whole
+/// `tls` map would move to the base and only `c` would need a diff (`tls: null`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MinorityKeys {
+    /// A missing key counts as `null`, so the key stays in the diffs of the files setting it.
+    #[default]
+    KeepInDiff,
+    /// The key is extracted among the files setting it; the others remove it with `null`.
+    Hoist,
+    /// Like `Hoist`, but only if every file setting the key has the same value.
+    HoistIfAllPresentAgree,
+}
+
+impl FromStr for MinorityKeys {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep-in-diff" => Ok(MinorityKeys::KeepInDiff),
+            "hoist" => Ok(MinorityKeys::Hoist),
+            "hoist-if-all-present-agree" => Ok(MinorityKeys::HoistIfAllPresentAgree),
+            _ => Err(format!(
+                "unknown minority key policy '{}', expected one of: keep-in-diff, hoist, hoist-if-all-present-agree",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MinorityKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MinorityKeys::KeepInDiff => "keep-in-diff",
+            MinorityKeys::Hoist => "hoist",
+            MinorityKeys::HoistIfAllPresentAgree => "hoist-if-all-present-agree",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Where the extracted base comes from.
+///
+/// A `Synthetic` base is assembled per leaf from quorum winners, which keeps the diffs minimal
+/// but may produce a combination no input actually has. A `Representative` base is one input
+/// taken verbatim, the one closest to that synthetic consensus, so the base is guaranteed to be
+/// a real configuration. The price is larger diffs: values only the representative has end up
+/// in the base and must be overridden (or removed with an explicit `null`) by every other file.
+///
+/// Closeness is the number of leaves an input differs from the synthetic base by: a changed
+/// value counts once, a missing or extra subtree counts once per leaf in it. Ties go to the
+/// input listed first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BaseMode {
+    /// The base is assembled from the quorum winners of each leaf.
+    #[default]
+    Synthetic,
+    /// The base is the input closest to the synthetic base.
+    Representative,
+}
+
+impl FromStr for BaseMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "synthetic" => Ok(BaseMode::Synthetic),
+            "representative" => Ok(BaseMode::Representative),
+            _ => Err(format!(
+                "unknown base mode '{}', expected one of: synthetic, representative",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BaseMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BaseMode::Synthetic => "synthetic",
+            BaseMode::Representative => "representative",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A scalar type strings can be coerced to; see [`StringCoercion`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoercedType {
+    /// Decimal integers without a sign other than `-` or leading zeros, e.g. `"8080"`.
+    Int,
+    /// Decimal numbers with a fractional part and no exponent, e.g. `"0.5"`.
+    Float,
+    /// Lowercase `"true"` and `"false"`.
+    Bool,
+}
+
+impl FromStr for CoercedType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "int" => Ok(CoercedType::Int),
+            "float" => Ok(CoercedType::Float),
+            "bool" => Ok(CoercedType::Bool),
+            _ => Err(format!("unknown coerced type '{}', expected one of: int, float, bool", s)),
+        }
+    }
+}
+
+impl fmt::Display for CoercedType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CoercedType::Int => "int",
+            CoercedType::Float => "float",
+            CoercedType::Bool => "bool",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The form coerced values take in the base and the diffs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoercedForm {
+    /// Strings are read as the typed value: `"8080"` becomes `8080`.
+    #[default]
+    Typed,
+    /// Typed values are read as the string: `8080` becomes `"8080"`.
+    String,
+}
+
+impl FromStr for CoercedForm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "typed" => Ok(CoercedForm::Typed),
+            "string" => Ok(CoercedForm::String),
+            _ => Err(format!("unknown coerced form '{}', expected one of: typed, string", s)),
+        }
+    }
+}
+
+impl fmt::Display for CoercedForm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CoercedForm::Typed => "typed",
+            CoercedForm::String => "string",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Reading quoted scalars (`"8080"`, `"true"`) and their unquoted equivalents as one value.
+///
+/// Templating and `--set-string` quote values that other files leave unquoted, and the two
+/// never compare equal. With a coercion, every string of one of the `types` and every value of
+/// that type are read in one canonical `form` before anything is compared, so the base and the
+/// diffs hold that form too.
+///
+/// Only strings that are exactly the canonical text of a value are coerced: `"0123"`, `"+1"`,
+/// `"1e3"` and `"True"` stay strings, as they do not read back the same. A string that must stay
+/// a string can still look like a number, e.g. a version `"1.10"` that would become `1.10`, so
+/// `paths` limits the coercion to the given subtrees (dotted paths, see [`crate::path`]): a leaf
+/// is coerced if its path is one of them or lies below one. No paths means everywhere.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringCoercion {
+    /// Types to coerce.
+    pub types: Vec<CoercedType>,
+    /// Canonical form of coerced values.
+    pub form: CoercedForm,
+    /// Subtrees to coerce in; empty for the whole document.
+    pub paths: Vec<String>,
+}
+
+impl StringCoercion {
+    /// Checks if the leaf at `path` (as written by [`crate::path::format_path`]) is coerced.
+    pub fn applies_to(&self, path: &str) -> bool {
+        self.paths.is_empty()
+            || self.paths.iter().any(|scope| {
+                path.strip_prefix(scope.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[']))
+            })
+    }
+}
+
+/// What to do with a file that sets both the old and the new path of a [`KeyAliases`] entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AliasConflict {
+    /// Keep the value at the new path and drop the old one.
+    #[default]
+    PreferNew,
+    /// Move the old value to the new path, replacing the value there.
+    PreferOld,
+    /// Refuse the inputs; see [`crate::normalize::alias_conflicts`].
+    Fail,
+}
+
+impl FromStr for AliasConflict {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "prefer-new" => Ok(AliasConflict::PreferNew),
+            "prefer-old" => Ok(AliasConflict::PreferOld),
+            "fail" => Ok(AliasConflict::Fail),
+            _ => Err(format!("unknown alias conflict policy '{}', expected one of: prefer-new, prefer-old, fail", s)),
+        }
+    }
+}
+
+impl fmt::Display for AliasConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AliasConflict::PreferNew => "prefer-new",
+            AliasConflict::PreferOld => "prefer-old",
+            AliasConflict::Fail => "fail",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Keys renamed over time, read under their current names.
+///
+/// Each alias maps an old key path to its new one (dotted paths of map keys, see
+/// [`crate::path`]), e.g. `ingress.hosts` to `ingress.hostnames`. Before anything is compared, a
+/// file's value at an old path is moved to the new path, so older and newer files line up and
+/// the base and the diffs hold the new names. Aliases are applied in order, so `a` to `b` then
+/// `b` to `c` moves `a` to `c`. Maps left empty by a move are removed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KeyAliases {
+    /// Old and new paths, in order.
+    pub aliases: Vec<(String, String)>,
+    /// Policy for files setting both paths of an alias.
+    pub on_conflict: AliasConflict,
+}
+
+impl KeyAliases {
+    /// Reads aliases from a map of old paths to new paths, in order.
+    ///
+    /// Fails unless every entry maps a string to a string, both being paths of map keys.
+    pub fn from_yaml(doc: &Yaml, on_conflict: AliasConflict) -> Result<Self, String> {
+        let Yaml::Hash(hash) = doc else {
+            return Err("key aliases must be a map of old paths to new paths".to_string());
+        };
+        let mut aliases = Vec::new();
+        for (old, new) in hash {
+            let (Some(old), Some(new)) = (old.as_str(), new.as_str()) else {
+                return Err(format!("key alias {:?}: {:?} must map a path to a path", old, new));
+            };
+            for path in [old, new] {
+                let segments = parse_path(path)?;
+                if segments.is_empty() || segments.iter().any(|segment| matches!(segment, Segment::Index(_))) {
+                    return Err(format!("key alias path '{}' must be a non-empty path of map keys", path));
+                }
+            }
+            aliases.push((old.to_string(), new.to_string()));
+        }
+        Ok(KeyAliases { aliases, on_conflict })
+    }
+}
+
+/// Key names dropped from every input at any depth, e.g. generated timestamps or checksums.
+///
+/// Each pattern is a regular expression matched against the whole key name (so `.*checksum.*`
+/// for any key holding `checksum`); keys that are not strings are matched as written in
+/// [`crate::path::key_segment`]. A matching key is removed with its value wherever it appears,
+/// in maps nested in arrays too, before anything else is compared: it takes no part in the
+/// quorum and is in neither the base nor the diffs, so merging them does not bring it back.
+#[derive(Debug, Clone)]
+pub struct ExcludedKeys {
+    /// The patterns as given.
+    pub patterns: Vec<String>,
+    regexes: Vec<Regex>,
+}
+
+impl ExcludedKeys {
+    /// Fails if a pattern is not a valid regular expression.
+    pub fn new<S: AsRef<str>>(patterns: impl IntoIterator<Item = S>) -> Result<Self, String> {
+        let patterns: Vec<String> = patterns.into_iter().map(|pattern| pattern.as_ref().to_string()).collect();
+        let regexes = patterns
+            .iter()
+            .map(|pattern| {
+                // Checked on its own first, so errors point into the pattern as given
+                Regex::new(pattern)
+                    .and_then(|_| Regex::new(&format!("^(?:{})$", pattern)))
+                    .map_err(|err| format!("invalid key pattern '{}': {}", pattern, err))
+            })
+            .collect::<Result<Vec<Regex>, String>>()?;
+        Ok(ExcludedKeys { patterns, regexes })
+    }
+
+    /// Checks whether a map key matches any of the patterns.
+    pub fn matches(&self, key: &Yaml) -> bool {
+        let key = key_segment(key);
+        self.regexes.iter().any(|regex| regex.is_match(&key))
+    }
+}
+
+impl PartialEq for ExcludedKeys {
+    fn eq(&self, other: &Self) -> bool {
+        self.patterns == other.patterns
+    }
+}
+
+/// A function applied to every leaf of every input before diffing.
+///
+/// It receives the leaf's dotted path (see [`crate::path`], e.g. `resources.limits.cpu` or
+/// `args[0]`) and its value, and returns the value to compare instead. Leaves are scalars;
+/// maps and arrays are walked into, and empty ones are left as they are.
+///
+/// ```
+/// use yabe::{diff_and_common_with_options, yaml, DiffOptions, Yaml};
+/// use yabe::options::LeafTransform;
+///
+/// let trim = LeafTransform::new(|_path, value| match value {
+///     Yaml::String(s) => Yaml::String(s.trim().to_string()),
+///     other => other.clone(),
+/// });
+/// let options = DiffOptions { leaf_transform: Some(trim), ..DiffOptions::default() };
+///
+/// let a = yaml!("image: 'nginx '");
+/// let b = yaml!("image: nginx");
+/// let inputs = [&a, &b];
+/// let (base, diffs) = diff_and_common_with_options(&inputs, &options).into_parts();
+/// assert_eq!(base.unwrap()["image"].as_str(), Some("nginx"));
+/// assert!(diffs.iter().all(Option::is_none));
+/// ```
+#[derive(Clone)]
+pub struct LeafTransform(Arc<LeafFn>);
+
+type LeafFn = dyn Fn(&str, &Yaml) -> Yaml + Send + Sync;
+
+impl LeafTransform {
+    /// Wraps a function of a leaf's path and value.
+    pub fn new(transform: impl Fn(&str, &Yaml) -> Yaml + Send + Sync + 'static) -> Self {
+        LeafTransform(Arc::new(transform))
+    }
+
+    /// Transforms the leaf at `path`.
+    pub fn apply(&self, path: &str, value: &Yaml) -> Yaml {
+        (self.0)(path, value)
+    }
+}
+
+impl fmt::Debug for LeafTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LeafTransform(..)")
+    }
+}
+
+/// Two transforms are equal only if they are clones of each other.
+impl PartialEq for LeafTransform {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// A function mapping a value to the bucket it is counted in for the quorum.
+///
+/// Values in the same bucket count as one value for the quorum, so a tier that values cluster
+/// around can be hoisted although no single value is shared by enough files. The base then
+/// holds the value most files of the winning bucket set, ties going to the first file, so that
+/// it is always a value some file actually has; every file with a different value, inside the
+/// bucket or not, keeps its exact value in its diff. A value mapped to `None` is a bucket of
+/// its own.
+///
+/// ```
+/// use yabe::{diff_and_common_with_options, yaml, DiffOptions};
+/// use yabe::options::{Bucketing, ValueBuckets};
+///
+/// let buckets = ValueBuckets::new("resources.*.cpu", Bucketing::round(50.0)).unwrap();
+/// let options = DiffOptions { value_buckets: vec![buckets], ..DiffOptions::default() };
+///
+/// let a = yaml!("resources: {limits: {cpu: 100m}}");
+/// let b = yaml!("resources: {limits: {cpu: 110m}}");
+/// let c = yaml!("resources: {limits: {cpu: 100m}}");
+/// let d = yaml!("resources: {limits: {cpu: 300m}}");
+/// let inputs = [&a, &b, &c, &d];
+/// let (base, diffs) = diff_and_common_with_options(&inputs, &options).into_parts();
+/// assert_eq!(base.unwrap()["resources"]["limits"]["cpu"].as_str(), Some("100m"));
+/// assert_eq!(diffs[1].as_deref(), Some(&b));
+/// assert!(diffs[2].is_none());
+/// ```
+#[derive(Clone)]
+pub struct Bucketing {
+    name: String,
+    bucket: Arc<BucketFn>,
+}
+
+type BucketFn = dyn Fn(&Yaml) -> Option<Yaml> + Send + Sync;
+
+impl Bucketing {
+    /// Wraps a function returning a value's bucket; the name identifies it in debug output and
+    /// in cache keys, so distinct functions need distinct names.
+    pub fn new(name: &str, bucket: impl Fn(&Yaml) -> Option<Yaml> + Send + Sync + 'static) -> Self {
+        Bucketing {
+            name: name.to_string(),
+            bucket: Arc::new(bucket),
+        }
+    }
+
+    /// Buckets numbers, and strings of a number followed by a unit such as `100m` or `512Mi`,
+    /// by rounding them to the nearest multiple of `step`. Numbers with different units fall in
+    /// different buckets (`1` and `1000m` are not converted), and other values are not
+    /// bucketed. Nothing is bucketed unless `step` is positive.
+    pub fn round(step: f64) -> Self {
+        Bucketing::new(&format!("round {}", step), move |value| {
+            if step.is_nan() || step <= 0.0 {
+                return None;
+            }
+            let (number, unit) = match value {
+                Yaml::Integer(i) => (*i as f64, ""),
+                Yaml::Real(_) => (value.as_f64()?, ""),
+                Yaml::String(s) => {
+                    let unit_at = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+                    let (number, unit) = s.split_at(unit_at);
+                    if !unit.chars().all(|c| c.is_ascii_alphabetic()) {
+                        return None;
+                    }
+                    (number.parse::<f64>().ok().filter(|n| n.is_finite())?, unit)
+                }
+                _ => return None,
+            };
+            Some(Yaml::String(format!("{}{}", (number / step).round() * step, unit)))
+        })
+    }
+
+    /// Returns the bucket of a value.
+    pub fn bucket(&self, value: &Yaml) -> Option<Yaml> {
+        (self.bucket)(value)
+    }
+}
+
+impl fmt::Debug for Bucketing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Bucketing({})", self.name)
+    }
+}
+
+impl PartialEq for Bucketing {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && Arc::ptr_eq(&self.bucket, &other.bucket)
+    }
+}
+
+/// Bucketing of the values at the map key paths matching a pattern. Values inside arrays are
+/// not bucketed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueBuckets {
+    /// The bucketed key paths.
+    pub pattern: KeyPattern,
+    pub bucketing: Bucketing,
+}
+
+impl ValueBuckets {
+    /// Fails unless the pattern is a valid [`KeyPattern`].
+    pub fn new(path: &str, bucketing: Bucketing) -> Result<Self, String> {
+        Ok(ValueBuckets {
+            pattern: KeyPattern::new(path)?,
+            bucketing,
+        })
+    }
+
+    /// Checks whether the pattern matches a path, given as its keys.
+    pub fn matches(&self, path: &[String]) -> bool {
+        self.pattern.matches(path)
+    }
+}
+
+/// A map key path whose values are atomic (see [`DiffOptions::atomic_paths`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtomicPath {
+    /// The atomic key paths.
+    pub pattern: KeyPattern,
+}
+
+impl AtomicPath {
+    /// Fails unless the pattern is a valid [`KeyPattern`].
+    pub fn new(path: &str) -> Result<Self, String> {
+        Ok(AtomicPath {
+            pattern: KeyPattern::new(path)?,
+        })
+    }
+
+    /// Checks whether the pattern matches a path, given as its keys.
+    pub fn matches(&self, path: &[String]) -> bool {
+        self.pattern.matches(path)
+    }
+}
+
+/// Options controlling base extraction and diffing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffOptions {
+    /// Fraction (0.0-1.0) of files that must agree on a value for it to become base.
+    pub quorum: f64,
+    /// Interpretation of explicit `null` values.
+    pub null_handling: NullHandling,
+    /// How arrays are compared, diffed and merged.
+    pub array_strategy: ArrayStrategy,
+    /// Expand literal dotted keys (`a.b.c: 1`) into nested maps before diffing.
+    pub expand_dotted_keys: bool,
+    /// Strings of at least this many bytes are treated as blobs (see [`crate::blob`]).
+    pub blob_threshold: Option<usize>,
+    /// Whether the base is synthetic or one of the inputs.
+    pub base_mode: BaseMode,
+    /// How keys that only some files set are extracted.
+    pub minority_keys: MinorityKeys,
+    /// Applied to every leaf of every input before diffing, after dotted keys are expanded.
+    ///
+    /// Transformed values are what the quorum counts and what [`crate::deep_equal`] compares,
+    /// and they are also what ends up in the base and the diffs. Diffs may be normalized again
+    /// when extracted further (against a read-only base or into groups), so the transform
+    /// should be idempotent.
+    pub leaf_transform: Option<LeafTransform>,
+    /// Only hoist scalars (strings, numbers, booleans and nulls) into the base.
+    ///
+    /// Maps are still walked into, so the base holds the shared scalars below them, but arrays
+    /// are never hoisted, whatever the array strategy: every file keeps its own copy, even when
+    /// the arrays are identical everywhere. This duplication is intended, for a first migration
+    /// whose review should not involve moving lists around. It does not apply to a
+    /// [`BaseMode::Representative`] base, which is always a whole input.
+    pub hoist_scalars_only: bool,
+    /// Minimum number of files that must set a key to something other than `null` for it to
+    /// be hoisted, at every level of nesting; `0` requires nothing.
+    ///
+    /// This is checked in addition to the value quorum, so a value that a few files set
+    /// and agree on is not hoisted when most files do not set it. How a file counts:
+    ///
+    /// | The file's value at the key   | presence | quorum (`KeepInDiff`) | quorum (`Hoist*`)  |
+    /// |-------------------------------|----------|-----------------------|--------------------|
+    /// | set, not `null`               | yes      | yes                   | yes                |
+    /// | `null` (`Absent` or `Value`)  | no       | yes, as `null`        | yes, as `null`     |
+    /// | `null` (`Inherit`)            | no       | no                    | no                 |
+    /// | missing                       | no       | yes, as `null`        | no                 |
+    ///
+    /// (see [`NullHandling`] and [`MinorityKeys`]). A key reaches the base only if its presence is
+    /// at least `min_presence` and a value meets the quorum among the files counted for it;
+    /// otherwise every file keeps its own value. E.g. with five files of which two set `tls`
+    /// to the same map, [`MinorityKeys::Hoist`] hoists `tls` (the quorum is counted among those
+    /// two), unless `min_presence` is 3 or more.
+    pub min_presence: usize,
+    /// Read an explicit `null` as an empty map or array wherever the other files, or the
+    /// read-only base, have a map or array at that key.
+    ///
+    /// `labels:` and `labels: {}` then no longer conflict: the `null` is read exactly as `{}`
+    /// would be, so the maps are extracted together, with [`MinorityKeys`] deciding about the
+    /// keys only the other maps have. Missing keys stay missing, and a `null` among values of
+    /// mixed types, or among scalars, stays `null`. This is opt-in because Helm tells the two
+    /// apart: `null` removes a key of the base while `{}` merges with it, so against a
+    /// read-only base a coerced map inherits the base's entries. An empty array still replaces
+    /// the base array. Inherited nulls (see [`NullHandling::Inherit`]) are never coerced.
+    pub null_as_empty: bool,
+    /// Read quoted numbers and booleans and their unquoted equivalents as one value (see
+    /// [`StringCoercion`]). Runs after dotted keys are expanded and before the leaf transform.
+    pub coercion: Option<StringCoercion>,
+    /// Key names dropped from every input, at any depth (see [`ExcludedKeys`]). Runs after
+    /// dotted keys are expanded and before keys are renamed, so patterns match the names as
+    /// written in the inputs.
+    pub excluded_keys: Option<ExcludedKeys>,
+    /// Key paths kept out of the base, e.g. from a `.yabeignore` file: every input keeps its own
+    /// value at them in its diff (see [`crate::ignore::keep_out_of_base`]).
+    pub ignored_paths: Vec<KeyPattern>,
+    /// Key paths whose values are compared, hoisted and diffed as a whole, e.g. embedded
+    /// configuration blobs; a `*` segment matches any key. A map there is not walked into: it is
+    /// hoisted only when a quorum of files set it to the same map, and a file setting another map
+    /// keeps all of it in its diff, with `null` for the keys of the base it lacks.
+    pub atomic_paths: Vec<AtomicPath>,
+    /// Renamed keys, moved to their new paths (see [`KeyAliases`]). Runs after dotted keys are
+    /// expanded and before strings are coerced.
+    pub key_aliases: Option<KeyAliases>,
+    /// Paths whose values are bucketed for the quorum (see [`Bucketing`]); the first matching
+    /// pattern applies.
+    pub value_buckets: Vec<ValueBuckets>,
+    /// Ignore the order of map keys and of array elements everywhere, comparing arrays as
+    /// multisets (see [`crate::normalize::sort_canonically`]). Runs after every other
+    /// preprocessing pass.
+    ///
+    /// Only for configuration known not to depend on order: the inputs are sorted before diffing,
+    /// so the base and the diffs are written in the sorted order, and merging them rebuilds each
+    /// input up to order only.
+    pub order_insensitive: bool,
+    /// Fingerprint every subtree of the two values [`crate::diff::compute_diff_with_options`]
+    /// diffs (see [`crate::fingerprint::SubtreeFingerprints`]), so that subtrees whose
+    /// fingerprints differ are diffed without first being compared in full.
+    ///
+    /// The diffs are the same either way: subtrees whose fingerprints match are still compared,
+    /// so a collision cannot make different values equal. Fingerprinting costs one pass over
+    /// both values; it pays off on large, deeply nested inputs that differ in a few leaves.
+    pub fingerprint_subtrees: bool,
+    /// Read maps of diffs shaped like slots patches, `{$slots: {...}, $length: N}`, as the arrays
+    /// they stand for when merging (see [`crate::arrays::apply_slots_patch`]).
+    ///
+    /// Off by default, so that merging plain data never turns such a map into an array; it is
+    /// meant for diffs written with [`crate::output::index_unchanged_slots`].
+    pub slots_patches: bool,
+}
+
+impl DiffOptions {
+    /// Returns the strategic merge key if that array strategy is selected.
+    pub fn merge_key(&self) -> Option<&str> {
+        match &self.array_strategy {
+            ArrayStrategy::StrategicMerge { merge_key } => Some(merge_key),
+            _ => None,
+        }
+    }
+
+    /// Returns the bucketing of the values at a path, given as its keys.
+    pub fn bucketing_at(&self, path: &[String]) -> Option<&Bucketing> {
+        self.value_buckets.iter().find(|buckets| buckets.matches(path)).map(|buckets| &buckets.bucketing)
+    }
+
+    /// Checks whether the values at a path, given as its keys, are atomic (see
+    /// [`DiffOptions::atomic_paths`]).
+    pub fn is_atomic(&self, path: &[String]) -> bool {
+        self.atomic_paths.iter().any(|pattern| pattern.matches(path))
+    }
+
+    /// Returns the LCS length cap if that array strategy is selected.
+    pub fn lcs_max_len(&self) -> Option<usize> {
+        match self.array_strategy {
+            ArrayStrategy::Lcs { max_len } => Some(max_len),
+            _ => None,
+        }
+    }
+
+    /// Checks whether array diffs are edit scripts (see [`crate::lcs`]), with the LCS or the
+    /// ordered-set strategy.
+    pub fn uses_edit_scripts(&self) -> bool {
+        matches!(self.array_strategy, ArrayStrategy::Lcs { .. } | ArrayStrategy::OrderedSet)
+    }
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            quorum: 0.51,
+            null_handling: NullHandling::default(),
+            array_strategy: ArrayStrategy::default(),
+            expand_dotted_keys: false,
+            blob_threshold: None,
+            base_mode: BaseMode::default(),
+            minority_keys: MinorityKeys::default(),
+            leaf_transform: None,
+            hoist_scalars_only: false,
+            min_presence: 0,
+            null_as_empty: false,
+            coercion: None,
+            excluded_keys: None,
+            ignored_paths: Vec::new(),
+            atomic_paths: Vec::new(),
+            key_aliases: None,
+            value_buckets: Vec::new(),
+            order_insensitive: false,
+            fingerprint_subtrees: false,
+            slots_patches: false,
+        }
+    }
+}
@@ -0,0 +1,649 @@
+//! Serialization of results.
+//!
+//! yaml_rust2 does not keep the style scalars were written in, so styles are chosen on output.
+//! Multi-line strings are written as literal block scalars (`|`, or `|-` without a trailing
+//! line break), the way they are usually written in values files. This applies to strings read
+//! from folded (`>`) or quoted scalars too. A string that a literal block cannot reproduce
+//! exactly, e.g. one with several trailing line breaks, leading spaces, or control characters,
+//! stays a double-quoted scalar with escapes. Other strings are quoted only when they would
+//! otherwise read as something else; single quotes and flow collections are not preserved.
+//!
+//! Large documents can be written straight to a file or stdout with [`write_yaml`]: a map is
+//! rendered one top-level entry at a time, so only the text of one entry is held in memory.
+//! [`to_yaml_string`] writes the same way, so both produce the same text.
+//!
+//! [`write_yaml_with_comments`] also writes documentation from a sidecar file as comments
+//! above the keys it documents (see [`crate::comments`]).
+//!
+//! Small maps and arrays can be written in flow style, `{a: 1, b: 2}` and `[80, 443]`, with
+//! an [`OutputStyle`]: a map or array below the top level is written on one line when its flow
+//! form is at most [`OutputStyle::flow_width`] characters long. The metric is the flow text
+//! itself, from the opening bracket to the closing one, nested collections included, without the
+//! indentation and the key before it. Collections holding multi-line strings, or keys that are
+//! not scalars, stay in block style, as do documented keys' maps. Keys keep their order, so a
+//! sort configuration applies as in block style. Like blocks, a flow collection that would not
+//! read back as the same document is written in block style instead.
+//!
+//! Positional array diffs mark unchanged elements with `null`, which reviewers easily misread
+//! as values being cleared. [`index_unchanged_slots`] rewrites such arrays as indexed slots
+//! patches (see [`crate::arrays`]), which merging reads when asked to, so the output still
+//! applies.
+//!
+//! Reals are written as read, so `1e3` and `1.50` stay as they are in the input they come from.
+//! Inputs formatting the same numbers differently then make the base and diffs change with the
+//! input a value is taken from; [`RealFormat::Canonical`] rewrites every real from its value
+//! instead, with a fixed number of decimals or the fewest that read back as the same number,
+//! and in scientific notation beyond a magnitude. `.inf` and `.nan` are kept, as are keys.
+
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::{EmitError, Yaml, YamlEmitter, YamlLoader};
+
+use crate::arrays::slots_patch;
+use crate::comments::{comment_lines, DocComments};
+use crate::path::key_segment;
+
+/// How unchanged elements of positional array diffs are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnchangedSlots {
+    /// As `null`, the way diffs hold them.
+    #[default]
+    Null,
+    /// Left out of an indexed slots patch; see [`index_unchanged_slots`].
+    Indexed,
+}
+
+impl FromStr for UnchangedSlots {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "null" => Ok(UnchangedSlots::Null),
+            "indexed" => Ok(UnchangedSlots::Indexed),
+            _ => Err(format!("unknown unchanged slots rendering '{}', expected one of: null, indexed", s)),
+        }
+    }
+}
+
+impl fmt::Display for UnchangedSlots {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            UnchangedSlots::Null => "null",
+            UnchangedSlots::Indexed => "indexed",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How reals are written, see the [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RealFormat {
+    /// As read.
+    #[default]
+    Preserve,
+    /// Rewritten from their value.
+    Canonical {
+        /// Digits after the point, rounded; `None` writes the fewest that read back as the same
+        /// number, and at least one.
+        decimals: Option<usize>,
+        /// Reals whose magnitude is at least `10^exponent`, or not zero and below
+        /// `10^-exponent`, are written in scientific notation, e.g. `1.5e-7`; `None` never does.
+        exponent: Option<i32>,
+    },
+}
+
+impl FromStr for RealFormat {
+    type Err = String;
+
+    /// Parses `preserve`, or `shortest` or `fixed:<DECIMALS>` optionally followed by
+    /// `,sci:<EXPONENT>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expected = "expected preserve, shortest or fixed:<DECIMALS>, optionally followed by ,sci:<EXPONENT>";
+        let (format, exponent) = match s.split_once(',') {
+            Some((format, scientific)) => {
+                let exponent = scientific
+                    .strip_prefix("sci:")
+                    .and_then(|exponent| exponent.parse::<i32>().ok())
+                    .filter(|exponent| *exponent >= 0)
+                    .ok_or_else(|| format!("invalid scientific notation threshold '{}', {}", scientific, expected))?;
+                (format, Some(exponent))
+            }
+            None => (s, None),
+        };
+        let decimals = match format {
+            "preserve" if exponent.is_none() => return Ok(RealFormat::Preserve),
+            "shortest" => None,
+            _ => match format.strip_prefix("fixed:").and_then(|decimals| decimals.parse::<usize>().ok()) {
+                // Without decimals most reals would read back as integers
+                Some(0) => return Err("a fixed real format needs at least 1 decimal".to_string()),
+                Some(decimals) => Some(decimals),
+                None => return Err(format!("unknown real format '{}', {}", s, expected)),
+            },
+        };
+        Ok(RealFormat::Canonical { decimals, exponent })
+    }
+}
+
+impl fmt::Display for RealFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RealFormat::Preserve => f.write_str("preserve"),
+            RealFormat::Canonical { decimals, exponent } => {
+                match decimals {
+                    Some(decimals) => write!(f, "fixed:{}", decimals)?,
+                    None => f.write_str("shortest")?,
+                }
+                match exponent {
+                    Some(exponent) => write!(f, ",sci:{}", exponent),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Layout choices for writing documents, see the [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OutputStyle {
+    /// The longest flow form a map or array can have to be written in flow style; `None` writes
+    /// every non-empty collection in block style.
+    pub flow_width: Option<usize>,
+    /// How reals are written.
+    pub reals: RealFormat,
+}
+
+/// Writes the text of a real as a format says, keeping text that is not a finite number.
+///
+/// ```
+/// use yabe::output::{format_real, RealFormat};
+///
+/// let shortest = RealFormat::Canonical { decimals: None, exponent: Some(6) };
+/// assert_eq!(format_real("1e3", shortest), "1000.0");
+/// assert_eq!(format_real("1.50", shortest), "1.5");
+/// assert_eq!(format_real("0.00000015", shortest), "1.5e-7");
+/// assert_eq!(format_real(".inf", shortest), ".inf");
+/// assert_eq!(format_real("1e3", RealFormat::Preserve), "1e3");
+/// ```
+pub fn format_real(text: &str, format: RealFormat) -> String {
+    let (decimals, exponent) = match format {
+        RealFormat::Preserve => return text.to_string(),
+        RealFormat::Canonical { decimals, exponent } => (decimals, exponent),
+    };
+    let value = match text.parse::<f64>() {
+        Ok(value) if value.is_finite() => value,
+        _ => return text.to_string(),
+    };
+    let scientific = exponent.is_some_and(|exponent| {
+        let magnitude = value.abs();
+        value != 0.0 && (magnitude >= 10f64.powi(exponent) || magnitude < 10f64.powi(-exponent))
+    });
+    let formatted = match (scientific, decimals) {
+        (true, Some(decimals)) => format!("{:.*e}", decimals, value),
+        (true, None) => format!("{:e}", value),
+        (false, Some(decimals)) => format!("{:.*}", decimals, value),
+        (false, None) => format!("{}", value),
+    };
+    if formatted.contains(['.', 'e']) {
+        formatted
+    } else {
+        formatted + ".0"
+    }
+}
+
+/// Rewrites every real of a document as a format says (see [`format_real`]), keys aside.
+pub fn format_reals(doc: &Yaml, format: RealFormat) -> Yaml {
+    match doc {
+        Yaml::Real(text) => Yaml::Real(format_real(text, format)),
+        Yaml::Hash(hash) => Yaml::Hash(hash.iter().map(|(k, v)| (k.clone(), format_reals(v, format))).collect()),
+        Yaml::Array(items) => Yaml::Array(items.iter().map(|item| format_reals(item, format)).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Rewrites every array of a diff holding `null` elements against a base array of the same
+/// length as a slots patch listing the other elements by index, nested arrays included.
+///
+/// `base` is what the diff applies to. Merging the result onto it, with
+/// [`crate::options::DiffOptions::slots_patches`] set, gives the same document as merging the
+/// diff would with the positional strategy, but for unchanged elements being kept rather than
+/// made `null`. An element changed to `null` cannot be told from an unchanged one and is kept
+/// too. Arrays the base has no array of the same length for hold no unchanged slots, so their
+/// `null` elements are values and they are left as they are, as are edit scripts (see
+/// [`crate::lcs`]).
+///
+/// ```
+/// use yabe::output::index_unchanged_slots;
+/// use yabe::yaml;
+///
+/// let base = yaml!("args: [--a, --b, --c]");
+/// let diff = yaml!("args: [~, --verbose, ~]\nextra: [x, ~]");
+/// assert_eq!(
+///     index_unchanged_slots(&diff, Some(&base)),
+///     yaml!("args: {$slots: {1: --verbose}, $length: 3}\nextra: [x, ~]")
+/// );
+/// ```
+pub fn index_unchanged_slots(diff: &Yaml, base: Option<&Yaml>) -> Yaml {
+    match (diff, base) {
+        (Yaml::Hash(hash), Some(Yaml::Hash(base))) => Yaml::Hash(
+            hash.iter().map(|(k, v)| (k.clone(), index_unchanged_slots(v, base.get(k)))).collect(),
+        ),
+        (Yaml::Array(items), Some(Yaml::Array(base))) if items.len() == base.len() => {
+            let items: Vec<Yaml> = items.iter().zip(base).map(|(item, base)| index_unchanged_slots(item, Some(base))).collect();
+            slots_patch(&items).unwrap_or(Yaml::Array(items))
+        }
+        (other, _) => other.clone(),
+    }
+}
+
+/// Renders a document as YAML, without the leading `---` marker and with a trailing newline.
+pub fn to_yaml_string(doc: &Yaml) -> Result<String, EmitError> {
+    to_yaml_string_with_style(doc, OutputStyle::default())
+}
+
+/// Renders a document as YAML like [`to_yaml_string`], laid out with a style.
+///
+/// ```
+/// use yabe::output::{to_yaml_string_with_style, OutputStyle};
+/// use yabe::yaml;
+///
+/// let doc = yaml!("image:\n  repository: nginx\n  tag: v2\nports: [80, 443]");
+/// let style = OutputStyle { flow_width: Some(32), ..OutputStyle::default() };
+/// assert_eq!(to_yaml_string_with_style(&doc, style).unwrap(), "image: {repository: nginx, tag: v2}\nports: [80, 443]\n");
+/// ```
+pub fn to_yaml_string_with_style(doc: &Yaml, style: OutputStyle) -> Result<String, EmitError> {
+    let mut out = Vec::new();
+    match write_entries(doc, &DocComments::default(), style, &mut out) {
+        Ok(()) => Ok(String::from_utf8(out).expect("the emitter writes UTF-8")),
+        Err(WriteError::Emit(e)) => Err(e),
+        Err(WriteError::Io(e)) => unreachable!("writing to memory failed: {}", e),
+    }
+}
+
+/// Writes a document as YAML to `writer`, formatted as by [`to_yaml_string`].
+pub fn write_yaml<W: io::Write>(doc: &Yaml, writer: &mut W) -> io::Result<()> {
+    write_yaml_with_comments(doc, &DocComments::default(), writer)
+}
+
+/// Writes a document as YAML to `writer` like [`write_yaml`], with the documentation of its
+/// keys as comment lines above them.
+///
+/// Should the comments not read back as the same document, e.g. for a key the emitter writes
+/// in a form they cannot precede, the entry is written without them.
+///
+/// ```
+/// use yabe::comments::DocComments;
+/// use yabe::output::write_yaml_with_comments;
+/// use yabe::yaml;
+///
+/// let docs = DocComments::from_yaml(&yaml!("image.tag: Deployed image tag.")).unwrap();
+/// let mut out = Vec::new();
+/// write_yaml_with_comments(&yaml!("image:\n  tag: v1"), &docs, &mut out).unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), "image:\n  # Deployed image tag.\n  tag: v1\n");
+/// ```
+pub fn write_yaml_with_comments<W: io::Write>(doc: &Yaml, comments: &DocComments, writer: &mut W) -> io::Result<()> {
+    write_yaml_with_style(doc, comments, OutputStyle::default(), writer)
+}
+
+/// Writes a document as YAML to `writer` like [`write_yaml_with_comments`], laid out with a
+/// style.
+pub fn write_yaml_with_style<W: io::Write>(
+    doc: &Yaml,
+    comments: &DocComments,
+    style: OutputStyle,
+    writer: &mut W,
+) -> io::Result<()> {
+    write_entries(doc, comments, style, writer).map_err(|e| match e {
+        WriteError::Io(e) => e,
+        WriteError::Emit(e) => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+    })
+}
+
+enum WriteError {
+    Io(io::Error),
+    Emit(EmitError),
+}
+
+impl From<io::Error> for WriteError {
+    fn from(e: io::Error) -> Self {
+        WriteError::Io(e)
+    }
+}
+
+impl From<EmitError> for WriteError {
+    fn from(e: EmitError) -> Self {
+        WriteError::Emit(e)
+    }
+}
+
+/// Writes a non-empty map one top-level entry at a time, and anything else as a whole.
+fn write_entries<W: io::Write>(
+    doc: &Yaml,
+    comments: &DocComments,
+    style: OutputStyle,
+    writer: &mut W,
+) -> Result<(), WriteError> {
+    let formatted;
+    let doc = match style.reals {
+        RealFormat::Preserve => doc,
+        format => {
+            formatted = format_reals(doc, format);
+            &formatted
+        }
+    };
+    match doc {
+        Yaml::Hash(hash) if !hash.is_empty() => {
+            for (key, value) in hash {
+                let mut entry = Hash::new();
+                entry.insert(key.clone(), value.clone());
+                writer.write_all(render_commented(&Yaml::Hash(entry), comments, style)?.as_bytes())?;
+            }
+        }
+        _ => writer.write_all(render_commented(doc, comments, style)?.as_bytes())?,
+    }
+    Ok(())
+}
+
+/// Renders a document with the documented keys renamed to placeholders, which are then
+/// replaced by the comments and the keys as the emitter writes them.
+fn render_commented(doc: &Yaml, comments: &DocComments, style: OutputStyle) -> Result<String, EmitError> {
+    let plain = render_styled(doc, style)?;
+    if comments.entries.is_empty() {
+        return Ok(plain);
+    }
+    let mut notes = Vec::new();
+    let marked = mark_documented_keys(doc, comments, &mut Vec::new(), &mut notes)?;
+    if notes.is_empty() {
+        return Ok(plain);
+    }
+    let mut out_str = render_styled(&marked, style)?;
+    for (placeholder, key, text) in &notes {
+        out_str = insert_comment(&out_str, placeholder, key, text);
+    }
+    let read_back = |s: &str| YamlLoader::load_from_str(s).ok();
+    if read_back(&out_str) == read_back(&plain) {
+        Ok(out_str)
+    } else {
+        Ok(plain)
+    }
+}
+
+const COMMENT_PLACEHOLDER_PREFIX: &str = "yabe-comment-";
+
+/// Replaces each documented key of the maps along the paths with a unique plain placeholder,
+/// recording the placeholder, the key as written and its documentation.
+fn mark_documented_keys(
+    value: &Yaml,
+    comments: &DocComments,
+    path: &mut Vec<String>,
+    notes: &mut Vec<(String, String, String)>,
+) -> Result<Yaml, EmitError> {
+    let Yaml::Hash(hash) = value else {
+        return Ok(value.clone());
+    };
+    let mut marked = Hash::new();
+    for (key, child) in hash {
+        path.push(key_segment(key));
+        let child = mark_documented_keys(child, comments, path, notes)?;
+        let written = match comments.get(path) {
+            Some(text) => emit_key(key)?.map(|written| (written, text)),
+            None => None,
+        };
+        match written {
+            Some((written, text)) => {
+                let placeholder = format!("{}{}-placeholder", COMMENT_PLACEHOLDER_PREFIX, notes.len());
+                notes.push((placeholder.clone(), written, text.to_string()));
+                marked.insert(Yaml::String(placeholder), child);
+            }
+            None => {
+                marked.insert(key.clone(), child);
+            }
+        }
+        path.pop();
+    }
+    Ok(Yaml::Hash(marked))
+}
+
+/// Renders a map key the way the emitter writes it before its `:`, if it is a simple key.
+fn emit_key(key: &Yaml) -> Result<Option<String>, EmitError> {
+    let mut entry = Hash::new();
+    entry.insert(key.clone(), Yaml::Null);
+    let written = emit(&Yaml::Hash(entry))?;
+    Ok(written.strip_suffix(": ~\n").filter(|key| !key.contains('\n')).map(str::to_string))
+}
+
+/// Writes comment lines above the line of a placeholder key, and the key in its place.
+fn insert_comment(out_str: &str, placeholder: &str, key: &str, text: &str) -> String {
+    let Some(at) = out_str.find(placeholder) else {
+        return out_str.to_string();
+    };
+    let line_start = out_str[..at].rfind('\n').map_or(0, |i| i + 1);
+    let indent = &out_str[line_start..at];
+    if !indent.chars().all(|c| c == ' ') {
+        return out_str.to_string();
+    }
+    format!(
+        "{}{}{}{}{}",
+        &out_str[..line_start],
+        comment_lines(text, indent),
+        indent,
+        key,
+        &out_str[at + placeholder.len()..]
+    )
+}
+
+/// Renders a document with the small collections of the style replaced by flow placeholders,
+/// which are then replaced by their flow text.
+fn render_styled(doc: &Yaml, style: OutputStyle) -> Result<String, EmitError> {
+    let Some(width) = style.flow_width else {
+        return render(doc);
+    };
+    let mut flows = Vec::new();
+    let with_placeholders = extract_flows(doc, width, true, &mut flows)?;
+    if flows.is_empty() {
+        return render(doc);
+    }
+    let mut out_str = render(&with_placeholders)?;
+    for (placeholder, text) in &flows {
+        out_str = out_str.replacen(placeholder.as_str(), text, 1);
+    }
+    let read_back = |s: &str| YamlLoader::load_from_str(s).ok();
+    if read_back(&out_str).is_some() && read_back(&out_str) == read_back(&emit(doc)?) {
+        Ok(out_str)
+    } else {
+        render(doc)
+    }
+}
+
+/// Replaces the collections below the root whose flow text fits `width` with unique plain
+/// placeholders, recording each with its text.
+fn extract_flows(value: &Yaml, width: usize, root: bool, flows: &mut Vec<(String, String)>) -> Result<Yaml, EmitError> {
+    let empty = match value {
+        Yaml::Hash(hash) => hash.is_empty(),
+        Yaml::Array(items) => items.is_empty(),
+        other => return Ok(other.clone()),
+    };
+    if !root && !empty {
+        if let Some(text) = flow_text(value, width)? {
+            let placeholder = format!("yabe-flow-{}-placeholder", flows.len());
+            flows.push((placeholder.clone(), text));
+            return Ok(Yaml::String(placeholder));
+        }
+    }
+    Ok(match value {
+        Yaml::Hash(hash) => Yaml::Hash(
+            hash.iter()
+                .map(|(key, child)| Ok((key.clone(), extract_flows(child, width, false, flows)?)))
+                .collect::<Result<Hash, EmitError>>()?,
+        ),
+        Yaml::Array(items) => Yaml::Array(
+            items.iter()
+                .map(|item| extract_flows(item, width, false, flows))
+                .collect::<Result<Vec<Yaml>, EmitError>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+/// Renders a value in flow style, if it can be and its text is at most `width` characters long.
+fn flow_text(value: &Yaml, width: usize) -> Result<Option<String>, EmitError> {
+    let (open, close, entries): (&str, &str, Vec<(Option<&Yaml>, &Yaml)>) = match value {
+        Yaml::Hash(hash) => ("{", "}", hash.iter().map(|(key, child)| (Some(key), child)).collect()),
+        Yaml::Array(items) => ("[", "]", items.iter().map(|item| (None, item)).collect()),
+        Yaml::String(s) if is_block(s) => return Ok(None),
+        scalar => return Ok(flow_scalar(scalar)?.filter(|text| text.chars().count() <= width)),
+    };
+    let mut text = open.to_string();
+    for (index, (key, child)) in entries.into_iter().enumerate() {
+        if index > 0 {
+            text.push_str(", ");
+        }
+        if let Some(key) = key {
+            let key_text = match key {
+                Yaml::String(s) if s.starts_with(COMMENT_PLACEHOLDER_PREFIX) => None,
+                Yaml::Hash(_) | Yaml::Array(_) => None,
+                key => flow_scalar(key)?,
+            };
+            let Some(key_text) = key_text else {
+                return Ok(None);
+            };
+            text.push_str(&key_text);
+            text.push_str(": ");
+        }
+        let budget = width.saturating_sub(text.chars().count());
+        let Some(child_text) = flow_text(child, budget)? else {
+            return Ok(None);
+        };
+        text.push_str(&child_text);
+    }
+    text.push_str(close);
+    Ok((text.chars().count() <= width).then_some(text))
+}
+
+/// Renders a scalar for a flow collection: as the emitter writes it, double-quoted should it be
+/// a plain scalar holding a flow indicator.
+fn flow_scalar(value: &Yaml) -> Result<Option<String>, EmitError> {
+    if matches!(value, Yaml::Alias(_) | Yaml::BadValue) {
+        return Ok(None);
+    }
+    let written = emit(value)?;
+    let written = written.strip_suffix('\n').unwrap_or(&written);
+    if written.contains('\n') {
+        return Ok(None);
+    }
+    match value {
+        Yaml::String(s) if !written.starts_with(['"', '\'']) && written.contains([',', '[', ']', '{', '}']) => {
+            Ok(Some(double_quoted(s)))
+        }
+        _ => Ok(Some(written.to_string())),
+    }
+}
+
+fn double_quoted(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' | '"' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn render(doc: &Yaml) -> Result<String, EmitError> {
+    let plain = emit(doc)?;
+    let mut blocks = Vec::new();
+    let with_placeholders = extract_blocks(doc, &mut blocks);
+    if blocks.is_empty() {
+        return Ok(plain);
+    }
+    let mut out_str = emit(&with_placeholders)?;
+    for (placeholder, value) in &blocks {
+        out_str = insert_block(&out_str, placeholder, value);
+    }
+    // Fall back to quoted strings should a block not read back the way the quoted string does
+    let read_back = |s: &str| YamlLoader::load_from_str(s).ok();
+    let with_blocks = read_back(&out_str);
+    if with_blocks.is_some() && with_blocks == read_back(&plain) {
+        Ok(out_str)
+    } else {
+        Ok(plain)
+    }
+}
+
+fn emit(doc: &Yaml) -> Result<String, EmitError> {
+    let mut out_str = String::new();
+    {
+        let mut emitter = YamlEmitter::new(&mut out_str);
+        emitter.dump(doc)?;
+    }
+    let mut out_str = out_str.trim_start_matches("---\n").to_string();
+    out_str.push('\n');
+    Ok(out_str)
+}
+
+/// Checks if a string is written as a literal block scalar.
+fn is_block(value: &str) -> bool {
+    let body = value.strip_suffix('\n').unwrap_or(value);
+    body.contains('\n')
+        && !body.ends_with('\n')
+        && !body.trim_start_matches('\n').starts_with([' ', '\t'])
+        && value.chars().all(|c| matches!(c, '\t' | '\n' | ' '..='~' | '\u{a0}'..='\u{d7ff}' | '\u{e000}'..='\u{fffd}'))
+}
+
+/// Replaces block strings in values with unique plain placeholders, recording each pair.
+fn extract_blocks(value: &Yaml, blocks: &mut Vec<(String, String)>) -> Yaml {
+    match value {
+        Yaml::String(s) if is_block(s) => {
+            let placeholder = format!("yabe-block-{}-placeholder", blocks.len());
+            blocks.push((placeholder.clone(), s.clone()));
+            Yaml::String(placeholder)
+        }
+        Yaml::Array(items) => Yaml::Array(items.iter().map(|item| extract_blocks(item, blocks)).collect()),
+        Yaml::Hash(hash) => Yaml::Hash(
+            hash.iter()
+                .map(|(key, child)| (key.clone(), extract_blocks(child, blocks)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Writes `value` as a literal block in place of its placeholder.
+///
+/// Content is indented two spaces deeper than the node the placeholder is the value of, i.e.
+/// past the line's indentation and any `- ` sequence markers.
+fn insert_block(out_str: &str, placeholder: &str, value: &str) -> String {
+    let Some(at) = out_str.find(placeholder) else {
+        return out_str.to_string();
+    };
+    let line_start = out_str[..at].rfind('\n').map_or(0, |i| i + 1);
+    let node_indent = out_str[line_start..at]
+        .chars()
+        .take_while(|c| matches!(c, ' ' | '-'))
+        .count();
+    let indent = " ".repeat(node_indent + 2);
+
+    let (header, body) = match value.strip_suffix('\n') {
+        Some(body) => ("|", body),
+        None => ("|-", value),
+    };
+    let mut block = header.to_string();
+    for line in body.split('\n') {
+        block.push('\n');
+        if !line.is_empty() {
+            block.push_str(&indent);
+            block.push_str(line);
+        }
+    }
+    format!("{}{}{}", &out_str[..at], block, &out_str[at + placeholder.len()..])
+}
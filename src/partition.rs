@@ -0,0 +1,143 @@
+//! Partitioning of heterogeneous inputs, such as the documents of a rendered manifest bundle,
+//! with a base extracted within each partition.
+//!
+//! A Deployment and a Service share nothing worth a base, so extracting one across them is
+//! meaningless. Instead, each input is keyed by its values at one or more discriminator paths
+//! (e.g. `kind` and `metadata.name`), inputs with equal values form a partition, and extraction
+//! runs within each partition as if its members were the only inputs: the quorum is a share of
+//! the partition's members.
+//!
+//! An input missing a discriminator, or holding a map or an array at one, is in no partition: it
+//! gets no base, and its diff is the whole input. So is the only member of a partition, as there
+//! is nothing to share it with. Empty inputs are in no partition either and get no diff.
+
+use yaml_rust2::yaml::{Hash, Yaml};
+
+use crate::diff::diff_and_common_with_options;
+use crate::input::is_empty_document;
+use crate::options::DiffOptions;
+use crate::path::{key_segment, parse_path, value_at, Segment};
+
+/// Inputs with the same discriminator values and what they share.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Partition {
+    /// The discriminator values joined with `/`, e.g. `Deployment/web`; usable as a relative
+    /// file path, as `/` inside a value is replaced by `_`.
+    pub name: String,
+    /// The value at each discriminator path, in order.
+    pub values: Vec<Yaml>,
+    /// Indices of the member inputs, in input order.
+    pub members: Vec<usize>,
+    /// What the members share; `None` if they share nothing.
+    pub base: Option<Yaml>,
+}
+
+/// The partitions, in order of their first member, and the per-input diffs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Partitioned {
+    pub partitions: Vec<Partition>,
+    /// Indices of the non-empty inputs that are in no partition, in input order.
+    pub unpartitioned: Vec<usize>,
+    /// Each input's diff against its partition base, or the whole input if it has none.
+    pub diffs: Vec<Option<Yaml>>,
+}
+
+impl Partitioned {
+    /// Returns the partition of the `index`-th input, if any.
+    pub fn partition_of(&self, index: usize) -> Option<&Partition> {
+        self.partitions.iter().find(|partition| partition.members.contains(&index))
+    }
+
+    /// Maps each input name to the name of its partition, or `null` if it has none.
+    pub fn assignment(&self, names: &[String]) -> Yaml {
+        let mut assignment = Hash::new();
+        for (index, name) in names.iter().enumerate() {
+            let partition = match self.partition_of(index) {
+                Some(partition) => Yaml::String(partition.name.clone()),
+                None => Yaml::Null,
+            };
+            assignment.insert(Yaml::String(name.clone()), partition);
+        }
+        Yaml::Hash(assignment)
+    }
+}
+
+/// Returns the scalar value of a document at each discriminator path, or `None` if it lacks
+/// one of them.
+pub fn discriminator_values(doc: &Yaml, paths: &[Vec<Segment>]) -> Option<Vec<Yaml>> {
+    paths
+        .iter()
+        .map(|path| match value_at(doc, path)? {
+            Yaml::Hash(_) | Yaml::Array(_) | Yaml::BadValue => None,
+            value => Some(value.clone()),
+        })
+        .collect()
+}
+
+/// Partitions the inputs by their values at the discriminator `paths` (see
+/// [`crate::path::parse_path`]) and extracts a base within each partition of at least two inputs.
+///
+/// Fails on a malformed path, or when no path is given.
+pub fn partition_inputs(objs: &[&Yaml], paths: &[String], options: &DiffOptions) -> Result<Partitioned, String> {
+    if paths.is_empty() {
+        return Err("no discriminator path given".to_string());
+    }
+    let paths = paths.iter().map(|path| parse_path(path)).collect::<Result<Vec<_>, _>>()?;
+
+    let mut keyed: Vec<(Vec<Yaml>, Vec<usize>)> = Vec::new();
+    let mut unpartitioned = Vec::new();
+    for (index, obj) in objs.iter().enumerate() {
+        if is_empty_document(obj) {
+            continue;
+        }
+        match discriminator_values(obj, &paths) {
+            Some(values) => match keyed.iter_mut().find(|(v, _)| *v == values) {
+                Some((_, members)) => members.push(index),
+                None => keyed.push((values, vec![index])),
+            },
+            None => unpartitioned.push(index),
+        }
+    }
+
+    let mut diffs: Vec<Option<Yaml>> =
+        objs.iter().map(|obj| Some((*obj).clone()).filter(|obj| !is_empty_document(obj))).collect();
+    let mut partitions = Vec::new();
+    for (values, members) in keyed {
+        if members.len() < 2 {
+            unpartitioned.extend(members);
+            continue;
+        }
+        let member_objs: Vec<&Yaml> = members.iter().map(|&index| objs[index]).collect();
+        let (base, member_diffs) = diff_and_common_with_options(&member_objs, options).into_parts();
+        for (&index, diff) in members.iter().zip(member_diffs) {
+            diffs[index] = diff.map(|diff| diff.into_owned());
+        }
+        partitions.push(Partition {
+            name: partition_name(&values),
+            values,
+            members,
+            base: base.map(|base| base.into_owned()),
+        });
+    }
+    unpartitioned.sort_unstable();
+
+    Ok(Partitioned {
+        partitions,
+        unpartitioned,
+        diffs,
+    })
+}
+
+fn partition_name(values: &[Yaml]) -> String {
+    values
+        .iter()
+        .map(|value| {
+            let segment = key_segment(value).replace(['/', '\\'], "_");
+            match segment.as_str() {
+                "" | "." | ".." => "_".to_string(),
+                _ => segment,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
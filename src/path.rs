@@ -0,0 +1,144 @@
+//! Dotted key paths, following Helm's `--set` conventions.
+//!
+//! Segments are separated by `.`; a literal dot inside a key is written `\.` and a literal
+//! backslash `\\`. So `a\.b.c` has the segments `a.b` and `c`. Array indices are written
+//! `key[0]`, so a literal `[` inside a key is escaped as `\[`.
+
+use yaml_rust2::Yaml;
+
+/// Splits a dotted path into its unescaped segments.
+pub fn split_path(path: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(next @ ('.' | '\\' | '[')) => current.push(next),
+                Some(next) => {
+                    current.push('\\');
+                    current.push(next);
+                }
+                None => current.push('\\'),
+            },
+            '.' => segments.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    segments.push(current);
+    segments
+}
+
+/// Escapes a single key so it survives [`split_path`] as one segment.
+pub fn escape_segment(segment: &str) -> String {
+    segment
+        .replace('\\', "\\\\")
+        .replace('.', "\\.")
+        .replace('[', "\\[")
+}
+
+/// Joins segments into a dotted path, escaping each of them.
+pub fn join_path<S: AsRef<str>>(segments: &[S]) -> String {
+    segments
+        .iter()
+        .map(|segment| escape_segment(segment.as_ref()))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Renders a map key as a path segment.
+pub fn key_segment(key: &Yaml) -> String {
+    match key {
+        Yaml::String(s) | Yaml::Real(s) => s.clone(),
+        Yaml::Integer(i) => i.to_string(),
+        Yaml::Boolean(b) => b.to_string(),
+        Yaml::Null => "~".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// A step in a path: a map key or an array index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Formats segments as a path, e.g. `spec.containers[0].name`.
+pub fn format_path(segments: &[Segment]) -> String {
+    let mut path = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Key(key) => {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(&escape_segment(key));
+            }
+            Segment::Index(index) => path.push_str(&format!("[{}]", index)),
+        }
+    }
+    path
+}
+
+/// Parses a path written by [`format_path`].
+pub fn parse_path(path: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    if path.is_empty() {
+        return Ok(segments);
+    }
+    let mut current = String::new();
+    let mut last_was_index = false;
+    let mut chars = path.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                match chars.next() {
+                    Some(next @ ('.' | '\\' | '[')) => current.push(next),
+                    Some(next) => {
+                        current.push('\\');
+                        current.push(next);
+                    }
+                    None => current.push('\\'),
+                }
+                last_was_index = false;
+            }
+            '.' => {
+                if !(last_was_index && current.is_empty()) {
+                    segments.push(Segment::Key(std::mem::take(&mut current)));
+                }
+                last_was_index = false;
+            }
+            '[' => {
+                if !current.is_empty() {
+                    segments.push(Segment::Key(std::mem::take(&mut current)));
+                }
+                let digits: String = chars.by_ref().take_while(|&d| d != ']').collect();
+                let index = digits
+                    .parse()
+                    .map_err(|_| format!("invalid array index '[{}]' in path '{}'", digits, path))?;
+                segments.push(Segment::Index(index));
+                if chars.peek().is_some_and(|&next| next != '.' && next != '[') {
+                    return Err(format!("expected '.' or '[' after an index in path '{}'", path));
+                }
+                last_was_index = true;
+            }
+            _ => {
+                current.push(c);
+                last_was_index = false;
+            }
+        }
+    }
+    if !(last_was_index && current.is_empty()) {
+        segments.push(Segment::Key(current));
+    }
+    Ok(segments)
+}
+
+/// Returns the value at a parsed path, walking maps by key and arrays by index.
+pub fn value_at<'a>(value: &'a Yaml, segments: &[Segment]) -> Option<&'a Yaml> {
+    segments.iter().try_fold(value, |value, segment| match segment {
+        Segment::Key(key) => value.as_hash()?.iter().find(|(k, _)| key_segment(k) == *key).map(|(_, v)| v),
+        Segment::Index(index) => value.as_vec()?.get(*index),
+    })
+}
@@ -0,0 +1,78 @@
+//! Previews of the effective configuration of each input after extraction.
+//!
+//! A preview is the read-only base, the extracted base and the input's diff merged in that
+//! order: what the environment actually gets once the extraction is deployed. Comparing it with
+//! the read-only base merged with the original input shows whether the extraction changed
+//! anything; a lossless extraction changes nothing.
+
+use yaml_rust2::yaml::{Hash, Yaml};
+
+use crate::deep_equal::deep_equal;
+use crate::input::is_empty_document;
+use crate::merge::apply_diff;
+use crate::options::{DiffOptions, NullHandling};
+
+/// Computes the effective configuration of an input from the extraction results.
+pub fn preview(
+    read_only_base: Option<&Yaml>,
+    base: Option<&Yaml>,
+    diff: Option<&Yaml>,
+    options: &DiffOptions,
+) -> Option<Yaml> {
+    let overrides = apply_diff(base, diff, options);
+    apply_diff(read_only_base, overrides.as_ref(), options)
+}
+
+/// Computes the preview of every input, and whether it has the same effect as the input.
+///
+/// `inputs` are what the extraction ran on, i.e. the inputs' diffs against the read-only base,
+/// and `diffs` the resulting per-file diffs. Empty inputs get no preview.
+pub fn previews(
+    read_only_base: Option<&Yaml>,
+    base: Option<&Yaml>,
+    diffs: &[Option<&Yaml>],
+    inputs: &[&Yaml],
+    options: &DiffOptions,
+) -> Vec<Option<(Yaml, bool)>> {
+    diffs
+        .iter()
+        .zip(inputs)
+        .map(|(diff, input)| {
+            if is_empty_document(input) {
+                return None;
+            }
+            let effective = preview(read_only_base, base, *diff, options)?;
+            let original = apply_diff(read_only_base, Some(input), options)?;
+            let same = same_effect(&effective, &original, options);
+            Some((effective, same))
+        })
+        .collect()
+}
+
+/// Checks whether two effective configurations have the same effect.
+///
+/// A `null` value deletes its key when deployed, so null entries are ignored unless nulls are
+/// values of their own ([`NullHandling::Value`]).
+pub fn same_effect(a: &Yaml, b: &Yaml, options: &DiffOptions) -> bool {
+    if options.null_handling == NullHandling::Value {
+        return deep_equal(a, b);
+    }
+    deep_equal(&without_nulls(a), &without_nulls(b))
+}
+
+/// Drops the null-valued entries of every map.
+fn without_nulls(value: &Yaml) -> Yaml {
+    match value {
+        Yaml::Hash(hash) => {
+            let mut result = Hash::new();
+            for (key, child) in hash {
+                if !child.is_null() {
+                    result.insert(key.clone(), without_nulls(child));
+                }
+            }
+            Yaml::Hash(result)
+        }
+        Yaml::Array(items) => Yaml::Array(items.iter().map(without_nulls).collect()),
+        other => other.clone(),
+    }
+}
@@ -0,0 +1,510 @@
+//! Reports derived from inputs and extraction results, kept separate from the diffs.
+
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::Yaml;
+
+use crate::deep_equal::deep_equal;
+use crate::diff::{count_leaves_with, LeafCounting};
+use crate::input::is_empty_document;
+use crate::normalize::{normalize_all, AliasRename};
+use crate::options::{AliasConflict, DiffOptions, MinorityKeys};
+use crate::path::{join_path, key_segment, parse_path, value_at};
+use crate::schema::type_name;
+
+/// Lists the paths of `base` that `obj` does not override, i.e. leaves at their default.
+///
+/// This is the coverage counterpart of [`crate::diff::compute_diff`], which ignores such keys.
+/// Maps are walked in parallel; a key missing from `obj` is reported once at the highest level
+/// it is missing, and everything else (scalars, arrays) counts as overridden when present.
+pub fn default_paths(obj: &Yaml, base: &Yaml) -> Vec<String> {
+    let mut found = Vec::new();
+    collect_default_paths(obj, base, &mut Vec::new(), &mut found);
+    found
+}
+
+fn collect_default_paths(obj: &Yaml, base: &Yaml, path: &mut Vec<String>, found: &mut Vec<String>) {
+    let (Yaml::Hash(obj_hash), Yaml::Hash(base_hash)) = (obj, base) else {
+        return;
+    };
+    for (key, base_value) in base_hash {
+        path.push(key_segment(key));
+        match obj_hash.get(key) {
+            Some(obj_value) => collect_default_paths(obj_value, base_value, path, found),
+            None => found.push(join_path(path)),
+        }
+        path.pop();
+    }
+}
+
+/// Lists the paths at which the inputs hold more than `max_distinct` distinct values, with the
+/// number of distinct values.
+///
+/// Maps are descended into when every input has a map at the path; any other value counts as a
+/// whole, as in base extraction. Inputs missing the key are not counted. Keys are visited in
+/// the order they first appear.
+pub fn fan_out_paths(objs: &[&Yaml], max_distinct: usize) -> Vec<(String, usize)> {
+    let mut found = Vec::new();
+    collect_fan_out(objs, max_distinct, &mut Vec::new(), &mut found);
+    found
+}
+
+fn collect_fan_out(
+    values: &[&Yaml],
+    max_distinct: usize,
+    path: &mut Vec<String>,
+    found: &mut Vec<(String, usize)>,
+) {
+    if values.is_empty() {
+        return;
+    }
+    let hashes: Option<Vec<_>> = values.iter().map(|value| value.as_hash()).collect();
+    let Some(hashes) = hashes else {
+        let distinct: HashSet<&Yaml> = values.iter().copied().collect();
+        if distinct.len() > max_distinct {
+            found.push((join_path(path), distinct.len()));
+        }
+        return;
+    };
+
+    let mut keys: Vec<&Yaml> = Vec::new();
+    for hash in &hashes {
+        for key in hash.keys() {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    for key in keys {
+        let at_key: Vec<&Yaml> = hashes.iter().filter_map(|hash| hash.get(key)).collect();
+        path.push(key_segment(key));
+        collect_fan_out(&at_key, max_distinct, path, found);
+        path.pop();
+    }
+}
+
+/// What [`values_at`] counts for an input without a value at the path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingValues {
+    /// A missing value counts as `null`, as for the quorum of base extraction.
+    #[default]
+    Null,
+    /// Inputs without a value at the path are not counted.
+    Skip,
+}
+
+/// Lists the distinct values at a dotted path (see [`crate::path::parse_path`]) across the
+/// inputs, each with the number of inputs holding it, in the order they first appear.
+///
+/// Values are compared with [`deep_equal`], so maps differing only in key order are one value.
+/// An explicit `null` always counts as `null`; an input without the path, an empty one included,
+/// counts as `missing` says. Only malformed paths are an error.
+///
+/// ```
+/// use yabe::report::{values_at, MissingValues};
+/// use yabe::yaml;
+///
+/// let docs = [yaml!("image: {tag: v1}"), yaml!("image: {tag: v2}"), yaml!("image: {tag: v1}"), yaml!("replicas: 1")];
+/// let objs: Vec<&_> = docs.iter().collect();
+/// let tags = values_at(&objs, "image.tag", MissingValues::Null).unwrap();
+/// assert_eq!(tags, [(&yaml!("v1"), 2), (&yaml!("v2"), 1), (&yaml!("~"), 1)]);
+/// assert_eq!(values_at(&objs, "image.tag", MissingValues::Skip).unwrap().len(), 2);
+/// ```
+pub fn values_at<'a>(objs: &[&'a Yaml], path: &str, missing: MissingValues) -> Result<Vec<(&'a Yaml, usize)>, String> {
+    let segments = parse_path(path)?;
+    let mut counts: Vec<(&'a Yaml, usize)> = Vec::new();
+    for obj in objs {
+        let value = match (value_at(obj, &segments), missing) {
+            (Some(value), _) => value,
+            (None, MissingValues::Null) => &Yaml::Null,
+            (None, MissingValues::Skip) => continue,
+        };
+        match counts.iter_mut().find(|(seen, _)| deep_equal(seen, value)) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((value, 1)),
+        }
+    }
+    Ok(counts)
+}
+
+/// Returns the index and leaf count (see [`crate::diff::count_leaves`]) of every input's
+/// overrides, largest first; inputs of the same size keep their order. An input without
+/// overrides counts 0.
+pub fn override_sizes(diffs: &[Option<&Yaml>]) -> Vec<(usize, usize)> {
+    override_sizes_with(diffs, LeafCounting::WholeArrays)
+}
+
+/// Returns the override sizes like [`override_sizes`], leaves counted with
+/// [`count_leaves_with`].
+pub fn override_sizes_with(diffs: &[Option<&Yaml>], counting: LeafCounting) -> Vec<(usize, usize)> {
+    let mut sizes: Vec<(usize, usize)> = diffs
+        .iter()
+        .map(|diff| diff.map_or(0, |diff| count_leaves_with(diff, counting)))
+        .enumerate()
+        .collect();
+    sizes.sort_by_key(|&(_, leaves)| std::cmp::Reverse(leaves));
+    sizes
+}
+
+/// Returns the fraction of an input's leaves that come from the base: `1 - diff_leaves /
+/// input_leaves`, both counted with [`crate::diff::count_leaves`].
+///
+/// The denominator is the leaf count of the input as extracted, `obj`, and the numerator that of
+/// its overrides, `diff`; no diff gives 1. A diff can hold more leaves than the input, e.g. the
+/// `null`s removing base keys the input lacks, so the result is clamped to 0.
+///
+/// ```
+/// use yabe::report::override_coverage;
+/// use yabe::yaml;
+///
+/// let obj = yaml!("replicas: 3\nimage:\n  repository: nginx\n  tag: v2\nports: [80]");
+/// assert_eq!(override_coverage(&obj, Some(&yaml!("replicas: 3"))), 0.75);
+/// assert_eq!(override_coverage(&obj, None), 1.0);
+/// ```
+pub fn override_coverage(obj: &Yaml, diff: Option<&Yaml>) -> f64 {
+    override_coverage_with(obj, diff, LeafCounting::WholeArrays)
+}
+
+/// Returns the coverage like [`override_coverage`], both leaf counts taken with
+/// [`count_leaves_with`], so the `null` slots of a positional diff never lower it.
+///
+/// ```
+/// use yabe::diff::LeafCounting;
+/// use yabe::report::override_coverage_with;
+/// use yabe::yaml;
+///
+/// let obj = yaml!("replicas: 3\nargs: [-v, --trace, --color]");
+/// let diff = yaml!("args: [~, --trace, ~]");
+/// assert_eq!(override_coverage_with(&obj, Some(&diff), LeafCounting::WholeArrays), 0.5);
+/// assert_eq!(override_coverage_with(&obj, Some(&diff), LeafCounting::Elements), 0.75);
+/// ```
+pub fn override_coverage_with(obj: &Yaml, diff: Option<&Yaml>, counting: LeafCounting) -> f64 {
+    let overridden = diff.map_or(0, |diff| count_leaves_with(diff, counting)) as f64;
+    (1.0 - overridden / count_leaves_with(obj, counting) as f64).max(0.0)
+}
+
+/// Renders [`override_sizes`] as a report: a `files` map of name to leaf count, largest first,
+/// with `coverage`, a `coverage` map of name to [`override_coverage`] in the same order, and with
+/// `max_leaves`, the names of the files exceeding it as `exceeding`.
+///
+/// `coverage` is indexed like the inputs; an empty slice leaves the map out.
+pub fn size_report(names: &[String], sizes: &[(usize, usize)], coverage: &[f64], max_leaves: Option<usize>) -> Yaml {
+    let key = |name: &str| Yaml::String(name.to_string());
+    let files = sizes
+        .iter()
+        .map(|&(index, leaves)| (key(&names[index]), Yaml::Integer(leaves as i64)))
+        .collect();
+    let mut report = Hash::new();
+    report.insert(key("files"), Yaml::Hash(files));
+    if !coverage.is_empty() {
+        let coverage = sizes
+            .iter()
+            .map(|&(index, _)| (key(&names[index]), Yaml::Real(format!("{:.3}", coverage[index]))))
+            .collect();
+        report.insert(key("coverage"), Yaml::Hash(coverage));
+    }
+    if let Some(max_leaves) = max_leaves {
+        let exceeding = sizes
+            .iter()
+            .filter(|(_, leaves)| *leaves > max_leaves)
+            .map(|&(index, _)| key(&names[index]))
+            .collect();
+        report.insert(key("max_leaves"), Yaml::Integer(max_leaves as i64));
+        report.insert(key("exceeding"), Yaml::Array(exceeding));
+    }
+    Yaml::Hash(report)
+}
+
+/// Text format of a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    /// Lines for people to read.
+    #[default]
+    Text,
+    /// A JSON object, for scripts.
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(format!("unknown report format '{}', expected one of: text, json", s)),
+        }
+    }
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ReportFormat::Text => "text",
+            ReportFormat::Json => "json",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Renders the key renames of each input (see [`crate::normalize::alias_renames`]), indexed
+/// like `names`.
+///
+/// The text form lists each file's renames as `old -> new` under the file name, a conflict
+/// naming the value, `old` or `new`, the `on_conflict` policy keeps; the JSON form is an object
+/// with a `files` array of `file` and `renames`, each rename an object of `old`, `new`,
+/// `conflict` and `kept`, the value kept (`old` without a conflict, the value moving), or
+/// `null` when the policy fails instead.
+///
+/// ```
+/// use yabe::normalize::alias_renames;
+/// use yabe::options::{AliasConflict, KeyAliases};
+/// use yabe::report::{rename_report, ReportFormat};
+/// use yabe::yaml;
+///
+/// let aliases = KeyAliases::from_yaml(&yaml!("image: container.image"), AliasConflict::PreferNew).unwrap();
+/// let docs = [yaml!("image: nginx"), yaml!("container: {image: nginx}")];
+/// let renames: Vec<_> = docs.iter().map(|doc| alias_renames(doc, &aliases)).collect();
+/// let names = ["old.yaml".to_string(), "new.yaml".to_string()];
+/// let report = rename_report(&names, &renames, AliasConflict::PreferNew, ReportFormat::Text);
+/// assert_eq!(report, "old.yaml: 1 rename(s)\n  image -> container.image\nnew.yaml: no renames\n");
+/// ```
+pub fn rename_report(
+    names: &[String],
+    renames: &[Vec<AliasRename>],
+    on_conflict: AliasConflict,
+    format: ReportFormat,
+) -> String {
+    // Which of the two values a conflict keeps
+    let kept = |rename: &AliasRename| match (rename.conflict, on_conflict) {
+        (false, _) | (true, AliasConflict::PreferOld) => Some("old"),
+        (true, AliasConflict::PreferNew) => Some("new"),
+        (true, AliasConflict::Fail) => None,
+    };
+    let mut report = String::new();
+    match format {
+        ReportFormat::Text => {
+            for (name, file_renames) in names.iter().zip(renames) {
+                if file_renames.is_empty() {
+                    let _ = writeln!(report, "{}: no renames", name);
+                    continue;
+                }
+                let _ = writeln!(report, "{}: {} rename(s)", name, file_renames.len());
+                for rename in file_renames {
+                    let _ = write!(report, "  {} -> {}", rename.old, rename.new);
+                    let _ = match (rename.conflict, kept(rename)) {
+                        (false, _) => writeln!(report),
+                        (true, Some(kept)) => writeln!(report, " (conflict, keeping the {} value)", kept),
+                        (true, None) => writeln!(report, " (conflict, failing)"),
+                    };
+                }
+            }
+        }
+        ReportFormat::Json => {
+            report.push_str("{\n  \"files\": [");
+            for (file_index, (name, file_renames)) in names.iter().zip(renames).enumerate() {
+                let separator = if file_index == 0 { "" } else { "," };
+                let _ = write!(report, "{}\n    {{\"file\": {}, \"renames\": [", separator, json_string(name));
+                for (index, rename) in file_renames.iter().enumerate() {
+                    let separator = if index == 0 { "" } else { "," };
+                    let _ = write!(
+                        report,
+                        "{}\n      {{\"old\": {}, \"new\": {}, \"conflict\": {}, \"kept\": {}}}",
+                        separator,
+                        json_string(rename.old),
+                        json_string(rename.new),
+                        rename.conflict,
+                        kept(rename).map_or("null".to_string(), json_string)
+                    );
+                }
+                report.push_str(if file_renames.is_empty() { "]}" } else { "\n    ]}" });
+            }
+            report.push_str(if names.is_empty() { "]\n}\n" } else { "\n  ]\n}\n" });
+        }
+    }
+    report
+}
+
+/// Why a top-level key got no base, from [`explain_empty_base`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MissReason {
+    /// The inputs setting the key hold values of these types, in the order they first appear.
+    TypeConflict(Vec<&'static str>),
+    /// Fewer inputs than [`DiffOptions::min_presence`] set the key.
+    FewerThanMinPresence {
+        /// Inputs setting the key.
+        set: usize,
+    },
+    /// Only some inputs set the key, and [`DiffOptions::minority_keys`] keeps it in the diffs.
+    SetBySome {
+        /// Inputs setting the key.
+        set: usize,
+    },
+    /// No value reached the quorum.
+    NoQuorum {
+        /// Inputs needed to agree on a value.
+        needed: usize,
+        /// The most inputs agreeing on a value at the key, or, for maps, at a key below it.
+        closest: usize,
+    },
+}
+
+/// A top-level key that got no base, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissedKey {
+    /// The key; empty when the documents themselves are not all maps.
+    pub key: String,
+    pub reason: MissReason,
+}
+
+/// Explains, key by key, why an extraction found no base: the quorum outcome of each top-level
+/// key of the non-empty inputs, normalized as for extraction, in the order the keys first appear.
+///
+/// The reasons follow the extraction: a key set by fewer inputs than `min_presence` stays in the
+/// diffs, then one whose values differ in type (`null` aside), then one only some inputs set
+/// unless [`DiffOptions::minority_keys`] hoists it, and otherwise no value met the quorum.
+/// Documents that are not all maps are explained as a whole, under an empty key.
+///
+/// ```
+/// use yabe::report::{explain_empty_base, MissReason};
+/// use yabe::{yaml, DiffOptions};
+///
+/// let objs = [yaml!("port: 80\nregion: eu"), yaml!("port: '80'\nregion: us"), yaml!("region: ap")];
+/// let objs: Vec<&_> = objs.iter().collect();
+/// let missed = explain_empty_base(&objs, &DiffOptions { quorum: 0.5, ..DiffOptions::default() });
+/// assert_eq!(missed[0].reason, MissReason::TypeConflict(vec!["int", "string"]));
+/// assert_eq!(missed[1].reason, MissReason::NoQuorum { needed: 2, closest: 1 });
+/// ```
+pub fn explain_empty_base(objs: &[&Yaml], options: &DiffOptions) -> Vec<MissedKey> {
+    let normalized = normalize_all(objs, options);
+    let present: Vec<&Yaml> = normalized.iter().map(|obj| obj.as_ref()).filter(|obj| !is_empty_document(obj)).collect();
+    if present.is_empty() {
+        return vec![];
+    }
+    let hashes: Option<Vec<&Hash>> = present.iter().map(|obj| obj.as_hash()).collect();
+    let Some(hashes) = hashes else {
+        let slots: Vec<Option<&Yaml>> = present.iter().map(|obj| Some(*obj)).collect();
+        return vec![MissedKey { key: String::new(), reason: miss_reason(&slots, options) }];
+    };
+    let mut keys: Vec<&Yaml> = Vec::new();
+    for key in hashes.iter().flat_map(|hash| hash.keys()) {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys.into_iter()
+        .map(|key| {
+            let slots: Vec<Option<&Yaml>> = hashes.iter().map(|hash| hash.get(key)).collect();
+            MissedKey { key: key_segment(key), reason: miss_reason(&slots, options) }
+        })
+        .collect()
+}
+
+fn miss_reason(slots: &[Option<&Yaml>], options: &DiffOptions) -> MissReason {
+    let set: Vec<&Yaml> = slots.iter().flatten().copied().filter(|value| !value.is_null()).collect();
+    let mut types: Vec<&'static str> = Vec::new();
+    for value in &set {
+        if !types.contains(&type_name(value)) {
+            types.push(type_name(value));
+        }
+    }
+    if set.len() < options.min_presence {
+        return MissReason::FewerThanMinPresence { set: set.len() };
+    }
+    if types.len() > 1 {
+        return MissReason::TypeConflict(types);
+    }
+    let counted = if set.len() == slots.len() {
+        slots.len()
+    } else {
+        let agree = set.windows(2).all(|pair| deep_equal(pair[0], pair[1]));
+        match options.minority_keys {
+            MinorityKeys::Hoist => set.len(),
+            MinorityKeys::HoistIfAllPresentAgree if agree => set.len(),
+            _ => return MissReason::SetBySome { set: set.len() },
+        }
+    };
+    MissReason::NoQuorum {
+        needed: (options.quorum * counted as f64).ceil() as usize,
+        closest: closest_agreement(&set),
+    }
+}
+
+/// The most values agreeing at a path: maps are descended into, through the keys every map
+/// sets with values of one type.
+fn closest_agreement(values: &[&Yaml]) -> usize {
+    let hashes: Option<Vec<&Hash>> = values.iter().map(|value| value.as_hash()).collect();
+    match hashes {
+        Some(hashes) if !hashes.is_empty() => hashes[0]
+            .iter()
+            .filter_map(|(key, _)| {
+                let children: Option<Vec<&Yaml>> = hashes.iter().map(|hash| hash.get(key)).collect();
+                let children = children?;
+                children.iter().all(|child| type_name(child) == type_name(children[0])).then(|| closest_agreement(&children))
+            })
+            .max()
+            .unwrap_or(0),
+        _ => {
+            let mut counts: Vec<(&Yaml, usize)> = Vec::new();
+            for value in values {
+                match counts.iter_mut().find(|(seen, _)| deep_equal(seen, value)) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((value, 1)),
+                }
+            }
+            counts.iter().map(|(_, count)| *count).max().unwrap_or(0)
+        }
+    }
+}
+
+/// Summarizes [`explain_empty_base`] in a line per reason, the most frequent first, e.g.
+/// `2 key(s) with no value reaching the quorum: replicas (2 input(s) agreeing, 3 needed), image (1 input(s)
+/// agreeing, 3 needed)`.
+pub fn empty_base_summary(missed: &[MissedKey]) -> Vec<String> {
+    let named = |missed: &MissedKey| if missed.key.is_empty() { "the documents".to_string() } else { missed.key.clone() };
+    let mut lines: Vec<(&str, Vec<String>)> = Vec::new();
+    for missed in missed {
+        let (heading, detail) = match &missed.reason {
+            MissReason::TypeConflict(types) => ("with values of different types", types.join(", ")),
+            MissReason::FewerThanMinPresence { set } => ("set by fewer inputs than the minimum presence", format!("{} input(s)", set)),
+            MissReason::SetBySome { set } => ("set by only some inputs, so kept in the diffs", format!("{} input(s)", set)),
+            MissReason::NoQuorum { needed, closest } => (
+                "with no value reaching the quorum",
+                format!("{} input(s) agreeing, {} needed", closest, needed),
+            ),
+        };
+        let entry = format!("{} ({})", named(missed), detail);
+        match lines.iter_mut().find(|(seen, _)| *seen == heading) {
+            Some((_, entries)) => entries.push(entry),
+            None => lines.push((heading, vec![entry])),
+        }
+    }
+    lines.sort_by_key(|(_, entries)| std::cmp::Reverse(entries.len()));
+    lines
+        .into_iter()
+        .map(|(heading, entries)| format!("{} key(s) {}: {}", entries.len(), heading, entries.join(", ")))
+        .collect()
+}
+
+/// Quotes a string as JSON.
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
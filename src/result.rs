@@ -0,0 +1,371 @@
+//! The result of base extraction: the common base and one diff per input.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use hashlink::LinkedHashMap;
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::Yaml;
+
+use crate::output::to_yaml_string;
+use crate::path::{format_path, key_segment, Segment};
+use crate::report::override_coverage;
+
+/// Diffs keyed by input name, in input order.
+pub type DiffsByName<'a> = LinkedHashMap<String, Option<Cow<'a, Yaml>>>;
+
+/// The common base and the per-input diffs, optionally named after their inputs.
+///
+/// `Display` renders the result as a YAML stream, one `--- # <name>` document for the base
+/// and for each diff (an empty document when an input has no diff). Unnamed diffs are
+/// numbered from 0. `Debug` shows the values inline in YAML flow style.
+#[derive(Clone, PartialEq)]
+pub struct DiffResult<'a> {
+    /// The common base, if any value met the quorum.
+    pub base: Option<Cow<'a, Yaml>>,
+    /// What each input needs on top of the base, in input order; `None` means nothing.
+    pub diffs: Vec<Option<Cow<'a, Yaml>>>,
+    /// Names of the inputs, e.g. their file names; empty if unnamed.
+    pub names: Vec<String>,
+}
+
+impl<'a> DiffResult<'a> {
+    /// Creates an unnamed result.
+    pub fn new(base: Option<Cow<'a, Yaml>>, diffs: Vec<Option<Cow<'a, Yaml>>>) -> Self {
+        DiffResult {
+            base,
+            diffs,
+            names: vec![],
+        }
+    }
+
+    /// Names the diffs after their inputs, in order.
+    pub fn with_names<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.names = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Returns the name of the `index`-th diff, or its index if unnamed.
+    pub fn name(&self, index: usize) -> Cow<'_, str> {
+        match self.names.get(index) {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(index.to_string()),
+        }
+    }
+
+    /// Returns the diff of the input with the given name.
+    pub fn diff(&self, name: &str) -> Option<&Yaml> {
+        let index = self.names.iter().position(|n| n == name)?;
+        self.diffs.get(index)?.as_deref()
+    }
+
+    /// Counts the inputs that need a diff on top of the base.
+    pub fn diff_count(&self) -> usize {
+        self.diffs.iter().filter(|diff| diff.is_some()).count()
+    }
+
+    /// Returns the fraction of each input's leaves that come from the base (see
+    /// [`override_coverage`]), given the inputs the result was extracted from, in order.
+    pub fn override_coverage(&self, objs: &[&Yaml]) -> Vec<f64> {
+        objs.iter()
+            .zip(&self.diffs)
+            .map(|(obj, diff)| override_coverage(obj, diff.as_deref()))
+            .collect()
+    }
+
+    /// Splits the result into the base and the diffs.
+    pub fn into_parts(self) -> (Option<Cow<'a, Yaml>>, Vec<Option<Cow<'a, Yaml>>>) {
+        (self.base, self.diffs)
+    }
+
+    /// Splits the result into the base and the diffs keyed by input name (see [`DiffResult::name`]),
+    /// in input order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if two inputs have the same name.
+    pub fn into_named_parts(self) -> (Option<Cow<'a, Yaml>>, DiffsByName<'a>) {
+        let mut by_name = DiffsByName::new();
+        for (index, diff) in self.diffs.iter().enumerate() {
+            let name = self.name(index).into_owned();
+            assert!(!by_name.contains_key(&name), "duplicate input name '{}'", name);
+            by_name.insert(name, diff.clone());
+        }
+        (self.base, by_name)
+    }
+
+    /// Converts the result into one that owns its values.
+    pub fn into_owned(self) -> DiffResult<'static> {
+        DiffResult {
+            base: self.base.map(|base| Cow::Owned(base.into_owned())),
+            diffs: self
+                .diffs
+                .into_iter()
+                .map(|diff| diff.map(|diff| Cow::Owned(diff.into_owned())))
+                .collect(),
+            names: self.names,
+        }
+    }
+
+    /// Compares the result with a newer one of the same inputs, leaf by leaf (see [`ResultDelta`]).
+    ///
+    /// Diffs are matched by name (see [`DiffResult::name`]); an input only one of the results has
+    /// has no diff in the other.
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    /// use yabe::result::DiffResult;
+    /// use yabe::yaml;
+    ///
+    /// let (old_base, old_prod) = (yaml!("replicas: 1"), yaml!("image: {tag: v2}"));
+    /// let (new_base, new_prod) = (yaml!("replicas: 1\nimage: {tag: v2}"), yaml!("replicas: 3"));
+    /// let old = DiffResult::new(Some(Cow::Borrowed(&old_base)), vec![Some(Cow::Borrowed(&old_prod))]);
+    /// let new = DiffResult::new(Some(Cow::Borrowed(&new_base)), vec![Some(Cow::Borrowed(&new_prod))]);
+    ///
+    /// let delta = old.delta(&new);
+    /// assert_eq!(delta.base.added, ["image.tag"]);
+    /// let (name, prod) = &delta.diffs[0];
+    /// assert_eq!((name.as_str(), prod.added.as_slice(), prod.removed.as_slice()), ("0", &["replicas".to_string()][..], &["image.tag".to_string()][..]));
+    /// ```
+    pub fn delta(&self, newer: &DiffResult) -> ResultDelta {
+        let mut names: Vec<String> = (0..newer.diffs.len()).map(|index| newer.name(index).into_owned()).collect();
+        for index in 0..self.diffs.len() {
+            let name = self.name(index).into_owned();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        let diffs = names
+            .into_iter()
+            .map(|name| {
+                let part = PartDelta::between(named_diff(self, &name), named_diff(newer, &name));
+                (name, part)
+            })
+            .filter(|(_, part)| !part.is_empty())
+            .collect();
+        ResultDelta {
+            base: PartDelta::between(self.base.as_deref(), newer.base.as_deref()),
+            diffs,
+        }
+    }
+}
+
+/// How the leaves of the base or of one diff changed between two results, as dotted paths (see
+/// [`crate::path::format_path`]) in document order.
+///
+/// A leaf is a scalar, an empty map or an empty array; a `null` leaf, which in a diff removes a
+/// key, counts like any other.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PartDelta {
+    /// Leaves only the newer result has.
+    pub added: Vec<String>,
+    /// Leaves only the older result has.
+    pub removed: Vec<String>,
+    /// Leaves both results have, with different values.
+    pub changed: Vec<String>,
+}
+
+impl PartDelta {
+    fn between(old: Option<&Yaml>, new: Option<&Yaml>) -> Self {
+        let (mut old_leaves, mut new_leaves) = (Vec::new(), Vec::new());
+        if let Some(old) = old {
+            collect_leaves(old, &mut Vec::new(), &mut old_leaves);
+        }
+        if let Some(new) = new {
+            collect_leaves(new, &mut Vec::new(), &mut new_leaves);
+        }
+        let old_values: HashMap<&str, &Yaml> = old_leaves.iter().map(|(path, value)| (path.as_str(), *value)).collect();
+        let new_values: HashMap<&str, &Yaml> = new_leaves.iter().map(|(path, value)| (path.as_str(), *value)).collect();
+        let mut delta = PartDelta::default();
+        for (path, value) in &new_leaves {
+            match old_values.get(path.as_str()) {
+                None => delta.added.push(path.clone()),
+                Some(old_value) if old_value != value => delta.changed.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        for (path, _) in &old_leaves {
+            if !new_values.contains_key(path.as_str()) {
+                delta.removed.push(path.clone());
+            }
+        }
+        delta
+    }
+
+    /// Returns whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    fn to_yaml(&self) -> Yaml {
+        let mut hash = Hash::new();
+        for (key, paths) in [("added", &self.added), ("removed", &self.removed), ("changed", &self.changed)] {
+            if !paths.is_empty() {
+                let paths = paths.iter().map(|path| Yaml::String(path.clone())).collect();
+                hash.insert(Yaml::String(key.to_string()), Yaml::Array(paths));
+            }
+        }
+        Yaml::Hash(hash)
+    }
+}
+
+/// How an extraction changed between two results, from [`DiffResult::delta`]: which leaves
+/// moved into the base or left it, and how each diff grew or shrank.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResultDelta {
+    /// Changes of the base.
+    pub base: PartDelta,
+    /// Changes of each diff that changed, by input name: the newer result's inputs in order,
+    /// then those only the older one has.
+    pub diffs: Vec<(String, PartDelta)>,
+}
+
+impl ResultDelta {
+    /// Returns whether the results are the same.
+    pub fn is_empty(&self) -> bool {
+        self.base.is_empty() && self.diffs.is_empty()
+    }
+
+    /// Renders the delta as a map with a `base` and a `diffs` key, leaving out what did not
+    /// change:
+    ///
+    /// ```yaml
+    /// base:
+    ///   added: [image.tag]
+    /// diffs:
+    ///   prod.yaml:
+    ///     added: [replicas]
+    ///     removed: [image.tag]
+    /// ```
+    pub fn to_yaml(&self) -> Yaml {
+        let mut hash = Hash::new();
+        if !self.base.is_empty() {
+            hash.insert(Yaml::String("base".to_string()), self.base.to_yaml());
+        }
+        if !self.diffs.is_empty() {
+            let diffs = self.diffs.iter().map(|(name, part)| (Yaml::String(name.clone()), part.to_yaml())).collect();
+            hash.insert(Yaml::String("diffs".to_string()), Yaml::Hash(diffs));
+        }
+        Yaml::Hash(hash)
+    }
+}
+
+/// Returns the diff of the input with the given name, or index if unnamed.
+fn named_diff<'r>(result: &'r DiffResult, name: &str) -> Option<&'r Yaml> {
+    let index = (0..result.diffs.len()).find(|index| result.name(*index) == name)?;
+    result.diffs[index].as_deref()
+}
+
+/// Collects the path and value of each leaf, in document order.
+fn collect_leaves<'y>(value: &'y Yaml, path: &mut Vec<Segment>, leaves: &mut Vec<(String, &'y Yaml)>) {
+    match value {
+        Yaml::Hash(hash) if !hash.is_empty() => {
+            for (key, child) in hash {
+                path.push(Segment::Key(key_segment(key)));
+                collect_leaves(child, path, leaves);
+                path.pop();
+            }
+        }
+        Yaml::Array(items) if !items.is_empty() => {
+            for (index, child) in items.iter().enumerate() {
+                path.push(Segment::Index(index));
+                collect_leaves(child, path, leaves);
+                path.pop();
+            }
+        }
+        leaf => leaves.push((format_path(path), leaf)),
+    }
+}
+
+impl<'a> From<(Option<Cow<'a, Yaml>>, Vec<Option<Cow<'a, Yaml>>>)> for DiffResult<'a> {
+    fn from((base, diffs): (Option<Cow<'a, Yaml>>, Vec<Option<Cow<'a, Yaml>>>)) -> Self {
+        DiffResult::new(base, diffs)
+    }
+}
+
+impl<'a> From<DiffResult<'a>> for (Option<Cow<'a, Yaml>>, Vec<Option<Cow<'a, Yaml>>>) {
+    fn from(result: DiffResult<'a>) -> Self {
+        result.into_parts()
+    }
+}
+
+impl fmt::Display for DiffResult<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let render = |doc: &Yaml| to_yaml_string(doc).map_err(|_| fmt::Error);
+        if let Some(base) = &self.base {
+            write!(f, "--- # base\n{}", render(base)?)?;
+        }
+        for (index, diff) in self.diffs.iter().enumerate() {
+            writeln!(f, "--- # {}", self.name(index))?;
+            if let Some(diff) = diff {
+                write!(f, "{}", render(diff)?)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for DiffResult<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DiffResult")
+            .field("base", &self.base.as_deref().map(Flow))
+            .field("diffs", &NamedDiffs(self))
+            .finish()
+    }
+}
+
+/// Renders the diffs as a map of input name to diff.
+struct NamedDiffs<'r, 'a>(&'r DiffResult<'a>);
+
+impl fmt::Debug for NamedDiffs<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let result = self.0;
+        f.debug_map()
+            .entries(
+                result
+                    .diffs
+                    .iter()
+                    .enumerate()
+                    .map(|(index, diff)| (result.name(index), diff.as_deref().map(Flow))),
+            )
+            .finish()
+    }
+}
+
+/// Renders a value in YAML flow style on one line, e.g. `{a: 1, b: ["x", ~]}`.
+struct Flow<'a>(&'a Yaml);
+
+impl fmt::Debug for Flow<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Yaml::Real(s) => f.write_str(s),
+            Yaml::Integer(i) => write!(f, "{}", i),
+            Yaml::String(s) => write!(f, "{:?}", s),
+            Yaml::Boolean(b) => write!(f, "{}", b),
+            Yaml::Null => f.write_str("~"),
+            Yaml::Array(items) => {
+                f.write_str("[")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{:?}", Flow(item))?;
+                }
+                f.write_str("]")
+            }
+            Yaml::Hash(hash) => {
+                f.write_str("{")?;
+                for (index, (key, value)) in hash.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{:?}: {:?}", Flow(key), Flow(value))?;
+                }
+                f.write_str("}")
+            }
+            Yaml::Alias(index) => write!(f, "*{}", index),
+            Yaml::BadValue => f.write_str("<bad value>"),
+        }
+    }
+}
@@ -0,0 +1,598 @@
+//! Structural comparison: keys and types, ignoring values.
+//!
+//! A schema is a document whose leaves are replaced by their type name (`string`, `int`, …);
+//! maps are kept as maps of their keys' schemas. Arrays are leaves, as in base extraction. Two
+//! files are schema-equal when they have the same keys and the same leaf types.
+//!
+//! Type conflicts are the paths at which the inputs hold values of different types, which base
+//! extraction leaves in every file's diff; strict runs reject them. Collection-scalar conflicts
+//! are the narrower case of a scalar in some inputs and a map or an array in others, such as
+//! `resources: 512Mi` against `resources: {limits: ...}`, which is almost always a mistake where
+//! an `int` against a `real` often is not.
+//!
+//! The union schema has every key of any document instead of those shared by a quorum, as a
+//! reference for completeness: [`coverage`] reports the keys each document is missing from it.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use yaml_rust2::yaml::{Hash, Yaml};
+
+use crate::deep_equal::deep_equal;
+use crate::path::{format_path, key_segment, Segment};
+
+/// Returns the type name used to classify a value.
+pub fn type_name(value: &Yaml) -> &'static str {
+    match value {
+        Yaml::Null => "null",
+        Yaml::Boolean(_) => "bool",
+        Yaml::Integer(_) => "int",
+        Yaml::Real(_) => "real",
+        Yaml::String(_) => "string",
+        Yaml::Array(_) => "array",
+        Yaml::Hash(_) => "hash",
+        _ => "unknown",
+    }
+}
+
+/// Returns the schema of a single document.
+pub fn schema_of(doc: &Yaml) -> Yaml {
+    match doc {
+        Yaml::Hash(hash) => Yaml::Hash(
+            hash.iter()
+                .map(|(key, value)| (key.clone(), schema_of(value)))
+                .collect(),
+        ),
+        other => Yaml::String(type_name(other).to_string()),
+    }
+}
+
+/// Computes the schema shared by at least `quorum` (0.0-1.0) of the documents.
+///
+/// A key belongs to the common schema when enough documents have it with the same type; a
+/// missing key counts against the quorum. Keys keep the order in which they first appear.
+pub fn common_schema(objs: &[&Yaml], quorum: f64) -> Option<Yaml> {
+    let quorum_count = (quorum * objs.len() as f64).ceil() as usize;
+    let slots: Vec<Option<&Yaml>> = objs.iter().map(|obj| Some(*obj)).collect();
+    common_schema_of(&slots, quorum_count.max(1))
+}
+
+fn common_schema_of(slots: &[Option<&Yaml>], quorum_count: usize) -> Option<Yaml> {
+    let mut counts: HashMap<&'static str, usize> = HashMap::new();
+    let mut order = Vec::new();
+    for value in slots.iter().flatten() {
+        let count = counts.entry(type_name(value)).or_insert(0);
+        if *count == 0 {
+            order.push(type_name(value));
+        }
+        *count += 1;
+    }
+    // The most common type wins; ties go to the type seen first
+    let common_type = order
+        .into_iter()
+        .filter(|t| counts[t] >= quorum_count)
+        .fold(None, |best, t| match best {
+            Some(best) if counts[best] >= counts[t] => Some(best),
+            _ => Some(t),
+        })?;
+    if common_type != "hash" {
+        return Some(Yaml::String(common_type.to_string()));
+    }
+
+    let hashes: Vec<Option<&Hash>> = slots
+        .iter()
+        .map(|slot| slot.and_then(|value| value.as_hash()))
+        .collect();
+    let mut keys: Vec<&Yaml> = Vec::new();
+    for hash in hashes.iter().flatten() {
+        for key in hash.keys() {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+
+    let mut schema = Hash::new();
+    for key in keys {
+        let at_key: Vec<Option<&Yaml>> =
+            hashes.iter().map(|hash| hash.and_then(|h| h.get(key))).collect();
+        if let Some(child) = common_schema_of(&at_key, quorum_count) {
+            schema.insert(key.clone(), child);
+        }
+    }
+    Some(Yaml::Hash(schema))
+}
+
+/// How a document deviates from a schema at a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviationKind {
+    /// The schema has the path but the document does not.
+    Missing { expected: String },
+    /// The document has a path the schema does not.
+    Unexpected { found: String },
+    /// Both have the path with different types.
+    TypeMismatch { expected: String, found: String },
+}
+
+impl fmt::Display for DeviationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviationKind::Missing { expected } => write!(f, "missing, expected {}", expected),
+            DeviationKind::Unexpected { found } => write!(f, "unexpected {}", found),
+            DeviationKind::TypeMismatch { expected, found } => {
+                write!(f, "{}, expected {}", found, expected)
+            }
+        }
+    }
+}
+
+/// A structural deviation of a document from a schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deviation {
+    /// Dotted path (see [`crate::path`]); empty for the document root.
+    pub path: String,
+    pub kind: DeviationKind,
+}
+
+/// Lists where a document deviates from a schema.
+///
+/// Only the topmost deviation is reported: a missing or mistyped map is not followed by one
+/// entry per key inside it.
+pub fn schema_deviations(doc: &Yaml, schema: &Yaml) -> Vec<Deviation> {
+    let mut found = Vec::new();
+    collect_deviations(doc, schema, &mut Vec::new(), &mut found);
+    found
+}
+
+fn schema_type(schema: &Yaml) -> &str {
+    match schema {
+        Yaml::Hash(_) => "hash",
+        Yaml::String(s) => s,
+        _ => "unknown",
+    }
+}
+
+fn collect_deviations(
+    doc: &Yaml,
+    schema: &Yaml,
+    path: &mut Vec<Segment>,
+    found: &mut Vec<Deviation>,
+) {
+    let expected = schema_type(schema);
+    let actual = type_name(doc);
+    if expected != actual {
+        found.push(Deviation {
+            path: format_path(path),
+            kind: DeviationKind::TypeMismatch {
+                expected: expected.to_string(),
+                found: actual.to_string(),
+            },
+        });
+        return;
+    }
+
+    let (Yaml::Hash(doc_hash), Yaml::Hash(schema_hash)) = (doc, schema) else {
+        return;
+    };
+    for (key, child_schema) in schema_hash {
+        path.push(Segment::Key(key_segment(key)));
+        match doc_hash.get(key) {
+            Some(child) => collect_deviations(child, child_schema, path, found),
+            None => found.push(Deviation {
+                path: format_path(path),
+                kind: DeviationKind::Missing {
+                    expected: schema_type(child_schema).to_string(),
+                },
+            }),
+        }
+        path.pop();
+    }
+    for (key, child) in doc_hash {
+        if !schema_hash.contains_key(key) {
+            path.push(Segment::Key(key_segment(key)));
+            found.push(Deviation {
+                path: format_path(path),
+                kind: DeviationKind::Unexpected {
+                    found: type_name(child).to_string(),
+                },
+            });
+            path.pop();
+        }
+    }
+}
+
+/// Computes the common schema and each document's structural deviations from it.
+///
+/// This is the structural counterpart of [`crate::diff::diff_and_common_multiple`]. Without a
+/// common schema every document is reported as a deviation at the root.
+pub fn schema_diff(objs: &[&Yaml], quorum: f64) -> (Option<Yaml>, Vec<Vec<Deviation>>) {
+    let schema = common_schema(objs, quorum);
+    let deviations = objs
+        .iter()
+        .map(|obj| match &schema {
+            Some(schema) => schema_deviations(obj, schema),
+            None => vec![Deviation {
+                path: String::new(),
+                kind: DeviationKind::Unexpected {
+                    found: type_name(obj).to_string(),
+                },
+            }],
+        })
+        .collect();
+    (schema, deviations)
+}
+
+/// How a strict type check reports conflicts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrictTypes {
+    /// Stop at the first conflict.
+    FailFast,
+    /// Report every conflict, so all of them can be fixed in one pass.
+    CollectAll,
+}
+
+impl FromStr for StrictTypes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail-fast" => Ok(StrictTypes::FailFast),
+            "collect-all" => Ok(StrictTypes::CollectAll),
+            _ => Err(format!("unknown strict types mode '{}', expected one of: fail-fast, collect-all", s)),
+        }
+    }
+}
+
+impl fmt::Display for StrictTypes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            StrictTypes::FailFast => "fail-fast",
+            StrictTypes::CollectAll => "collect-all",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A path at which the inputs hold values of different types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeConflict {
+    /// Dotted path (see [`crate::path`]); empty for the document root.
+    pub path: String,
+    /// The type of each input's value, in input order; `None` for an input that does not set
+    /// the path, or sets it to `null`.
+    pub types: Vec<Option<&'static str>>,
+}
+
+impl TypeConflict {
+    /// Describes the conflict with the inputs' names, e.g. `port: int in a.yaml; string in b.yaml`.
+    pub fn describe(&self, names: &[String]) -> String {
+        let mut by_type: Vec<(&str, Vec<String>)> = Vec::new();
+        for (index, value_type) in self.types.iter().enumerate() {
+            let Some(value_type) = value_type else {
+                continue;
+            };
+            let name = names.get(index).cloned().unwrap_or_else(|| index.to_string());
+            match by_type.iter_mut().find(|(t, _)| t == value_type) {
+                Some((_, files)) => files.push(name),
+                None => by_type.push((value_type, vec![name])),
+            }
+        }
+        let types: Vec<String> = by_type
+            .iter()
+            .map(|(value_type, files)| format!("{} in {}", value_type, files.join(", ")))
+            .collect();
+        let path = if self.path.is_empty() { "(root)" } else { &self.path };
+        format!("{}: {}", path, types.join("; "))
+    }
+}
+
+/// Lists the paths at which the inputs hold values of different types.
+///
+/// These are the values base extraction cannot merge: each file keeps its own in its diff.
+/// `null`, missing keys and empty documents do not conflict with anything. Maps are walked
+/// into and arrays are compared as a whole, as in extraction, and only the topmost conflict of
+/// a subtree is reported. Paths are in the order their keys first appear.
+pub fn type_conflicts(objs: &[&Yaml], mode: StrictTypes) -> Vec<TypeConflict> {
+    let slots: Vec<Option<&Yaml>> = objs.iter().map(|obj| Some(*obj)).collect();
+    let mut found = Vec::new();
+    collect_conflicts(&slots, mode, &mut Vec::new(), &mut found);
+    found
+}
+
+fn collect_conflicts(
+    slots: &[Option<&Yaml>],
+    mode: StrictTypes,
+    path: &mut Vec<Segment>,
+    found: &mut Vec<TypeConflict>,
+) {
+    if mode == StrictTypes::FailFast && !found.is_empty() {
+        return;
+    }
+    let types: Vec<Option<&'static str>> = slots
+        .iter()
+        .map(|slot| slot.filter(|value| !matches!(value, Yaml::Null | Yaml::BadValue)).map(type_name))
+        .collect();
+    let mut present = types.iter().flatten();
+    let Some(first) = present.next() else {
+        return;
+    };
+    if present.any(|value_type| value_type != first) {
+        found.push(TypeConflict {
+            path: format_path(path),
+            types,
+        });
+        return;
+    }
+    if *first != "hash" {
+        return;
+    }
+
+    let hashes: Vec<Option<&Hash>> = slots.iter().map(|slot| slot.and_then(|value| value.as_hash())).collect();
+    let mut keys: Vec<&Yaml> = Vec::new();
+    for hash in hashes.iter().flatten() {
+        for key in hash.keys() {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    for key in keys {
+        let at_key: Vec<Option<&Yaml>> = hashes.iter().map(|hash| hash.and_then(|h| h.get(key))).collect();
+        path.push(Segment::Key(key_segment(key)));
+        collect_conflicts(&at_key, mode, path, found);
+        path.pop();
+    }
+}
+
+/// What an input holds at a collection-scalar conflict.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConflictingValue {
+    /// A scalar, which is not `null`.
+    Scalar(Yaml),
+    /// A map, with its keys (see [`crate::path::key_segment`]).
+    Map(Vec<String>),
+    /// An array, with its length.
+    Array(usize),
+}
+
+impl fmt::Display for ConflictingValue {
+    /// Writes a scalar as YAML, strings quoted and cut after 40 characters, a map as
+    /// `map {<keys>}` and an array as `array of <n>`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictingValue::Scalar(Yaml::String(s)) if s.chars().count() > 40 => {
+                write!(f, "{:?}...", s.chars().take(40).collect::<String>())
+            }
+            ConflictingValue::Scalar(Yaml::String(s)) => write!(f, "{:?}", s),
+            ConflictingValue::Scalar(Yaml::Integer(i)) => write!(f, "{}", i),
+            ConflictingValue::Scalar(Yaml::Real(r)) => f.write_str(r),
+            ConflictingValue::Scalar(Yaml::Boolean(b)) => write!(f, "{}", b),
+            ConflictingValue::Scalar(other) => write!(f, "{:?}", other),
+            ConflictingValue::Map(keys) => write!(f, "map {{{}}}", keys.join(", ")),
+            ConflictingValue::Array(len) => write!(f, "array of {}", len),
+        }
+    }
+}
+
+/// A path at which some inputs hold a scalar and others a map or an array.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionScalarConflict {
+    /// Dotted path (see [`crate::path`]); empty for the document root.
+    pub path: String,
+    /// The value of each input setting the path, by input index, in input order.
+    pub values: Vec<(usize, ConflictingValue)>,
+}
+
+impl CollectionScalarConflict {
+    /// Describes the conflict with the inputs' names, inputs holding the same being listed
+    /// together, e.g. `resources: "512Mi" in a.yaml; map {limits, requests} in b.yaml, c.yaml`.
+    pub fn describe(&self, names: &[String]) -> String {
+        let mut by_value: Vec<(&ConflictingValue, Vec<String>)> = Vec::new();
+        for (index, value) in &self.values {
+            let name = names.get(*index).cloned().unwrap_or_else(|| index.to_string());
+            match by_value.iter_mut().find(|(v, _)| *v == value) {
+                Some((_, files)) => files.push(name),
+                None => by_value.push((value, vec![name])),
+            }
+        }
+        let values: Vec<String> = by_value
+            .iter()
+            .map(|(value, files)| format!("{} in {}", value, files.join(", ")))
+            .collect();
+        let path = if self.path.is_empty() { "(root)" } else { &self.path };
+        format!("{}: {}", path, values.join("; "))
+    }
+}
+
+/// Lists the paths at which some inputs hold a scalar and others a map or an array, the
+/// mistakes among the [`type_conflicts`].
+///
+/// Scalars of different types, such as `80` and `"80"`, and maps against arrays are tolerated.
+/// `null`, missing keys and empty documents conflict with nothing. Maps are walked into, past
+/// any arrays at the same path, and only the topmost conflict of a subtree is reported. Paths
+/// are in the order their keys first appear.
+///
+/// ```
+/// use yabe::schema::collection_scalar_conflicts;
+/// use yabe::yaml;
+///
+/// let a = yaml!("port: 80\nresources: 512Mi");
+/// let b = yaml!("port: '80'\nresources: {limits: {memory: 512Mi}, requests: {memory: 256Mi}}");
+/// let names = ["a.yaml".to_string(), "b.yaml".to_string()];
+/// let conflicts = collection_scalar_conflicts(&[&a, &b]);
+/// assert_eq!(conflicts.len(), 1);
+/// assert_eq!(conflicts[0].describe(&names), "resources: \"512Mi\" in a.yaml; map {limits, requests} in b.yaml");
+/// ```
+pub fn collection_scalar_conflicts(objs: &[&Yaml]) -> Vec<CollectionScalarConflict> {
+    let slots: Vec<Option<&Yaml>> = objs.iter().map(|obj| Some(*obj)).collect();
+    let mut found = Vec::new();
+    collect_collection_scalar(&slots, &mut Vec::new(), &mut found);
+    found
+}
+
+fn collect_collection_scalar(slots: &[Option<&Yaml>], path: &mut Vec<Segment>, found: &mut Vec<CollectionScalarConflict>) {
+    let present: Vec<(usize, &Yaml)> = slots
+        .iter()
+        .enumerate()
+        .filter_map(|(index, slot)| slot.filter(|value| !matches!(value, Yaml::Null | Yaml::BadValue)).map(|value| (index, value)))
+        .collect();
+    let is_collection = |value: &Yaml| matches!(value, Yaml::Hash(_) | Yaml::Array(_));
+    let collections = present.iter().filter(|(_, value)| is_collection(value)).count();
+    if collections > 0 && collections < present.len() {
+        let values = present
+            .iter()
+            .map(|&(index, value)| {
+                let value = match value {
+                    Yaml::Hash(hash) => ConflictingValue::Map(hash.keys().map(key_segment).collect()),
+                    Yaml::Array(items) => ConflictingValue::Array(items.len()),
+                    scalar => ConflictingValue::Scalar(scalar.clone()),
+                };
+                (index, value)
+            })
+            .collect();
+        found.push(CollectionScalarConflict { path: format_path(path), values });
+        return;
+    }
+
+    let hashes: Vec<Option<&Hash>> = slots.iter().map(|slot| slot.and_then(|value| value.as_hash())).collect();
+    let mut keys: Vec<&Yaml> = Vec::new();
+    for hash in hashes.iter().flatten() {
+        for key in hash.keys() {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    for key in keys {
+        let at_key: Vec<Option<&Yaml>> = hashes.iter().map(|hash| hash.and_then(|h| h.get(key))).collect();
+        path.push(Segment::Key(key_segment(key)));
+        collect_collection_scalar(&at_key, path, found);
+        path.pop();
+    }
+}
+
+/// How a document covers the union schema of a set of documents.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    /// Paths of the union schema the document does not have, with their type there; only the
+    /// topmost path of a missing subtree is listed.
+    pub missing: Vec<(String, String)>,
+    /// Paths at which the document's value is not the most common value of the documents that
+    /// have the path, with the document's value.
+    pub overrides: Vec<(String, Yaml)>,
+}
+
+/// Computes the union schema of the documents: every key any of them has.
+///
+/// A path is a map in the union when any document has a map there, with the keys of all those
+/// maps; otherwise its type is the most common type there, ties going to the type seen first.
+/// Keys keep the order in which they first appear.
+pub fn union_schema(objs: &[&Yaml]) -> Option<Yaml> {
+    coverage(objs).0
+}
+
+/// Computes the union schema and how each document covers it.
+///
+/// A document misses the union paths it does not have, and overrides the paths at which its
+/// value differs from the value most documents with the path hold. Maps are walked into and
+/// arrays compared as a whole. When no value is held by more documents than any other, every
+/// document with the path overrides it; a value only one document has is no override. A
+/// document holding a scalar where others hold a map overrides the path with it, and misses
+/// nothing below.
+///
+/// ```
+/// use yabe::schema::coverage;
+/// use yabe::yaml;
+///
+/// let prod = yaml!("replicas: 3\nimage: {tag: v1}\ntls: {enabled: true}");
+/// let staging = yaml!("replicas: 1\nimage: {tag: v1}\ntls: {enabled: true}");
+/// let dev = yaml!("replicas: 1\nimage: {tag: v1}");
+/// let (union, coverage) = coverage(&[&prod, &staging, &dev]);
+///
+/// assert_eq!(union.unwrap()["tls"]["enabled"].as_str(), Some("bool"));
+/// assert_eq!(coverage[2].missing, [("tls".to_string(), "hash".to_string())]);
+/// assert_eq!(coverage[0].overrides, [("replicas".to_string(), yaml!("3"))]);
+/// assert_eq!(coverage[1], Default::default());
+/// ```
+pub fn coverage(objs: &[&Yaml]) -> (Option<Yaml>, Vec<Coverage>) {
+    let slots: Vec<Option<&Yaml>> = objs.iter().map(|obj| Some(*obj)).collect();
+    let mut coverage = vec![Coverage::default(); objs.len()];
+    let union = collect_coverage(&slots, &mut Vec::new(), &mut coverage);
+    (union, coverage)
+}
+
+fn collect_coverage(slots: &[Option<&Yaml>], path: &mut Vec<Segment>, coverage: &mut [Coverage]) -> Option<Yaml> {
+    let present: Vec<(usize, &Yaml)> = slots
+        .iter()
+        .enumerate()
+        .filter_map(|(index, slot)| slot.map(|value| (index, value)))
+        .collect();
+    if present.is_empty() {
+        return None;
+    }
+
+    if !present.iter().any(|(_, value)| matches!(value, Yaml::Hash(_))) {
+        let mut types: Vec<(&'static str, usize)> = Vec::new();
+        let mut values: Vec<(&Yaml, usize)> = Vec::new();
+        for (_, value) in &present {
+            match types.iter_mut().find(|(t, _)| *t == type_name(value)) {
+                Some((_, count)) => *count += 1,
+                None => types.push((type_name(value), 1)),
+            }
+            match values.iter_mut().find(|(v, _)| deep_equal(v, value)) {
+                Some((_, count)) => *count += 1,
+                None => values.push((value, 1)),
+            }
+        }
+        let most = values.iter().map(|(_, count)| *count).max().unwrap_or(0);
+        let mut most_common = values.iter().filter(|(_, count)| *count == most);
+        let common = match (most_common.next(), most_common.next()) {
+            (Some((value, _)), None) => Some(*value),
+            _ => None,
+        };
+        for (index, value) in &present {
+            if !common.is_some_and(|common| deep_equal(common, value)) {
+                coverage[*index].overrides.push((format_path(path), (*value).clone()));
+            }
+        }
+        // The most common type wins; ties go to the type seen first
+        let (common_type, _) = types
+            .into_iter()
+            .fold(None, |best: Option<(&str, usize)>, (t, count)| match best {
+                Some(best) if best.1 >= count => Some(best),
+                _ => Some((t, count)),
+            })?;
+        return Some(Yaml::String(common_type.to_string()));
+    }
+
+    let hashes: Vec<Option<&Hash>> = slots.iter().map(|slot| slot.and_then(|value| value.as_hash())).collect();
+    for (index, value) in &present {
+        if hashes[*index].is_none() {
+            coverage[*index].overrides.push((format_path(path), (*value).clone()));
+        }
+    }
+    let mut keys: Vec<&Yaml> = Vec::new();
+    for hash in hashes.iter().flatten() {
+        for key in hash.keys() {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    let mut union = Hash::new();
+    for key in keys {
+        let at_key: Vec<Option<&Yaml>> = hashes.iter().map(|hash| hash.and_then(|h| h.get(key))).collect();
+        path.push(Segment::Key(key_segment(key)));
+        if let Some(child) = collect_coverage(&at_key, path, coverage) {
+            for (index, hash) in hashes.iter().enumerate() {
+                if hash.is_some_and(|hash| !hash.contains_key(key)) {
+                    coverage[index].missing.push((format_path(path), schema_type(&child).to_string()));
+                }
+            }
+            union.insert(key.clone(), child);
+        }
+        path.pop();
+    }
+    Some(Yaml::Hash(union))
+}
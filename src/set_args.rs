@@ -0,0 +1,177 @@
+//! Diffs as Helm `--set` arguments, for pipelines passing values on the command line instead of
+//! as files with `-f`.
+//!
+//! Each leaf of a diff becomes one argument setting its path, which Helm reads with its `strvals`
+//! parser:
+//!
+//! * Path keys are joined with `.` and array indices written `[0]`, after the key they index:
+//!   `ports[0].name`, `matrix[1][0]`. In a key, `\`, `.`, `,`, `=` and `[` are escaped with a
+//!   backslash, so the key `app.kubernetes.io/name` is written `app\.kubernetes\.io/name`. Keys
+//!   that are not strings are written as in [`crate::path::key_segment`]; an empty key, an index
+//!   above Helm's limit of 65536 or keys written the same way cannot be set.
+//! * Strings are set with `--set-string`, so that Helm keeps `"true"` or `"1.10"` a string.
+//!   Integers, booleans and `null` (which removes the key in Helm) are set with `--set`. In both,
+//!   `\` and `,` in the value are escaped with a backslash.
+//! * Helm reads a number with a fraction set with `--set` as a string, and an empty map or array
+//!   cannot be written at all, so floats, `{}` and `[]` are set as JSON with `--set-json` (Helm
+//!   3.10 or later); `.inf` and `.nan` have no JSON form and cannot be set.
+//!
+//! A diff that is not a map has no path to set. Like a values file, an array set by index
+//! replaces the array of the chart as a whole.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use yaml_rust2::Yaml;
+
+use crate::path::key_segment;
+
+/// Highest array index Helm accepts in a `--set` path.
+pub const MAX_INDEX: usize = 65536;
+
+/// The Helm flag an argument is passed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetFlag {
+    Set,
+    SetString,
+    SetJson,
+}
+
+impl fmt::Display for SetFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetFlag::Set => write!(f, "--set"),
+            SetFlag::SetString => write!(f, "--set-string"),
+            SetFlag::SetJson => write!(f, "--set-json"),
+        }
+    }
+}
+
+/// A value set at a path, with the path and value already escaped for Helm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetArg {
+    pub flag: SetFlag,
+    pub path: String,
+    pub value: String,
+}
+
+impl SetArg {
+    /// The argument following the flag, `path=value`.
+    pub fn argument(&self) -> String {
+        format!("{}={}", self.path, self.value)
+    }
+
+    /// The flag and its argument as a shell command line fragment, the argument single-quoted
+    /// when it holds characters the shell would interpret.
+    pub fn to_shell(&self) -> String {
+        format!("{} {}", self.flag, shell_quote(&self.argument()))
+    }
+}
+
+impl fmt::Display for SetArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_shell())
+    }
+}
+
+/// Renders a diff as the `--set` arguments of its leaves, in document order.
+///
+/// ```
+/// use yabe::set_args::set_args;
+/// use yabe::yaml;
+///
+/// let diff = yaml!("image:\n  tag: '1.10'\nports: [80, 443]\nannotations:\n  app.kubernetes.io/name: web");
+/// let args: Vec<String> = set_args(&diff).unwrap().iter().map(|arg| arg.to_string()).collect();
+/// assert_eq!(
+///     args,
+///     [
+///         "--set-string image.tag=1.10",
+///         "--set 'ports[0]=80'",
+///         "--set 'ports[1]=443'",
+///         "--set-string 'annotations.app\\.kubernetes\\.io/name=web'",
+///     ]
+/// );
+/// ```
+pub fn set_args(diff: &Yaml) -> Result<Vec<SetArg>, String> {
+    let Yaml::Hash(hash) = diff else {
+        return Err("only a map diff can be set by path".to_string());
+    };
+    let mut args = Vec::new();
+    for (key, value) in hash {
+        collect(value, escape_key(key)?, &mut args)?;
+    }
+    let mut seen = HashSet::new();
+    for arg in &args {
+        if !seen.insert(arg.path.as_str()) {
+            return Err(format!("several keys are written as the path '{}'", arg.path));
+        }
+    }
+    Ok(args)
+}
+
+fn collect(value: &Yaml, path: String, args: &mut Vec<SetArg>) -> Result<(), String> {
+    let (flag, value) = match value {
+        Yaml::Hash(hash) if !hash.is_empty() => {
+            for (key, child) in hash {
+                collect(child, format!("{}.{}", path, escape_key(key)?), args)?;
+            }
+            return Ok(());
+        }
+        Yaml::Array(items) if !items.is_empty() => {
+            if items.len() - 1 > MAX_INDEX {
+                return Err(format!("'{}' has more than {} elements, the most Helm can index", path, MAX_INDEX + 1));
+            }
+            for (index, item) in items.iter().enumerate() {
+                collect(item, format!("{}[{}]", path, index), args)?;
+            }
+            return Ok(());
+        }
+        Yaml::Hash(_) => (SetFlag::SetJson, "{}".to_string()),
+        Yaml::Array(_) => (SetFlag::SetJson, "[]".to_string()),
+        Yaml::String(s) => (SetFlag::SetString, escape_value(s)),
+        Yaml::Integer(i) => (SetFlag::Set, i.to_string()),
+        Yaml::Boolean(b) => (SetFlag::Set, b.to_string()),
+        Yaml::Null => (SetFlag::Set, "null".to_string()),
+        Yaml::Real(text) => match value.as_f64() {
+            Some(number) if number.is_finite() => (SetFlag::SetJson, format!("{:?}", number)),
+            _ => return Err(format!("'{}' is {}, which has no JSON form", path, text)),
+        },
+        other => return Err(format!("'{}' holds {:?}, which cannot be set", path, other)),
+    };
+    args.push(SetArg { flag, path, value });
+    Ok(())
+}
+
+/// Escapes a map key as a `--set` path segment.
+pub fn escape_key(key: &Yaml) -> Result<String, String> {
+    let key = key_segment(key);
+    if key.is_empty() {
+        return Err("an empty key cannot be set".to_string());
+    }
+    Ok(escape(&key, &['\\', '.', ',', '=', '[']))
+}
+
+/// Escapes a `--set` or `--set-string` value.
+pub fn escape_value(value: &str) -> String {
+    escape(value, &['\\', ','])
+}
+
+fn escape(text: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn shell_quote(text: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c);
+    if !text.is_empty() && text.chars().all(safe) {
+        text.to_string()
+    } else {
+        format!("'{}'", text.replace('\'', "'\\''"))
+    }
+}
@@ -0,0 +1,218 @@
+//! Two-phase extraction: the common structure first, then the values filled into it.
+//!
+//! The first phase computes the structure shared by the quorum, the keys and types of
+//! [`crate::schema::common_schema`]. The second extracts the base values as usual (see
+//! [`crate::diff::diff_and_common_with_options`]) and fills them into that structure. The
+//! result, the skeleton, is structurally complete even where the values diverge: a leaf the
+//! quorum does not agree on holds a placeholder naming its type, and its values are listed
+//! per document as annotations.
+//!
+//! A placeholder is a string such as `<divergent int>` (see [`placeholder`]): it never has the
+//! type of the leaf it stands for, except for string leaves, so it cannot pass for a default.
+//! The skeleton is a report and not a base to merge diffs onto; the base of the second phase is
+//! the one to write.
+//!
+//! A template is the starter file of a new environment: every key any document has, the
+//! structure of [`crate::schema::common_schema`] with no quorum, filled in the same way but with
+//! the empty value of the leaf's type (see [`default_value`]) where the base has none. Unlike a
+//! placeholder, a default has the type of its leaf, so the template parses as a configuration.
+
+use yaml_rust2::yaml::{Hash, Yaml};
+
+use crate::diff::diff_and_common_with_options;
+use crate::normalize::{needs_normalization, normalize_all};
+use crate::options::DiffOptions;
+use crate::path::{format_path, key_segment, Segment};
+use crate::schema::{common_schema, type_name};
+
+/// Prefix of the placeholder strings, see [`placeholder`].
+pub const PLACEHOLDER_PREFIX: &str = "<divergent ";
+
+/// The result of a two-phase extraction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TwoPhase {
+    /// The structure shared by the quorum: maps of keys, with type names as leaves.
+    pub structure: Option<Yaml>,
+    /// The structure with the agreed values filled in and a placeholder at each divergent leaf.
+    pub skeleton: Option<Yaml>,
+    /// The path of each placeholder, with every document's value there (`None` where it does
+    /// not have the path), in the order of the skeleton.
+    pub divergent: Vec<(String, Vec<Option<Yaml>>)>,
+}
+
+/// Returns the placeholder of a divergent leaf of a type (see [`crate::schema::type_name`]).
+pub fn placeholder(type_name: &str) -> Yaml {
+    Yaml::String(format!("{}{}>", PLACEHOLDER_PREFIX, type_name))
+}
+
+/// Checks whether a value is a placeholder written by [`two_phase`].
+pub fn is_placeholder(value: &Yaml) -> bool {
+    value.as_str().is_some_and(|s| s.starts_with(PLACEHOLDER_PREFIX) && s.ends_with('>'))
+}
+
+/// A starter file, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    /// Every key of the documents, holding the base value or the default of its type.
+    pub template: Option<Yaml>,
+    /// The keys not every document holding their map sets to something other than `null`, in
+    /// the order of the template: the path, the documents setting the key, and those holding
+    /// the map.
+    pub optional: Vec<(Vec<String>, usize, usize)>,
+}
+
+/// Returns the default of a leaf of a type (see [`crate::schema::type_name`]) in a template: the
+/// empty string, `0`, `0.0`, `false` and `[]`, and `null` for any other type.
+pub fn default_value(type_name: &str) -> Yaml {
+    match type_name {
+        "string" => Yaml::String(String::new()),
+        "int" => Yaml::Integer(0),
+        "real" => Yaml::Real("0.0".to_string()),
+        "bool" => Yaml::Boolean(false),
+        "array" => Yaml::Array(vec![]),
+        _ => Yaml::Null,
+    }
+}
+
+/// Builds a template of the documents: every key any of them has, with the base value extracted
+/// with `options` where it has the type of the key's most common value, and the default of that
+/// type otherwise. The documents are normalized first, as for extraction.
+///
+/// ```
+/// use yabe::skeleton::template;
+/// use yabe::{yaml, DiffOptions};
+///
+/// let prod = yaml!("replicas: 3\nimage: {repository: nginx, tag: v2}\ntls: {secretName: prod-tls}");
+/// let staging = yaml!("replicas: 2\nimage: {repository: nginx, tag: v1}");
+/// let result = template(&[&prod, &staging], &DiffOptions { quorum: 1.0, ..DiffOptions::default() });
+///
+/// assert_eq!(
+///     result.template,
+///     Some(yaml!("replicas: 0\nimage: {repository: nginx, tag: ''}\ntls: {secretName: ''}"))
+/// );
+/// assert_eq!(result.optional, [(vec!["tls".to_string()], 1, 2)]);
+/// ```
+pub fn template(objs: &[&Yaml], options: &DiffOptions) -> Template {
+    let normalized = needs_normalization(options).then(|| normalize_all(objs, options));
+    let refs: Vec<&Yaml> = match &normalized {
+        Some(normalized) => normalized.iter().map(|doc| doc.as_ref()).collect(),
+        None => objs.to_vec(),
+    };
+
+    let structure = common_schema(&refs, 0.0);
+    let base = diff_and_common_with_options(&refs, options).base.map(|base| base.into_owned());
+    let mut optional = Vec::new();
+    let template = structure.as_ref().map(|structure| {
+        let slots: Vec<Option<&Yaml>> = refs.iter().map(|doc| Some(*doc)).collect();
+        fill_template(structure, base.as_ref(), &slots, &mut Vec::new(), &mut optional)
+    });
+    Template { template, optional }
+}
+
+fn fill_template(
+    structure: &Yaml,
+    base: Option<&Yaml>,
+    slots: &[Option<&Yaml>],
+    path: &mut Vec<String>,
+    optional: &mut Vec<(Vec<String>, usize, usize)>,
+) -> Yaml {
+    let Yaml::Hash(keys) = structure else {
+        let expected = structure.as_str().unwrap_or_default();
+        return match base.filter(|value| type_name(value) == expected) {
+            Some(value) => value.clone(),
+            None => default_value(expected),
+        };
+    };
+
+    let holding = slots.iter().filter(|slot| slot.is_some_and(|value| value.as_hash().is_some())).count();
+    let mut filled = Hash::new();
+    for (key, child) in keys {
+        let child_base = base.and_then(|base| base.as_hash()).and_then(|hash| hash.get(key));
+        let child_slots: Vec<Option<&Yaml>> = slots
+            .iter()
+            .map(|slot| slot.and_then(|value| value.as_hash()).and_then(|hash| hash.get(key)))
+            .collect();
+        path.push(key_segment(key));
+        let set = child_slots.iter().filter(|slot| slot.is_some_and(|value| !value.is_null())).count();
+        if set < holding {
+            optional.push((path.clone(), set, holding));
+        }
+        filled.insert(key.clone(), fill_template(child, child_base, &child_slots, path, optional));
+        path.pop();
+    }
+    Yaml::Hash(filled)
+}
+
+/// Extracts the structure shared by `options.quorum` of the documents, then fills in the base
+/// values.
+///
+/// A leaf of the structure takes the base value at its path when there is one of the type the
+/// structure expects, and a placeholder otherwise; a map of the structure is always a map,
+/// holding the keys of the structure only. The documents are normalized first, as for
+/// extraction.
+///
+/// ```
+/// use yabe::skeleton::{placeholder, two_phase};
+/// use yabe::{yaml, DiffOptions};
+///
+/// let prod = yaml!("replicas: 3\nimage: {repository: nginx, tag: v2}");
+/// let staging = yaml!("replicas: 2\nimage: {repository: nginx, tag: v1}");
+/// let options = DiffOptions { quorum: 1.0, ..DiffOptions::default() };
+/// let result = two_phase(&[&prod, &staging], &options);
+///
+/// assert_eq!(result.structure, Some(yaml!("replicas: int\nimage: {repository: string, tag: string}")));
+/// let skeleton = result.skeleton.unwrap();
+/// assert_eq!(skeleton["replicas"], placeholder("int"));
+/// assert_eq!(skeleton["image"]["repository"].as_str(), Some("nginx"));
+/// assert_eq!(result.divergent[0], ("replicas".to_string(), vec![Some(yaml!("3")), Some(yaml!("2"))]));
+/// ```
+pub fn two_phase(objs: &[&Yaml], options: &DiffOptions) -> TwoPhase {
+    let normalized = needs_normalization(options).then(|| normalize_all(objs, options));
+    let refs: Vec<&Yaml> = match &normalized {
+        Some(normalized) => normalized.iter().map(|doc| doc.as_ref()).collect(),
+        None => objs.to_vec(),
+    };
+
+    let structure = common_schema(&refs, options.quorum);
+    let base = diff_and_common_with_options(&refs, options).base.map(|base| base.into_owned());
+    let mut divergent = Vec::new();
+    let skeleton = structure.as_ref().map(|structure| {
+        let slots: Vec<Option<&Yaml>> = refs.iter().map(|doc| Some(*doc)).collect();
+        fill(structure, base.as_ref(), &slots, &mut Vec::new(), &mut divergent)
+    });
+    TwoPhase {
+        structure,
+        skeleton,
+        divergent,
+    }
+}
+
+fn fill(
+    structure: &Yaml,
+    base: Option<&Yaml>,
+    slots: &[Option<&Yaml>],
+    path: &mut Vec<Segment>,
+    divergent: &mut Vec<(String, Vec<Option<Yaml>>)>,
+) -> Yaml {
+    let Yaml::Hash(keys) = structure else {
+        let expected = structure.as_str().unwrap_or_default();
+        if let Some(value) = base.filter(|value| type_name(value) == expected) {
+            return value.clone();
+        }
+        divergent.push((format_path(path), slots.iter().map(|slot| slot.cloned()).collect()));
+        return placeholder(expected);
+    };
+
+    let mut filled = Hash::new();
+    for (key, child) in keys {
+        let child_base = base.and_then(|base| base.as_hash()).and_then(|hash| hash.get(key));
+        let child_slots: Vec<Option<&Yaml>> = slots
+            .iter()
+            .map(|slot| slot.and_then(|value| value.as_hash()).and_then(|hash| hash.get(key)))
+            .collect();
+        path.push(Segment::Key(key_segment(key)));
+        filled.insert(key.clone(), fill(child, child_base, &child_slots, path, divergent));
+        path.pop();
+    }
+    Yaml::Hash(filled)
+}
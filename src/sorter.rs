@@ -1,14 +1,162 @@
 use yaml_rust2::yaml::{Array, Hash, Yaml};
 use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
 
+use crate::path::{parse_path, Segment};
+
+/// Sorts a document as described by the sort configuration.
+///
+/// `sortKey` sorts arrays of maps by that key, and `preOrder` puts the listed keys first in
+/// every map, followed by the others alphabetically. `orderTemplate` gives the canonical key
+/// order instead, see [`order_template`]; keys it does not list follow the listed ones, ordered
+/// by `preOrder` and then as `unlistedKeys` says (see [`UnlistedKeys`]).
 pub fn sort_yaml<'a>(doc: &'a Yaml, config: &Yaml) -> Cow<'a, Yaml> {
+    let template = order_template(config).ok().flatten();
+    let ordering = Ordering {
+        sort_key: config["sortKey"].as_str(),
+        pre_order: config["preOrder"]
+            .as_vec()
+            .map(|keys| keys.iter().filter_map(|x| x.as_str()).collect()),
+        unlisted: unlisted_keys(config).unwrap_or_default(),
+        templated: template.is_some(),
+    };
+    sort_node(doc, &ordering, template.as_ref())
+}
+
+/// How keys missing from the order template are ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnlistedKeys {
+    /// Alphabetically, after the listed keys.
+    #[default]
+    Alphabetical,
+    /// In the order they appear in the document, after the listed keys.
+    FirstSeen,
+}
+
+impl FromStr for UnlistedKeys {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "alphabetical" => Ok(UnlistedKeys::Alphabetical),
+            "first-seen" => Ok(UnlistedKeys::FirstSeen),
+            _ => Err(format!(
+                "unknown unlisted key order '{}', expected one of: alphabetical, first-seen",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for UnlistedKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            UnlistedKeys::Alphabetical => "alphabetical",
+            UnlistedKeys::FirstSeen => "first-seen",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Returns the `unlistedKeys` setting of a sort configuration.
+pub fn unlisted_keys(config: &Yaml) -> Result<UnlistedKeys, String> {
+    match &config["unlistedKeys"] {
+        Yaml::BadValue | Yaml::Null => Ok(UnlistedKeys::default()),
+        Yaml::String(s) => s.parse(),
+        other => Err(format!("unlistedKeys must be a string, got {:?}", other)),
+    }
+}
+
+/// Returns the `orderTemplate` of a sort configuration, as a template document.
+///
+/// The template is either a document whose keys are in the canonical order, with maps giving
+/// the order of nested keys and an array's first element the order of the keys of every
+/// element:
+///
+/// ```yaml
+/// orderTemplate:
+///   image: {repository: ~, tag: ~}
+///   containers: [{name: ~, image: ~}]
+/// ```
+///
+/// or a list of key paths (see [`crate::path`]), turned into the equivalent document with
+/// [`template_from_paths`]. Values in the template are ignored.
+pub fn order_template(config: &Yaml) -> Result<Option<Yaml>, String> {
+    match &config["orderTemplate"] {
+        Yaml::BadValue | Yaml::Null => Ok(None),
+        template @ Yaml::Hash(_) => Ok(Some(template.clone())),
+        Yaml::Array(paths) => {
+            let paths = paths
+                .iter()
+                .map(|path| path.as_str().ok_or_else(|| format!("orderTemplate path {:?} is not a string", path)))
+                .collect::<Result<Vec<&str>, String>>()?;
+            template_from_paths(&paths).map(Some)
+        }
+        other => Err(format!("orderTemplate must be a map or a list of key paths, got {:?}", other)),
+    }
+}
+
+/// Builds an order template from key paths in order, e.g. `metadata.name`, `metadata.labels`
+/// and `spec.containers[0].name`. An index stands for every element of the array.
+pub fn template_from_paths(paths: &[&str]) -> Result<Yaml, String> {
+    let mut template = Yaml::Hash(Hash::new());
+    for path in paths {
+        insert_path(&mut template, &parse_path(path)?);
+    }
+    Ok(template)
+}
+
+fn insert_path(node: &mut Yaml, segments: &[Segment]) {
+    let Some((first, rest)) = segments.split_first() else {
+        return;
+    };
+    match first {
+        Segment::Key(key) => {
+            if !matches!(node, Yaml::Hash(_)) {
+                *node = Yaml::Hash(Hash::new());
+            }
+            if let Yaml::Hash(hash) = node {
+                // Looked up with get_mut, as the entry API moves existing keys to the end
+                let key = Yaml::String(key.clone());
+                if !hash.contains_key(&key) {
+                    hash.insert(key.clone(), Yaml::Null);
+                }
+                if let Some(child) = hash.get_mut(&key) {
+                    insert_path(child, rest);
+                }
+            }
+        }
+        Segment::Index(_) => {
+            if !matches!(node, Yaml::Array(items) if items.len() == 1) {
+                *node = Yaml::Array(vec![Yaml::Null]);
+            }
+            if let Yaml::Array(items) = node {
+                insert_path(&mut items[0], rest);
+            }
+        }
+    }
+}
+
+struct Ordering<'c> {
+    sort_key: Option<&'c str>,
+    pre_order: Option<Vec<&'c str>>,
+    unlisted: UnlistedKeys,
+    templated: bool,
+}
+
+/// Sorts a node; `template` is the part of the order template describing it.
+fn sort_node<'a>(doc: &'a Yaml, ordering: &Ordering, template: Option<&Yaml>) -> Cow<'a, Yaml> {
     match doc {
         Yaml::Array(v) => {
-            if let Some(sort_key) = config["sortKey"].as_str() {
+            if ordering.sort_key.is_some() || ordering.templated {
                 let mut new_v = v.clone();
-                array_sorter(&mut new_v, sort_key);
+                if let Some(sort_key) = ordering.sort_key {
+                    array_sorter(&mut new_v, sort_key);
+                }
+                let element_template = template.and_then(|t| t.as_vec()).and_then(|t| t.first());
                 for x in &mut new_v {
-                    let sorted = sort_yaml(x, config);
+                    let sorted = sort_node(x, ordering, element_template);
                     *x = sorted.into_owned();
                 }
                 Cow::Owned(Yaml::Array(new_v))
@@ -17,15 +165,17 @@ pub fn sort_yaml<'a>(doc: &'a Yaml, config: &Yaml) -> Cow<'a, Yaml> {
             }
         }
         Yaml::Hash(h) => {
-            if let Some(pre_order_vec) = config["preOrder"].as_vec() {
+            if ordering.pre_order.is_some() || ordering.templated {
                 let mut new_h = h.clone();
-                let pre_order = pre_order_vec
-                    .iter()
-                    .filter_map(|x| x.as_str())
-                    .collect::<Vec<&str>>();
-                hash_sorter(&mut new_h, &pre_order);
-                for (_, v) in &mut new_h {
-                    let sorted = sort_yaml(v, config);
+                let listed: Vec<&Yaml> = match template {
+                    Some(Yaml::Hash(template)) => template.keys().collect(),
+                    _ => Vec::new(),
+                };
+                let pre_order = ordering.pre_order.as_deref().unwrap_or_default();
+                template_sorter(&mut new_h, &listed, pre_order, ordering.unlisted);
+                for (k, v) in &mut new_h {
+                    let child_template = template.and_then(|t| t.as_hash()).and_then(|t| t.get(k));
+                    let sorted = sort_node(v, ordering, child_template);
                     *v = sorted.into_owned();
                 }
                 Cow::Owned(Yaml::Hash(new_h))
@@ -37,6 +187,29 @@ pub fn sort_yaml<'a>(doc: &'a Yaml, config: &Yaml) -> Cow<'a, Yaml> {
     }
 }
 
+/// Orders a hash: the `listed` keys first, then the `pre_order` keys, then the rest as
+/// `unlisted` says.
+pub fn template_sorter(hash: &mut Hash, listed: &[&Yaml], pre_order: &[&str], unlisted: UnlistedKeys) {
+    let mut result = Hash::new();
+    for key in listed {
+        if let Some((k, v)) = hash.remove_entry(*key) {
+            result.insert(k, v);
+        }
+    }
+    match unlisted {
+        UnlistedKeys::Alphabetical => hash_sorter(hash, pre_order),
+        UnlistedKeys::FirstSeen => {
+            for key in pre_order {
+                if let Some((k, v)) = hash.remove_entry(&Yaml::String((*key).to_string())) {
+                    result.insert(k, v);
+                }
+            }
+        }
+    }
+    result.extend(std::mem::take(hash));
+    *hash = result;
+}
+
 pub fn hash_sorter(hash: &mut Hash, pre_order: &[&str]) {
     let mut result = Hash::new();
 
@@ -49,7 +222,7 @@ pub fn hash_sorter(hash: &mut Hash, pre_order: &[&str]) {
 
     // Collect the remaining keys
     let mut hash_keys: Vec<Yaml> = hash.keys().cloned().collect();
-    hash_keys.sort_by(|a, b| a.cmp(b));
+    hash_keys.sort();
 
     for key in hash_keys {
         if let Some((k, v)) = hash.remove_entry(&key) {
@@ -0,0 +1,153 @@
+//! Statistics of a run, for tracking how standardized the configuration is over time.
+//!
+//! The statistics are written as a flat JSON object, one key per line in a fixed order, so
+//! files stored per commit diff line by line and dashboards can chart them:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "files": 3,
+//!   "empty_files": 0,
+//!   "base_leaves": 42,
+//!   "override_leaves": 18,
+//!   "mean_override_leaves": 6.0000,
+//!   "max_override_leaves": 14,
+//!   "files_without_overrides": 1,
+//!   "mean_coverage": 0.8500,
+//!   "type_conflicts": 0,
+//!   "groups": 0,
+//!   "leaf_counting": "whole-arrays"
+//! }
+//! ```
+//!
+//! `version` is that of the schema: keys are only ever added, and a key whose meaning changes
+//! gets a new name, so stats of different versions compare key by key. Leaves are counted with
+//! [`crate::diff::count_leaves_with`] as `leaf_counting` says, and only stats counted the same
+//! way compare. A file's overrides include its group base, and its coverage is
+//! [`crate::report::override_coverage_with`]. Means are over the non-empty files, `null` without any;
+//! reals are written with four decimals.
+
+use std::fmt::Write;
+
+use yaml_rust2::Yaml;
+
+use crate::diff::{count_leaves_with, LeafCounting};
+use crate::input::is_empty_document;
+use crate::report::override_coverage_with;
+use crate::schema::{type_conflicts, StrictTypes};
+
+/// The version of the stats schema, see the [module documentation](self).
+pub const STATS_VERSION: u32 = 1;
+
+/// Statistics of a run, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunStats {
+    /// Number of input files.
+    pub files: usize,
+    /// Number of empty input files.
+    pub empty_files: usize,
+    /// Leaves of the extracted base.
+    pub base_leaves: usize,
+    /// Leaves overridden by all files together.
+    pub override_leaves: usize,
+    /// Leaves overridden by the average non-empty file.
+    pub mean_override_leaves: Option<f64>,
+    /// Leaves overridden by the file overriding the most.
+    pub max_override_leaves: usize,
+    /// Number of non-empty files overriding nothing, fully covered by the base.
+    pub files_without_overrides: usize,
+    /// Mean fraction of the non-empty files' leaves coming from the base.
+    pub mean_coverage: Option<f64>,
+    /// Number of paths at which the files hold values of different types (see
+    /// [`crate::schema::type_conflicts`]).
+    pub type_conflicts: usize,
+    /// Number of group bases.
+    pub groups: usize,
+    /// How leaves were counted.
+    pub leaf_counting: LeafCounting,
+}
+
+impl RunStats {
+    /// Renders the statistics as JSON, see the [module documentation](self).
+    pub fn to_json(&self) -> String {
+        let real = |value: Option<f64>| value.map_or("null".to_string(), |value| format!("{:.4}", value));
+        let fields = [
+            ("version", STATS_VERSION.to_string()),
+            ("files", self.files.to_string()),
+            ("empty_files", self.empty_files.to_string()),
+            ("base_leaves", self.base_leaves.to_string()),
+            ("override_leaves", self.override_leaves.to_string()),
+            ("mean_override_leaves", real(self.mean_override_leaves)),
+            ("max_override_leaves", self.max_override_leaves.to_string()),
+            ("files_without_overrides", self.files_without_overrides.to_string()),
+            ("mean_coverage", real(self.mean_coverage)),
+            ("type_conflicts", self.type_conflicts.to_string()),
+            ("groups", self.groups.to_string()),
+            ("leaf_counting", format!("\"{}\"", self.leaf_counting)),
+        ];
+        let mut json = String::from("{\n");
+        for (index, (key, value)) in fields.iter().enumerate() {
+            let separator = if index + 1 < fields.len() { "," } else { "" };
+            let _ = writeln!(json, "  \"{}\": {}{}", key, value, separator);
+        }
+        json.push_str("}\n");
+        json
+    }
+}
+
+/// Computes the statistics of a run from the inputs as extracted, the base, and each input's
+/// overrides on top of the base, in input order.
+///
+/// ```
+/// use yabe::stats::run_stats;
+/// use yabe::yaml;
+///
+/// let docs = [yaml!("replicas: 1\nimage: {tag: v1}"), yaml!("replicas: 3\nimage: {tag: v1}")];
+/// let docs: Vec<&_> = docs.iter().collect();
+/// let base = yaml!("image: {tag: v1}");
+/// let overrides = [Some(yaml!("replicas: 1")), Some(yaml!("replicas: 3"))];
+/// let overrides: Vec<_> = overrides.iter().map(Option::as_ref).collect();
+///
+/// let stats = run_stats(&docs, Some(&base), &overrides, 0);
+/// assert_eq!((stats.base_leaves, stats.override_leaves, stats.max_override_leaves), (1, 2, 1));
+/// assert_eq!(stats.mean_coverage, Some(0.5));
+/// assert!(stats.to_json().contains("\n  \"mean_coverage\": 0.5000,\n"));
+/// ```
+pub fn run_stats(docs: &[&Yaml], base: Option<&Yaml>, overrides: &[Option<&Yaml>], groups: usize) -> RunStats {
+    run_stats_with(docs, base, overrides, groups, LeafCounting::WholeArrays)
+}
+
+/// Computes the statistics of a run like [`run_stats`], leaves counted as `counting` says.
+pub fn run_stats_with(
+    docs: &[&Yaml],
+    base: Option<&Yaml>,
+    overrides: &[Option<&Yaml>],
+    groups: usize,
+    counting: LeafCounting,
+) -> RunStats {
+    let count_leaves = |value: &Yaml| count_leaves_with(value, counting);
+    let present: Vec<(&Yaml, Option<&Yaml>)> = docs
+        .iter()
+        .zip(overrides)
+        .filter(|(doc, _)| !is_empty_document(doc))
+        .map(|(doc, diff)| (*doc, *diff))
+        .collect();
+    let leaves: Vec<usize> = overrides.iter().map(|diff| diff.map_or(0, count_leaves)).collect();
+    let present_leaves: Vec<usize> = present.iter().map(|(_, diff)| diff.map_or(0, count_leaves)).collect();
+    let mean = |values: &[f64]| (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64);
+    let coverage: Vec<f64> = present.iter().map(|(doc, diff)| override_coverage_with(doc, *diff, counting)).collect();
+    let sizes: Vec<f64> = present_leaves.iter().map(|leaves| *leaves as f64).collect();
+    RunStats {
+        files: docs.len(),
+        empty_files: docs.len() - present.len(),
+        base_leaves: base.map_or(0, count_leaves),
+        override_leaves: leaves.iter().sum(),
+        mean_override_leaves: mean(&sizes),
+        max_override_leaves: leaves.iter().copied().max().unwrap_or(0),
+        files_without_overrides: present_leaves.iter().filter(|leaves| **leaves == 0).count(),
+        mean_coverage: mean(&coverage),
+        type_conflicts: type_conflicts(docs, StrictTypes::CollectAll).len(),
+        groups,
+        leaf_counting: counting,
+    }
+}
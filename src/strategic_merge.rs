@@ -0,0 +1,142 @@
+//! Kubernetes strategic merge patch semantics for arrays of maps.
+//!
+//! Arrays whose elements are all maps carrying a unique scalar value under the merge key are
+//! treated as keyed collections: elements are matched by that value instead of by position.
+//!
+//! A merge key can also be composite, its fields separated by commas (`name,protocol`): elements
+//! are then matched by the values of all the fields together, so two ports may share a name as
+//! long as their protocols differ. Every element must carry every field, and no two elements the
+//! same combination; otherwise the array is not keyed, just as with a single field, and is
+//! handled positionally.
+//!
+//! Supported directives:
+//! - `$patch: delete` inside an element removes the base element with the same merge key.
+//!   Diffs emit it when a base element is missing from a file.
+//! - `- $patch: replace` as an element replaces the whole base array with the remaining
+//!   elements. It is understood when merging but never emitted.
+//!
+//! Element order follows the base; elements only present in the patch are appended.
+
+use std::borrow::Cow;
+
+use yaml_rust2::yaml::{Array, Hash, Yaml};
+
+/// Directive key used by strategic merge patches.
+pub const PATCH_DIRECTIVE: &str = "$patch";
+
+/// The values of an element under each field of the merge key, in field order.
+pub type ElementKey<'a> = Vec<&'a Yaml>;
+
+/// Returns the fields of a merge key: `name,protocol` has two.
+pub fn key_fields(merge_key: &str) -> Vec<Yaml> {
+    merge_key.split(',').map(|field| Yaml::String(field.trim().to_string())).collect()
+}
+
+/// Returns the merge key of an element, or `None` if it is not a map carrying a scalar under
+/// every field.
+fn element_key<'a>(item: &'a Yaml, fields: &[Yaml]) -> Option<ElementKey<'a>> {
+    let hash = item.as_hash()?;
+    fields
+        .iter()
+        .map(|field| hash.get(field).filter(|value| !matches!(value, Yaml::Hash(_) | Yaml::Array(_))))
+        .collect()
+}
+
+/// Returns the merge key of every element, or `None` if the array is not keyed by `merge_key`.
+pub fn element_keys<'a>(array: &'a [Yaml], merge_key: &str) -> Option<Vec<ElementKey<'a>>> {
+    if array.is_empty() {
+        return None;
+    }
+    let fields = key_fields(merge_key);
+    let mut keys: Vec<ElementKey<'a>> = Vec::with_capacity(array.len());
+    for item in array {
+        let key = element_key(item, &fields)?;
+        if keys.contains(&key) {
+            return None;
+        }
+        keys.push(key);
+    }
+    Some(keys)
+}
+
+/// Checks if a patch element carries the given `$patch` directive.
+pub fn has_directive(item: &Yaml, directive: &str) -> bool {
+    item[PATCH_DIRECTIVE].as_str() == Some(directive)
+}
+
+/// Builds the `{<merge_key>: <value>, $patch: delete}` element, with every field of the key.
+pub fn delete_element(merge_key: &str, key: &[&Yaml]) -> Yaml {
+    let mut hash: Hash = key_fields(merge_key).into_iter().zip(key.iter().map(|value| (*value).clone())).collect();
+    hash.insert(
+        Yaml::String(PATCH_DIRECTIVE.to_string()),
+        Yaml::String("delete".to_string()),
+    );
+    Yaml::Hash(hash)
+}
+
+/// Makes sure a diff element still carries its merge key, so it can be matched when applied.
+///
+/// Missing fields of the key are put first, in field order.
+pub fn with_merge_key(diff: Yaml, merge_key: &str, key: &[&Yaml]) -> Yaml {
+    match diff {
+        Yaml::Hash(h) => {
+            let missing: Vec<(Yaml, &Yaml)> = key_fields(merge_key)
+                .into_iter()
+                .zip(key.iter().copied())
+                .filter(|(field, _)| !h.contains_key(field))
+                .collect();
+            if missing.is_empty() {
+                return Yaml::Hash(h);
+            }
+            let mut keyed: Hash = missing.into_iter().map(|(field, value)| (field, value.clone())).collect();
+            keyed.extend(h);
+            Yaml::Hash(keyed)
+        }
+        other => other,
+    }
+}
+
+/// Applies a strategic merge patch array onto a keyed base array.
+///
+/// Returns `None` if either side is not keyed by `merge_key`, leaving the caller to fall back.
+pub fn merge_arrays<'a, F>(
+    base: &'a Array,
+    patch: &'a Array,
+    merge_key: &str,
+    merge_item: F,
+) -> Option<Cow<'a, Yaml>>
+where
+    F: Fn(&'a Yaml, &'a Yaml) -> Cow<'a, Yaml>,
+{
+    if patch.iter().any(|item| has_directive(item, "replace")) {
+        let replaced = patch
+            .iter()
+            .filter(|item| !has_directive(item, "replace"))
+            .cloned()
+            .collect();
+        return Some(Cow::Owned(Yaml::Array(replaced)));
+    }
+
+    element_keys(base, merge_key)?;
+    let fields = key_fields(merge_key);
+    let patch_keys: Vec<ElementKey<'a>> = patch.iter().map(|item| element_key(item, &fields)).collect::<Option<_>>()?;
+
+    let mut merged: Array = base.clone();
+    for (item, item_key) in patch.iter().zip(&patch_keys) {
+        let position = merged.iter().position(|m| element_key(m, &fields).as_ref() == Some(item_key));
+        match position {
+            Some(index) if has_directive(item, "delete") => {
+                merged.remove(index);
+            }
+            Some(index) => {
+                merged[index] = match base.iter().find(|b| element_key(b, &fields).as_ref() == Some(item_key)) {
+                    Some(base_item) => merge_item(base_item, item).into_owned(),
+                    None => item.clone(),
+                };
+            }
+            None if has_directive(item, "delete") => {}
+            None => merged.push(item.clone()),
+        }
+    }
+    Some(Cow::Owned(Yaml::Array(merged)))
+}
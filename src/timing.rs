@@ -0,0 +1,110 @@
+//! Wall-clock timings of the phases of a run, to find where the time of a large run goes.
+//!
+//! Collecting is opt-in: callers hold an `Option<Timings>` and run each phase through [`timed`],
+//! which only reads the clock when timings are collected. The extraction phase can be broken
+//! down by top-level key with [`crate::diff::diff_and_common_timed`].
+
+use std::time::{Duration, Instant};
+
+/// A timed phase of a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phase {
+    pub name: String,
+    pub duration: Duration,
+    /// Time spent on each top-level key subtree within the phase, if broken down.
+    pub subtrees: Vec<(String, Duration)>,
+}
+
+/// The timed phases of a run, in the order they ran.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timings {
+    pub phases: Vec<Phase>,
+}
+
+impl Timings {
+    /// Records a phase, returning it so a breakdown can be attached. A phase recorded again
+    /// adds to its duration.
+    pub fn record(&mut self, name: &str, duration: Duration) -> &mut Phase {
+        let index = match self.phases.iter().position(|phase| phase.name == name) {
+            Some(index) => index,
+            None => {
+                self.phases.push(Phase {
+                    name: name.to_string(),
+                    duration: Duration::ZERO,
+                    subtrees: vec![],
+                });
+                self.phases.len() - 1
+            }
+        };
+        let phase = &mut self.phases[index];
+        phase.duration += duration;
+        phase
+    }
+
+    /// Runs `f`, recording its duration under `name`.
+    pub fn time<T>(&mut self, name: &str, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = f();
+        self.record(name, started.elapsed());
+        result
+    }
+
+    /// Total duration of the phases.
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|phase| phase.duration).sum()
+    }
+
+    /// Renders the phases with their share of the total, each followed by its slowest subtrees,
+    /// at most `max_subtrees` of them.
+    pub fn report(&self, max_subtrees: usize) -> String {
+        let total = self.total();
+        let share = |duration: Duration| {
+            if total.is_zero() {
+                0.0
+            } else {
+                100.0 * duration.as_secs_f64() / total.as_secs_f64()
+            }
+        };
+        let width = self
+            .phases
+            .iter()
+            .flat_map(|phase| {
+                let keys = phase.subtrees.iter().map(|(key, _)| key.len() + 2);
+                std::iter::once(phase.name.len()).chain(keys)
+            })
+            .max()
+            .unwrap_or(0)
+            .max(5);
+        let mut report = String::new();
+        for phase in &self.phases {
+            report.push_str(&format!(
+                "{:<width$}  {:>10.3} ms  {:>5.1}%\n",
+                phase.name,
+                millis(phase.duration),
+                share(phase.duration)
+            ));
+            let mut subtrees: Vec<&(String, Duration)> = phase.subtrees.iter().collect();
+            subtrees.sort_by_key(|(_, duration)| std::cmp::Reverse(*duration));
+            for (key, duration) in subtrees.iter().take(max_subtrees) {
+                report.push_str(&format!("  {:<key_width$}  {:>10.3} ms\n", key, millis(*duration), key_width = width - 2));
+            }
+            if subtrees.len() > max_subtrees {
+                report.push_str(&format!("  ({} more keys)\n", subtrees.len() - max_subtrees));
+            }
+        }
+        report.push_str(&format!("{:<width$}  {:>10.3} ms\n", "total", millis(total)));
+        report
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Runs `f`, recording its duration under `name` if timings are collected.
+pub fn timed<T>(timings: &mut Option<Timings>, name: &str, f: impl FnOnce() -> T) -> T {
+    match timings {
+        Some(timings) => timings.time(name, f),
+        None => f(),
+    }
+}
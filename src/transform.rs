@@ -0,0 +1,126 @@
+//! Transformations of an extraction result before it is written, such as organization-specific
+//! cleanups, metadata or policy checks, without changing the extraction itself.
+//!
+//! A [`DiffTransform`] receives the whole [`DiffResult`] and modifies it in place; any
+//! `Fn(&mut DiffResult)` is one. [`apply_transforms`] runs transforms in the order given, each
+//! seeing what the previous ones left, so a transform that should see the final documents goes
+//! last. Transforms compose like functions: running A then B is a single transform doing both.
+//! [`SortKeys`] and [`AddMetadata`] are built in and implemented the same way.
+//!
+//! A transform can make the diffs no longer rebuild their inputs when merged onto the base, e.g.
+//! by adding keys to them; keeping that property is up to the transform.
+
+use std::borrow::Cow;
+
+use yaml_rust2::Yaml;
+
+use crate::result::DiffResult;
+use crate::sorter::sort_yaml;
+
+/// A transformation of the base and the diffs.
+pub trait DiffTransform {
+    /// Modifies the result in place; `result.names` is empty unless the caller named the inputs.
+    fn transform(&self, result: &mut DiffResult<'_>);
+}
+
+impl<F: Fn(&mut DiffResult<'_>)> DiffTransform for F {
+    fn transform(&self, result: &mut DiffResult<'_>) {
+        self(result)
+    }
+}
+
+/// Runs the transforms on a result, in order.
+///
+/// ```
+/// use yabe::transform::{apply_transforms, AddMetadata, SortKeys};
+/// use yabe::{diff_and_common_multiple, yaml, DiffResult, Yaml};
+///
+/// let a = yaml!("replicas: 1\nimage: nginx");
+/// let b = yaml!("replicas: 3\nimage: nginx");
+/// let objs = [&a, &b];
+/// let mut result = diff_and_common_multiple(&objs, 1.0);
+///
+/// // A policy leaving replica counts to the autoscaler
+/// let no_replicas = |result: &mut DiffResult| {
+///     for diff in &mut result.diffs {
+///         if let Some(Yaml::Hash(hash)) = diff.as_mut().map(|diff| diff.to_mut()) {
+///             hash.remove(&yaml!("replicas"));
+///             if hash.is_empty() {
+///                 *diff = None;
+///             }
+///         }
+///     }
+/// };
+/// let metadata = AddMetadata::new("managed-by", yaml!("yabe"));
+/// let sort = SortKeys::new(yaml!("preOrder: [managed-by]"));
+/// apply_transforms(&mut result, &[&no_replicas, &metadata, &sort]);
+///
+/// assert!(result.diffs.iter().all(Option::is_none));
+/// let base = result.base.as_deref().unwrap();
+/// let keys: Vec<&str> = base.as_hash().unwrap().keys().filter_map(Yaml::as_str).collect();
+/// assert_eq!(keys, ["managed-by", "image"]);
+/// ```
+pub fn apply_transforms(result: &mut DiffResult<'_>, transforms: &[&dyn DiffTransform]) {
+    for transform in transforms {
+        transform.transform(result);
+    }
+}
+
+/// Sorts the base and every diff with a sort configuration, see [`crate::sorter::sort_yaml`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKeys {
+    pub config: Yaml,
+}
+
+impl SortKeys {
+    pub fn new(config: Yaml) -> Self {
+        SortKeys { config }
+    }
+}
+
+impl DiffTransform for SortKeys {
+    fn transform(&self, result: &mut DiffResult<'_>) {
+        for doc in std::iter::once(&mut result.base).chain(&mut result.diffs).flatten() {
+            if let Cow::Owned(sorted) = sort_yaml(doc, &self.config) {
+                *doc = Cow::Owned(sorted);
+            }
+        }
+    }
+}
+
+/// Sets a top-level key in the base and every diff that is a map, e.g. to mark generated files.
+///
+/// The key replaces an existing one in place and is appended otherwise. An input without a diff
+/// gets none for the metadata alone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddMetadata {
+    pub key: Yaml,
+    pub value: Yaml,
+}
+
+impl AddMetadata {
+    pub fn new(key: &str, value: Yaml) -> Self {
+        AddMetadata {
+            key: Yaml::String(key.to_string()),
+            value,
+        }
+    }
+}
+
+impl DiffTransform for AddMetadata {
+    fn transform(&self, result: &mut DiffResult<'_>) {
+        for doc in std::iter::once(&mut result.base).chain(&mut result.diffs).flatten() {
+            if !matches!(doc.as_ref(), Yaml::Hash(_)) {
+                continue;
+            }
+            if let Yaml::Hash(hash) = doc.to_mut() {
+                match hash.get_mut(&self.key) {
+                    Some(existing) => *existing = self.value.clone(),
+                    None => {
+                        hash.insert(self.key.clone(), self.value.clone());
+                    }
+                }
+            }
+        }
+    }
+}
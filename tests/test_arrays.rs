@@ -0,0 +1,172 @@
+use yabe::arrays::{duplicated_arrays, has_duplicates, ordered_common_elements};
+use yabe::deep_equal::deep_equal;
+use yabe::diff::{compute_diff_with_options, diff_and_common_with_options};
+use yabe::merge::merge_yaml_with_options;
+use yabe::options::{ArrayStrategy, DiffOptions};
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+fn append_unique() -> DiffOptions {
+    DiffOptions { array_strategy: ArrayStrategy::AppendUnique, ..DiffOptions::default() }
+}
+
+#[test]
+fn test_append_unique_extraction() {
+    let yaml1 = yaml!("extraVolumes:\n  - name: tmp\n  - name: cache\n  - name: dev-certs");
+    let yaml2 = yaml!("extraVolumes:\n  - name: tmp\n  - name: cache");
+    let yaml3 = yaml!("extraVolumes:\n  - name: cache\n  - name: tmp\n  - name: prod-certs\n  - name: prod-certs");
+    let objs = vec![&yaml1, &yaml2, &yaml3];
+    let options = append_unique();
+
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+
+    let base = base.unwrap();
+    assert!(deep_equal(&base, &yaml!("extraVolumes:\n  - name: tmp\n  - name: cache")));
+    assert!(deep_equal(diffs[0].as_ref().unwrap(), &yaml!("extraVolumes:\n  - name: dev-certs")));
+    assert!(diffs[1].is_none());
+    assert!(deep_equal(diffs[2].as_ref().unwrap(), &yaml!("extraVolumes:\n  - name: prod-certs")));
+
+    let rebuilt = merge_yaml_with_options(&base, diffs[2].as_ref().unwrap(), &options);
+    let expected = yaml!("extraVolumes:\n  - name: tmp\n  - name: cache\n  - name: prod-certs");
+    assert!(deep_equal(&rebuilt, &expected));
+}
+
+#[test]
+fn test_append_unique_compute_diff() {
+    let helm_yaml = yaml!("args: [--a, --b]");
+    let obj_yaml = yaml!("args: [--b, --c, --a, --c]");
+    let options = append_unique();
+
+    let diff = compute_diff_with_options(&obj_yaml, &helm_yaml, &options).unwrap();
+    assert!(deep_equal(&diff, &yaml!("args: [--c]")));
+
+    let subset = yaml!("args: [--a]");
+    assert!(compute_diff_with_options(&subset, &helm_yaml, &options).is_none());
+}
+
+fn prefix() -> DiffOptions {
+    DiffOptions { array_strategy: ArrayStrategy::Prefix, ..DiffOptions::default() }
+}
+
+#[test]
+fn test_prefix_extraction() {
+    let yaml1 = yaml!("args: [--a, --b, --c]");
+    let yaml2 = yaml!("args: [--a, --b]");
+    let yaml3 = yaml!("args: [--a, --b, --d, --d]");
+    let objs = vec![&yaml1, &yaml2, &yaml3];
+    let options = prefix();
+
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+
+    let base = base.unwrap();
+    assert!(deep_equal(&base, &yaml!("args: [--a, --b]")));
+    assert!(deep_equal(diffs[0].as_ref().unwrap(), &yaml!("args: [--c]")));
+    assert!(diffs[1].is_none());
+    assert!(deep_equal(diffs[2].as_ref().unwrap(), &yaml!("args: [--d, --d]")));
+
+    for (obj, diff) in objs.iter().zip(&diffs) {
+        let rebuilt = match diff {
+            Some(diff) => merge_yaml_with_options(&base, diff, &options).into_owned(),
+            None => base.clone().into_owned(),
+        };
+        assert!(deep_equal(&rebuilt, obj));
+    }
+}
+
+#[test]
+fn test_prefix_not_shared() {
+    let yaml1 = yaml!("args: [--a, --b]");
+    let yaml2 = yaml!("args: [--b, --a]");
+    let objs = vec![&yaml1, &yaml2];
+    let options = prefix();
+
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+
+    assert!(base.is_none());
+    assert!(deep_equal(diffs[0].as_ref().unwrap(), &yaml1));
+    assert!(deep_equal(diffs[1].as_ref().unwrap(), &yaml2));
+}
+
+#[test]
+fn test_prefix_compute_diff() {
+    let helm_yaml = yaml!("args: [--a, --b]");
+    let options = prefix();
+
+    let longer = yaml!("args: [--a, --b, --c]");
+    let diff = compute_diff_with_options(&longer, &helm_yaml, &options).unwrap();
+    assert!(deep_equal(&diff, &yaml!("args: [--c]")));
+
+    assert!(compute_diff_with_options(&helm_yaml, &helm_yaml, &options).is_none());
+
+    let diverging = yaml!("args: [--b]");
+    let diff = compute_diff_with_options(&diverging, &helm_yaml, &options).unwrap();
+    assert!(deep_equal(&diff, &diverging));
+}
+
+fn ordered_set() -> DiffOptions {
+    DiffOptions { array_strategy: ArrayStrategy::OrderedSet, ..DiffOptions::default() }
+}
+
+#[test]
+fn test_ordered_set_extraction() {
+    let yaml1 = yaml!("priorities: [a, b, c]");
+    let yaml2 = yaml!("priorities: [b, a]");
+    let yaml3 = yaml!("priorities: [b, a, d]");
+    let objs = vec![&yaml1, &yaml2, &yaml3];
+    let options = ordered_set();
+
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+
+    let base = base.unwrap();
+    assert!(deep_equal(&base, &yaml!("priorities: [b, a]")));
+    assert!(diffs[1].is_none());
+    assert!(deep_equal(diffs[2].as_ref().unwrap(), &yaml!("priorities:\n  $lcs: [{at: 2, insert: [d]}]")));
+    for (obj, diff) in objs.iter().zip(&diffs) {
+        let rebuilt = match diff {
+            Some(diff) => merge_yaml_with_options(&base, diff, &options).into_owned(),
+            None => base.clone().into_owned(),
+        };
+        assert!(deep_equal(&rebuilt, obj));
+    }
+}
+
+#[test]
+fn test_ordered_set_deletes_missing_elements() {
+    let yaml1 = yaml!("rules: [{host: a}, {host: b}, {host: c}]");
+    let yaml2 = yaml!("rules: [{host: a}, {host: c}]");
+    let yaml3 = yaml!("rules: [{host: a}, {host: b}, {host: c}]");
+    let objs = vec![&yaml1, &yaml2, &yaml3];
+    let options = ordered_set();
+
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+
+    let base = base.unwrap();
+    assert!(deep_equal(&base, &yaml1));
+    assert!(deep_equal(diffs[1].as_ref().unwrap(), &yaml!("rules:\n  $lcs: [{at: 1, delete: 1}]")));
+    assert!(deep_equal(&merge_yaml_with_options(&base, diffs[1].as_ref().unwrap(), &options), &yaml2));
+}
+
+#[test]
+fn test_ordered_set_order_needs_a_quorum() {
+    let arrays = [yaml!("[a, b, c]"), yaml!("[b, a, c]"), yaml!("[a, c, b]")];
+    let arrays: Vec<_> = arrays.iter().map(|array| array.as_vec().unwrap()).collect();
+
+    let strings = |elements: Vec<&Yaml>| elements.iter().map(|e| e.as_str().unwrap().to_string()).collect::<Vec<_>>();
+    assert_eq!(strings(ordered_common_elements(&arrays, 2)), ["a", "b", "c"]);
+    // No order of all three is shared by every array
+    assert_eq!(strings(ordered_common_elements(&arrays, 3)), ["a", "c"]);
+}
+
+#[test]
+fn test_ordered_set_duplicates_fall_back_to_positional() {
+    let helm_yaml = yaml!("args: [--a, --b]");
+    let obj_yaml = yaml!("args: [--a, --a]");
+    let options = ordered_set();
+
+    let diff = compute_diff_with_options(&obj_yaml, &helm_yaml, &options).unwrap();
+    assert!(deep_equal(&diff, &yaml!("args: [~, --a]")));
+    assert!(has_duplicates(obj_yaml["args"].as_vec().unwrap()));
+
+    let doc = yaml!("a: [x, y]\nb: {c: [[1, 1], [2]]}\nd: [z, z]");
+    assert_eq!(duplicated_arrays(&doc), ["b.c[0]", "d"]);
+}
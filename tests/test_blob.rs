@@ -0,0 +1,63 @@
+use yabe::blob::{abbreviate, as_blob, blob_equal, blob_summary};
+use yabe::diff::diff_and_common_with_options;
+use yabe::options::DiffOptions;
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+fn blob(c: char) -> String {
+    c.to_string().repeat(64)
+}
+
+#[test]
+fn test_as_blob_respects_threshold() {
+    let value = Yaml::String(blob('a'));
+    assert!(as_blob(&value, Some(64)).is_some());
+    assert!(as_blob(&value, Some(65)).is_none());
+    assert!(as_blob(&value, None).is_none());
+    assert!(as_blob(&Yaml::Integer(1), Some(0)).is_none());
+}
+
+#[test]
+fn test_blob_equal() {
+    let a = yaml!(&format!("ca: {}\nport: 1", blob('a')));
+    let same = yaml!(&format!("port: 1\nca: {}", blob('a')));
+    let other = yaml!(&format!("ca: {}\nport: 1", blob('b')));
+    assert!(blob_equal(&a, &same, Some(32)));
+    assert!(!blob_equal(&a, &other, Some(32)));
+    assert!(!blob_equal(&a, &other, None));
+}
+
+#[test]
+fn test_abbreviate_replaces_only_blobs() {
+    let doc = yaml!(&format!("tls:\n  cert: {}\n  name: short", blob('a')));
+    let abbreviated = abbreviate(&doc, Some(32));
+    assert_eq!(
+        abbreviated["tls"]["cert"].as_str(),
+        Some(blob_summary(&blob('a')).as_str())
+    );
+    assert!(blob_summary(&blob('a')).starts_with("<blob 64 bytes, fnv "));
+    assert_eq!(abbreviated["tls"]["name"].as_str(), Some("short"));
+
+    // Nothing to abbreviate keeps the original
+    assert_eq!(abbreviate(&doc, None).into_owned(), doc);
+    assert_eq!(abbreviate(&doc, Some(1000)).into_owned(), doc);
+}
+
+#[test]
+fn test_blob_mode_keeps_full_values() {
+    let a = yaml!(&format!("ca: {}\nkey: {}", blob('a'), blob('x')));
+    let b = yaml!(&format!("ca: {}\nkey: {}", blob('a'), blob('y')));
+    let options = DiffOptions {
+        quorum: 1.0,
+        blob_threshold: Some(32),
+        ..DiffOptions::default()
+    };
+
+    let objs = vec![&a, &b];
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    let base = base.unwrap();
+    assert_eq!(base["ca"].as_str(), Some(blob('a').as_str()));
+    assert!(base["key"].is_badvalue());
+    assert_eq!(diffs[0].as_ref().unwrap()["key"].as_str(), Some(blob('x').as_str()));
+    assert_eq!(diffs[1].as_ref().unwrap()["key"].as_str(), Some(blob('y').as_str()));
+}
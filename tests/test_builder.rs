@@ -0,0 +1,43 @@
+use yabe::builder::{array, map, IntoYaml};
+use yabe::{yaml, Yaml};
+
+#[test]
+fn test_scalars_keep_their_rust_type() {
+    assert_eq!("true".into_yaml(), Yaml::String("true".into()));
+    assert_eq!(String::from("80").into_yaml(), Yaml::String("80".into()));
+    assert_eq!(80u16.into_yaml(), Yaml::Integer(80));
+    assert_eq!(1.0.into_yaml(), Yaml::Real("1.0".into()));
+    assert_eq!(f64::NEG_INFINITY.into_yaml().as_f64(), Some(f64::NEG_INFINITY));
+    assert_eq!(None::<i64>.into_yaml(), Yaml::Null);
+    assert_eq!(Some(false).into_yaml(), Yaml::Boolean(false));
+}
+
+#[test]
+fn test_collections_match_parsed_source() {
+    let built = map([
+        ("image", map([("repository", "nginx"), ("tag", "v1")])),
+        ("ports", array([80, 443])),
+        ("args", vec!["--verbose"].into_yaml()),
+    ]);
+    assert_eq!(built, yaml!("image: {repository: nginx, tag: v1}\nports: [80, 443]\nargs: [--verbose]"));
+
+    // A repeated key keeps its first position and its last value
+    assert_eq!(map([("a", 1), ("b", 2), ("a", 3)]), yaml!("a: 3\nb: 2"));
+    assert_eq!(array(Vec::<Yaml>::new()), yaml!("[]"));
+}
+
+#[test]
+fn test_macro_builds_nested_values() {
+    let tag = String::from("v2");
+    let doc = yaml!({
+        "image" => yaml!({ "tag" => tag.as_str() }),
+        "replicas" => 3,
+        "hosts" => yaml!(["a.example.com", "b.example.com"]),
+        1 => "integer key",
+    });
+    assert_eq!(doc["image"]["tag"].as_str(), Some("v2"));
+    assert_eq!(doc["hosts"][1].as_str(), Some("b.example.com"));
+    assert_eq!(doc[1].as_str(), Some("integer key"));
+    assert_eq!(yaml!({}), Yaml::Hash(Default::default()));
+    assert_eq!(yaml!([]), Yaml::Array(vec![]));
+}
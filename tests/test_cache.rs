@@ -0,0 +1,54 @@
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+use yabe::cache::{cache_key, Cache, CacheKey};
+use yabe::yaml;
+
+mod common;
+use common::temp_dir;
+
+#[test]
+fn test_cache_round_trip() {
+    let dir = temp_dir("cache");
+    let cache = Cache::open(&dir).unwrap();
+    let key = cache_key(&[b"content"]);
+    assert!(cache.get(&key).is_none());
+
+    let value = yaml!("a: 1.50\nb: \"01\"\nc: [x, ~]\nd: {}");
+    cache.put(&key, &value).unwrap();
+    assert_eq!(cache.get(&key), Some(value));
+
+    // A corrupt entry is a miss
+    fs::write(dir.join(format!("{}.yaml", key.name())), "a: [").unwrap();
+    assert!(cache.get(&key).is_none());
+}
+
+#[test]
+fn test_cache_prune_removes_least_recently_used() {
+    let dir = temp_dir("cache_prune");
+    let cache = Cache::open(&dir).unwrap();
+    let keys: Vec<_> = (0..3).map(|i| cache_key(&[format!("input {}", i).as_bytes()])).collect();
+    let entry = |key: &CacheKey| dir.join(format!("{}.yaml", key.name()));
+    for (i, key) in keys.iter().enumerate() {
+        cache.put(key, &yaml!("value: 1")).unwrap();
+        let used = SystemTime::now() - Duration::from_secs(60 * (3 - i as u64));
+        fs::File::options().append(true).open(entry(key)).unwrap().set_modified(used).unwrap();
+    }
+    fs::write(dir.join("notes.txt"), "not an entry").unwrap();
+    let size = fs::metadata(entry(&keys[0])).unwrap().len();
+
+    // Reading the oldest entry makes it the most recently used
+    assert!(cache.get(&keys[0]).is_some());
+    assert_eq!(cache.prune(2 * size).unwrap(), 1);
+    assert!(entry(&keys[0]).exists() && !entry(&keys[1]).exists() && entry(&keys[2]).exists());
+    assert_eq!(cache.prune(u64::MAX).unwrap(), 0);
+    assert!(dir.join("notes.txt").exists());
+}
+
+#[test]
+fn test_cache_key_depends_on_every_part() {
+    assert_eq!(cache_key(&[b"a", b"b"]), cache_key(&[b"a", b"b"]));
+    assert_ne!(cache_key(&[b"a", b"b"]), cache_key(&[b"a", b"c"]));
+    assert_ne!(cache_key(&[b"ab", b"c"]), cache_key(&[b"a", b"bc"]));
+    assert_eq!(cache_key(&[b"a"]).name().len(), 64);
+}
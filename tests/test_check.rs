@@ -0,0 +1,94 @@
+use yabe::check::{
+    base_like_inputs, duplicate_inputs, duplicated_diff_paths, mean_base_coverage, redundant_paths, DuplicateInputs,
+    UnrelatedInputs,
+};
+use yabe::options::DiffOptions;
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+#[test]
+fn test_duplicated_diff_paths() {
+    let diff1 = yaml!("a:\n  b: 1\n  c: 2\nd: x");
+    let diff2 = yaml!("a:\n  b: 1\n  c: 3\nd: x");
+    let diff3 = yaml!("a:\n  b: 1\nd: x\ne: y");
+
+    let found = duplicated_diff_paths(&[Some(&diff1), Some(&diff2), Some(&diff3)]);
+    assert_eq!(found, vec!["a.b", "d"]);
+}
+
+#[test]
+fn test_duplicated_diff_paths_needs_every_file() {
+    let diff1 = yaml!("a: 1");
+    let diff2 = yaml!("a: 1");
+
+    assert!(duplicated_diff_paths(&[Some(&diff1), Some(&diff2), None]).is_empty());
+    assert!(duplicated_diff_paths(&[Some(&diff1)]).is_empty());
+    assert_eq!(duplicated_diff_paths(&[Some(&diff1), Some(&diff2)]), vec![""]);
+}
+
+#[test]
+fn test_redundant_paths() {
+    let base = yaml!("image:\n  repository: nginx\n  tag: v1\nreplicas: 1\ntls: {}");
+    let options = DiffOptions::default();
+
+    let minimal = yaml!("image:\n  tag: v2\nreplicas: 2");
+    assert!(redundant_paths(&minimal, &base, &options).is_empty());
+
+    let file = yaml!("image:\n  repository: nginx\n  tag: v2\nreplicas: 1\nregion: eu");
+    assert_eq!(redundant_paths(&file, &base, &options), vec!["image.repository", "replicas"]);
+
+    let same = yaml!("replicas: 1");
+    assert_eq!(redundant_paths(&same, &base, &options), vec![""]);
+    assert!(redundant_paths(&Yaml::Null, &base, &options).is_empty());
+}
+
+#[test]
+fn test_base_like_inputs() {
+    let base = yaml!("a: 1\nb: {c: 2}");
+    let x = yaml!("a: 1\nb: {c: 2, d: 3}");
+    let y = yaml!("a: 1\nb: {c: 2}\ne: 4");
+    let z = yaml!("a: 2\nb: {c: 2}");
+    let empty = Yaml::Null;
+    let options = DiffOptions::default();
+
+    assert_eq!(base_like_inputs(&[&x, &base, &empty, &y], &options), vec![1]);
+    // A value another input sets differently, or too few inputs to tell
+    assert!(base_like_inputs(&[&x, &base, &y, &z], &options).is_empty());
+    assert!(base_like_inputs(&[&x, &base], &options).is_empty());
+    // Identical inputs are not a base of each other
+    assert!(base_like_inputs(&[&base, &base, &base], &options).is_empty());
+}
+
+#[test]
+fn test_duplicate_inputs() {
+    let a = yaml!("a: 1\nb: [x, y]");
+    let reordered = yaml!("b: [x, y]\na: 1");
+    let other = yaml!("a: 1\nb: [y, x]");
+    let empty = Yaml::Null;
+
+    assert_eq!(duplicate_inputs(&[&a, &other, &empty, &reordered, &empty]), vec![(0, 3)]);
+    assert_eq!(duplicate_inputs(&[&a, &a, &a]), vec![(0, 1), (0, 2), (1, 2)]);
+    assert!(duplicate_inputs(&[&a, &other]).is_empty());
+    assert_eq!("error".parse(), Ok(DuplicateInputs::Error));
+    assert!("fail".parse::<DuplicateInputs>().is_err());
+}
+
+#[test]
+fn test_mean_base_coverage() {
+    let a = yaml!("replicas: 2\nimage: {repository: nginx, tag: v1}");
+    let b = yaml!("replicas: 2\nimage: {repository: nginx, tag: v2}");
+    let diff_a = yaml!("image: {tag: v1}");
+    let diff_b = yaml!("image: {tag: v2}");
+    let coverage = mean_base_coverage(&[&a, &b], &[Some(&diff_a), Some(&diff_b)]).unwrap();
+    assert!((coverage - 2.0 / 3.0).abs() < 1e-9);
+
+    // Unrelated files share nothing: every leaf is an override
+    let c = yaml!("database: {host: db, port: 5432}");
+    let empty = yaml!("~");
+    assert_eq!(mean_base_coverage(&[&a, &c, &empty], &[Some(&a), Some(&c), None]), Some(0.0));
+    assert_eq!(mean_base_coverage(&[&a, &empty], &[Some(&a), None]), None);
+
+    assert_eq!("error".parse::<UnrelatedInputs>(), Ok(UnrelatedInputs::Error));
+    assert_eq!(UnrelatedInputs::default().to_string(), "warn");
+    assert!("loud".parse::<UnrelatedInputs>().is_err());
+}
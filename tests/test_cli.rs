@@ -0,0 +1,566 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use yaml_rust2::{Yaml, YamlLoader};
+
+mod common;
+use common::temp_dir;
+
+fn yabe(dir: &Path, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_yabe"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_stdout_contains_only_yaml() {
+    let dir = temp_dir("cli-stdout");
+    fs::write(dir.join("a.yaml"), "a: 1\nb: 2\n").unwrap();
+    fs::write(dir.join("b.yaml"), "a: 1\nb: 3\n").unwrap();
+
+    let output = yabe(&dir, &["--stdout", "a.yaml", "b.yaml"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let docs = YamlLoader::load_from_str(&stdout).unwrap();
+    assert_eq!(docs.len(), 3);
+    assert_eq!(docs[0]["a"], Yaml::Integer(1));
+    assert_eq!(docs[1]["b"], Yaml::Integer(2));
+    assert_eq!(docs[2]["b"], Yaml::Integer(3));
+
+    // Diagnostics go to stderr and no files are written
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Reading input file"));
+    assert!(!dir.join("base.yaml").exists());
+}
+
+#[test]
+fn test_quiet_silences_logs() {
+    let dir = temp_dir("cli-quiet");
+    fs::write(dir.join("a.yaml"), "a: 1\n").unwrap();
+    fs::write(dir.join("b.yaml"), "a: 1\n").unwrap();
+
+    let output = yabe(&dir, &["--quiet", "--stdout", "a.yaml", "b.yaml"]);
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_cached_run_matches_cold_run() {
+    let dir = temp_dir("cli-cache");
+    fs::write(dir.join("a.yaml"), "a: 1\nb: 2\n").unwrap();
+    fs::write(dir.join("b.yaml"), "a: 1\nb: 3\n").unwrap();
+
+    let cold = yabe(&dir, &["--no-cache", "--stdout", "a.yaml", "b.yaml"]);
+    assert!(!dir.join(".yabe-cache").exists());
+    let first = yabe(&dir, &["--stdout", "a.yaml", "b.yaml"]);
+    let warm = yabe(&dir, &["--debug", "--stdout", "a.yaml", "b.yaml"]);
+    assert_eq!(cold.stdout, first.stdout);
+    assert_eq!(cold.stdout, warm.stdout);
+    assert!(String::from_utf8(warm.stderr).unwrap().contains("Using cached"));
+
+    // A changed file invalidates its entries
+    fs::write(dir.join("b.yaml"), "a: 1\nb: 4\n").unwrap();
+    let changed = yabe(&dir, &["--stdout", "a.yaml", "b.yaml"]);
+    let docs = YamlLoader::load_from_str(&String::from_utf8(changed.stdout).unwrap()).unwrap();
+    assert_eq!(docs[2]["b"], Yaml::Integer(4));
+}
+
+#[test]
+fn test_timing_with_cache() {
+    let dir = temp_dir("cli-timing_cache");
+    fs::write(dir.join("a.yaml"), "image: {tag: v1}\nreplicas: 2\n").unwrap();
+    fs::write(dir.join("b.yaml"), "image: {tag: v1}\nreplicas: 3\n").unwrap();
+
+    // A cache miss breaks the extraction down by top-level key, like an uncached run
+    let miss = String::from_utf8(yabe(&dir, &["--quiet", "--timing", "--stdout", "a.yaml", "b.yaml"]).stderr).unwrap();
+    assert!(miss.contains("input diffs (cached)"), "{}", miss);
+    assert!(miss.lines().any(|line| line.starts_with("  image ")), "{}", miss);
+    let hit = String::from_utf8(yabe(&dir, &["--quiet", "--timing", "--stdout", "a.yaml", "b.yaml"]).stderr).unwrap();
+    assert!(!hit.lines().any(|line| line.starts_with("  image ")), "{}", hit);
+}
+
+#[test]
+fn test_empty_inputs() {
+    let dir = temp_dir("cli-empty");
+    fs::write(dir.join("a.yaml"), "a: 1\nb: 2\n").unwrap();
+    fs::write(dir.join("empty.yaml"), "# stub\n").unwrap();
+    fs::write(dir.join("b.yaml"), "a: 1\nb: 3\n").unwrap();
+    let args = ["--no-cache", "--stdout", "a.yaml", "empty.yaml", "b.yaml"];
+
+    let output = yabe(&dir, &args);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let docs = YamlLoader::load_from_str(&stdout).unwrap();
+    assert_eq!(docs[0]["a"], Yaml::Integer(1));
+    // The empty file keeps its place and gets no diff
+    assert!(stdout.contains("--- # empty.yaml\n--- # b.yaml\nb: 3"));
+
+    let output = yabe(&dir, &[&["-q", "100", "--empty-inputs", "error"], &args[..]].concat());
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("empty.yaml"));
+}
+
+#[test]
+fn test_previews() {
+    let dir = temp_dir("cli-preview");
+    fs::write(dir.join("a.yaml"), "a: 1\nb: 2\n").unwrap();
+    fs::write(dir.join("b.yaml"), "a: 1\nb: 3\n").unwrap();
+
+    let output = yabe(&dir, &["--no-cache", "--stdout", "--preview-dir", "previews", "a.yaml", "b.yaml"]);
+    assert!(output.status.success());
+    let preview = fs::read_to_string(dir.join("previews/b_preview.yaml")).unwrap();
+    let preview = YamlLoader::load_from_str(&preview).unwrap().remove(0);
+    assert_eq!(preview["a"], Yaml::Integer(1));
+    assert_eq!(preview["b"], Yaml::Integer(3));
+
+    // A lossless extraction has no changed previews
+    let args = ["--no-cache", "--stdout", "--preview-dir", "changed", "--preview-changed-only", "a.yaml", "b.yaml"];
+    assert!(yabe(&dir, &args).status.success());
+    assert_eq!(fs::read_dir(dir.join("changed")).unwrap().count(), 0);
+}
+
+#[test]
+fn test_groups() {
+    let dir = temp_dir("cli-groups");
+    fs::create_dir_all(dir.join("out")).unwrap();
+    fs::write(dir.join("prod-eu.yaml"), "name: app\ntier: prod\nregion: eu\n").unwrap();
+    fs::write(dir.join("prod-us.yaml"), "name: app\ntier: prod\nregion: us\n").unwrap();
+    fs::write(dir.join("dev.yaml"), "name: app\ndebug: true\n").unwrap();
+
+    let args = ["--no-cache", "--groups", "2", "prod-eu.yaml", "prod-us.yaml", "dev.yaml"];
+    let output = yabe(&dir, &args);
+    assert!(output.status.success());
+
+    let group_base = fs::read_to_string(dir.join("out/group-1.yaml")).unwrap();
+    assert_eq!(YamlLoader::load_from_str(&group_base).unwrap()[0]["tier"].as_str(), Some("prod"));
+    let assignment = fs::read_to_string(dir.join("out/groups.yaml")).unwrap();
+    let assignment = &YamlLoader::load_from_str(&assignment).unwrap()[0];
+    assert_eq!(assignment["prod-us.yaml"].as_str(), Some("group-1"));
+    assert!(assignment["dev.yaml"].is_null());
+    let diff = fs::read_to_string(dir.join("out/prod-eu_diff.yaml")).unwrap();
+    assert!(!diff.contains("tier"));
+}
+
+#[test]
+fn test_check() {
+    let dir = temp_dir("cli-check");
+    fs::write(dir.join("base.yaml"), "image: nginx\nreplicas: 1\n").unwrap();
+    fs::write(dir.join("a.yaml"), "replicas: 2\n").unwrap();
+    fs::write(dir.join("b.yaml"), "replicas: 3\nimage: nginx\n").unwrap();
+
+    let output = yabe(&dir, &["--quiet", "--check", "-b", "base.yaml", "a.yaml"]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let output = yabe(&dir, &["--quiet", "--check", "-b", "base.yaml", "a.yaml", "b.yaml"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, "b.yaml: image: already set to the same value in the base\n");
+    assert!(!dir.join("out").exists());
+}
+
+#[cfg(feature = "helm")]
+#[test]
+fn test_chart_as_read_base() {
+    let dir = temp_dir("cli-chart");
+    fs::create_dir_all(dir.join("chart/charts/redis")).unwrap();
+    fs::write(dir.join("chart/Chart.yaml"), "apiVersion: v2\nname: app\n").unwrap();
+    fs::write(dir.join("chart/values.yaml"), "replicas: 1\n").unwrap();
+    fs::write(dir.join("chart/charts/redis/Chart.yaml"), "apiVersion: v2\nname: redis\n").unwrap();
+    fs::write(dir.join("chart/charts/redis/values.yaml"), "port: 6379\n").unwrap();
+    fs::write(dir.join("a.yaml"), "replicas: 1\nredis:\n  port: 6380\n").unwrap();
+    fs::write(dir.join("b.yaml"), "replicas: 2\nredis:\n  port: 6380\n").unwrap();
+
+    let output = yabe(&dir, &["--quiet", "--no-cache", "--stdout", "--chart", "chart", "a.yaml", "b.yaml"]);
+    assert!(output.status.success());
+    let docs = YamlLoader::load_from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(docs[0]["redis"]["port"], Yaml::Integer(6380));
+    assert_eq!(docs[2]["replicas"], Yaml::Integer(2));
+
+    let output = yabe(&dir, &["--quiet", "--chart", "chart", "-r", "chart/values.yaml", "a.yaml"]);
+    assert!(!output.status.success());
+}
+
+#[cfg(feature = "helm")]
+#[test]
+fn test_umbrella() {
+    let dir = temp_dir("cli-umbrella");
+    fs::write(dir.join("api.yaml"), "image: {tag: v1}\nreplicas: 3\nglobal: {domain: example.com}\n").unwrap();
+    fs::write(dir.join("worker.yaml"), "image: {tag: v1}\nreplicas: 1\nglobal: {domain: example.com}\n").unwrap();
+    fs::write(dir.join("umbrella.yaml"), "subcharts:\n  api.yaml: api\n  worker.yaml: worker\n").unwrap();
+
+    let output = yabe(&dir, &["--quiet", "--umbrella", "umbrella.yaml", "api.yaml", "worker.yaml"]);
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(dir.join("out/values.yaml")).unwrap(),
+        "image:\n  tag: v1\nglobal:\n  domain: example.com\napi:\n  replicas: 3\nworker:\n  replicas: 1\n"
+    );
+    assert!(!dir.join("base.yaml").exists());
+    assert!(!dir.join("out/api.yaml").exists());
+
+    // The api subchart would get the worker's global
+    fs::write(dir.join("api.yaml"), "image: {tag: v1}\nreplicas: 3\n").unwrap();
+    let output = yabe(&dir, &["--quiet", "--umbrella", "umbrella.yaml", "--out", "other", "api.yaml", "worker.yaml"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("api.yaml keeps the base value of the top-level global"));
+}
+
+#[cfg(feature = "helm")]
+#[test]
+fn test_umbrella_needs_plain_array_values() {
+    let dir = temp_dir("cli-umbrella_arrays");
+    fs::write(dir.join("api.yaml"), "args: [a, b, c]\n").unwrap();
+    fs::write(dir.join("worker.yaml"), "args: [a, b, q, c]\n").unwrap();
+    fs::write(dir.join("umbrella.yaml"), "subcharts:\n  api.yaml: api\n  worker.yaml: worker\n").unwrap();
+
+    for strategy in ["lcs", "ordered-set", "strategic-merge", "append-unique", "prefix"] {
+        let output = yabe(&dir, &["--quiet", "--umbrella", "umbrella.yaml", "--array-strategy", strategy, "api.yaml", "worker.yaml"]);
+        assert!(!output.status.success(), "{}", strategy);
+        assert!(String::from_utf8(output.stderr).unwrap().contains("--umbrella does not apply"), "{}", strategy);
+    }
+    let output = yabe(&dir, &["--quiet", "--umbrella", "umbrella.yaml", "--unchanged-slots", "indexed", "api.yaml", "worker.yaml"]);
+    assert!(!output.status.success());
+    assert!(!dir.join("out/values.yaml").exists());
+
+    let output = yabe(&dir, &["--quiet", "--umbrella", "umbrella.yaml", "api.yaml", "worker.yaml"]);
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(dir.join("out/values.yaml")).unwrap(),
+        "api:\n  args:\n    - a\n    - b\n    - c\nworker:\n  args:\n    - a\n    - b\n    - q\n    - c\n"
+    );
+}
+
+#[test]
+fn test_compare() {
+    let dir = temp_dir("cli-compare");
+    fs::create_dir_all(dir.join("before")).unwrap();
+    fs::create_dir_all(dir.join("after")).unwrap();
+    fs::write(dir.join("before/a.yaml"), "replicas: 1\n").unwrap();
+    fs::write(dir.join("after/a.yaml"), "replicas: 2\n").unwrap();
+    fs::write(dir.join("after/b.yaml"), "replicas: 1\n").unwrap();
+
+    let output = yabe(&dir, &["--quiet", "--compare", "before", "after"]);
+    assert!(output.status.success());
+    let report = YamlLoader::load_from_str(&String::from_utf8(output.stdout).unwrap()).unwrap().remove(0);
+    assert_eq!(report["files"]["a.yaml"]["diff"]["replicas"], Yaml::Integer(2));
+    assert_eq!(report["files"]["b.yaml"]["status"].as_str(), Some("added"));
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn test_minimize() {
+    let dir = temp_dir("cli-minimize");
+    fs::create_dir_all(dir.join("envs/eu")).unwrap();
+    fs::write(dir.join("values.yaml"), "replicas: 1\ntag: v1\n").unwrap();
+    fs::write(dir.join("envs/prod.yaml"), "replicas: 3\ntag: v2\n").unwrap();
+    fs::write(dir.join("envs/eu/dev.yaml"), "replicas: 1\ntag: v2\n").unwrap();
+
+    let args = ["--quiet", "-i", "--minimize", "values.yaml", "envs"];
+    assert!(yabe(&dir, &args).status.success());
+    let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+    let base = YamlLoader::load_from_str(&read("values.yaml")).unwrap().remove(0);
+    assert_eq!(base["tag"].as_str(), Some("v2"));
+    assert_eq!(read("envs/prod.yaml"), "replicas: 3\n");
+    assert_eq!(read("envs/eu/dev.yaml"), "");
+
+    // Running again changes nothing
+    let before = [read("values.yaml"), read("envs/prod.yaml"), read("envs/eu/dev.yaml")];
+    assert!(yabe(&dir, &args).status.success());
+    assert_eq!([read("values.yaml"), read("envs/prod.yaml"), read("envs/eu/dev.yaml")], before);
+}
+
+#[test]
+fn test_ignore_file() {
+    let dir = temp_dir("cli-ignore");
+    fs::create_dir_all(dir.join("generated")).unwrap();
+    fs::write(dir.join("a.yaml"), "a: 1\nb: 2\n").unwrap();
+    fs::write(dir.join("b.yaml"), "a: 1\nb: 2\n").unwrap();
+    fs::write(dir.join("generated/c.yaml"), "a: 3\n").unwrap();
+    fs::write(dir.join(".yabeignore"), "generated\nkey: b\n").unwrap();
+
+    let output = yabe(&dir, &["--stdout", "a.yaml", "b.yaml", "generated/c.yaml"]);
+    assert!(output.status.success());
+    let docs = YamlLoader::load_from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(docs.len(), 3);
+    assert_eq!(docs[0], YamlLoader::load_from_str("a: 1").unwrap()[0]);
+    assert_eq!(docs[1]["b"], Yaml::Integer(2));
+
+    let output = yabe(&dir, &["--stdout", "--no-ignore-file", "a.yaml", "b.yaml", "generated/c.yaml"]);
+    let docs = YamlLoader::load_from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(docs.len(), 4);
+}
+
+#[test]
+fn test_set_args() {
+    let dir = temp_dir("cli-set_args");
+    fs::create_dir_all(dir.join("out")).unwrap();
+    fs::write(dir.join("a.yaml"), "image:\n  tag: v1\nreplicas: 2\n").unwrap();
+    fs::write(dir.join("b.yaml"), "image:\n  tag: v2\nreplicas: 2\n").unwrap();
+
+    let output = yabe(&dir, &["--set-args", "a.yaml", "b.yaml"]);
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(dir.join("out/a.set")).unwrap(), "--set-string image.tag=v1\n");
+    assert_eq!(fs::read_to_string(dir.join("out/b.set")).unwrap(), "--set-string image.tag=v2\n");
+    assert!(!dir.join("out/a_diff.yaml").exists());
+    assert!(dir.join("base.yaml").exists());
+}
+
+#[test]
+fn test_stdout_diff() {
+    let dir = temp_dir("cli-stdout_diff");
+    fs::write(dir.join("a.yaml"), "image:\n  tag: v1\nreplicas: 2\n").unwrap();
+    fs::write(dir.join("b.yaml"), "image:\n  tag: v2\nreplicas: 2\n").unwrap();
+
+    let output = yabe(&dir, &["--stdout-diff", "./b.yaml", "a.yaml", "b.yaml"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "image:\n  tag: v2\n");
+    assert!(!dir.join("base.yaml").exists());
+
+    let output = yabe(&dir, &["--stdout-diff", "c.yaml", "a.yaml", "b.yaml"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_two_phase() {
+    let dir = temp_dir("cli-two_phase");
+    fs::write(dir.join("a.yaml"), "image:\n  tag: v1\nreplicas: 2\n").unwrap();
+    fs::write(dir.join("b.yaml"), "image:\n  tag: v2\nreplicas: 2\n").unwrap();
+
+    let output = yabe(&dir, &["--two-phase", "--quorum", "100", "a.yaml", "b.yaml"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "--- # structure\nimage:\n  tag: string\nreplicas: int\n--- # skeleton\nimage:\n  tag: \"<divergent string>\"\nreplicas: 2\n\
+         --- # divergent\nimage.tag:\n  a.yaml: v1\n  b.yaml: v2\n"
+    );
+    assert!(!dir.join("base.yaml").exists());
+}
+
+#[test]
+fn test_template() {
+    let dir = temp_dir("cli-template");
+    fs::write(dir.join("a.yaml"), "image:\n  tag: v1\nreplicas: 2\ntls:\n  enabled: true\n").unwrap();
+    fs::write(dir.join("b.yaml"), "image:\n  tag: v2\nreplicas: 2\n").unwrap();
+
+    let output = yabe(&dir, &["--template", "a.yaml", "b.yaml"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "image:\n  tag: \"\"\nreplicas: 2\n# optional: set by 1 of 2 files\ntls:\n  enabled: false\n"
+    );
+    assert!(!dir.join("base.yaml").exists());
+}
+
+#[test]
+fn test_unchanged_slots_indexed() {
+    let dir = temp_dir("cli-unchanged_slots_indexed");
+    fs::create_dir_all(dir.join("out")).unwrap();
+    fs::write(dir.join("helm.yaml"), "args: [a, b, c]\n").unwrap();
+    fs::write(dir.join("a.yaml"), "args: [a, x, c]\nflags: [--a, ~]\n").unwrap();
+    fs::write(dir.join("b.yaml"), "args: [a, y, c]\nflags: [--a, ~]\n").unwrap();
+
+    let output = yabe(&dir, &["-r", "helm.yaml", "--unchanged-slots", "indexed", "a.yaml", "b.yaml"]);
+    assert!(output.status.success());
+    // The base's nulls are values, not unchanged slots
+    assert_eq!(fs::read_to_string(dir.join("base.yaml")).unwrap(), "flags:\n  - \"--a\"\n  - ~\n");
+    assert_eq!(fs::read_to_string(dir.join("out/a_diff.yaml")).unwrap(), "args:\n  $slots:\n    1: x\n  $length: 3\n");
+}
+
+#[test]
+fn test_malformed_atomic_key_is_rejected() {
+    let dir = temp_dir("cli-atomic_key");
+    fs::write(dir.join("a.yaml"), "config: {level: info}\n").unwrap();
+    fs::write(dir.join("b.yaml"), "config: {level: debug}\n").unwrap();
+
+    let output = yabe(&dir, &["--atomic-key", "config[0]", "a.yaml", "b.yaml"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("--atomic-key: key path 'config[0]' indexes an array"));
+    assert!(!dir.join("base.yaml").exists());
+}
+
+#[test]
+fn test_real_format() {
+    let dir = temp_dir("cli-real_format");
+    fs::create_dir_all(dir.join("out")).unwrap();
+    fs::write(dir.join("a.yaml"), "cpu: 1.50\nratio: 1e3\n").unwrap();
+    fs::write(dir.join("b.yaml"), "cpu: 1.50\nratio: 0.00000025\n").unwrap();
+
+    let output = yabe(&dir, &["--real-format", "shortest,sci:6", "a.yaml", "b.yaml"]);
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(dir.join("base.yaml")).unwrap(), "cpu: 1.5\n");
+    assert_eq!(fs::read_to_string(dir.join("out/a_diff.yaml")).unwrap(), "ratio: 1000.0\n");
+    assert_eq!(fs::read_to_string(dir.join("out/b_diff.yaml")).unwrap(), "ratio: 2.5e-7\n");
+
+    let output = yabe(&dir, &["--real-format", "fixed:0", "a.yaml", "b.yaml"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_env_file() {
+    let dir = temp_dir("cli-env_file");
+    fs::create_dir_all(dir.join("out")).unwrap();
+    fs::write(dir.join("a.yaml"), "image:\n  tag: v1\ngreeting: hello world\n").unwrap();
+    fs::write(dir.join("b.yaml"), "image:\n  tag: v2\ngreeting: hello world\n").unwrap();
+
+    let output = yabe(&dir, &["--env-file", "a.yaml", "b.yaml"]);
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(dir.join("out/a.env")).unwrap(), "IMAGE_TAG=v1\n");
+    assert_eq!(fs::read_to_string(dir.join("out/b.env")).unwrap(), "IMAGE_TAG=v2\n");
+    assert_eq!(fs::read_to_string(dir.join("base.env")).unwrap(), "GREETING=\"hello world\"\n");
+    assert!(dir.join("base.yaml").exists());
+    assert!(!dir.join("out/a_diff.yaml").exists());
+}
+
+#[test]
+fn test_unrelated_inputs() {
+    let dir = temp_dir("cli-unrelated_inputs");
+    fs::write(dir.join("app.yaml"), "image:\n  tag: v1\nreplicas: 2\n").unwrap();
+    fs::write(dir.join("db.yaml"), "database:\n  host: db\n  port: 5432\n").unwrap();
+
+    let output = yabe(&dir, &["--stdout", "app.yaml", "db.yaml"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("the inputs may be unrelated files"));
+
+    let output = yabe(&dir, &["--stdout", "--strict", "app.yaml", "db.yaml"]);
+    assert!(!output.status.success());
+    let output = yabe(&dir, &["--stdout", "--unrelated-inputs", "error", "--min-base-coverage", "0", "app.yaml", "db.yaml"]);
+    assert!(output.status.success());
+    let output = yabe(&dir, &["--stdout", "--strict", "--unrelated-inputs", "ignore", "app.yaml", "db.yaml"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_unrelated_inputs_with_read_only_base() {
+    let dir = temp_dir("cli-unrelated_read_only");
+    fs::write(dir.join("values.yaml"), "image: {repository: nginx, tag: v1}\nreplicas: 2\nargs: [a, b, c]\n").unwrap();
+    fs::write(dir.join("a.yaml"), "image: {repository: nginx, tag: v2}\nreplicas: 2\nargs: [a, x, c]\n").unwrap();
+    fs::write(dir.join("b.yaml"), "image: {repository: nginx, tag: v3}\nreplicas: 2\nargs: [a, y, c]\nz: 1\n").unwrap();
+
+    // The inputs only differ in their overrides of the read-only base, which covers the rest
+    for counting in ["whole-arrays", "elements"] {
+        let output = yabe(&dir, &["--stdout", "--strict", "--leaf-counting", counting, "-r", "values.yaml", "a.yaml", "b.yaml"]);
+        assert!(output.status.success(), "{}", counting);
+        assert!(!String::from_utf8(output.stderr).unwrap().contains("the inputs may be unrelated files"), "{}", counting);
+    }
+}
+
+#[test]
+fn test_preserve_directives() {
+    let dir = temp_dir("cli-preserve_directives");
+    fs::create_dir_all(dir.join("out")).unwrap();
+    fs::write(dir.join("a.yaml"), "%YAML 1.2\n---\nreplicas: 1\nimage: v1\n...\n").unwrap();
+    fs::write(dir.join("b.yaml"), "%YAML 1.1\n---\nreplicas: 1\nimage: v2\n").unwrap();
+
+    let output = yabe(&dir, &["--preserve-directives", "a.yaml", "b.yaml"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("b.yaml has YAML version 1.1, not 1.2"));
+    assert_eq!(fs::read_to_string(dir.join("base.yaml")).unwrap(), "%YAML 1.2\n---\nreplicas: 1\n...\n");
+    assert_eq!(fs::read_to_string(dir.join("out/b_diff.yaml")).unwrap(), "%YAML 1.2\n---\nimage: v2\n...\n");
+
+    let output = yabe(&dir, &["a.yaml", "b.yaml"]);
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(dir.join("base.yaml")).unwrap(), "replicas: 1\n");
+}
+
+#[test]
+fn test_denylist() {
+    let dir = temp_dir("cli-denylist");
+    fs::write(dir.join("prod.yaml"), "db:\n  user: app\n  password: hunter2\n").unwrap();
+    fs::write(dir.join("staging.yaml"), "db:\n  user: app\n  password: hunter2\n").unwrap();
+    fs::write(dir.join("denylist.yaml"), "values: [hunter2]\n").unwrap();
+
+    let output = yabe(&dir, &["--stdout", "--denylist", "denylist.yaml", "prod.yaml", "staging.yaml"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("denied value at db.password (matching value #1)"));
+    assert!(output.stdout.is_empty());
+
+    fs::write(dir.join("staging.yaml"), "db:\n  user: app\n  password: s3cret\n").unwrap();
+    let output = yabe(&dir, &["--stdout", "--denylist", "denylist.yaml", "prod.yaml", "staging.yaml"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_stats() {
+    let dir = temp_dir("cli-stats");
+    fs::write(dir.join("prod.yaml"), "replicas: 3\nimage: {repository: nginx, tag: v1}\n").unwrap();
+    fs::write(dir.join("staging.yaml"), "replicas: 1\nimage: {repository: nginx, tag: v1}\n").unwrap();
+
+    let output = yabe(&dir, &["--stdout", "--stats", "stats.json", "prod.yaml", "staging.yaml"]);
+    assert!(output.status.success());
+    let stats = fs::read_to_string(dir.join("stats.json")).unwrap();
+    assert!(stats.starts_with("{\n  \"version\": 1,\n  \"files\": 2,\n"));
+    assert!(stats.contains("\n  \"base_leaves\": 2,\n  \"override_leaves\": 2,\n"));
+    assert!(stats.contains("\n  \"mean_coverage\": 0.6667,\n"));
+}
+
+#[test]
+fn test_ordered_set_rejects_duplicates() {
+    let dir = temp_dir("cli-ordered_set_duplicates");
+    fs::write(dir.join("prod.yaml"), "tolerations: [{key: a}, {key: b}]\n").unwrap();
+    fs::write(dir.join("staging.yaml"), "tolerations: [{key: b}, {key: a}, {key: b}]\n").unwrap();
+
+    let output = yabe(&dir, &["--stdout", "--array-strategy", "ordered-set", "prod.yaml", "staging.yaml"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("staging.yaml: the array at tolerations holds duplicate elements"));
+    assert!(!stderr.contains("prod.yaml:"));
+
+    fs::write(dir.join("staging.yaml"), "tolerations: [{key: b}, {key: a}]\n").unwrap();
+    let output = yabe(&dir, &["--stdout", "--array-strategy", "ordered-set", "prod.yaml", "staging.yaml"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_preview_renames() {
+    let dir = temp_dir("cli-preview_renames");
+    fs::write(dir.join("aliases.yaml"), "ingress.hosts: ingress.hostnames\n").unwrap();
+    fs::write(dir.join("old.yaml"), "ingress:\n  hosts: [a.com]\n").unwrap();
+    fs::write(dir.join("new.yaml"), "ingress:\n  hostnames: [a.com]\n").unwrap();
+
+    let output = yabe(&dir, &["--key-aliases", "aliases.yaml", "--preview-renames", "old.yaml", "new.yaml"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, "old.yaml: 1 rename(s)\n  ingress.hosts -> ingress.hostnames\nnew.yaml: no renames\n");
+    assert!(!dir.join("base.yaml").exists());
+
+    let args = ["--key-aliases", "aliases.yaml", "--preview-renames", "--rename-format", "json", "old.yaml"];
+    let stdout = String::from_utf8(yabe(&dir, &args).stdout).unwrap();
+    assert!(stdout.contains("{\"old\": \"ingress.hosts\", \"new\": \"ingress.hostnames\", \"conflict\": false, \"kept\": \"old\"}"));
+}
+
+#[test]
+fn test_strict_collection_scalar() {
+    let dir = temp_dir("cli-strict_collection_scalar");
+    fs::write(dir.join("prod.yaml"), "port: 80\nresources:\n  limits: {memory: 1Gi}\n").unwrap();
+    fs::write(dir.join("dev.yaml"), "port: '80'\nresources: 512Mi\n").unwrap();
+
+    let output = yabe(&dir, &["--stdout", "--strict-collection-scalar", "prod.yaml", "dev.yaml"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("resources: map {limits} in prod.yaml; \"512Mi\" in dev.yaml"));
+    assert!(!stderr.contains("port:"));
+
+    let output = yabe(&dir, &["--stdout", "--strict-collection-scalar", "prod.yaml", "prod.yaml"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_require_base_explains_the_empty_base() {
+    let dir = temp_dir("cli-explain_no_base");
+    fs::write(dir.join("prod.yaml"), "port: 80\nregion: eu\n").unwrap();
+    fs::write(dir.join("dev.yaml"), "port: '80'\nregion: us\n").unwrap();
+
+    let output = yabe(&dir, &["--stdout", "--require-base", "prod.yaml", "dev.yaml"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("1 key(s) with values of different types: port (int, string)"));
+    assert!(stderr.contains("1 key(s) with no value reaching the quorum: region (1 input(s) agreeing, 2 needed)"));
+
+    let output = yabe(&dir, &["--stdout", "prod.yaml", "dev.yaml"]);
+    assert!(!String::from_utf8(output.stderr).unwrap().contains("key(s) with"));
+}
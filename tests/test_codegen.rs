@@ -0,0 +1,71 @@
+#![cfg(feature = "codegen")]
+
+use yabe::codegen::rust_structs;
+use yabe::{yaml, DiffOptions};
+
+#[test]
+fn test_rust_structs() {
+    let base = yaml!("image: {repository: nginx, tag: v1}\nimagePullPolicy: Always\nports: [{name: http, port: 80}]");
+    let prod = yaml!("replicas: 3\ntls: {secretName: prod-tls, enabled: true}\ntype: web\nratio: 0.5");
+    let staging = yaml!("replicas: 1\nratio: 1\nports: [{name: http, port: 8080, protocol: TCP}, {name: admin, port: 9000}]");
+    let code = rust_structs(Some(&base), &[Some(&prod), Some(&staging)], &DiffOptions::default(), "values").unwrap();
+    assert_eq!(
+        code,
+        r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Values {
+    pub image: Image,
+    #[serde(rename = "imagePullPolicy")]
+    pub image_pull_policy: String,
+    pub ports: Vec<PortsItem>,
+    pub replicas: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<Tls>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub type_: Option<String>,
+    pub ratio: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Image {
+    pub repository: String,
+    pub tag: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PortsItem {
+    pub name: String,
+    pub port: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Tls {
+    #[serde(rename = "secretName")]
+    pub secret_name: String,
+    pub enabled: bool,
+}
+"#
+    );
+}
+
+#[test]
+fn test_rust_struct_names_and_untyped_values() {
+    let a = yaml!("app: {image: {tag: v1}}\nsidecar: {image: {tag: v2}}\nlabels: {app-name: a, app_name: b}\nextra: ~\nvalue: 1\n2fa: true");
+    let b = yaml!("app: {image: {tag: v1}}\nsidecar: {image: {tag: v2}}\nlabels: {app-name: a, app_name: b}\nextra: ~\nvalue: one\n2fa: false");
+    let code = rust_structs(None, &[Some(&a), Some(&b)], &DiffOptions::default(), "Config").unwrap();
+    assert!(code.contains("pub struct Config {"));
+    assert!(code.contains("pub struct Image {") && code.contains("pub struct SidecarImage {"));
+    assert!(code.contains("    pub app_name: String,\n    #[serde(rename = \"app_name\")]\n    pub app_name_2: String,\n"));
+    assert!(code.contains("    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    pub extra: Option<serde_yaml::Value>,\n"));
+    assert!(code.contains("    pub value: serde_yaml::Value,\n"));
+    assert!(code.contains("    #[serde(rename = \"2fa\")]\n    pub _2fa: bool,\n"));
+
+    assert!(rust_structs(Some(&yaml!("[1]")), &[], &DiffOptions::default(), "Values").is_err());
+    assert_eq!(
+        rust_structs(None, &[None], &DiffOptions::default(), "Values").unwrap(),
+        "use serde::{Deserialize, Serialize};\n\n#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]\npub struct Values {\n}\n"
+    );
+}
@@ -0,0 +1,44 @@
+use yabe::comments::DocComments;
+use yabe::output::{to_yaml_string, write_yaml_with_comments};
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+fn write(doc: &Yaml, docs: &DocComments) -> String {
+    let mut out = Vec::new();
+    write_yaml_with_comments(doc, docs, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_doc_comments_from_yaml() {
+    let docs = DocComments::from_yaml(&yaml!("a.b: Nested.\n'c\\.d': Dotted key.")).unwrap();
+    assert_eq!(docs.get(&["a".to_string(), "b".to_string()]), Some("Nested."));
+    assert_eq!(docs.get(&["c.d".to_string()]), Some("Dotted key."));
+    assert_eq!(docs.get(&["a".to_string()]), None);
+
+    assert_eq!(DocComments::from_yaml(&Yaml::Null), Ok(DocComments::default()));
+    assert!(DocComments::from_yaml(&yaml!("a: 1")).is_err());
+    assert!(DocComments::from_yaml(&yaml!("a[0]: Element.")).is_err());
+    assert!(DocComments::from_yaml(&yaml!("[a]")).is_err());
+}
+
+#[test]
+fn test_comments_are_written_above_their_keys() {
+    let doc = yaml!("a:\n  b: 1\n  'x: y': 2\nlist: [1, 2]\ntext: \"line\\nmore\\n\"");
+    let docs = DocComments::from_yaml(&yaml!("a: |\n  Section.\n\n  Second paragraph.\n'a.x: y': Quoted key.\nlist: List.\ntext: Block.")).unwrap();
+    assert_eq!(
+        write(&doc, &docs),
+        "# Section.\n#\n# Second paragraph.\na:\n  b: 1\n  # Quoted key.\n  \"x: y\": 2\n# List.\nlist:\n  - 1\n  - 2\n# Block.\ntext: |\n  line\n  more\n"
+    );
+    assert_eq!(write(&doc, &DocComments::default()), to_yaml_string(&doc).unwrap());
+}
+
+#[test]
+fn test_comments_read_back_as_the_same_document() {
+    let doc = yaml!("image:\n  tag: v1\n  pull: Always\nreplicas: 2");
+    let docs = DocComments::from_yaml(&yaml!("image.tag: Tag.\nreplicas: Count.\nimage.missing: Gone.")).unwrap();
+    let written = write(&doc, &docs);
+    assert!(written.contains("  # Tag.\n  tag: v1\n"));
+    assert_eq!(yaml!(&written), doc);
+    assert_eq!(docs.unmatched(&doc), vec!["image.missing"]);
+}
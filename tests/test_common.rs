@@ -13,7 +13,7 @@ fn test_empty_documents() {
     assert!(diff.is_none());
 
     let objs = vec![&yaml1, &yaml2];
-    let (base, diffs) = diff_and_common_multiple(&objs, 0.51);
+    let (base, diffs) = diff_and_common_multiple(&objs, 0.51).into_parts();
 
     assert!(base.is_some());
     assert!(deep_equal(base.as_ref().unwrap().as_ref(), &Yaml::Null));
@@ -26,7 +26,7 @@ fn test_different_types_same_key() {
     let yaml2 = YamlLoader::load_from_str("key:\n  subkey: value").unwrap().into_iter().next().unwrap();
 
     let objs = vec![&yaml1, &yaml2];
-    let (base, diffs) = diff_and_common_multiple(&objs, 0.51);
+    let (base, diffs) = diff_and_common_multiple(&objs, 0.51).into_parts();
 
     assert!(base.is_none());
 
@@ -52,7 +52,7 @@ fn test_different_types_same_key() {
 //     let objs = vec![&yaml1, &yaml2, &yaml3];
 
 //     let quorum_percentage = 0.66; // 66%
-//     let (base, diffs) = diff_and_common_multiple(&objs, quorum_percentage);
+//     let (base, diffs) = diff_and_common_multiple(&objs, quorum_percentage).into_parts();
 //     assert!(base.is_none());
 
 //     // Expected diffs
@@ -81,7 +81,7 @@ fn test_quorum_base_determination() {
     let objs = vec![&yaml1, &yaml2, &yaml3];
 
     let quorum_percentage = 0.66; // 66%
-    let (base, diffs) = diff_and_common_multiple(&objs, quorum_percentage);
+    let (base, diffs) = diff_and_common_multiple(&objs, quorum_percentage).into_parts();
 
     assert!(base.is_some());
     let expected_base = YamlLoader::load_from_str("key: value1").unwrap().into_iter().next().unwrap();
@@ -101,7 +101,7 @@ fn test_recursive_diff_with_nested_structures() {
     let objs = vec![&yaml1, &yaml2, &yaml3];
 
     let quorum_percentage = 0.51;
-    let (base, diffs) = diff_and_common_multiple(&objs, quorum_percentage);
+    let (base, diffs) = diff_and_common_multiple(&objs, quorum_percentage).into_parts();
 
     // Expected base
     let expected_base_str = "a:\n  b:\n    c: 1";
@@ -0,0 +1,139 @@
+use std::fs;
+
+use yabe::compare::{compare_dirs, compare_dirs_with_lines, compare_docs, comparison_report, context_keys, line_diffs, FileChange};
+use yabe::deep_equal::deep_equal;
+use yabe::options::DiffOptions;
+use yabe::yaml;
+
+mod common;
+use common::temp_dir;
+
+#[test]
+fn test_compare_docs() {
+    let before = yaml!("image: {tag: v1}\nreplicas: 1\ningress: {enabled: true}\n");
+    let after = yaml!("image: {tag: v2}\nreplicas: 1\nregion: eu\n");
+
+    let change = compare_docs(&before, &after, &DiffOptions::default());
+    let FileChange::Changed { diff, removed } = &change else {
+        panic!("expected a change, got {:?}", change);
+    };
+    assert!(deep_equal(diff.as_ref().unwrap(), &yaml!("image: {tag: v2}\nregion: eu\n")));
+    assert_eq!(removed, &["ingress"]);
+    assert_eq!(change.changed_paths(), ["image.tag", "region", "ingress"]);
+
+    assert_eq!(compare_docs(&before, &before, &DiffOptions::default()), FileChange::Unchanged);
+}
+
+#[test]
+fn test_compare_dirs() {
+    let dir = temp_dir("compare-dirs");
+    let (before, after) = (dir.join("before"), dir.join("after"));
+    fs::create_dir_all(before.join("prod")).unwrap();
+    fs::create_dir_all(after.join("prod")).unwrap();
+    fs::write(before.join("prod/app.yaml"), "tag: v1\n").unwrap();
+    fs::write(after.join("prod/app.yaml"), "tag: v2\n").unwrap();
+    fs::write(before.join("dev.yml"), "tag: v1\n").unwrap();
+    fs::write(after.join("dev.yaml"), "tag: v1\n").unwrap();
+    fs::write(before.join("old.yaml"), "a: 1\n").unwrap();
+    fs::write(after.join("new.yaml"), "a: 1\n").unwrap();
+    fs::write(after.join("README.md"), "not values\n").unwrap();
+
+    let comparisons = compare_dirs(&before, &after, &DiffOptions::default()).unwrap();
+    let summary: Vec<(&str, &FileChange)> = comparisons.iter().map(|c| (c.name.as_str(), &c.change)).collect();
+    assert_eq!(summary[0], ("dev.yml -> dev.yaml", &FileChange::Unchanged));
+    assert_eq!(summary[1], ("new.yaml", &FileChange::Added));
+    assert_eq!(summary[2], ("old.yaml", &FileChange::Removed));
+    assert_eq!(summary[3].0, "prod/app.yaml");
+    assert_eq!(comparisons.len(), 4);
+
+    let report = comparison_report(&comparisons);
+    assert_eq!(report["files"]["prod/app.yaml"]["status"].as_str(), Some("changed"));
+    assert!(deep_equal(&report["files"]["prod/app.yaml"]["diff"], &yaml!("tag: v2")));
+    assert!(deep_equal(
+        &report["summary"],
+        &yaml!("added: 1\nremoved: 1\nchanged: 1\nunchanged: 1\npaths: {tag: 1}")
+    ));
+
+    assert!(compare_dirs(&before, &dir.join("missing"), &DiffOptions::default()).is_err());
+}
+
+#[test]
+fn test_context_keys() {
+    let before = yaml!("image:\n  registry: docker.io\n  repository: app\n  tag: v1\n  pullPolicy: Always\nreplicas: 1\nport: 80\nlabels: {team: a}\n");
+    let after = yaml!("image:\n  registry: docker.io\n  repository: app\n  tag: v2\n  pullPolicy: Always\nreplicas: 1\nlabels: {team: a}\n");
+    let change = compare_docs(&before, &after, &DiffOptions::default());
+
+    let context = context_keys(&before, &after, &change, 1);
+    let paths: Vec<&str> = context.iter().map(|(path, _)| path.as_str()).collect();
+    // The neighbours of image.tag in image, and of the removed port in the before file
+    assert_eq!(paths, ["image.repository", "image.pullPolicy", "replicas", "labels"]);
+    assert_eq!(context[0].1.as_str(), Some("app"));
+    assert!(deep_equal(&context[3].1, &yaml!("team: a")));
+
+    assert_eq!(context_keys(&before, &after, &change, 0), vec![]);
+    assert_eq!(context_keys(&before, &before, &FileChange::Unchanged, 1), vec![]);
+}
+
+#[test]
+fn test_context_skips_changes() {
+    let before = yaml!("a: 1\nb: 1\nc: 1\nd: {e: 1}\n");
+    let after = yaml!("a: 2\nb: 1\nc: 2\nd: {e: 2}\n");
+    let change = compare_docs(&before, &after, &DiffOptions::default());
+    let context = context_keys(&before, &after, &change, 2);
+    let paths: Vec<&str> = context.iter().map(|(path, _)| path.as_str()).collect();
+    assert_eq!(paths, ["b"]);
+}
+
+fn script<S: AsRef<str>>(lines: &[S]) -> String {
+    format!("script: |\n{}", lines.iter().map(|line| format!("  {}\n", line.as_ref())).collect::<String>())
+}
+
+#[test]
+fn test_line_diffs() {
+    let before: Vec<String> = (1..=12).map(|n| format!("step {}", n)).collect();
+    let mut after = before.clone();
+    after[2] = "step three".to_string();
+    after.remove(9);
+    after.insert(0, "set -e".to_string());
+    let before = yaml!(&format!("{}name: app\n", script(&before)));
+    let after = yaml!(&format!("{}name: web\n", script(&after)));
+    let change = compare_docs(&before, &after, &DiffOptions::default());
+
+    // Changes within twice the context share a hunk; the single-line name is left out
+    let lines = line_diffs(&before, &after, &change, 2);
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].0, "script");
+    assert_eq!(
+        lines[0].1,
+        [
+            "@@ -1,5 +1,6 @@", "+set -e", " step 1", " step 2", "-step 3", "+step three", " step 4", " step 5",
+            "@@ -8,5 +9,4 @@", " step 8", " step 9", "-step 10", " step 11", " step 12",
+        ]
+    );
+    assert_eq!(line_diffs(&before, &after, &change, 3)[0].1.iter().filter(|line| line.starts_with("@@")).count(), 1);
+
+    // Only the final newline changed: an empty last line goes away
+    let before = yaml!("script: \"a\\nb\\n\"");
+    let after = yaml!("script: \"a\\nb\"");
+    let change = compare_docs(&before, &after, &DiffOptions::default());
+    assert_eq!(line_diffs(&before, &after, &change, 0)[0].1, ["@@ -3 +2,0 @@", "-"]);
+    assert_eq!(line_diffs(&before, &before, &FileChange::Unchanged, 1), vec![]);
+}
+
+#[test]
+fn test_line_diffs_in_the_report() {
+    let dir = temp_dir("compare-lines");
+    let (before, after) = (dir.join("before"), dir.join("after"));
+    fs::create_dir_all(&before).unwrap();
+    fs::create_dir_all(&after).unwrap();
+    fs::write(before.join("app.yaml"), script(&["set -e", "echo v1"])).unwrap();
+    fs::write(after.join("app.yaml"), script(&["set -e", "echo v2"])).unwrap();
+
+    let comparisons = compare_dirs_with_lines(&before, &after, &DiffOptions::default(), 0, Some(1)).unwrap();
+    let report = comparison_report(&comparisons);
+    assert!(deep_equal(
+        &report["files"]["app.yaml"]["lines"],
+        &yaml!("script: ['@@ -1,3 +1,3 @@', ' set -e', '-echo v1', '+echo v2', ' ']")
+    ));
+    assert!(compare_dirs(&before, &after, &DiffOptions::default()).unwrap()[0].lines.is_empty());
+}
@@ -1,4 +1,4 @@
-use yabe::deep_equal::deep_equal;
+use yabe::deep_equal::{deep_equal, deep_equal_unordered};
 use yaml_rust2::{YamlLoader, Yaml};
 
 #[test]
@@ -76,4 +76,13 @@ fn test_deep_equal_arrays_nested() {
 
     assert!(deep_equal(&yaml1, &yaml2));
     assert!(!deep_equal(&yaml1, &yaml3));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_deep_equal_unordered() {
+    let load = |s: &str| YamlLoader::load_from_str(s).unwrap().into_iter().next().unwrap();
+    assert!(deep_equal_unordered(&load("a: [1, [2, 3], {x: [y, z]}]"), &load("a: [{x: [z, y]}, [3, 2], 1]")));
+    assert!(!deep_equal_unordered(&load("[1, 1, 2]"), &load("[1, 2, 2]")));
+    assert!(!deep_equal_unordered(&load("[1, 2]"), &load("[1, 2, 2]")));
+    assert!(!deep_equal_unordered(&load("a: [1]"), &load("b: [1]")));
+}
@@ -0,0 +1,36 @@
+use yabe::denylist::{denied_values, Denylist};
+use yabe::yaml;
+
+#[test]
+fn test_denylist_from_yaml() {
+    let denylist = Denylist::from_yaml(&yaml!("values: [hunter2, 5432, true]\npatterns: ['-----BEGIN .*KEY-----']")).unwrap();
+    assert_eq!(denylist.values, ["hunter2", "5432", "true"]);
+    assert_eq!(denylist.patterns[0].as_str(), "-----BEGIN .*KEY-----");
+    assert!(Denylist::from_yaml(&yaml!("")).unwrap().is_empty());
+    assert!(Denylist::from_yaml(&yaml!("values:")).unwrap().is_empty());
+
+    assert!(Denylist::from_yaml(&yaml!("[hunter2]")).is_err());
+    assert!(Denylist::from_yaml(&yaml!("values: hunter2")).is_err());
+    assert!(Denylist::from_yaml(&yaml!("values: [{a: 1}]")).is_err());
+    assert!(Denylist::from_yaml(&yaml!("patterns: ['(']")).is_err());
+    assert!(Denylist::from_yaml(&yaml!("secrets: [hunter2]")).is_err());
+}
+
+#[test]
+fn test_denied_values() {
+    let denylist = Denylist::from_yaml(&yaml!("values: [hunter2, 5432]\npatterns: ['^AKIA[0-9A-Z]{8}$']")).unwrap();
+    let base = yaml!(
+        "db: {password: hunter2, port: '5432', user: app}\naws: {keys: [AKIA12345678, AKIA1234]}\nreplicas: 5432\ntag: ~"
+    );
+    assert_eq!(
+        denied_values(&base, &denylist),
+        [
+            ("db.password".to_string(), "value #1".to_string()),
+            ("db.port".to_string(), "value #2".to_string()),
+            ("aws.keys[0]".to_string(), "pattern '^AKIA[0-9A-Z]{8}$'".to_string()),
+            ("replicas".to_string(), "value #2".to_string()),
+        ]
+    );
+    assert!(denied_values(&yaml!("db: {user: app}"), &denylist).is_empty());
+    assert!(denied_values(&base, &Denylist::default()).is_empty());
+}
@@ -1,6 +1,8 @@
-use yabe::diff::{compute_diff, diff_and_common_multiple};
+use yabe::diff::{common_base, compute_diff, compute_diff_with_options, diff_and_common_from, diff_and_common_multiple, diff_and_common_named, diff_and_common_with_options, overlay_chain};
 use yabe::deep_equal::deep_equal;
-use yaml_rust2::YamlLoader;
+use yabe::merge::merge_yaml_with_options;
+use yabe::options::{AtomicPath, BaseMode, Bucketing, DiffOptions, MinorityKeys, NullHandling, ValueBuckets};
+use yaml_rust2::{Yaml, YamlLoader};
 
 #[test]
 fn test_compute_diff_identical() {
@@ -72,7 +74,7 @@ fn test_diff_and_common_multiple_identical() {
     let yaml2 = YamlLoader::load_from_str("a: 1\nb: 2").unwrap().into_iter().next().unwrap();
     let objs = vec![&yaml1, &yaml2];
 
-    let (base, diffs) = diff_and_common_multiple(&objs, 0.51);
+    let (base, diffs) = diff_and_common_multiple(&objs, 0.51).into_parts();
 
     assert!(base.is_some());
     let expected_base = YamlLoader::load_from_str("a: 1\nb: 2").unwrap().into_iter().next().unwrap();
@@ -87,7 +89,7 @@ fn test_diff_and_common_multiple_different() {
     let yaml2 = YamlLoader::load_from_str("a: 1\nb: 3").unwrap().into_iter().next().unwrap();
     let objs = vec![&yaml1, &yaml2];
 
-    let (base, diffs) = diff_and_common_multiple(&objs, 0.51);
+    let (base, diffs) = diff_and_common_multiple(&objs, 0.51).into_parts();
 
     assert!(base.is_some());
     let expected_base = YamlLoader::load_from_str("a: 1").unwrap().into_iter().next().unwrap();
@@ -112,7 +114,7 @@ fn test_diff_and_common_multiple_three_files_quorum_66() {
     let objs = vec![&yaml1, &yaml2, &yaml3];
 
     let quorum_percentage = 0.66; // 66%
-    let (base, diffs) = diff_and_common_multiple(&objs, quorum_percentage);
+    let (base, diffs) = diff_and_common_multiple(&objs, quorum_percentage).into_parts();
 
     assert!(base.is_some());
     let expected_base = YamlLoader::load_from_str("a: 1\nb: 2\nc: 3").unwrap().into_iter().next().unwrap();
@@ -161,7 +163,7 @@ fn test_arrays_with_base() {
 
     let objs = vec![&yaml1, &yaml2, &yaml3];
     let quorum_percentage = 0.51;
-    let (base, diffs) = diff_and_common_multiple(&objs, quorum_percentage);
+    let (base, diffs) = diff_and_common_multiple(&objs, quorum_percentage).into_parts();
 
     assert!(base.is_some());
     let expected_base = YamlLoader::load_from_str(
@@ -196,4 +198,503 @@ fn test_arrays_with_base() {
             None => assert!(diff.is_none()),
         }
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_null_handling_inherit() {
+    let yaml1 = YamlLoader::load_from_str("a: 1\nb: 2").unwrap().into_iter().next().unwrap();
+    let yaml2 = YamlLoader::load_from_str("a: 1\nb: 2").unwrap().into_iter().next().unwrap();
+    let yaml3 = YamlLoader::load_from_str("a: 1\nb: null").unwrap().into_iter().next().unwrap();
+    let objs = vec![&yaml1, &yaml2, &yaml3];
+
+    let options = DiffOptions { quorum: 1.0, null_handling: NullHandling::Inherit, ..DiffOptions::default() };
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+
+    let expected_base = YamlLoader::load_from_str("a: 1\nb: 2").unwrap().into_iter().next().unwrap();
+    assert!(deep_equal(base.as_ref().unwrap().as_ref(), &expected_base));
+    assert!(diffs.iter().all(|d| d.is_none()));
+}
+
+#[test]
+fn test_null_handling_inherit_only_nulls() {
+    let yaml1 = YamlLoader::load_from_str("a: 1\nb: null").unwrap().into_iter().next().unwrap();
+    let yaml2 = YamlLoader::load_from_str("a: 1\nb: null").unwrap().into_iter().next().unwrap();
+    let objs = vec![&yaml1, &yaml2];
+
+    let options = DiffOptions { null_handling: NullHandling::Inherit, ..DiffOptions::default() };
+    let (base, _) = diff_and_common_with_options(&objs, &options).into_parts();
+
+    let expected_base = YamlLoader::load_from_str("a: 1").unwrap().into_iter().next().unwrap();
+    assert!(deep_equal(base.as_ref().unwrap().as_ref(), &expected_base));
+}
+
+#[test]
+fn test_null_handling_value_keeps_explicit_null() {
+    let yaml1 = YamlLoader::load_from_str("a: 1\nb: 2").unwrap().into_iter().next().unwrap();
+    let yaml2 = YamlLoader::load_from_str("a: 1\nb: null").unwrap().into_iter().next().unwrap();
+    let objs = vec![&yaml1, &yaml2];
+
+    let absent = diff_and_common_with_options(&objs, &DiffOptions::default()).diffs;
+    let expected_absent = YamlLoader::load_from_str("b: 2").unwrap().into_iter().next().unwrap();
+    assert!(deep_equal(absent[0].as_ref().unwrap().as_ref(), &expected_absent));
+    assert!(absent[1].is_none());
+
+    let options = DiffOptions { null_handling: NullHandling::Value, ..DiffOptions::default() };
+    let (_, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    let expected_diff = YamlLoader::load_from_str("b: null").unwrap().into_iter().next().unwrap();
+    assert!(deep_equal(diffs[1].as_ref().unwrap().as_ref(), &expected_diff));
+}
+
+#[test]
+fn test_compute_diff_null_handling() {
+    let helm_yaml = YamlLoader::load_from_str("a: 1").unwrap().into_iter().next().unwrap();
+    let obj_yaml = YamlLoader::load_from_str("a: null\nb: null").unwrap().into_iter().next().unwrap();
+
+    let diff = compute_diff(&obj_yaml, &helm_yaml).unwrap();
+    let expected_diff = YamlLoader::load_from_str("a: null").unwrap().into_iter().next().unwrap();
+    assert!(deep_equal(&diff, &expected_diff));
+
+    let inherit = DiffOptions { null_handling: NullHandling::Inherit, ..DiffOptions::default() };
+    assert!(compute_diff_with_options(&obj_yaml, &helm_yaml, &inherit).is_none());
+
+    let value = DiffOptions { null_handling: NullHandling::Value, ..DiffOptions::default() };
+    let diff = compute_diff_with_options(&obj_yaml, &helm_yaml, &value).unwrap();
+    assert!(deep_equal(&diff, &obj_yaml));
+}
+
+#[test]
+fn test_common_base_matches_full_extraction() {
+    let yaml1 = YamlLoader::load_from_str("a: 1\nb:\n  c: 2\n  d: [1, 2]\ne: x").unwrap().into_iter().next().unwrap();
+    let yaml2 = YamlLoader::load_from_str("a: 1\nb:\n  c: 3\n  d: [1, 2]\ne: y").unwrap().into_iter().next().unwrap();
+    let yaml3 = YamlLoader::load_from_str("a: 2\nb:\n  c: 2\n  d: [1, 2]\ne: z").unwrap().into_iter().next().unwrap();
+    let objs = vec![&yaml1, &yaml2, &yaml3];
+    let options = DiffOptions::default();
+
+    let (base, _) = diff_and_common_with_options(&objs, &options).into_parts();
+    let only_base = common_base(&objs, &options);
+
+    let expected_base = YamlLoader::load_from_str("a: 1\nb:\n  c: 2\n  d: [1, 2]").unwrap().into_iter().next().unwrap();
+    assert!(deep_equal(only_base.as_ref().unwrap(), &expected_base));
+    assert!(deep_equal(base.as_ref().unwrap().as_ref(), only_base.as_ref().unwrap()));
+
+    let diverging = [&yaml1, &yaml2, &yaml3].map(|y| &y["e"]);
+    assert!(common_base(&diverging, &options).is_none());
+}
+
+#[test]
+fn test_overlay_chain() {
+    let base = YamlLoader::load_from_str("replicas: 1\nimage:\n  tag: v1\nregion: us").unwrap().into_iter().next().unwrap();
+    let staging = YamlLoader::load_from_str("replicas: 2\nimage:\n  tag: v1\nregion: us").unwrap().into_iter().next().unwrap();
+    let staging_eu = YamlLoader::load_from_str("region: eu").unwrap().into_iter().next().unwrap();
+    let canary = YamlLoader::load_from_str("replicas: 2\nimage:\n  tag: v2\nregion: eu").unwrap().into_iter().next().unwrap();
+    let levels = vec![&base, &staging, &staging_eu, &canary];
+
+    let chain = overlay_chain(&levels, &DiffOptions::default());
+
+    let expected = [
+        Some("replicas: 1\nimage:\n  tag: v1\nregion: us"),
+        Some("replicas: 2"),
+        Some("region: eu"),
+        Some("image:\n  tag: v2"),
+    ];
+    assert_eq!(chain.len(), expected.len());
+    for (level, expected) in chain.iter().zip(expected) {
+        let expected = YamlLoader::load_from_str(expected.unwrap()).unwrap().into_iter().next().unwrap();
+        assert!(deep_equal(level.as_ref().unwrap(), &expected));
+    }
+
+    let unchanged = vec![&base, &base];
+    assert!(overlay_chain(&unchanged, &DiffOptions::default())[1].is_none());
+}
+
+#[test]
+fn test_representative_base() {
+    let yaml1 = YamlLoader::load_from_str("x: 1\ny: 1\nz: 1").unwrap().into_iter().next().unwrap();
+    let yaml2 = YamlLoader::load_from_str("x: 1\ny: 2\nz: 2").unwrap().into_iter().next().unwrap();
+    let yaml3 = YamlLoader::load_from_str("x: 2\ny: 2\nz: 1\nextra: 3").unwrap().into_iter().next().unwrap();
+    let objs = vec![&yaml1, &yaml2, &yaml3];
+
+    // The synthetic base x: 1, y: 2, z: 1 matches no input; the first closest one is used
+    let options = DiffOptions { base_mode: BaseMode::Representative, ..DiffOptions::default() };
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    assert!(deep_equal(&base.unwrap(), &yaml1));
+    assert!(diffs[0].is_none());
+
+    let expected2 = YamlLoader::load_from_str("y: 2\nz: 2").unwrap().into_iter().next().unwrap();
+    let expected3 = YamlLoader::load_from_str("x: 2\ny: 2\nextra: 3").unwrap().into_iter().next().unwrap();
+    assert!(deep_equal(diffs[1].as_ref().unwrap(), &expected2));
+    assert!(deep_equal(diffs[2].as_ref().unwrap(), &expected3));
+    assert!(deep_equal(&common_base(&objs, &options).unwrap(), &yaml1));
+}
+
+#[test]
+fn test_representative_base_removes_missing_keys() {
+    let yaml1 = YamlLoader::load_from_str("x: 1\ny: 1\nextra: 1").unwrap().into_iter().next().unwrap();
+    let yaml2 = YamlLoader::load_from_str("x: 1\ny: 2").unwrap().into_iter().next().unwrap();
+    let yaml3 = YamlLoader::load_from_str("x: 2\ny: 1").unwrap().into_iter().next().unwrap();
+    let objs = vec![&yaml1, &yaml2, &yaml3];
+
+    let options = DiffOptions { base_mode: BaseMode::Representative, ..DiffOptions::default() };
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    assert!(deep_equal(&base.unwrap(), &yaml1));
+    let expected2 = YamlLoader::load_from_str("y: 2\nextra: ~").unwrap().into_iter().next().unwrap();
+    assert!(deep_equal(diffs[1].as_ref().unwrap(), &expected2));
+}
+
+#[test]
+fn test_empty_documents_are_ignored() {
+    let yaml1 = YamlLoader::load_from_str("a: 1\nb: 2").unwrap().into_iter().next().unwrap();
+    let yaml2 = YamlLoader::load_from_str("a: 1\nb: 3").unwrap().into_iter().next().unwrap();
+    let empty = YamlLoader::load_from_str("").unwrap().into_iter().next().unwrap_or(Yaml::Null);
+    let objs = vec![&yaml1, &empty, &yaml2, &Yaml::Null];
+
+    // Two of the four inputs agree, which is a quorum of the two non-empty ones
+    let options = DiffOptions { quorum: 1.0, ..DiffOptions::default() };
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    let expected_base = YamlLoader::load_from_str("a: 1").unwrap().into_iter().next().unwrap();
+    assert!(deep_equal(&base.unwrap(), &expected_base));
+    assert_eq!(diffs.len(), 4);
+    assert!(diffs[1].is_none());
+    assert!(diffs[3].is_none());
+    let expected = YamlLoader::load_from_str("b: 3").unwrap().into_iter().next().unwrap();
+    assert!(deep_equal(diffs[2].as_ref().unwrap(), &expected));
+    assert!(deep_equal(&common_base(&objs, &options).unwrap(), &expected_base));
+}
+
+fn minority_example() -> Vec<Yaml> {
+    ["tls:\n  enabled: true\n  port: 443\nname: a", "tls:\n  enabled: true\n  port: 8443\nname: a", "name: a"]
+        .iter()
+        .map(|s| YamlLoader::load_from_str(s).unwrap().into_iter().next().unwrap())
+        .collect()
+}
+
+fn with_minority_keys(minority_keys: MinorityKeys) -> DiffOptions {
+    DiffOptions { minority_keys, ..DiffOptions::default() }
+}
+
+#[test]
+fn test_minority_keys_kept_in_diff() {
+    let docs = minority_example();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+
+    let (base, diffs) = diff_and_common_with_options(&objs, &with_minority_keys(MinorityKeys::KeepInDiff)).into_parts();
+
+    assert!(base.unwrap()["tls"].is_badvalue());
+    assert!(deep_equal(&diffs[0].as_ref().unwrap()["tls"], &docs[0]["tls"]));
+    assert!(diffs[2].is_none());
+}
+
+#[test]
+fn test_minority_keys_hoisted() {
+    let docs = minority_example();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+    let options = with_minority_keys(MinorityKeys::Hoist);
+
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+
+    let base = base.unwrap();
+    let expected = YamlLoader::load_from_str("enabled: true").unwrap().remove(0);
+    assert!(deep_equal(&base["tls"], &expected));
+    assert_eq!(diffs[0].as_ref().unwrap()["tls"]["port"].as_i64(), Some(443));
+    assert_eq!(diffs[1].as_ref().unwrap()["tls"]["port"].as_i64(), Some(8443));
+    assert!(diffs[2].as_ref().unwrap()["tls"].is_null());
+
+    let rebuilt = merge_yaml_with_options(&base, diffs[0].as_ref().unwrap(), &options);
+    assert!(deep_equal(&rebuilt, &docs[0]));
+}
+
+#[test]
+fn test_minority_keys_hoisted_if_all_present_agree() {
+    let mut docs = minority_example();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+    let options = with_minority_keys(MinorityKeys::HoistIfAllPresentAgree);
+
+    let (base, _) = diff_and_common_with_options(&objs, &options).into_parts();
+    assert!(base.unwrap()["tls"].is_badvalue());
+
+    docs[1] = docs[0].clone();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+
+    assert!(deep_equal(&base.unwrap()["tls"], &docs[0]["tls"]));
+    assert!(diffs[0].is_none());
+    assert!(diffs[1].is_none());
+    assert!(diffs[2].as_ref().unwrap()["tls"].is_null());
+}
+
+#[test]
+fn test_diff_and_common_named() {
+    let a = YamlLoader::load_from_str("image: nginx\nreplicas: 1").unwrap().remove(0);
+    let b = YamlLoader::load_from_str("image: nginx\nreplicas: 3").unwrap().remove(0);
+    let c = YamlLoader::load_from_str("image: nginx\nreplicas: 1").unwrap().remove(0);
+    let inputs = [("prod".to_string(), &b), ("dev".to_string(), &a), ("qa".to_string(), &c)];
+
+    let (base, diffs) = diff_and_common_named(&inputs, &DiffOptions::default());
+    assert_eq!(base.unwrap()["replicas"], Yaml::Integer(1));
+    assert_eq!(diffs.keys().collect::<Vec<_>>(), ["prod", "dev", "qa"]);
+    assert_eq!(diffs["prod"].as_ref().unwrap()["replicas"], Yaml::Integer(3));
+    assert!(diffs["dev"].is_none() && diffs["qa"].is_none());
+}
+
+#[test]
+fn test_hoist_scalars_only() {
+    let load = |s: &str| YamlLoader::load_from_str(s).unwrap().remove(0);
+    let yaml1 = load("image: {repository: nginx, tag: v1}\nports: [80, 443]\nenv: [{name: A}]");
+    let yaml2 = load("image: {repository: nginx, tag: v2}\nports: [80, 443]\nenv: [{name: A}]");
+    let objs = vec![&yaml1, &yaml2];
+
+    let options = DiffOptions { quorum: 1.0, hoist_scalars_only: true, ..DiffOptions::default() };
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+
+    // The shared scalar inside the map is hoisted, the identical arrays are not
+    let base = base.unwrap();
+    assert!(deep_equal(&base, &load("image: {repository: nginx}")));
+    assert!(deep_equal(diffs[0].as_ref().unwrap(), &load("image: {tag: v1}\nports: [80, 443]\nenv: [{name: A}]")));
+    for (obj, diff) in objs.iter().zip(&diffs) {
+        let rebuilt = merge_yaml_with_options(&base, diff.as_ref().unwrap(), &options);
+        assert!(deep_equal(&rebuilt, obj));
+    }
+
+    let options = DiffOptions { array_strategy: "append-unique".parse().unwrap(), ..options };
+    let (base, _) = diff_and_common_with_options(&objs, &options).into_parts();
+    assert!(base.unwrap()["ports"].is_badvalue());
+}
+
+#[test]
+fn test_min_presence() {
+    let load = |s: &str| YamlLoader::load_from_str(s).unwrap().remove(0);
+    let with_tls = load("name: app\ntls: {enabled: true}");
+    let without_tls = load("name: app");
+    let objs = vec![&with_tls, &with_tls, &without_tls, &without_tls, &without_tls];
+
+    let hoist = DiffOptions { minority_keys: MinorityKeys::Hoist, ..DiffOptions::default() };
+    let (base, _) = diff_and_common_with_options(&objs, &hoist).into_parts();
+    assert!(deep_equal(&base.unwrap(), &load("name: app\ntls: {enabled: true}")));
+
+    // Two agreeing files are too few when three must set the key
+    let options = DiffOptions { min_presence: 3, ..hoist };
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    assert!(deep_equal(&base.unwrap(), &load("name: app")));
+    assert!(deep_equal(diffs[0].as_ref().unwrap(), &load("tls: {enabled: true}")));
+    assert!(diffs[2].is_none());
+}
+
+#[test]
+fn test_null_as_empty() {
+    let load = |s: &str| YamlLoader::load_from_str(s).unwrap().remove(0);
+    let yaml1 = load("labels: {team: a}\nports: [80]\nname: x");
+    let yaml2 = load("labels: {team: a}\nports: [80]\nname: y");
+    let yaml3 = load("labels:\nports:\nname:");
+    let objs = vec![&yaml1, &yaml2, &yaml3];
+
+    // Without the option the nulls conflict with the collections, which stay in the diffs
+    let hoist = DiffOptions { minority_keys: MinorityKeys::Hoist, ..DiffOptions::default() };
+    let (base, diffs) = diff_and_common_with_options(&objs, &hoist).into_parts();
+    assert!(base.is_none());
+    assert!(deep_equal(diffs[0].as_ref().unwrap(), &yaml1));
+
+    // With it they read as `{}` and `[]`; the scalar null still conflicts
+    let options = DiffOptions { null_as_empty: true, ..hoist };
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    assert!(deep_equal(&base.unwrap(), &load("labels: {team: a}\nports: [80]")));
+    assert!(deep_equal(diffs[2].as_ref().unwrap(), &load("labels: {team: ~}\nports: []")));
+
+    // Against a read-only base, a null map inherits the base's entries
+    let helm = load("labels: {team: a}\nports: [80]");
+    let obj = load("labels:\nports:");
+    let diff = compute_diff_with_options(&obj, &helm, &options).unwrap();
+    assert!(deep_equal(&diff, &load("ports: []")));
+}
+
+#[test]
+fn test_extraction_order_is_stable() {
+    let load = |s: &str| YamlLoader::load_from_str(s).unwrap().remove(0);
+    let yaml1 = load("e: 1\nd: 2\nc: 3\nb: 4\na: 5\nf: {z: 1, y: 2, x: 3}");
+    let yaml2 = load("a: 5\nb: 4\nc: 3\nd: 2\ne: 1\nf: {x: 3, y: 2, z: 1}");
+    let objs = vec![&yaml1, &yaml2];
+    let keys = |doc: &Yaml| -> Vec<Yaml> {
+        let hash = doc.as_hash().unwrap();
+        hash.keys().chain(hash[&Yaml::String("f".to_string())].as_hash().unwrap().keys()).cloned().collect()
+    };
+
+    let (base, _) = diff_and_common_with_options(&objs, &DiffOptions::default()).into_parts();
+    let first = keys(&base.unwrap());
+    for _ in 0..20 {
+        let (base, _) = diff_and_common_with_options(&objs, &DiffOptions::default()).into_parts();
+        assert_eq!(keys(&base.unwrap()), first);
+    }
+}
+
+#[test]
+fn test_base_from_contributor_subset() {
+    let load = |s: &str| YamlLoader::load_from_str(s).unwrap().into_iter().next().unwrap();
+    let eu_dev = load("region: eu\ntier: dev\nreplicas: 1");
+    let eu_prod = load("region: eu\ntier: prod\nreplicas: 3");
+    let eu_staging = load("region: eu\ntier: staging\nreplicas: 1");
+    let us_prod = load("region: us\ntier: prod\nreplicas: 3");
+    let empty = Yaml::Null;
+    let objs = vec![&eu_dev, &us_prod, &eu_prod, &empty, &eu_staging];
+    let options = DiffOptions { quorum: 0.6, ..DiffOptions::default() };
+
+    // Two of the three EU files meet the quorum, whatever the US file sets
+    let contributors = [0, 2, 4];
+    let result = diff_and_common_from(&objs, |index| contributors.contains(&index), &options);
+    let base = result.base.as_deref().unwrap();
+    assert!(deep_equal(base, &load("region: eu\nreplicas: 1")));
+    assert!(result.diffs[3].is_none());
+
+    // Contributors get the diffs they would get on their own
+    let eu = vec![&eu_dev, &eu_prod, &eu_staging];
+    let alone = diff_and_common_with_options(&eu, &options);
+    assert_eq!(result.diffs[0], alone.diffs[0]);
+    assert_eq!(result.diffs[2], alone.diffs[1]);
+
+    // Every file, contributing or not, merges back into its original
+    for (obj, diff) in objs.iter().zip(&result.diffs).filter(|(obj, _)| !obj.is_null()) {
+        let merged = merge_yaml_with_options(base, diff.as_deref().unwrap_or(&Yaml::Null), &options);
+        assert!(deep_equal(&merged, obj));
+    }
+    assert!(deep_equal(result.diffs[1].as_deref().unwrap(), &load("region: us\ntier: prod\nreplicas: 3")));
+}
+
+#[test]
+fn test_bucketed_quorum_hoists_an_observed_value() {
+    let load = |s: &str| YamlLoader::load_from_str(s).unwrap().into_iter().next().unwrap();
+    let docs: Vec<Yaml> = ["cpu: 120m\nmem: 1", "cpu: 110m\nmem: 2", "cpu: 110m\nmem: 3", "cpu: 150m\nmem: 4", "cpu: '1'\nmem: 5"]
+        .iter()
+        .map(|s| load(s))
+        .collect();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+
+    // No exact value reaches the quorum of three files
+    let result = diff_and_common_with_options(&objs, &DiffOptions::default());
+    assert!(result.base.is_none());
+
+    let buckets = |path: &str| ValueBuckets::new(path, Bucketing::round(50.0)).unwrap();
+    let options = DiffOptions { value_buckets: vec![buckets("cpu")], ..DiffOptions::default() };
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    // 120m and 110m round to 100m, 150m does not; the base is the most common of them
+    assert_eq!(base.as_deref(), Some(&load("cpu: 110m")));
+    assert_eq!(diffs[0].as_deref(), Some(&docs[0]));
+    assert_eq!(diffs[1].as_deref(), Some(&load("mem: 2")));
+    assert_eq!(diffs[3].as_deref(), Some(&docs[3]));
+    assert_eq!(diffs[4].as_deref(), Some(&docs[4]));
+
+    // Only the matching paths are bucketed
+    let options = DiffOptions { value_buckets: vec![buckets("limits.cpu")], ..DiffOptions::default() };
+    assert!(diff_and_common_with_options(&objs, &options).base.is_none());
+}
+
+#[test]
+fn test_round_buckets() {
+    let load = |s: &str| YamlLoader::load_from_str(s).unwrap().into_iter().next().unwrap();
+    let round = Bucketing::round(0.5);
+    assert_eq!(round.bucket(&load("1.2")), round.bucket(&load("0.9")));
+    assert_eq!(round.bucket(&load("'512Mi'")), Some(Yaml::String("512Mi".to_string())));
+    assert_ne!(round.bucket(&load("'1Gi'")), round.bucket(&load("1")));
+    assert_eq!(round.bucket(&load("v1.2")), None);
+    assert_eq!(round.bucket(&load("'1e3'")), None);
+    assert_eq!(Bucketing::round(0.0).bucket(&load("1")), None);
+
+    let buckets = ValueBuckets::new("resources.*.cpu", round.clone()).unwrap();
+    let path = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+    assert!(buckets.matches(&path(&["resources", "limits", "cpu"])));
+    assert!(!buckets.matches(&path(&["resources", "cpu"])));
+    assert!(ValueBuckets::new("ports[0]", round.clone()).is_err());
+    assert!(ValueBuckets::new("", round).is_err());
+}
+
+#[test]
+fn test_atomic_paths_are_hoisted_or_kept_whole() {
+    let load = |s: &str| YamlLoader::load_from_str(s).unwrap().into_iter().next().unwrap();
+    let docs: Vec<Yaml> = [
+        "replicas: 1\nconfig: {level: info, format: json}",
+        "replicas: 1\nconfig: {level: info, format: json}",
+        "replicas: 1\nconfig: {level: debug, format: json}",
+        "replicas: 1\nconfig: {format: text}",
+    ]
+    .iter()
+    .map(|s| load(s))
+    .collect();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+
+    // Walked into, the format is hoisted on its own
+    let options = DiffOptions { quorum: 0.5, ..DiffOptions::default() };
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    assert_eq!(base.as_deref(), Some(&load("replicas: 1\nconfig: {format: json}")));
+    assert_eq!(diffs[2].as_deref(), Some(&load("config: {level: debug}")));
+
+    // As a whole, the map two files share is hoisted, and the others are kept whole
+    let options = DiffOptions { quorum: 0.5, atomic_paths: vec![AtomicPath::new("config").unwrap()], ..DiffOptions::default() };
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    let base = base.unwrap();
+    assert_eq!(*base, load("replicas: 1\nconfig: {level: info, format: json}"));
+    assert!(diffs[0].is_none());
+    assert_eq!(diffs[2].as_deref(), Some(&load("config: {level: debug, format: json}")));
+    assert_eq!(diffs[3].as_deref(), Some(&load("config: {format: text, level: ~}")));
+    assert_eq!(compute_diff_with_options(&docs[3], &base, &options).as_deref(), diffs[3].as_deref());
+
+    // No map reaching the quorum, none is hoisted
+    let options = DiffOptions { atomic_paths: vec![AtomicPath::new("*").unwrap()], ..DiffOptions::default() };
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    assert_eq!(base.as_deref(), Some(&load("replicas: 1")));
+    assert_eq!(diffs[3].as_deref(), Some(&load("config: {format: text}")));
+
+    // Malformed patterns are rejected rather than matching nothing
+    assert!(AtomicPath::new("secrets.*").unwrap().matches(&["secrets".to_string(), "db".to_string()]));
+    for malformed in ["", "config[0]", "config[level"] {
+        assert!(AtomicPath::new(malformed).is_err(), "{}", malformed);
+    }
+}
+
+#[test]
+fn test_fingerprint_subtrees_give_the_same_diffs() {
+    let load = |s: &str| YamlLoader::load_from_str(s).unwrap().into_iter().next().unwrap();
+    let base = load("replicas: 1\nimage: {repository: nginx, tag: v1}\nports: [{name: http, port: 80}, {name: https, port: 443}]\nlabels: {team: web}");
+    let envs = [
+        "replicas: 1\nimage: {repository: nginx, tag: v1}\nports: [{name: http, port: 80}, {name: https, port: 443}]\nlabels: {team: web}",
+        "labels: {team: web}\nports: [{name: http, port: 80}, {name: https, port: 8443}]\nimage: {tag: v1, repository: nginx}\nreplicas: 1",
+        "replicas: 3\nimage: {repository: nginx, tag: v2}\nports: [{name: http, port: 80}]\nlabels: ~",
+        "image: {repository: nginx, tag: v1, pullPolicy: Always}\nextra: {a: [1, 2]}",
+    ];
+    let fingerprinted = |options: &DiffOptions| DiffOptions { fingerprint_subtrees: true, ..options.clone() };
+    for options in [
+        DiffOptions::default(),
+        DiffOptions { null_handling: NullHandling::Value, ..DiffOptions::default() },
+        DiffOptions { blob_threshold: Some(4), ..DiffOptions::default() },
+        DiffOptions { atomic_paths: vec![AtomicPath::new("image").unwrap()], ..DiffOptions::default() },
+    ] {
+        for env in envs {
+            let env = load(env);
+            assert_eq!(
+                compute_diff_with_options(&env, &base, &fingerprinted(&options)),
+                compute_diff_with_options(&env, &base, &options)
+            );
+        }
+    }
+    assert!(compute_diff_with_options(&load(envs[1]), &base, &fingerprinted(&DiffOptions::default())).is_some());
+    assert!(compute_diff_with_options(&load(envs[0]), &base, &fingerprinted(&DiffOptions::default())).is_none());
+}
+
+#[test]
+fn test_quorum_counts_values_as_deep_equal_compares_them() {
+    // The maps inside the arrays differ in key order only, which exact hashing tells apart
+    let yaml1 = YamlLoader::load_from_str("ports: [{name: http, port: 80}]").unwrap().remove(0);
+    let yaml2 = YamlLoader::load_from_str("ports: [{port: 80, name: http}]").unwrap().remove(0);
+    let yaml3 = YamlLoader::load_from_str("ports: [{name: https, port: 443}]").unwrap().remove(0);
+    assert!(deep_equal(&yaml1, &yaml2));
+    assert_ne!(yaml1, yaml2);
+    let objs = vec![&yaml1, &yaml2, &yaml3];
+
+    let (base, diffs) = diff_and_common_multiple(&objs, 0.6).into_parts();
+
+    assert!(deep_equal(base.as_deref().unwrap(), &yaml1));
+    assert!(diffs[0].is_none());
+    assert!(diffs[1].is_none());
+    assert!(deep_equal(diffs[2].as_deref().unwrap(), &yaml3));
+
+    // Values meeting a low quorum alike go by input order
+    let objs = vec![&yaml3, &yaml1, &yaml2];
+    let (base, _) = diff_and_common_multiple(&objs, 0.3).into_parts();
+    assert!(deep_equal(base.as_deref().unwrap(), &yaml3));
+}
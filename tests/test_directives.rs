@@ -0,0 +1,39 @@
+use std::io::Write;
+
+use yabe::directives::{disagreeing_versions, scan_directives, Directives};
+
+#[test]
+fn test_scan_directives() {
+    let directives = scan_directives("%YAML 1.2\n%TAG ! tag:example.com,2024:\n--- # values\na: 1\n...\n--- \nb: 2\n");
+    assert_eq!(directives.version.as_deref(), Some("1.2"));
+    assert!(directives.start_marker);
+    assert!(directives.end_marker);
+
+    // Markers only count at the start of a line, and the end marker only in the first document
+    let directives = scan_directives("a: |\n  ---\n  ...\nb: ---\n---\nc: 1\n...\n");
+    assert_eq!(directives, Directives::default());
+    assert!(directives.is_empty());
+    assert_eq!(directives.header(), "");
+    assert_eq!(directives.footer(), "");
+
+    let directives = scan_directives("# generated\n\n---\na: 1\n");
+    assert_eq!(directives.header(), "---\n");
+    assert!(!directives.end_marker);
+    assert!(!scan_directives("---a: 1\n").start_marker);
+}
+
+#[test]
+fn test_write_around_and_disagreeing_versions() {
+    let directives = scan_directives("%YAML 1.2\na: 1\n...");
+    let mut out = Vec::new();
+    directives.write_around(&mut out, |out| out.write_all(b"a: 1\n")).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "%YAML 1.2\n---\na: 1\n...\n");
+
+    let all: Vec<Directives> = ["%YAML 1.2\n---\na: 1", "%YAML 1.2\n---\na: 2", "%YAML 1.1\n---\na: 3", "a: 4"]
+        .iter()
+        .map(|source| scan_directives(source))
+        .collect();
+    assert_eq!(disagreeing_versions(&all), [2, 3]);
+    assert!(disagreeing_versions(&all[..2]).is_empty());
+    assert!(disagreeing_versions(&[]).is_empty());
+}
@@ -0,0 +1,54 @@
+use yabe::env_file::{env_name, env_vars, EnvVar, NonScalar};
+use yabe::path::parse_path;
+use yabe::yaml;
+
+fn lines(doc: &str, non_scalar: NonScalar) -> Vec<String> {
+    env_vars(&yaml!(doc), non_scalar).unwrap().iter().map(EnvVar::to_line).collect()
+}
+
+#[test]
+fn test_env_names() {
+    let name = |path: &str| env_name(&parse_path(path).unwrap());
+    assert_eq!(name("image.tag"), "IMAGE_TAG");
+    assert_eq!(name("replicaCount"), "REPLICACOUNT");
+    assert_eq!(name("ports[0].name"), "PORTS_0_NAME");
+    assert_eq!(name("matrix[1][0]"), "MATRIX_1_0");
+    assert_eq!(name(r"podAnnotations.app\.kubernetes\.io/name"), "PODANNOTATIONS_APP_KUBERNETES_IO_NAME");
+    assert_eq!(name("1st.key"), "_1ST_KEY");
+    assert_eq!(name("héllo-wörld"), "H_LLO_W_RLD");
+}
+
+#[test]
+fn test_env_values_are_quoted_as_needed() {
+    let doc = "s: plain\nt: 'true'\nn: 3\nf: 1.50\nb: false\nx: null\ne: ''\nspace: a b\n\
+               dollar: $HOME\nq: 'say \"hi\"'\nnl: \"a\\nb\"\nurl: https://example.com/a?b=c";
+    assert_eq!(
+        lines(doc, NonScalar::Error),
+        [
+            "B=false",
+            "DOLLAR=\"\\$HOME\"",
+            "E=\"\"",
+            "F=1.50",
+            "N=3",
+            "NL=\"a\\nb\"",
+            "Q=\"say \\\"hi\\\"\"",
+            "S=plain",
+            "SPACE=\"a b\"",
+            "T=true",
+            "URL=\"https://example.com/a?b=c\"",
+            "X=\"\"",
+        ]
+    );
+}
+
+#[test]
+fn test_env_non_scalar_leaves_and_collisions() {
+    assert!(env_vars(&yaml!("labels: {}"), NonScalar::Error).is_err());
+    assert_eq!(lines("labels: {}\nargs: []", NonScalar::Json), ["ARGS=\"[]\"", "LABELS=\"{}\""]);
+
+    let err = env_vars(&yaml!("a.b: 1\na:\n  b: 2"), NonScalar::Error).unwrap_err();
+    assert!(err.contains("A_B"), "{}", err);
+    assert!(env_vars(&yaml!("a_b: 1\na: {b: 2}"), NonScalar::Error).is_err());
+    assert!(env_vars(&yaml!("[1]"), NonScalar::Error).is_err());
+    assert!(env_vars(&yaml!("{}"), NonScalar::Error).unwrap().is_empty());
+}
@@ -0,0 +1,56 @@
+use yabe::deep_equal::deep_equal;
+use yabe::fingerprint::{fingerprint, fingerprint_hex, SubtreeFingerprints};
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+#[test]
+fn test_equal_values_have_equal_fingerprints() {
+    let pairs = [
+        ("a: 1\nb:\n  c: [1, 2]\n  d: x", "b:\n  d: x\n  c: [1, 2]\na: 1"),
+        ("- a\n- b", "- a\n- b"),
+        ("1.5", "1.5"),
+        ("~", "null"),
+    ];
+    for (left, right) in pairs {
+        let (left, right) = (yaml!(left), yaml!(right));
+        assert!(deep_equal(&left, &right));
+        assert_eq!(fingerprint(&left), fingerprint(&right));
+    }
+}
+
+#[test]
+fn test_different_values_have_different_fingerprints() {
+    let values = ["a: 1", "a: 2", "a: '1'", "b: 1", "[a, b]", "[b, a]", "1", "1.0", "true", "~", "{}", "[]"];
+    let fingerprints: Vec<u64> = values.iter().map(|v| fingerprint(&yaml!(v))).collect();
+    for (i, left) in fingerprints.iter().enumerate() {
+        for right in &fingerprints[i + 1..] {
+            assert_ne!(left, right);
+        }
+    }
+}
+
+#[test]
+fn test_fingerprint_is_stable() {
+    // Fingerprints are persisted as cache keys, so they must not change between releases
+    assert_eq!(fingerprint_hex(&Yaml::Null), "af63e34c8601f871");
+    assert_eq!(fingerprint_hex(&yaml!("a: 1")).len(), 16);
+}
+
+#[test]
+fn test_subtree_fingerprints_match_fingerprints() {
+    let a = yaml!("image: {repository: nginx, tag: v1}\nports: [{port: 80}, {port: 443}]\nreplicas: 2");
+    let b = yaml!("ports: [{port: 80}, {port: 8443}]\nimage: {tag: v1, repository: nginx}");
+    let fingerprints = SubtreeFingerprints::new(&[&a, &b]);
+    for value in [&a, &a["image"], &a["ports"], &a["ports"][1], &b, &b["image"], &b["ports"][0]] {
+        assert_eq!(fingerprints.get(value), Some(fingerprint(value)));
+    }
+    // Scalars and values outside the roots are not kept
+    assert_eq!(fingerprints.get(&a["replicas"]), None);
+    assert_eq!(fingerprints.get(&yaml!("{port: 80}")), None);
+
+    assert!(!fingerprints.differ(&a["image"], &b["image"]));
+    assert!(!fingerprints.differ(&a["ports"][0], &b["ports"][0]));
+    assert!(fingerprints.differ(&a["ports"], &b["ports"]));
+    // Unknown to the fingerprints, nothing is known to differ
+    assert!(!fingerprints.differ(&a["replicas"], &b["image"]));
+}
@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+
+use yabe::deep_equal::deep_equal;
+use yabe::flatten::{flatten, unflatten};
+use yabe::path::{format_path, parse_path, Segment};
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+#[test]
+fn test_flatten() {
+    let doc = yaml!("a:\n  b: 1\n  c: [x, {d: true}]\ne: {}\nf.g: 2\n'h[0]': 3");
+    let flat = flatten(&doc).unwrap();
+
+    let keys: Vec<&str> = flat.keys().map(|k| k.as_str()).collect();
+    assert_eq!(keys, vec!["a.b", "a.c[0]", "a.c[1].d", "e", "f\\.g", "h\\[0]"]);
+    assert_eq!(flat["a.c[1].d"], Yaml::Boolean(true));
+    assert!(deep_equal(&flat["e"], &yaml!("{}")));
+}
+
+#[test]
+fn test_flatten_round_trip() {
+    let docs = [
+        "a:\n  b: 1\n  c: [x, {d: true}, [1, 2]]\ne: {}\nf.g: 2\n'h[0]': 3\n'back\\slash': ~",
+        "items: [a, b, c, d, e, f, g, h, i, j, k, l]",
+        "just a scalar",
+        "{}",
+    ];
+    for doc in docs {
+        let doc = yaml!(doc);
+        let rebuilt = unflatten(&flatten(&doc).unwrap()).unwrap();
+        assert!(deep_equal(&rebuilt, &doc), "round trip failed for {:?}", doc);
+    }
+}
+
+#[test]
+fn test_flatten_key_collision() {
+    let doc = yaml!("1: a\n'1': b");
+    assert!(flatten(&doc).is_err());
+}
+
+#[test]
+fn test_unflatten_conflicts_and_gaps() {
+    let mut flat = BTreeMap::new();
+    flat.insert("a".to_string(), Yaml::Integer(1));
+    flat.insert("a.b".to_string(), Yaml::Integer(2));
+    assert!(unflatten(&flat).is_err());
+
+    let mut flat = BTreeMap::new();
+    flat.insert("a[2]".to_string(), Yaml::Integer(1));
+    assert!(deep_equal(&unflatten(&flat).unwrap(), &yaml!("a: [~, ~, 1]")));
+}
+
+#[test]
+fn test_parse_path() {
+    let segments = vec![
+        Segment::Key("spec".to_string()),
+        Segment::Key("containers".to_string()),
+        Segment::Index(0),
+        Segment::Index(1),
+        Segment::Key("a.b[c]".to_string()),
+    ];
+    assert_eq!(format_path(&segments), "spec.containers[0][1].a\\.b\\[c]");
+    assert_eq!(parse_path(&format_path(&segments)).unwrap(), segments);
+    assert!(parse_path("a[x]").is_err());
+    assert!(parse_path("a[0]b").is_err());
+}
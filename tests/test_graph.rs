@@ -0,0 +1,59 @@
+use yabe::graph::{render_graph, GraphFormat, GraphOptions};
+use yabe::yaml;
+
+fn names() -> Vec<String> {
+    vec!["dev.yaml".to_string(), "prod.yaml".to_string()]
+}
+
+#[test]
+fn test_mermaid_graph() {
+    let base = yaml!("image: {repository: nginx, tag: v1}\nreplicas: 1");
+    let prod = yaml!("image: {tag: v2}\ningress: {enabled: true}");
+    let graph = render_graph(Some(&base), &[None, Some(&prod)], &names(), &GraphOptions::default());
+
+    assert_eq!(
+        graph,
+        "graph LR\n  classDef shared fill:#dbeafe\n  classDef override fill:#fee2e2\n\
+         \x20 n0[\"base\"]:::shared\n\
+         \x20 n1[\"image\"]:::shared\n  n0 --> n1\n\
+         \x20 n2[\"repository = nginx\"]:::shared\n  n1 --> n2\n\
+         \x20 n3[\"tag = v1\"]:::shared\n  n1 --> n3\n\
+         \x20 n4[\"replicas = 1\"]:::shared\n  n0 --> n4\n\
+         \x20 n5[\"tag = v2\"]:::override\n  n1 -->|\"prod.yaml\"| n5\n\
+         \x20 n6[\"ingress.enabled = true\"]:::override\n  n0 -->|\"prod.yaml\"| n6\n"
+    );
+}
+
+#[test]
+fn test_dot_graph_with_depth_and_subtree() {
+    let base = yaml!("image: {tag: v1}\nresources: {limits: {cpu: 1, memory: 1Gi}}");
+    let dev = yaml!("resources: {limits: {cpu: 2, memory: 2Gi}}");
+    let prod = yaml!("image: {tag: \"v\\\"2\"}\nresources: {limits: {cpu: 4}}");
+    let options = GraphOptions {
+        format: GraphFormat::Dot,
+        max_depth: Some(1),
+        subtrees: vec!["resources".to_string()],
+    };
+    let graph = render_graph(Some(&base), &[Some(&dev), Some(&prod)], &names(), &options);
+
+    assert_eq!(
+        graph,
+        "digraph yabe {\n  rankdir=LR;\n  node [shape=box, style=filled];\n\
+         \x20 n0 [label=\"base\", fillcolor=\"#dbeafe\"];\n\
+         \x20 n1 [label=\"resources = {1 key}\", fillcolor=\"#dbeafe\"];\n  n0 -> n1;\n\
+         \x20 n2 [label=\"resources: 2 overrides\", fillcolor=\"#fee2e2\"];\n  n0 -> n2 [label=\"dev.yaml\"];\n\
+         \x20 n3 [label=\"resources: 1 override\", fillcolor=\"#fee2e2\"];\n  n0 -> n3 [label=\"prod.yaml\"];\n}\n"
+    );
+
+    // Labels are escaped
+    let options = GraphOptions { format: GraphFormat::Dot, ..GraphOptions::default() };
+    let graph = render_graph(None, &[None, Some(&prod)], &names(), &options);
+    assert!(graph.contains("[label=\"image.tag = v\\\"2\""));
+}
+
+#[test]
+fn test_parse_graph_format() {
+    assert_eq!("dot".parse::<GraphFormat>(), Ok(GraphFormat::Dot));
+    assert_eq!(GraphFormat::Mermaid.to_string(), "mermaid");
+    assert!("svg".parse::<GraphFormat>().is_err());
+}
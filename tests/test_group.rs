@@ -0,0 +1,153 @@
+use yabe::deep_equal::deep_equal;
+use yabe::diff::diff_and_common_with_options;
+use yabe::group::{cluster, consolidate_bases, split_into_groups, Grouping};
+use yabe::merge::apply_diff;
+use yabe::options::DiffOptions;
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+fn environments() -> Vec<Yaml> {
+    [
+        "name: app\nreplicas: 3\nresources: {cpu: 2, mem: 4Gi}\nregion: eu",
+        "name: app\nreplicas: 1\nresources: {cpu: 1, mem: 1Gi}\ndebug: true\nregion: eu",
+        "name: app\nreplicas: 3\nresources: {cpu: 2, mem: 4Gi}\nregion: us",
+        "name: app\nreplicas: 1\nresources: {cpu: 1, mem: 1Gi}\ndebug: true\nregion: us",
+    ]
+    .iter()
+    .map(|s| yaml!(s))
+    .collect()
+}
+
+fn grouping(max_groups: usize) -> Grouping {
+    Grouping::new(max_groups)
+}
+
+#[test]
+fn test_cluster_by_similarity() {
+    let docs = environments();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+    let options = DiffOptions::default();
+
+    assert_eq!(cluster(&objs, &grouping(2), &options), vec![vec![0, 2], vec![1, 3]]);
+    assert_eq!(cluster(&objs, &grouping(3), &options), vec![vec![0, 2], vec![1], vec![3]]);
+    assert_eq!(cluster(&objs, &grouping(4), &options).len(), 4);
+
+    let coarse = Grouping { max_distance: Some(1), ..Grouping::new(4) };
+    assert_eq!(cluster(&objs, &coarse, &options), vec![vec![0, 2], vec![1, 3]]);
+}
+
+#[test]
+fn test_group_bases_rebuild_inputs() {
+    let docs = environments();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+    let options = DiffOptions::default();
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    let diff_values: Vec<Option<&Yaml>> = diffs.iter().map(|diff| diff.as_deref()).collect();
+
+    let groups = split_into_groups(&objs, &diff_values, &grouping(2), &options);
+
+    assert_eq!(groups.groups.len(), 2);
+    assert_eq!(groups.groups[0].name, "group-1");
+    assert!(deep_equal(&groups.groups[0].base, &yaml!("replicas: 3\nresources: {cpu: 2, mem: 4Gi}")));
+    assert!(deep_equal(groups.diffs[0].as_ref().unwrap(), &yaml!("region: eu")));
+    for (i, obj) in objs.iter().enumerate() {
+        let group_base = groups.group_of(i).map(|group| &group.base);
+        let overrides = apply_diff(group_base, groups.diffs[i].as_ref(), &options);
+        let rebuilt = apply_diff(base.as_deref(), overrides.as_ref(), &options).unwrap();
+        assert!(deep_equal(&rebuilt, obj));
+    }
+
+    let names: Vec<String> = ["a", "b", "c", "d"].iter().map(|s| s.to_string()).collect();
+    let expected = yaml!("a: group-1\nb: group-2\nc: group-1\nd: group-2");
+    assert!(deep_equal(&groups.assignment(&names), &expected));
+}
+
+#[test]
+fn test_singletons_have_no_group() {
+    let docs = environments();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+    let options = DiffOptions::default();
+    let (_, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    let diff_values: Vec<Option<&Yaml>> = diffs.iter().map(|diff| diff.as_deref()).collect();
+
+    let groups = split_into_groups(&objs, &diff_values, &grouping(3), &options);
+
+    assert_eq!(groups.groups.len(), 1);
+    assert!(groups.group_of(1).is_none());
+    assert!(deep_equal(groups.diffs[1].as_ref().unwrap(), diff_values[1].unwrap()));
+}
+
+#[test]
+fn test_consolidated_bases_recover_originals() {
+    let bases = [
+        yaml!("image: nginx\nreplicas: 3\nregion: eu"),
+        yaml!("image: nginx\nreplicas: 3\nregion: us"),
+        yaml!("image: nginx\nreplicas: 1\nregion: ap"),
+    ];
+    let refs: Vec<&Yaml> = bases.iter().collect();
+    let options = DiffOptions::default();
+
+    let result = consolidate_bases(&refs, &options);
+    assert!(deep_equal(result.base.as_deref().unwrap(), &yaml!("image: nginx\nreplicas: 3")));
+    assert!(deep_equal(result.diffs[2].as_deref().unwrap(), &yaml!("replicas: 1\nregion: ap")));
+    for (base, residual) in bases.iter().zip(&result.diffs) {
+        let recovered = apply_diff(result.base.as_deref(), residual.as_deref(), &options).unwrap();
+        assert!(deep_equal(&recovered, base));
+    }
+}
+
+#[test]
+fn test_consolidated_group_bases_fit_between_levels() {
+    let docs = environments();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+    // Only the name is shared by a quorum, so every file keeps overrides and joins a group
+    let options = DiffOptions { quorum: 0.75, ..DiffOptions::default() };
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    let diff_values: Vec<Option<&Yaml>> = diffs.iter().map(|diff| diff.as_deref()).collect();
+    let groups = split_into_groups(&objs, &diff_values, &grouping(2), &options);
+
+    let consolidated = groups.consolidated_bases(&options);
+    assert_eq!(consolidated.names, ["group-1", "group-2"]);
+    for (i, obj) in objs.iter().enumerate() {
+        let group = groups.group_of(i).unwrap();
+        let residual = consolidated.diff(&group.name);
+        let group_base = apply_diff(consolidated.base.as_deref(), residual, &options);
+        let overrides = apply_diff(group_base.as_ref(), groups.diffs[i].as_ref(), &options);
+        let rebuilt = apply_diff(base.as_deref(), overrides.as_ref(), &options).unwrap();
+        assert!(deep_equal(&rebuilt, obj));
+    }
+}
+
+#[test]
+fn test_group_limits_merge_small_groups_back() {
+    let docs: Vec<Yaml> = [
+        "name: app\nid: 1\na1: 1\na2: 1\na3: 1",
+        "name: app\nid: 2\nb1: 1\nb2: 1",
+        "name: app\nid: 3\na1: 1\na2: 1\na3: 1",
+        "name: app\nid: 4\nc1: 1",
+        "name: app\nid: 5\nb1: 1\nb2: 1",
+        "name: app\nid: 6\na1: 1\na2: 1\na3: 1",
+        "name: app\nid: 7\nc1: 1",
+    ]
+    .iter()
+    .map(|s| yaml!(s))
+    .collect();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+    let options = DiffOptions::default();
+    let (_, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    let diff_values: Vec<Option<&Yaml>> = diffs.iter().map(|diff| diff.as_deref()).collect();
+    let members = |grouping: &Grouping| -> Vec<Vec<usize>> {
+        let groups = split_into_groups(&objs, &diff_values, grouping, &options);
+        groups.groups.into_iter().map(|group| group.members).collect()
+    };
+
+    assert_eq!(members(&grouping(3)), [vec![0, 2, 5], vec![1, 4], vec![3, 6]]);
+    assert_eq!(members(&Grouping { min_size: 3, ..grouping(3) }), [vec![0, 2, 5]]);
+    // Of the two groups of two, the one whose base holds fewer leaves goes first
+    assert_eq!(members(&Grouping { max_bases: Some(2), ..grouping(3) }), [vec![0, 2, 5], vec![1, 4]]);
+    assert_eq!(members(&Grouping { max_bases: Some(1), ..grouping(3) }), [vec![0, 2, 5]]);
+
+    let groups = split_into_groups(&objs, &diff_values, &Grouping { max_bases: Some(1), ..grouping(3) }, &options);
+    assert_eq!(groups.groups[0].name, "group-1");
+    assert_eq!(groups.diffs[3].as_ref(), diff_values[3]);
+}
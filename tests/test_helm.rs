@@ -0,0 +1,135 @@
+#![cfg(feature = "helm")]
+
+use std::fs;
+use std::path::Path;
+
+use yabe::deep_equal::deep_equal;
+use yabe::diff::compute_diff;
+use yabe::helm::{chart_defaults, coalesce, umbrella_values, Umbrella};
+use yabe::options::{ArrayStrategy, DiffOptions};
+use yabe::yaml;
+
+mod common;
+use common::temp_dir;
+
+fn write_chart(dir: &Path, chart: &str, values: Option<&str>) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join("Chart.yaml"), chart).unwrap();
+    if let Some(values) = values {
+        fs::write(dir.join("values.yaml"), values).unwrap();
+    }
+}
+
+#[test]
+fn test_chart_defaults_scope_subcharts() {
+    let dir = temp_dir("helm-scope");
+    write_chart(
+        &dir,
+        "apiVersion: v2\nname: app\ndependencies:\n  - name: redis\n    alias: cache\n  - name: postgresql\n",
+        Some("replicas: 1\ncache:\n  auth:\n    enabled: false\nglobal:\n  region: eu\n"),
+    );
+    write_chart(
+        &dir.join("charts/redis"),
+        "apiVersion: v2\nname: redis\n",
+        Some("auth:\n  enabled: true\n  password: ~\nport: 6379\nglobal:\n  region: us\n  registry: docker.io\n"),
+    );
+    write_chart(&dir.join("charts/postgresql"), "apiVersion: v2\nname: postgresql\n", Some("port: 5432\n"));
+    fs::write(dir.join("charts/nginx-1.0.0.tgz"), "").unwrap();
+
+    let defaults = chart_defaults(&dir).unwrap();
+    let expected = yaml!(
+        "replicas: 1\n\
+         cache:\n  auth:\n    enabled: false\n    password: ~\n  port: 6379\n\
+         postgresql:\n  port: 5432\n\
+         global:\n  region: eu\n  registry: docker.io\n"
+    );
+    assert!(deep_equal(&defaults, &expected));
+
+    // An override of a subchart value is scoped under its alias
+    let overrides = yaml!("replicas: 1\ncache:\n  port: 6380\n");
+    let merged = coalesce(&overrides, &defaults);
+    let diff = compute_diff(&merged, &defaults).unwrap();
+    assert!(deep_equal(&diff, &yaml!("cache:\n  port: 6380\n")));
+}
+
+#[test]
+fn test_chart_defaults_nested_subcharts() {
+    let dir = temp_dir("helm-nested");
+    write_chart(&dir, "apiVersion: v2\nname: app\n", None);
+    write_chart(&dir.join("charts/api"), "apiVersion: v2\nname: api\n", Some("image: api:1\n"));
+    write_chart(&dir.join("charts/api/charts/db"), "apiVersion: v2\nname: db\n", Some("size: 1Gi\n"));
+
+    let defaults = chart_defaults(&dir).unwrap();
+    assert!(deep_equal(&defaults, &yaml!("api:\n  image: api:1\n  db:\n    size: 1Gi\n")));
+}
+
+#[test]
+fn test_chart_defaults_requires_a_chart() {
+    let dir = temp_dir("helm-missing");
+    assert!(chart_defaults(&dir).is_err());
+
+    write_chart(&dir, "apiVersion: v2\nname: app\n", Some("- not a map\n"));
+    assert!(chart_defaults(&dir).is_err());
+}
+
+#[test]
+fn test_coalesce_null_removes_default() {
+    let defaults = yaml!("a: 1\nb:\n  c: 2\n  d: 3\n");
+    let values = yaml!("a: ~\nb:\n  d: 4\ne: 5\n");
+    assert!(deep_equal(&coalesce(&values, &defaults), &yaml!("b:\n  c: 2\n  d: 4\ne: 5\n")));
+}
+
+#[test]
+fn test_umbrella_values_nest_diffs_under_subcharts() {
+    let umbrella = Umbrella::from_yaml(&yaml!("subcharts: {api.yaml: api, worker.yaml: worker}\ntop_level: [ingress]")).unwrap();
+    assert_eq!(umbrella.subchart_of("worker.yaml"), Some("worker"));
+    assert!(umbrella.is_top_level("global") && umbrella.is_top_level("ingress") && !umbrella.is_top_level("replicas"));
+
+    let names = ["api.yaml".to_string(), "worker.yaml".to_string()];
+    let base = yaml!("image: {registry: ghcr.io}\nglobal: {domain: example.com, region: eu}");
+    let api = yaml!("replicas: 3\nglobal: {region: ~}\ningress: {enabled: true, class: nginx}");
+    let worker = yaml!("queue: jobs\nglobal: {region: ~}\ningress: {class: nginx, enabled: true}");
+    let options = DiffOptions::default();
+    let values = umbrella_values(Some(&base), &[Some(&api), Some(&worker)], &names, &umbrella, &options).unwrap();
+    let expected = yaml!(
+        "image: {registry: ghcr.io}\nglobal: {domain: example.com, region: ~}\ningress: {enabled: true, class: nginx}\n\
+         api: {replicas: 3}\nworker: {queue: jobs}"
+    );
+    assert!(deep_equal(&values, &expected));
+
+    // A single values file holds one value per top-level path
+    let other = yaml!("ingress: {enabled: false}");
+    let error = umbrella_values(Some(&base), &[Some(&api), Some(&other)], &names, &umbrella, &options).unwrap_err();
+    assert_eq!(error, "worker.yaml sets the top-level ingress.enabled differently from an input before it");
+
+    // An override of the top level applies to every subchart, so every input must set it
+    let partial = yaml!("queue: jobs\nglobal: {region: ~}\ningress: {enabled: true}");
+    let error = umbrella_values(Some(&base), &[Some(&api), Some(&partial)], &names, &umbrella, &options).unwrap_err();
+    assert_eq!(error, "worker.yaml keeps the base value of the top-level ingress.class, which another input overrides for every subchart");
+    let only_api = yaml!("replicas: 3\nglobal: {region: ~}");
+    let error = umbrella_values(Some(&base), &[Some(&only_api), Some(&worker)], &names, &umbrella, &options).unwrap_err();
+    assert_eq!(error, "api.yaml keeps the base value of the top-level ingress, which another input overrides for every subchart");
+    let error = umbrella_values(Some(&base), &[Some(&only_api), Some(&yaml!("queue: jobs"))], &names, &umbrella, &options).unwrap_err();
+    assert_eq!(error, "worker.yaml keeps the base value of the top-level global, which another input overrides for every subchart");
+    assert!(umbrella_values(Some(&base), &[Some(&only_api), None], &names, &umbrella, &options).is_err());
+
+    let clashing = yaml!("api: {replicas: 1}");
+    assert!(umbrella_values(Some(&clashing), &[Some(&api), None], &names, &umbrella, &options).is_err());
+    let unmapped = ["api.yaml".to_string(), "db.yaml".to_string()];
+    assert!(umbrella_values(Some(&base), &[None, None], &unmapped, &umbrella, &options).is_err());
+
+    // Helm reads arrays as plain values
+    let lcs = DiffOptions { array_strategy: ArrayStrategy::Lcs { max_len: 100 }, ..DiffOptions::default() };
+    let error = umbrella_values(Some(&base), &[Some(&api), None], &names, &umbrella, &lcs).unwrap_err();
+    assert_eq!(error, "Helm cannot read the array diffs of the lcs:100 array strategy");
+    let indexed = DiffOptions { slots_patches: true, ..DiffOptions::default() };
+    assert!(umbrella_values(Some(&base), &[Some(&api), None], &names, &umbrella, &indexed).is_err());
+}
+
+#[test]
+fn test_umbrella_mapping_is_validated() {
+    assert!(Umbrella::from_yaml(&yaml!("subcharts: {a.yaml: api, b.yaml: api}")).is_err());
+    assert!(Umbrella::from_yaml(&yaml!("subcharts: [api]")).is_err());
+    assert!(Umbrella::from_yaml(&yaml!("charts: {a.yaml: api}")).is_err());
+    assert!(Umbrella::from_yaml(&yaml!("- a.yaml")).is_err());
+}
@@ -0,0 +1,74 @@
+use yabe::deep_equal::deep_equal;
+use yabe::diff::diff_and_common_with_options;
+use yabe::ignore::{matches_file_glob, parse_ignore_file, strip_ignored, IgnoreRules, KeyPattern};
+use yabe::merge::apply_diff;
+use yabe::options::DiffOptions;
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+#[test]
+fn test_parse_ignore_file() {
+    let rules = parse_ignore_file("# generated files\n*.rendered.yaml\n\n  key: metadata.annotations.* \n").unwrap();
+    assert_eq!(
+        rules,
+        IgnoreRules {
+            file_globs: vec!["*.rendered.yaml".to_string()],
+            key_paths: vec![KeyPattern::new("metadata.annotations.*").unwrap()],
+        }
+    );
+
+    assert_eq!(parse_ignore_file("a.yaml\n!b.yaml").unwrap_err(), "line 2: negated pattern '!b.yaml' is not supported");
+    assert!(parse_ignore_file("key: containers[0].image").unwrap_err().starts_with("line 1: "));
+    assert!(parse_ignore_file("key:").is_err());
+}
+
+#[test]
+fn test_key_patterns() {
+    let keys = |path: &[&str]| path.iter().map(|key| key.to_string()).collect::<Vec<String>>();
+    let pattern = KeyPattern::new("metadata.annotations.*").unwrap();
+    assert!(pattern.matches(&keys(&["metadata", "annotations", "sha"])));
+    assert!(!pattern.matches(&keys(&["metadata", "annotations"])));
+    assert!(!pattern.matches(&keys(&["metadata", "labels", "app"])));
+    assert!(KeyPattern::new("a\\.b").unwrap().matches(&keys(&["a.b"])));
+
+    assert_eq!(KeyPattern::new("").unwrap_err(), "empty key path");
+    assert_eq!(KeyPattern::new("ports[0]").unwrap_err(), "key path 'ports[0]' indexes an array");
+}
+
+#[test]
+fn test_file_globs() {
+    assert!(matches_file_glob("*.yaml", "envs/prod.yaml"));
+    assert!(matches_file_glob("generated", "./charts/generated/values.yaml"));
+    assert!(!matches_file_glob("generated", "charts/generated.yaml"));
+    assert!(matches_file_glob("envs/p?od.yaml", "./envs/prod.yaml"));
+    assert!(!matches_file_glob("envs/*.yaml", "other/envs/prod.yaml"));
+    assert!(matches_file_glob("/envs/*.yaml", "envs/prod.yaml"));
+    assert!(matches_file_glob("charts/**/values.yaml", "charts/values.yaml"));
+    assert!(matches_file_glob("charts/**/values.yaml", "charts/a/b/values.yaml"));
+    assert!(!matches_file_glob("charts/*/values.yaml", "charts/a/b/values.yaml"));
+}
+
+#[test]
+fn test_ignored_paths_stay_in_diffs() {
+    let docs = [
+        yaml!("name: web\nmetadata: {annotations: {sha: abc, team: a}, labels: {app: web}}"),
+        yaml!("name: web\nmetadata: {annotations: {sha: abc, team: a}, labels: {app: web}}"),
+        yaml!("name: web\nmetadata: {labels: {app: web}}"),
+    ];
+    let objs: Vec<&Yaml> = docs.iter().collect();
+    let options = DiffOptions {
+        ignored_paths: vec![KeyPattern::new("metadata.annotations.*").unwrap()],
+        ..DiffOptions::default()
+    };
+
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    assert!(deep_equal(base.as_deref().unwrap(), &yaml!("name: web\nmetadata: {labels: {app: web}}")));
+    assert!(deep_equal(diffs[0].as_deref().unwrap(), &yaml!("metadata: {annotations: {sha: abc, team: a}}")));
+    assert!(diffs[2].is_none());
+    for (obj, diff) in objs.iter().zip(&diffs) {
+        let rebuilt = apply_diff(base.as_deref(), diff.as_deref(), &options).unwrap();
+        assert!(deep_equal(&rebuilt, obj));
+    }
+
+    assert!(deep_equal(&strip_ignored(&docs[0], &options.ignored_paths), &docs[2]));
+}
@@ -0,0 +1,111 @@
+use std::fs;
+
+use yabe::deep_equal::deep_equal;
+use yabe::input::{diff_files, expand_manifest_args, parse_document, partition_excluded};
+use yabe::{yaml, DiffError, DiffOptions};
+
+mod common;
+use common::temp_dir;
+
+#[test]
+fn test_expand_manifest_args() {
+    let dir = temp_dir("manifest");
+    fs::write(dir.join("a.yaml"), "a: 1").unwrap();
+    fs::write(dir.join("b.yaml"), "b: 1").unwrap();
+    let manifest = dir.join("files.txt");
+    fs::write(&manifest, "# environments\nb.yaml\n\n  a.yaml  \n").unwrap();
+
+    let args = vec!["first.yaml".to_string(), format!("@{}", manifest.display())];
+    let files = expand_manifest_args(&args).unwrap();
+
+    assert_eq!(
+        files,
+        vec![
+            "first.yaml".to_string(),
+            dir.join("b.yaml").to_string_lossy().into_owned(),
+            dir.join("a.yaml").to_string_lossy().into_owned(),
+        ]
+    );
+}
+
+#[test]
+fn test_expand_manifest_args_missing_file() {
+    let dir = temp_dir("manifest-missing");
+    let manifest = dir.join("files.txt");
+    fs::write(&manifest, "missing.yaml\n").unwrap();
+
+    let err = expand_manifest_args(&[format!("@{}", manifest.display())]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    assert!(err.to_string().contains("files.txt:1"));
+}
+
+#[test]
+fn test_partition_excluded() {
+    let dir = temp_dir("exclude");
+    for name in ["base.yaml", "a.yaml", "b.yaml"] {
+        fs::write(dir.join(name), "a: 1\n").unwrap();
+    }
+    let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+    let files = vec![path("a.yaml"), path("base.yaml"), path("b.yaml")];
+
+    // The same file under another path is excluded too
+    let excluded = [dir.join("./base.yaml").to_string_lossy().into_owned(), path("missing.yaml")];
+    let (taking_part, left_out, unmatched) = partition_excluded(files, &excluded);
+    assert_eq!(taking_part, [path("a.yaml"), path("b.yaml")]);
+    assert_eq!(left_out, [path("base.yaml")]);
+    assert_eq!(unmatched, [path("missing.yaml")]);
+}
+
+#[test]
+fn test_diff_files() {
+    let dir = temp_dir("diff_files");
+    fs::write(dir.join("prod.yaml"), "replicas: 3\nimage: v1").unwrap();
+    fs::write(dir.join("staging.yaml"), "replicas: 1\nimage: v1").unwrap();
+    let (prod, staging) = (dir.join("prod.yaml"), dir.join("staging.yaml"));
+    let options = DiffOptions { quorum: 1.0, ..DiffOptions::default() };
+
+    let result = diff_files(&[&prod, &staging], &options).unwrap();
+    assert_eq!(result.base.as_deref(), Some(&yaml!("image: v1")));
+    assert_eq!(result.diff(&staging.display().to_string()), Some(&yaml!("replicas: 1")));
+
+    // Errors name the file, and where in it parsing failed
+    fs::write(dir.join("broken.yaml"), "replicas: 3\nimage: [v1\n").unwrap();
+    let broken = dir.join("broken.yaml");
+    let error = diff_files(&[&prod, &broken], &options).unwrap_err();
+    assert!(matches!(error, DiffError::Parse { .. }));
+    assert_eq!(error.path(), broken);
+    assert!(error.to_string().starts_with(&format!("{}:3:1: ", broken.display())), "{}", error);
+
+    let missing = dir.join("missing.yaml");
+    let error = diff_files(&[&prod, &missing], &options).unwrap_err();
+    assert!(matches!(error, DiffError::Read { .. }));
+    assert!(error.to_string().starts_with(&format!("{}: ", missing.display())));
+}
+
+#[test]
+fn test_line_endings_and_bom_do_not_make_inputs_differ() {
+    let lf = "replicas: 2\nname: web\nscript: |\n  set -e\n  run\nmotd: >\n  hello\n  world\nnote: \"a\n  b\"\n";
+    let crlf = lf.replace('\n', "\r\n");
+    let bom = format!("\u{feff}{}", lf);
+    let bom_crlf = format!("\u{feff}{}", crlf);
+    let mixed: String = lf
+        .split_inclusive('\n')
+        .enumerate()
+        .map(|(i, line)| if i % 2 == 0 { line.replace('\n', "\r\n") } else { line.to_string() })
+        .collect();
+    let lone_cr = lf.replace('\n', "\r");
+
+    let expected = parse_document(lf).unwrap();
+    assert_eq!(expected["script"].as_str(), Some("set -e\nrun\n"));
+    for source in [&crlf, &bom, &bom_crlf, &mixed, &lone_cr] {
+        assert_eq!(parse_document(source).unwrap(), expected, "{:?}", source);
+    }
+
+    let dir = temp_dir("line-endings");
+    fs::write(dir.join("unix.yaml"), lf).unwrap();
+    fs::write(dir.join("windows.yaml"), &bom_crlf).unwrap();
+    let result = diff_files(&[&dir.join("unix.yaml"), &dir.join("windows.yaml")], &DiffOptions::default()).unwrap();
+    let (base, diffs) = result.into_parts();
+    assert!(deep_equal(&base.unwrap(), &expected));
+    assert!(diffs.iter().all(Option::is_none));
+}
@@ -0,0 +1,156 @@
+use yabe::deep_equal::deep_equal;
+use yabe::diff::{compute_diff_with_options, diff_and_common_with_options};
+use yabe::lcs::{apply_script, edit_script, matches};
+use yabe::merge::merge_yaml_with_options;
+use yabe::options::{ArrayStrategy, DiffOptions};
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+fn lcs() -> DiffOptions {
+    DiffOptions { array_strategy: "lcs".parse().unwrap(), ..DiffOptions::default() }
+}
+
+fn ints(values: &[i64]) -> Vec<Yaml> {
+    values.iter().map(|v| Yaml::Integer(*v)).collect()
+}
+
+/// Length of a longest common subsequence by dynamic programming.
+fn lcs_len(a: &[Yaml], b: &[Yaml]) -> usize {
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in 0..a.len() {
+        for j in 0..b.len() {
+            table[i + 1][j + 1] = if a[i] == b[j] {
+                table[i][j] + 1
+            } else {
+                table[i][j + 1].max(table[i + 1][j])
+            };
+        }
+    }
+    table[a.len()][b.len()]
+}
+
+/// Deterministic pseudo-random small integers.
+fn sequence(seed: u64, len: usize) -> Vec<Yaml> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            Yaml::Integer(((state >> 33) % 5) as i64)
+        })
+        .collect()
+}
+
+#[test]
+fn test_matches_are_a_longest_common_subsequence() {
+    for seed in 0..50 {
+        let a = sequence(seed, (seed % 9) as usize);
+        let b = sequence(seed + 100, (seed % 7) as usize + 2);
+        let pairs = matches(&a, &b, deep_equal);
+
+        assert_eq!(pairs.len(), lcs_len(&a, &b));
+        assert!(pairs.iter().all(|&(i, j)| a[i] == b[j]));
+        assert!(pairs.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1));
+    }
+}
+
+#[test]
+fn test_edit_script_replays() {
+    for seed in 0..50 {
+        let base = sequence(seed, (seed % 8) as usize);
+        let items = sequence(seed + 7, (seed % 5) as usize);
+        let script = edit_script(&base, &items, deep_equal, |item, _| Some(item.clone()));
+        let replayed = match &script {
+            Some(script) => apply_script(&base, script, |_, diff| diff.clone()),
+            None => base.clone(),
+        };
+        assert_eq!(replayed, items);
+    }
+}
+
+#[test]
+fn test_edit_script_hunks() {
+    let base = ints(&[1, 2, 3, 4, 5]);
+    let items = ints(&[1, 3, 4, 9, 5, 6, 7]);
+    let script = edit_script(&base, &items, deep_equal, |item, _| Some(item.clone())).unwrap();
+
+    let expected = yaml!("$lcs:\n  - {at: 1, delete: 1}\n  - {at: 4, insert: [9]}\n  - {at: 5, insert: [6, 7]}");
+    assert!(deep_equal(&script, &expected));
+    assert!(edit_script(&base, &base, deep_equal, |_, _| None).is_none());
+}
+
+#[test]
+fn test_compute_diff_changes_elements_in_the_middle() {
+    let helm_yaml = yaml!("containers:\n  - {name: init, image: busybox}\n  - {name: app, image: nginx:1}\n  - {name: sidecar, image: envoy}");
+    let obj_yaml = yaml!("containers:\n  - {name: init, image: busybox}\n  - {name: metrics, image: prom}\n  - {name: app, image: nginx:2}\n  - {name: sidecar, image: envoy}");
+    let options = lcs();
+
+    let diff = compute_diff_with_options(&obj_yaml, &helm_yaml, &options).unwrap();
+    let expected = yaml!("containers:\n  $lcs:\n    - {at: 1, delete: 1, insert: [{name: metrics, image: prom}, {name: app, image: nginx:2}]}");
+    assert!(deep_equal(&diff, &expected));
+
+    let rebuilt = merge_yaml_with_options(&helm_yaml, &diff, &options);
+    assert!(deep_equal(&rebuilt, &obj_yaml));
+}
+
+#[test]
+fn test_compute_diff_keeps_element_diffs() {
+    let helm_yaml = yaml!("args: [--a, {port: 80, host: x}, --c]");
+    let obj_yaml = yaml!("args: [--a, {port: 81, host: x}, --c, --d]");
+    let options = lcs();
+
+    let diff = compute_diff_with_options(&obj_yaml, &helm_yaml, &options).unwrap();
+    let expected = yaml!("args:\n  $lcs:\n    - {at: 1, change: [{port: 81}]}\n    - {at: 3, insert: [--d]}");
+    assert!(deep_equal(&diff, &expected));
+
+    let rebuilt = merge_yaml_with_options(&helm_yaml, &diff, &options);
+    assert!(deep_equal(&rebuilt, &obj_yaml));
+}
+
+#[test]
+fn test_lcs_extraction() {
+    let yaml1 = yaml!("args: [--a, --b, --c, --d]");
+    let yaml2 = yaml!("args: [--a, --x, --c, --d]");
+    let yaml3 = yaml!("args: [--a, --c, --d, --y]");
+    let objs = vec![&yaml1, &yaml2, &yaml3];
+    let options = lcs();
+
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+
+    let base = base.unwrap();
+    assert!(deep_equal(&base, &yaml!("args: [--a, --c, --d]")));
+    assert!(deep_equal(diffs[0].as_ref().unwrap(), &yaml!("args:\n  $lcs: [{at: 1, insert: [--b]}]")));
+    for (obj, diff) in objs.iter().zip(&diffs) {
+        let rebuilt = merge_yaml_with_options(&base, diff.as_ref().unwrap(), &options);
+        assert!(deep_equal(&rebuilt, obj));
+    }
+}
+
+#[test]
+fn test_lcs_without_common_elements() {
+    let yaml1 = yaml!("args: [--a]");
+    let yaml2 = yaml!("args: [--b]");
+    let objs = vec![&yaml1, &yaml2];
+
+    let (base, diffs) = diff_and_common_with_options(&objs, &lcs()).into_parts();
+
+    assert!(base.is_none());
+    assert!(deep_equal(diffs[0].as_ref().unwrap(), &yaml1));
+    assert!(deep_equal(diffs[1].as_ref().unwrap(), &yaml2));
+}
+
+#[test]
+fn test_lcs_cap_falls_back_to_positional() {
+    let helm_yaml = yaml!("args: [--a, --b, --c]");
+    let obj_yaml = yaml!("args: [--a, --x, --c]");
+    let options = DiffOptions { array_strategy: ArrayStrategy::Lcs { max_len: 2 }, ..DiffOptions::default() };
+
+    let diff = compute_diff_with_options(&obj_yaml, &helm_yaml, &options).unwrap();
+    assert!(deep_equal(&diff, &yaml!("args: [~, --x, ~]")));
+}
+
+#[test]
+fn test_parse_lcs_strategy() {
+    assert_eq!("lcs:50".parse::<ArrayStrategy>(), Ok(ArrayStrategy::Lcs { max_len: 50 }));
+    assert_eq!(ArrayStrategy::Lcs { max_len: 50 }.to_string(), "lcs:50");
+    assert!("lcs:many".parse::<ArrayStrategy>().is_err());
+}
@@ -0,0 +1,61 @@
+use yabe::deep_equal::deep_equal;
+use yabe::minimize::{minimize_overlays, MinimizeStages};
+use yabe::{yaml, DiffOptions, Yaml};
+
+fn run(base: &Yaml, overlays: &[Yaml], stages: &MinimizeStages) -> (Yaml, Vec<Option<Yaml>>) {
+    let refs: Vec<&Yaml> = overlays.iter().collect();
+    let minimized = minimize_overlays(base, &refs, stages, &DiffOptions::default());
+    (minimized.base, minimized.overlays)
+}
+
+#[test]
+fn test_minimize_overlays() {
+    let base = yaml!("image:\n  repo: app\n  tag: v1\nreplicas: 1\n");
+    let overlays = [
+        yaml!("image:\n  tag: v2\nreplicas: 3\nlogLevel: info\n"),
+        yaml!("image:\n  tag: v2\nreplicas: 1\n"),
+        yaml!(""),
+    ];
+    let (new_base, minimized) = run(&base, &overlays, &MinimizeStages::default());
+    assert!(deep_equal(&new_base, &yaml!("image:\n  repo: app\n  tag: v2\nreplicas: 1\n")));
+    assert!(deep_equal(minimized[0].as_ref().unwrap(), &yaml!("replicas: 3\nlogLevel: info\n")));
+    assert_eq!(minimized[1], None);
+    assert_eq!(minimized[2], None);
+
+    // A second run promotes and removes nothing
+    let again: Vec<Yaml> = minimized.iter().map(|overlay| overlay.clone().unwrap_or(Yaml::Null)).collect();
+    let (second_base, second) = run(&new_base, &again, &MinimizeStages::default());
+    assert!(deep_equal(&second_base, &new_base));
+    assert_eq!(second, minimized);
+}
+
+#[test]
+fn test_minimize_stages() {
+    let base = yaml!("a: 1\nb: 1\n");
+    let overlays = [yaml!("a: 1\nb: 2\n"), yaml!("a: 2\nb: 2\n")];
+
+    let promote_only = MinimizeStages {
+        promote: true,
+        minimize: false,
+    };
+    let (new_base, minimized) = run(&base, &overlays, &promote_only);
+    assert!(deep_equal(&new_base, &yaml!("a: 1\nb: 2\n")));
+    assert_eq!(minimized, overlays.iter().cloned().map(Some).collect::<Vec<_>>());
+
+    let minimize_only = MinimizeStages {
+        promote: false,
+        minimize: true,
+    };
+    let (new_base, minimized) = run(&base, &overlays, &minimize_only);
+    assert!(deep_equal(&new_base, &base));
+    assert!(deep_equal(minimized[0].as_ref().unwrap(), &yaml!("b: 2\n")));
+    assert!(deep_equal(minimized[1].as_ref().unwrap(), &yaml!("a: 2\nb: 2\n")));
+}
+
+#[test]
+fn test_minimize_single_overlay_promotes_nothing() {
+    let base = yaml!("a: 1\n");
+    let (new_base, minimized) = run(&base, &[yaml!("a: 1\nb: 2\n")], &MinimizeStages::default());
+    assert!(deep_equal(&new_base, &base));
+    assert!(deep_equal(minimized[0].as_ref().unwrap(), &yaml!("b: 2\n")));
+}
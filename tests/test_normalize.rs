@@ -0,0 +1,275 @@
+use yabe::deep_equal::deep_equal;
+use std::sync::{Arc, Mutex};
+
+use yabe::diff::{compute_diff_with_options, diff_and_common_with_options};
+use yabe::normalize::{
+    alias_conflicts, alias_renames, canonical_cmp, coerce_scalars, drop_keys, expand_dotted_keys, rename_keys, sort_canonically, transform_leaves,
+};
+use yabe::options::{
+    AliasConflict, CoercedForm, CoercedType, DiffOptions, ExcludedKeys, KeyAliases, LeafTransform, StringCoercion,
+};
+use yabe::path::{join_path, split_path};
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+#[test]
+fn test_split_path_escapes() {
+    assert_eq!(split_path("a.b.c"), vec!["a", "b", "c"]);
+    assert_eq!(split_path(r"a\.b.c"), vec!["a.b", "c"]);
+    assert_eq!(split_path(r"a\\.b"), vec![r"a\", "b"]);
+    assert_eq!(join_path(&["a.b", "c"]), r"a\.b.c");
+    assert_eq!(split_path(&join_path(&[r"x\.y", "z"])), vec![r"x\.y", "z"]);
+}
+
+#[test]
+fn test_expand_dotted_keys() {
+    let doc = yaml!("a.b.c: 1\na:\n  b:\n    d: 2\nx\\.y: 3\nbad..key: 4");
+    let expected = yaml!("a:\n  b:\n    c: 1\n    d: 2\nx.y: 3\nbad..key: 4");
+    assert!(deep_equal(&expand_dotted_keys(&doc), &expected));
+}
+
+#[test]
+fn test_expand_dotted_keys_later_entry_wins() {
+    let doc = yaml!("a:\n  b: 1\na.b: 2");
+    assert!(deep_equal(&expand_dotted_keys(&doc), &yaml!("a:\n  b: 2")));
+}
+
+#[test]
+fn test_dotted_and_nested_inputs_collapse() {
+    let yaml1 = yaml!("image.tag: v1\nreplicas: 2");
+    let yaml2 = yaml!("image:\n  tag: v1\nreplicas: 3");
+    let objs = vec![&yaml1, &yaml2];
+
+    let (base, _) = diff_and_common_with_options(&objs, &DiffOptions::default()).into_parts();
+    assert!(base.is_none());
+
+    let options = DiffOptions { expand_dotted_keys: true, ..DiffOptions::default() };
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    assert!(deep_equal(base.as_ref().unwrap().as_ref(), &yaml!("image:\n  tag: v1")));
+    assert!(deep_equal(diffs[0].as_ref().unwrap().as_ref(), &yaml!("replicas: 2")));
+}
+
+#[test]
+fn test_transform_leaves_sees_paths() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let recorder = Arc::clone(&seen);
+    let transform = LeafTransform::new(move |path, value| {
+        recorder.lock().unwrap().push(path.to_string());
+        value.clone()
+    });
+    let doc = yaml!("a:\n  b.c: 1\nargs: [x, {d: 2}]\nempty: {}");
+
+    let transformed = transform_leaves(&doc, &transform);
+
+    assert!(deep_equal(&transformed, &doc));
+    assert_eq!(*seen.lock().unwrap(), vec![r"a.b\.c", "args[0]", "args[1].d"]);
+}
+
+#[test]
+fn test_leaf_transform_before_quorum() {
+    // Quantities are compared in millicores, so "1" and "1000m" agree
+    let millicores = LeafTransform::new(|path, value| match (path.ends_with(".cpu"), value) {
+        (true, Yaml::String(s)) if s.ends_with('m') => Yaml::Integer(s.trim_end_matches('m').parse().unwrap()),
+        (true, Yaml::String(s)) => Yaml::Integer(s.parse::<i64>().unwrap() * 1000),
+        (true, Yaml::Integer(i)) => Yaml::Integer(i * 1000),
+        _ => value.clone(),
+    });
+    let options = DiffOptions { leaf_transform: Some(millicores), expand_dotted_keys: true, ..DiffOptions::default() };
+    let yaml1 = yaml!("limits.cpu: 1\nname: a");
+    let yaml2 = yaml!("limits:\n  cpu: 1000m\nname: a");
+    let yaml3 = yaml!("limits:\n  cpu: '2'\nname: a");
+    let objs = vec![&yaml1, &yaml2, &yaml3];
+
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+
+    assert!(deep_equal(&base.unwrap(), &yaml!("limits:\n  cpu: 1000\nname: a")));
+    assert!(diffs[0].is_none());
+    assert!(diffs[1].is_none());
+    assert!(deep_equal(diffs[2].as_ref().unwrap(), &yaml!("limits:\n  cpu: 2000")));
+
+    assert!(compute_diff_with_options(&yaml2, &yaml1, &options).is_none());
+}
+
+fn coercion(form: CoercedForm, paths: &[&str]) -> StringCoercion {
+    StringCoercion {
+        types: vec![CoercedType::Int, CoercedType::Float, CoercedType::Bool],
+        form,
+        paths: paths.iter().map(|path| path.to_string()).collect(),
+    }
+}
+
+#[test]
+fn test_coerce_scalars() {
+    let doc = yaml!(
+        "port: \"8080\"\nneg: \"-3\"\nratio: \"0.5\"\ndebug: \"true\"\n\
+         kept: [\"0123\", \"+1\", \"1e3\", \"True\", \"-0\", \"1.\", \"x\"]\n"
+    );
+    let typed = coerce_scalars(&doc, &coercion(CoercedForm::Typed, &[]));
+    let expected = yaml!("port: 8080\nneg: -3\nratio: 0.5\ndebug: true\n");
+    assert!(deep_equal(&typed["port"], &expected["port"]));
+    assert!(deep_equal(&typed["neg"], &expected["neg"]));
+    assert!(deep_equal(&typed["ratio"], &expected["ratio"]));
+    assert!(deep_equal(&typed["debug"], &expected["debug"]));
+    // Strings that do not read back the same are kept
+    assert!(deep_equal(&typed["kept"], &doc["kept"]));
+
+    let doc = yaml!("port: 8080\nratio: 0.5\ndebug: false\nexp: 1e3\n");
+    let strings = coerce_scalars(&doc, &coercion(CoercedForm::String, &[]));
+    assert!(deep_equal(&strings, &yaml!("port: \"8080\"\nratio: \"0.5\"\ndebug: \"false\"\nexp: 1e3\n")));
+}
+
+#[test]
+fn test_coerce_scalars_in_paths() {
+    let doc = yaml!("service:\n  port: \"80\"\n  ports: [\"81\"]\nservices: \"82\"\nversion: \"1.10\"\n");
+    let coerced = coerce_scalars(&doc, &coercion(CoercedForm::Typed, &["service"]));
+    let expected = yaml!("service:\n  port: 80\n  ports: [81]\nservices: \"82\"\nversion: \"1.10\"\n");
+    assert!(deep_equal(&coerced, &expected));
+}
+
+#[test]
+fn test_coerced_values_collapse() {
+    let a = yaml!("port: \"8080\"\ndebug: \"true\"\n");
+    let b = yaml!("port: 8080\ndebug: true\n");
+    let inputs = [&a, &b];
+    let options = DiffOptions {
+        quorum: 1.0,
+        coercion: Some(coercion(CoercedForm::String, &[])),
+        ..DiffOptions::default()
+    };
+
+    let (base, diffs) = diff_and_common_with_options(&inputs, &options).into_parts();
+    assert!(deep_equal(&base.unwrap(), &yaml!("port: \"8080\"\ndebug: \"true\"\n")));
+    assert!(diffs.iter().all(Option::is_none));
+    assert!(compute_diff_with_options(&a, &b, &DiffOptions::default()).is_some());
+}
+
+fn aliases(source: &str, on_conflict: AliasConflict) -> KeyAliases {
+    KeyAliases::from_yaml(&yaml!(source), on_conflict).unwrap()
+}
+
+#[test]
+fn test_rename_keys() {
+    let renamed = aliases("ingress.hosts: ingress.hostnames\nimage: container.image", AliasConflict::PreferNew);
+    let doc = yaml!("ingress: {hosts: [a.com], tls: true}\nimage: {tag: v1}");
+    let expected = yaml!("ingress: {tls: true, hostnames: [a.com]}\ncontainer: {image: {tag: v1}}");
+    assert!(deep_equal(&rename_keys(&doc, &renamed), &expected));
+
+    // Emptied maps are removed, and aliases apply in order
+    let chained = aliases("a.b: c\nc: d.e", AliasConflict::PreferNew);
+    assert!(deep_equal(&rename_keys(&yaml!("a: {b: 1}"), &chained), &yaml!("d: {e: 1}")));
+
+    let invalid = KeyAliases::from_yaml(&yaml!("hosts[0]: host"), AliasConflict::PreferNew);
+    assert!(invalid.unwrap_err().contains("hosts[0]"));
+    assert!(KeyAliases::from_yaml(&yaml!("[a, b]"), AliasConflict::PreferNew).is_err());
+}
+
+#[test]
+fn test_alias_conflicts() {
+    let doc = yaml!("ingress: {hosts: [old.com], hostnames: [new.com]}\nport: 80");
+    let source = "ingress.hosts: ingress.hostnames\nport: service.port";
+
+    let prefer_new = aliases(source, AliasConflict::PreferNew);
+    let conflicts = alias_conflicts(&doc, &prefer_new);
+    assert_eq!(conflicts, [&("ingress.hosts".to_string(), "ingress.hostnames".to_string())]);
+    let expected = yaml!("ingress: {hostnames: [new.com]}\nservice: {port: 80}");
+    assert!(deep_equal(&rename_keys(&doc, &prefer_new), &expected));
+
+    let prefer_old = aliases(source, AliasConflict::PreferOld);
+    let expected = yaml!("ingress: {hostnames: [old.com]}\nservice: {port: 80}");
+    assert!(deep_equal(&rename_keys(&doc, &prefer_old), &expected));
+
+    // A scalar in the way of the new path conflicts too
+    assert_eq!(alias_conflicts(&yaml!("port: 80\nservice: web"), &prefer_new).len(), 1);
+}
+
+#[test]
+fn test_alias_renames_list_the_moves_without_renaming() {
+    let chained = aliases("a.b: c\nc: d.e\nmissing: found", AliasConflict::PreferNew);
+    let doc = yaml!("a: {b: 1}\nd: {e: 2}");
+
+    let renames = alias_renames(&doc, &chained);
+    let moves: Vec<(usize, &str, &str, bool)> =
+        renames.iter().map(|rename| (rename.index, rename.old, rename.new, rename.conflict)).collect();
+    // The second alias moves what the first one moved, onto a path the document sets
+    assert_eq!(moves, [(0, "a.b", "c", false), (1, "c", "d.e", true)]);
+    assert!(alias_renames(&yaml!("other: 1"), &chained).is_empty());
+}
+
+#[test]
+fn test_renamed_keys_collapse() {
+    let old = yaml!("ingress: {hosts: [a.com]}\nname: web");
+    let new = yaml!("ingress: {hostnames: [a.com]}\nname: web");
+    let objs = [&old, &new];
+    let options = DiffOptions {
+        quorum: 1.0,
+        key_aliases: Some(aliases("ingress.hosts: ingress.hostnames", AliasConflict::PreferNew)),
+        ..DiffOptions::default()
+    };
+
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    assert!(deep_equal(base.as_deref().unwrap(), &new));
+    assert!(diffs.iter().all(Option::is_none));
+}
+
+#[test]
+fn test_sort_canonically() {
+    let doc = yaml!("b: [3, x, 1, ~, 2.5, true, [1], {k: v}]\na: [{n: 2, m: 1}, {m: 1, n: 1}]");
+    let sorted = sort_canonically(&doc);
+    assert_eq!(sorted, yaml!("a: [{m: 1, n: 1}, {m: 1, n: 2}]\nb: [~, true, 1, 3, 2.5, x, [1], {k: v}]"));
+    let keys: Vec<&str> = sorted.as_hash().unwrap().keys().filter_map(Yaml::as_str).collect();
+    assert_eq!(keys, ["a", "b"]);
+
+    assert!(canonical_cmp(&yaml!("1.0"), &yaml!("1.00")).is_lt());
+    assert!(canonical_cmp(&yaml!("[1, 2]"), &yaml!("[1, 2, 0]")).is_lt());
+}
+
+#[test]
+fn test_order_insensitive_extraction() {
+    let a = yaml!("env: [A, B]\nports: [{name: http, port: 80}]\nz: 1");
+    let b = yaml!("env: [B, A]\nports: [{port: 80, name: http}]\nz: 2");
+    let objs = [&a, &b];
+
+    let options = DiffOptions { quorum: 1.0, ..DiffOptions::default() };
+    let result = diff_and_common_with_options(&objs, &options);
+    // Map key order never matters, array order only when order-insensitive
+    assert_eq!(result.base.as_deref(), Some(&yaml!("ports: [{name: http, port: 80}]")));
+
+    let options = DiffOptions { quorum: 1.0, order_insensitive: true, ..DiffOptions::default() };
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    assert_eq!(base.as_deref(), Some(&yaml!("env: [A, B]\nports: [{name: http, port: 80}]")));
+    assert_eq!(diffs[1].as_deref(), Some(&yaml!("z: 2")));
+    assert!(compute_diff_with_options(&b, &a, &options).is_some_and(|diff| diff.as_ref() == &yaml!("z: 2")));
+}
+
+#[test]
+fn test_drop_keys() {
+    let excluded = ExcludedKeys::new([".*checksum.*", "_generated_at", r"kubectl\.kubernetes\.io/.*"]).unwrap();
+    let doc = yaml!(
+        "_generated_at: today\nmetadata:\n  annotations:\n    kubectl.kubernetes.io/last-applied-configuration: '{}'\n    owner: team\n    \
+         config-checksum: abc\ncontainers:\n  - name: app\n    checksum: def\n    not_generated_at_all: 1"
+    );
+    assert_eq!(
+        drop_keys(&doc, &excluded),
+        yaml!("metadata:\n  annotations:\n    owner: team\ncontainers:\n  - name: app\n    not_generated_at_all: 1")
+    );
+    // Patterns match whole key names
+    assert!(!excluded.matches(&yaml!("x_generated_at")));
+    assert!(excluded.matches(&yaml!("checksum")));
+    assert!(ExcludedKeys::new(["("]).is_err());
+}
+
+#[test]
+fn test_excluded_keys_take_no_part_in_extraction() {
+    let a = yaml!("replicas: 2\nchecksum/config: aaa\npod:\n  checksum/secret: x");
+    let b = yaml!("replicas: 2\nchecksum/config: bbb\npod:\n  checksum/secret: x");
+    let objs = [&a, &b];
+    let options = DiffOptions {
+        quorum: 1.0,
+        excluded_keys: Some(ExcludedKeys::new(["checksum/.*"]).unwrap()),
+        ..DiffOptions::default()
+    };
+
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    assert_eq!(base.as_deref(), Some(&yaml!("replicas: 2")));
+    assert!(diffs.iter().all(Option::is_none));
+}
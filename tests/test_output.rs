@@ -0,0 +1,228 @@
+use yabe::deep_equal::deep_equal;
+use yabe::diff::{compute_diff, compute_diff_with_options};
+use yabe::merge::{apply_diff, merge_yaml};
+use yabe::options::{ArrayStrategy, DiffOptions};
+use yabe::comments::DocComments;
+use yabe::output::{
+    format_real, index_unchanged_slots, to_yaml_string, to_yaml_string_with_style, write_yaml, write_yaml_with_style, OutputStyle,
+    RealFormat,
+};
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+fn round_trips(doc: &Yaml) -> String {
+    let out = to_yaml_string(doc).unwrap();
+    assert_eq!(&yaml!(&out), doc, "{}", out);
+    out
+}
+
+#[test]
+fn test_multiline_strings_are_literal_blocks() {
+    let doc = yaml!("config: |\n  a = 1\n\n  b = 2\nscript: |-\n  echo hi\n  exit 0\nname: app");
+
+    let out = round_trips(&doc);
+    assert_eq!(out, "config: |\n  a = 1\n\n  b = 2\nscript: |-\n  echo hi\n  exit 0\nname: app\n");
+}
+
+#[test]
+fn test_blocks_in_nested_values() {
+    let doc = yaml!("containers:\n  - name: app\n    args:\n      - |\n        line one\n        line two\n    env:\n      CONF: \"x\\ny\\n\"");
+
+    let out = round_trips(&doc);
+    assert!(out.contains("  - |\n"), "{}", out);
+    assert!(out.contains("CONF: |\n"), "{}", out);
+}
+
+#[test]
+fn test_unrepresentable_strings_stay_quoted() {
+    let doc = yaml!("trailing: \"a\\nb\\n\\n\"\nindented: \"  a\\nb\\n\"\ncontrol: \"a\\r\\nb\"");
+
+    let out = round_trips(&doc);
+    assert!(!out.contains('|'), "{}", out);
+}
+
+#[test]
+fn test_write_yaml_matches_to_yaml_string() {
+    let docs = [
+        yaml!("image:\n  tag: v1\nscript: |\n  echo hi\n  exit 0\nports: [80, 443]\nempty: {}\n\"a key\": ~\n"),
+        yaml!("bad: \"a\\n b\\n\"\nok: \"x\\ny\"\n"),
+        yaml!("[1, {a: 2}]"),
+        yaml!("{}"),
+        yaml!("plain"),
+    ];
+    for doc in &docs {
+        let mut out = Vec::new();
+        write_yaml(doc, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), to_yaml_string(doc).unwrap());
+    }
+}
+
+#[test]
+fn test_indexed_slots_round_trip_through_apply() {
+    let helm = yaml!("args: [--a, --b, --c]\nports:\n  - {name: http, port: 80}\n  - {name: tls, port: 443}\nmatrix: [[1, 2], [3, 4]]");
+    let env = yaml!("args: [--a, --verbose, --c]\nports:\n  - {name: http, port: 80}\n  - {name: tls, port: 8443}\nmatrix: [[1, 2], [3, 5]]\nextra: [x, ~]");
+    let diff = compute_diff(&env, &helm).unwrap();
+
+    let indexed = index_unchanged_slots(&diff, Some(&helm));
+    let text = to_yaml_string(&indexed).unwrap();
+    assert!(text.contains("$length: 3"));
+    // An array the base does not have holds values, not unchanged slots
+    assert!(deep_equal(&indexed["extra"], &yaml!("[x, ~]")));
+
+    // Unlisted slots keep the base elements
+    let parsed = yaml!(&text);
+    let options = DiffOptions { slots_patches: true, ..DiffOptions::default() };
+    let applied = apply_diff(Some(&helm), Some(&parsed), &options).unwrap();
+    assert!(deep_equal(&applied, &env));
+    assert_eq!(index_unchanged_slots(&yaml!("args: [a, b]"), Some(&helm)), yaml!("args: [a, b]"));
+    assert_eq!(index_unchanged_slots(&yaml!("args: [~, b]"), None), yaml!("args: [~, b]"));
+}
+
+#[test]
+fn test_indexed_slots_round_trip_with_every_strategy() {
+    let helm = yaml!("args: [a, b, c]\nports:\n  - {name: http, port: 80}\n  - {name: tls, port: 443}\nlevels: [[1, 2], [3, 4]]");
+    let envs = [
+        yaml!("args: [a, x, c]\nports:\n  - {name: http, port: 80}\n  - {name: tls, port: 8443}\nlevels: [[1, 2], [3, 5]]"),
+        yaml!("args: [a, b, ~, q, c]\nports:\n  - {name: tls, port: 443}\nlevels: [[1, 2]]"),
+    ];
+    let strategies = [
+        ArrayStrategy::Positional,
+        ArrayStrategy::StrategicMerge { merge_key: "name".to_string() },
+        ArrayStrategy::Lcs { max_len: 100 },
+        ArrayStrategy::OrderedSet,
+    ];
+    for array_strategy in strategies {
+        let options = DiffOptions { array_strategy, slots_patches: true, ..DiffOptions::default() };
+        for env in &envs {
+            let diff = compute_diff_with_options(env, &helm, &options).unwrap();
+            let text = to_yaml_string(&index_unchanged_slots(&diff, Some(&helm))).unwrap();
+            let applied = apply_diff(Some(&helm), Some(&yaml!(&text)), &options).unwrap();
+            assert!(deep_equal(&applied, env), "{}:\n{}", options.array_strategy, text);
+        }
+    }
+}
+
+#[test]
+fn test_slots_patches_are_read_only_when_asked_to() {
+    let base = yaml!("a: [x, y]");
+    let patch = yaml!("a: {$slots: {0: z}, $length: 2}");
+    assert!(deep_equal(&apply_diff(Some(&base), Some(&patch), &DiffOptions::default()).unwrap(), &patch));
+    assert!(deep_equal(&merge_yaml(&yaml!("b: 1"), &patch), &yaml!("b: 1\na: {$slots: {0: z}, $length: 2}")));
+
+    let options = DiffOptions { slots_patches: true, ..DiffOptions::default() };
+    assert!(deep_equal(&apply_diff(Some(&base), Some(&patch), &options).unwrap(), &yaml!("a: [z, y]")));
+    // The length of a patch that does not match the base array is not trusted
+    let huge = yaml!("a: {$slots: {1: z}, $length: 100000000000000}");
+    assert!(deep_equal(&apply_diff(Some(&base), Some(&huge), &options).unwrap(), &yaml!("a: [~, z]")));
+    assert!(deep_equal(&apply_diff(None, Some(&huge), &options).unwrap(), &huge));
+    assert!(deep_equal(&apply_diff(Some(&yaml!("b: 1")), Some(&huge), &options).unwrap(), &yaml!("b: 1\na: [~, z]")));
+}
+
+#[test]
+fn test_small_collections_in_flow_style() {
+    let style = OutputStyle { flow_width: Some(24), ..OutputStyle::default() };
+    let doc = yaml!(
+        "image:\n  repository: nginx\n  tag: v2\nports:\n  - 80\n  - 443\nresources:\n  limits: {cpu: 500m, memory: 1Gi}\n  requests: {cpu: 250m, memory: 512Mi, ephemeral-storage: 1Gi}\nempty: {}\n"
+    );
+    let out = to_yaml_string_with_style(&doc, style).unwrap();
+    assert_eq!(
+        out,
+        "image:\n  repository: nginx\n  tag: v2\nports: [80, 443]\nresources:\n  limits: {cpu: 500m, memory: 1Gi}\n  requests:\n    cpu: 250m\n    memory: 512Mi\n    ephemeral-storage: 1Gi\nempty: {}\n"
+    );
+    assert_eq!(yaml!(&out), doc);
+
+    // The width counts the whole flow text, brackets and nested collections included
+    let doc = yaml!("a: {b: [1, 2]}");
+    assert_eq!(to_yaml_string_with_style(&doc, OutputStyle { flow_width: Some(11), ..OutputStyle::default() }).unwrap(), "a: {b: [1, 2]}\n");
+    assert_eq!(to_yaml_string_with_style(&doc, OutputStyle { flow_width: Some(10), ..OutputStyle::default() }).unwrap(), "a:\n  b: [1, 2]\n");
+    assert_eq!(to_yaml_string_with_style(&doc, OutputStyle::default()).unwrap(), to_yaml_string(&doc).unwrap());
+}
+
+#[test]
+fn test_flow_style_round_trips() {
+    let style = OutputStyle { flow_width: Some(80), ..OutputStyle::default() };
+    let docs = [
+        yaml!("hosts: ['a,b', '[c]', '{d}', 'e: f', '#g', '', '~', 'true', '1']"),
+        yaml!("env: {\"a,b\": 1, \"x y\": \"tab\\there\"}"),
+        yaml!("list:\n  - {name: a, port: 1}\n  - {name: b, port: 2}"),
+        yaml!("script: {run: \"echo hi\\nexit 0\\n\"}"),
+        yaml!("nested: {a: {b: {c: [1, [2, 3], {}]}}}"),
+        yaml!("[1, {a: 2}]"),
+    ];
+    for doc in &docs {
+        let out = to_yaml_string_with_style(doc, style).unwrap();
+        assert_eq!(&yaml!(&out), doc, "{}", out);
+    }
+    // Multi-line strings keep their literal blocks
+    assert!(to_yaml_string_with_style(&docs[3], style).unwrap().contains("run: |\n"));
+    assert_eq!(to_yaml_string_with_style(&docs[2], style).unwrap(), "list: [{name: a, port: 1}, {name: b, port: 2}]\n");
+    let narrow = OutputStyle { flow_width: Some(24), ..OutputStyle::default() };
+    assert_eq!(to_yaml_string_with_style(&docs[2], narrow).unwrap(), "list:\n  - {name: a, port: 1}\n  - {name: b, port: 2}\n");
+
+    // Documented keys keep their comments
+    let comments = DocComments::from_yaml(&yaml!("image.tag: Deployed image tag.")).unwrap();
+    let mut out = Vec::new();
+    write_yaml_with_style(&yaml!("image: {tag: v1}\nports: [80]"), &comments, style, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "image:\n  # Deployed image tag.\n  tag: v1\nports: [80]\n");
+}
+
+#[test]
+fn test_reals_are_preserved() {
+    let doc = yaml!("a: 1e3\nb: 1.50\nc: .5\nd: 1.0E+10\ne: 0.000001");
+    assert_eq!(round_trips(&doc), "a: 1e3\nb: 1.50\nc: .5\nd: 1.0E+10\ne: 0.000001\n");
+}
+
+#[test]
+fn test_real_formats() {
+    let shortest = RealFormat::Canonical { decimals: None, exponent: None };
+    let fixed = RealFormat::Canonical { decimals: Some(2), exponent: None };
+    let scientific = RealFormat::Canonical { decimals: None, exponent: Some(6) };
+    let fixed_scientific = RealFormat::Canonical { decimals: Some(2), exponent: Some(6) };
+
+    // Exponents
+    assert_eq!(format_real("1e3", shortest), "1000.0");
+    assert_eq!(format_real("1.0E+10", shortest), "10000000000.0");
+    assert_eq!(format_real("1.0E+10", scientific), "1e10");
+    assert_eq!(format_real("2.5e-3", fixed), "0.00");
+    // Trailing zeros
+    assert_eq!(format_real("1.50", shortest), "1.5");
+    assert_eq!(format_real("1.5", fixed), "1.50");
+    assert_eq!(format_real("2.", shortest), "2.0");
+    assert_eq!(format_real("-0.0", shortest), "-0.0");
+    // Very small and large magnitudes
+    assert_eq!(format_real("0.00000015", shortest), "0.00000015");
+    assert_eq!(format_real("0.00000015", scientific), "1.5e-7");
+    assert_eq!(format_real("0.00000015", fixed_scientific), "1.50e-7");
+    assert_eq!(format_real("123456789.0", fixed_scientific), "1.23e8");
+    assert_eq!(format_real("999999.5", scientific), "999999.5");
+    assert_eq!(format_real("0.0", scientific), "0.0");
+    // Not finite numbers are kept
+    assert_eq!(format_real(".inf", shortest), ".inf");
+    assert_eq!(format_real("-.Inf", fixed), "-.Inf");
+    assert_eq!(format_real(".NaN", scientific), ".NaN");
+
+    for format in ["preserve", "shortest", "fixed:3", "shortest,sci:6", "fixed:2,sci:0"] {
+        assert_eq!(format.parse::<RealFormat>().unwrap().to_string(), format);
+    }
+    assert_eq!("fixed:2".parse(), Ok(fixed));
+    for invalid in ["fixed:0", "fixed:x", "preserve,sci:6", "shortest,sci:-1", "shortest,6", "exact"] {
+        assert!(invalid.parse::<RealFormat>().is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_real_format_style() {
+    let doc = yaml!("limits: {cpu: 1.50, ratio: 1e-9}\nweights: [1e3, 0.25]\n1.50: key\nname: '1.50'");
+    let style = OutputStyle { reals: "shortest,sci:6".parse().unwrap(), ..OutputStyle::default() };
+    let out = to_yaml_string_with_style(&doc, style).unwrap();
+    assert_eq!(out, "limits:\n  cpu: 1.5\n  ratio: 1e-9\nweights:\n  - 1000.0\n  - 0.25\n1.50: key\nname: \"1.50\"\n");
+    // The same numbers written differently give the same text
+    let other = yaml!("limits: {cpu: 1.5, ratio: 0.000000001}\nweights: [1000.0, .25]\n1.50: key\nname: '1.50'");
+    assert_eq!(to_yaml_string_with_style(&other, style).unwrap(), out);
+
+    let flow = OutputStyle { flow_width: Some(40), ..style };
+    assert_eq!(
+        to_yaml_string_with_style(&doc, flow).unwrap(),
+        "limits: {cpu: 1.5, ratio: 1e-9}\nweights: [1000.0, 0.25]\n1.50: key\nname: \"1.50\"\n"
+    );
+}
@@ -0,0 +1,58 @@
+use yabe::deep_equal::deep_equal;
+use yabe::options::DiffOptions;
+use yabe::partition::partition_inputs;
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+fn paths(paths: &[&str]) -> Vec<String> {
+    paths.iter().map(|path| path.to_string()).collect()
+}
+
+#[test]
+fn test_base_per_partition() {
+    let web = yaml!("kind: Deployment\nmetadata: {name: web}\nspec: {replicas: 1, image: nginx}");
+    let web_prod = yaml!("kind: Deployment\nmetadata: {name: web}\nspec: {replicas: 3, image: nginx}");
+    let svc = yaml!("kind: Service\nmetadata: {name: web}\nspec: {port: 80}");
+    let svc_prod = yaml!("kind: Service\nmetadata: {name: web}\nspec: {port: 443}");
+    let objs = vec![&web, &svc, &web_prod, &svc_prod];
+
+    let partitioned = partition_inputs(&objs, &paths(&["kind", "metadata.name"]), &DiffOptions::default()).unwrap();
+    let names: Vec<&str> = partitioned.partitions.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["Deployment/web", "Service/web"]);
+    assert_eq!(partitioned.partitions[0].members, [0, 2]);
+    assert_eq!(partitioned.partitions[1].values, [yaml!("Service"), yaml!("web")]);
+
+    // Nothing is shared across kinds: the Deployment base knows no port
+    let deployment = partitioned.partitions[0].base.as_ref().unwrap();
+    assert!(deep_equal(deployment, &yaml!("kind: Deployment\nmetadata: {name: web}\nspec: {image: nginx}")));
+    assert!(deep_equal(partitioned.diffs[2].as_ref().unwrap(), &yaml!("spec: {replicas: 3}")));
+    assert!(deep_equal(partitioned.diffs[3].as_ref().unwrap(), &yaml!("spec: {port: 443}")));
+    assert!(partitioned.unpartitioned.is_empty());
+}
+
+#[test]
+fn test_inputs_without_discriminators_are_left_whole() {
+    let web = yaml!("kind: Deployment\nmetadata: {name: web}\nspec: {replicas: 1}");
+    let other = yaml!("kind: Deployment\nmetadata: {name: web}\nspec: {replicas: 2}");
+    let unnamed = yaml!("kind: Deployment\nspec: {replicas: 1}");
+    let nested = yaml!("kind: {group: apps}\nmetadata: {name: web}");
+    let alone = yaml!("kind: ConfigMap\nmetadata: {name: web}\ndata: {a: b}");
+    let empty = Yaml::Null;
+    let objs = vec![&web, &unnamed, &other, &nested, &alone, &empty];
+
+    let partitioned = partition_inputs(&objs, &paths(&["kind", "metadata.name"]), &DiffOptions::default()).unwrap();
+    assert_eq!(partitioned.partitions.len(), 1);
+    assert_eq!(partitioned.unpartitioned, [1, 3, 4]);
+    for index in [1, 3, 4] {
+        assert_eq!(partitioned.diffs[index].as_ref(), Some(objs[index]));
+    }
+    assert!(partitioned.diffs[5].is_none());
+
+    let names: Vec<String> = ["web", "unnamed", "other", "nested", "alone", "empty"].iter().map(|s| s.to_string()).collect();
+    let assignment = partitioned.assignment(&names);
+    assert_eq!(assignment["other"].as_str(), Some("Deployment/web"));
+    assert!(assignment["alone"].is_null());
+
+    assert!(partition_inputs(&objs, &[], &DiffOptions::default()).is_err());
+    assert!(partition_inputs(&objs, &paths(&["items[x]"]), &DiffOptions::default()).is_err());
+}
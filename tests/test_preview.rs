@@ -0,0 +1,86 @@
+use yabe::diff::diff_and_common_multiple;
+use yabe::merge::{apply_diff, apply_diff_at};
+use yabe::options::{DiffOptions, NullHandling};
+use yabe::preview::{preview, previews, same_effect};
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+#[test]
+fn test_apply_diff() {
+    let options = DiffOptions::default();
+    let base = yaml!("a: 1\nb:\n  c: 2");
+    let diff = yaml!("b:\n  d: 3");
+    let applied = apply_diff(Some(&base), Some(&diff), &options).unwrap();
+    assert!(same_effect(&applied, &yaml!("a: 1\nb:\n  c: 2\n  d: 3"), &options));
+    assert_eq!(apply_diff(None, Some(&diff), &options), Some(diff.clone()));
+    assert_eq!(apply_diff(Some(&base), None, &options), Some(base));
+    assert_eq!(apply_diff(None, None, &options), None);
+}
+
+#[test]
+fn test_apply_diff_at() {
+    let options = DiffOptions::default();
+    let base = yaml!("image: {repo: app, tag: v1}\nreplicas: 1\nports: [{port: 80}]");
+    let diff = yaml!("image: {tag: v2}\nreplicas: 3\nports: [{port: 81}, {port: 82}]");
+
+    let applied = apply_diff_at(Some(&base), Some(&diff), "image", &options).unwrap().unwrap();
+    let expected = yaml!("image: {repo: app, tag: v2}\nreplicas: 1\nports: [{port: 80}]");
+    assert!(same_effect(&applied, &expected, &options));
+    let applied = apply_diff_at(Some(&base), Some(&diff), "ports[1].port", &options).unwrap().unwrap();
+    let expected = yaml!("image: {repo: app, tag: v1}\nreplicas: 1\nports: [{port: 80}, {port: 82}]");
+    assert!(same_effect(&applied, &expected, &options));
+
+    // A path the diff lacks applies nothing; the empty path applies everything
+    assert_eq!(apply_diff_at(Some(&base), Some(&diff), "image.repo", &options), Ok(Some(base.clone())));
+    assert_eq!(apply_diff_at(Some(&base), None, "image", &options), Ok(Some(base.clone())));
+    assert_eq!(
+        apply_diff_at(Some(&base), Some(&diff), "", &options),
+        Ok(apply_diff(Some(&base), Some(&diff), &options))
+    );
+    // Maps the base lacks are created
+    let nested = yaml!("a: {b: {c: 1}}");
+    assert_eq!(apply_diff_at(None, Some(&nested), "a.b", &options), Ok(Some(nested.clone())));
+
+    assert!(apply_diff_at(Some(&yaml!("ports: []")), Some(&diff), "ports[1]", &options).is_err());
+    assert!(apply_diff_at(Some(&yaml!("ports: 1")), Some(&diff), "ports[0]", &options).is_err());
+    assert!(apply_diff_at(Some(&base), Some(&diff), "ports[x]", &options).is_err());
+}
+
+#[test]
+fn test_preview_layers_read_only_base_base_and_diff() {
+    let options = DiffOptions::default();
+    let helm = yaml!("replicas: 1\nimage: nginx\nregion: us");
+    let base = yaml!("region: eu");
+    let diff = yaml!("replicas: 3");
+    let effective = preview(Some(&helm), Some(&base), Some(&diff), &options).unwrap();
+    assert!(same_effect(&effective, &yaml!("replicas: 3\nimage: nginx\nregion: eu"), &options));
+}
+
+#[test]
+fn test_same_effect_ignores_nulls_unless_they_are_values() {
+    let with_null = yaml!("a: 1\nb: ~");
+    let without = yaml!("a: 1");
+    assert!(same_effect(&with_null, &without, &DiffOptions::default()));
+    let options = DiffOptions { null_handling: NullHandling::Value, ..DiffOptions::default() };
+    assert!(!same_effect(&with_null, &without, &options));
+}
+
+#[test]
+fn test_previews_of_lossless_extraction_match() {
+    let a = yaml!("region: eu\nreplicas: 1");
+    let b = yaml!("region: eu\nreplicas: 2");
+    let c = yaml!("region: us");
+    let objs = vec![&a, &b, &c, &Yaml::Null];
+    let (base, diffs) = diff_and_common_multiple(&objs, 0.51).into_parts();
+    let diffs: Vec<Option<&Yaml>> = diffs.iter().map(|d| d.as_deref()).collect();
+
+    let options = DiffOptions::default();
+    let result = previews(None, base.as_deref(), &diffs, &objs, &options);
+    assert_eq!(result.len(), 4);
+    assert!(result[..3].iter().all(|p| p.as_ref().is_some_and(|(_, same)| *same)));
+    assert!(result[3].is_none());
+
+    // Dropping a diff loses information
+    let lossy = previews(None, base.as_deref(), &[None, diffs[1], diffs[2]], &objs[..3], &options);
+    assert!(!lossy[0].as_ref().unwrap().1);
+}
@@ -0,0 +1,213 @@
+use std::borrow::Cow;
+
+use yabe::diff::{compute_diff_with_options, LeafCounting};
+use yabe::normalize::alias_renames;
+use yabe::options::{AliasConflict, DiffOptions, KeyAliases, MinorityKeys};
+use yabe::report::{
+    default_paths, empty_base_summary, explain_empty_base, fan_out_paths, override_coverage, override_coverage_with, override_sizes,
+    override_sizes_with, rename_report, size_report, values_at, MissReason, MissedKey, MissingValues, ReportFormat,
+};
+use yabe::yaml;
+use yaml_rust2::{Yaml, YamlLoader};
+
+#[test]
+fn test_default_paths() {
+    let base = yaml!("a: 1\nb:\n  c: 2\n  d: 3\ne:\n  f: 4\ng: [1, 2]");
+    let obj = yaml!("a: 5\nb:\n  c: 6\ng: [3]\nh: 7");
+
+    assert_eq!(default_paths(&obj, &base), vec!["b.d", "e"]);
+}
+
+#[test]
+fn test_default_paths_full_override() {
+    let base = yaml!("a: 1\nb:\n  c: 2");
+    let obj = yaml!("a: 1\nb:\n  c: 3");
+
+    assert!(default_paths(&obj, &base).is_empty());
+    assert!(default_paths(&yaml!("x"), &base).is_empty());
+}
+
+#[test]
+fn test_fan_out_paths() {
+    let docs: Vec<Yaml> = (0..4)
+        .map(|i| yaml!(&format!("image:\n  tag: v{}\n  repository: nginx\nreplicas: {}\nports: [80]", i, i % 2)))
+        .collect();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+
+    assert_eq!(fan_out_paths(&objs, 3), vec![("image.tag".to_string(), 4)]);
+    assert_eq!(
+        fan_out_paths(&objs, 1),
+        vec![("image.tag".to_string(), 4), ("replicas".to_string(), 2)]
+    );
+    assert!(fan_out_paths(&objs, 4).is_empty());
+}
+
+#[test]
+fn test_fan_out_ignores_missing_keys() {
+    let a = yaml!("a: 1");
+    let b = yaml!("b: 2");
+    let c = yaml!("a:\n  nested: 1");
+    assert!(fan_out_paths(&[&a, &b], 1).is_empty());
+    // A map and a scalar at the same path are distinct values
+    assert_eq!(fan_out_paths(&[&a, &c], 1), vec![("a".to_string(), 2)]);
+}
+
+#[test]
+fn test_override_sizes_largest_first() {
+    let dev = yaml!("replicas: 1");
+    let prod = yaml!("replicas: 3\nimage:\n  tag: v2\n  pullPolicy: Always\nargs: [--fast, --quiet]");
+    let staging = yaml!("replicas: 2");
+    let diffs = vec![Some(&dev), None, Some(&prod), Some(&staging)];
+
+    let sizes = override_sizes(&diffs);
+    assert_eq!(sizes, [(2, 4), (0, 1), (3, 1), (1, 0)]);
+
+    let names: Vec<String> = ["dev", "base-like", "prod", "staging"].iter().map(|s| s.to_string()).collect();
+    let report = size_report(&names, &sizes, &[], Some(1));
+    let files: Vec<(&str, i64)> = report["files"]
+        .as_hash()
+        .unwrap()
+        .iter()
+        .map(|(k, v)| (k.as_str().unwrap(), v.as_i64().unwrap()))
+        .collect();
+    assert_eq!(files, [("prod", 4), ("dev", 1), ("staging", 1), ("base-like", 0)]);
+    assert_eq!(report["exceeding"], yaml!("[prod]"));
+    assert!(size_report(&names, &sizes, &[], None)["exceeding"].is_badvalue());
+}
+
+#[test]
+fn test_override_coverage() {
+    // 8 leaves: replicas, image.repository, image.tag, ports (one array), env.A, env.B, env.C, empty
+    let obj = yaml!("replicas: 3\nimage:\n  repository: nginx\n  tag: v2\nports: [80, 443]\nenv: {A: 1, B: 2, C: 3}\nempty: {}");
+    assert_eq!(override_coverage(&obj, None), 1.0);
+    assert_eq!(override_coverage(&obj, Some(&yaml!("replicas: 3\nimage:\n  tag: v2"))), 0.75);
+    assert_eq!(override_coverage(&obj, Some(&yaml!("env: {A: 1, B: 2, C: 3}\nports: [80]"))), 0.5);
+    assert_eq!(override_coverage(&obj, Some(&obj)), 0.0);
+    // Nulls removing base keys can outnumber the input's own leaves
+    assert_eq!(override_coverage(&yaml!("a: 1"), Some(&yaml!("a: 1\nb: ~\nc: ~"))), 0.0);
+    assert_eq!(override_coverage(&yaml!("~"), None), 1.0);
+
+    let names: Vec<String> = ["dev", "prod"].iter().map(|s| s.to_string()).collect();
+    let dev = yaml!("replicas: 1");
+    let sizes = override_sizes(&[Some(&dev), Some(&obj)]);
+    let report = size_report(&names, &sizes, &[2.0 / 3.0, 0.0], None);
+    assert_eq!(report["coverage"]["prod"], Yaml::Real("0.000".to_string()));
+    assert_eq!(report["coverage"]["dev"], Yaml::Real("0.667".to_string()));
+    let order: Vec<&str> = report["coverage"].as_hash().unwrap().keys().filter_map(Yaml::as_str).collect();
+    assert_eq!(order, ["prod", "dev"]);
+}
+
+#[test]
+fn test_values_at() {
+    let docs: Vec<Yaml> = [
+        "image: {repository: nginx, tag: v1}\nports: [80, 443]",
+        "image: {tag: v2, repository: nginx}\nports: [80]",
+        "image: {tag: v1, repository: nginx}\nports: [8080]",
+        "image: {tag: ~}",
+        "",
+    ]
+    .iter()
+    .map(|s| YamlLoader::load_from_str(s).unwrap().into_iter().next().unwrap_or(Yaml::Null))
+    .collect();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+
+    let tags = values_at(&objs, "image.tag", MissingValues::Null).unwrap();
+    assert_eq!(tags, [(&yaml!("v1"), 2), (&yaml!("v2"), 1), (&Yaml::Null, 2)]);
+    let tags = values_at(&objs, "image.tag", MissingValues::Skip).unwrap();
+    assert_eq!(tags, [(&yaml!("v1"), 2), (&yaml!("v2"), 1), (&Yaml::Null, 1)]);
+
+    // Maps are compared whatever their key order, and arrays are indexed
+    let images = values_at(&objs, "image", MissingValues::Skip).unwrap();
+    assert_eq!(images.iter().map(|(_, count)| *count).collect::<Vec<_>>(), [2, 1, 1]);
+    assert_eq!(values_at(&objs, "ports[0]", MissingValues::Skip).unwrap(), [(&yaml!("80"), 2), (&yaml!("8080"), 1)]);
+    assert!(values_at(&objs, "missing", MissingValues::Skip).unwrap().is_empty());
+    assert!(values_at(&objs, "ports[x]", MissingValues::Null).is_err());
+}
+
+#[test]
+fn test_rename_report() {
+    let aliases = KeyAliases::from_yaml(&yaml!("ingress.hosts: ingress.hostnames\nimage: container.image"), AliasConflict::PreferOld).unwrap();
+    let docs = [yaml!("ingress: {hosts: [a.com]}\nimage: nginx\ncontainer: {image: redis}"), yaml!("name: web")];
+    let renames: Vec<_> = docs.iter().map(|doc| alias_renames(doc, &aliases)).collect();
+    let names = ["old.yaml".to_string(), "say \"new\".yaml".to_string()];
+
+    let text = rename_report(&names, &renames, AliasConflict::PreferOld, ReportFormat::Text);
+    assert_eq!(
+        text,
+        "old.yaml: 2 rename(s)\n  ingress.hosts -> ingress.hostnames\n  image -> container.image (conflict, keeping the old value)\n\
+         say \"new\".yaml: no renames\n"
+    );
+
+    let json = rename_report(&names, &renames, AliasConflict::Fail, ReportFormat::Json);
+    assert!(json.starts_with("{\n  \"files\": [\n    {\"file\": \"old.yaml\", \"renames\": [\n"));
+    assert!(json.contains("{\"old\": \"ingress.hosts\", \"new\": \"ingress.hostnames\", \"conflict\": false, \"kept\": \"old\"},\n"));
+    assert!(json.contains("{\"old\": \"image\", \"new\": \"container.image\", \"conflict\": true, \"kept\": null}\n    ]},\n"));
+    assert!(json.ends_with("{\"file\": \"say \\\"new\\\".yaml\", \"renames\": []}\n  ]\n}\n"));
+
+    assert_eq!(rename_report(&[], &[], AliasConflict::Fail, ReportFormat::Json), "{\n  \"files\": []\n}\n");
+    assert_eq!("json".parse::<ReportFormat>(), Ok(ReportFormat::Json));
+    assert_eq!(ReportFormat::Text.to_string(), "text");
+    assert!("yaml".parse::<ReportFormat>().is_err());
+}
+
+#[test]
+fn test_explain_empty_base_follows_the_extraction() {
+    let objs = [
+        yaml!("image: {repository: nginx, tag: v1}\ntls: {enabled: true}\nport: 80"),
+        yaml!("image: {repository: nginx, tag: v2}\nport: '80'"),
+        yaml!("image: {repository: httpd, tag: v3}\nport: 80"),
+        Yaml::Null,
+    ];
+    let objs: Vec<&Yaml> = objs.iter().collect();
+    let options = DiffOptions { quorum: 1.0, ..DiffOptions::default() };
+    let missed = explain_empty_base(&objs, &options);
+    assert_eq!(
+        missed,
+        [
+            MissedKey { key: "image".to_string(), reason: MissReason::NoQuorum { needed: 3, closest: 2 } },
+            MissedKey { key: "tls".to_string(), reason: MissReason::SetBySome { set: 1 } },
+            MissedKey { key: "port".to_string(), reason: MissReason::TypeConflict(vec!["int", "string"]) },
+        ]
+    );
+    assert_eq!(
+        empty_base_summary(&missed),
+        [
+            "1 key(s) with no value reaching the quorum: image (2 input(s) agreeing, 3 needed)",
+            "1 key(s) set by only some inputs, so kept in the diffs: tls (1 input(s))",
+            "1 key(s) with values of different types: port (int, string)",
+        ]
+    );
+
+    let hoisting = DiffOptions { minority_keys: MinorityKeys::Hoist, min_presence: 2, ..options.clone() };
+    assert_eq!(explain_empty_base(&objs, &hoisting)[1].reason, MissReason::FewerThanMinPresence { set: 1 });
+
+    let roots = [yaml!("a: 1"), yaml!("[1]")];
+    let roots: Vec<&Yaml> = roots.iter().collect();
+    let missed = explain_empty_base(&roots, &options);
+    assert_eq!(missed[0].key, "");
+    assert_eq!(empty_base_summary(&missed), ["1 key(s) with values of different types: the documents (hash, array)"]);
+}
+
+#[test]
+fn test_unchanged_slots_do_not_inflate_metrics() {
+    let base = yaml!("args: [-v, --debug, --color, --quiet]\nreplicas: 2");
+    let docs = [
+        yaml!("args: [-v, --trace, --color, --quiet]\nreplicas: 2"),
+        base.clone(),
+        yaml!("args: [-v, --debug, --color, --quiet]\nreplicas: 3"),
+    ];
+    let options = DiffOptions::default();
+    let diffs: Vec<Option<Cow<Yaml>>> = docs.iter().map(|doc| compute_diff_with_options(doc, &base, &options)).collect();
+    let diffs: Vec<Option<&Yaml>> = diffs.iter().map(|diff| diff.as_deref()).collect();
+    assert_eq!(diffs[0], Some(&yaml!("args: [~, --trace, ~, ~]")));
+
+    // One changed element is one override, however many unchanged slots surround it
+    assert_eq!(override_sizes_with(&diffs, LeafCounting::Elements), [(0, 1), (2, 1), (1, 0)]);
+    assert_eq!(override_sizes_with(&diffs, LeafCounting::WholeArrays), override_sizes(&diffs));
+    assert_eq!(override_sizes(&diffs), [(0, 1), (2, 1), (1, 0)]);
+
+    // Four of the five leaves of the first file come from the base
+    assert_eq!(override_coverage_with(&docs[0], diffs[0], LeafCounting::Elements), 0.8);
+    assert_eq!(override_coverage(&docs[0], diffs[0]), 0.5);
+    assert_eq!(override_coverage_with(&docs[1], diffs[1], LeafCounting::Elements), 1.0);
+}
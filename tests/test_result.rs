@@ -0,0 +1,116 @@
+use std::borrow::Cow;
+
+use yabe::diff::diff_and_common_multiple;
+use yabe::result::DiffResult;
+use yabe::yaml;
+use yaml_rust2::{Yaml, YamlLoader};
+
+#[test]
+fn test_display_renders_yaml_stream() {
+    let base = yaml!("region: eu");
+    let diff = yaml!("replicas: 2");
+    let result = DiffResult::new(Some(Cow::Borrowed(&base)), vec![Some(Cow::Borrowed(&diff)), None])
+        .with_names(["a.yaml", "b.yaml"]);
+    assert_eq!(
+        result.to_string(),
+        "--- # base\nregion: eu\n--- # a.yaml\nreplicas: 2\n--- # b.yaml\n"
+    );
+
+    let docs = YamlLoader::load_from_str(&result.to_string()).unwrap();
+    assert_eq!(docs[0], base);
+    assert_eq!(docs[1], diff);
+}
+
+#[test]
+fn test_debug_is_inline() {
+    let base = yaml!("a: [1, x, ~]");
+    let result = DiffResult::new(Some(Cow::Borrowed(&base)), vec![None]);
+    assert_eq!(
+        format!("{:?}", result),
+        r#"DiffResult { base: Some({"a": [1, "x", ~]}), diffs: {"0": None} }"#
+    );
+}
+
+#[test]
+fn test_named_access_and_conversions() {
+    let a = yaml!("x: 1\ny: 1");
+    let b = yaml!("x: 1\ny: 2");
+    let objs = vec![&a, &b];
+    let result = diff_and_common_multiple(&objs, 0.51).with_names(vec!["a".to_string(), "b".to_string()]);
+
+    assert_eq!(result.name(1), "b");
+    assert_eq!(result.diff("missing"), None);
+    assert_eq!(result.diff_count(), 2);
+
+    let owned = result.clone().into_owned();
+    assert_eq!(owned, result);
+    let (base, diffs): (Option<Cow<Yaml>>, Vec<Option<Cow<Yaml>>>) = result.into();
+    assert_eq!(base.unwrap()["x"], Yaml::Integer(1));
+    assert_eq!(DiffResult::from((None, diffs.clone())).diffs, diffs);
+    assert_eq!(DiffResult::new(None, diffs).name(0), "0");
+}
+
+#[test]
+fn test_named_parts_keep_input_order() {
+    let base = yaml!("region: eu");
+    let diff = yaml!("replicas: 2");
+    let result = DiffResult::new(Some(Cow::Borrowed(&base)), vec![None, Some(Cow::Borrowed(&diff)), None])
+        .with_names(["c.yaml", "a.yaml", "b.yaml"]);
+
+    let (named_base, diffs) = result.into_named_parts();
+    assert_eq!(named_base.as_deref(), Some(&base));
+    assert_eq!(diffs.keys().collect::<Vec<_>>(), ["c.yaml", "a.yaml", "b.yaml"]);
+    assert_eq!(diffs["a.yaml"].as_deref(), Some(&diff));
+    assert!(diffs["b.yaml"].is_none());
+
+    // Unnamed diffs are keyed by index
+    let (_, diffs) = DiffResult::new(None, vec![None, None]).into_named_parts();
+    assert_eq!(diffs.keys().collect::<Vec<_>>(), ["0", "1"]);
+}
+
+#[test]
+#[should_panic(expected = "duplicate input name 'a.yaml'")]
+fn test_named_parts_reject_duplicate_names() {
+    let _ = DiffResult::new(None, vec![None, None]).with_names(["a.yaml", "a.yaml"]).into_named_parts();
+}
+
+#[test]
+fn test_override_coverage_per_input() {
+    let dev = yaml!("replicas: 1\nimage: {repository: nginx, tag: v1}\nregion: eu");
+    let staging = yaml!("replicas: 1\nimage: {repository: nginx, tag: v1}\nregion: eu");
+    let prod = yaml!("replicas: 5\nimage: {repository: nginx, tag: v2}\nregion: eu");
+    let objs = [&dev, &staging, &prod];
+
+    let result = diff_and_common_multiple(&objs, 0.6);
+    assert_eq!(result.override_coverage(&objs), [1.0, 1.0, 0.5]);
+}
+
+#[test]
+fn test_delta_reports_leaves_moved_into_and_out_of_the_base() {
+    let (old_base, old_prod, old_dev) = (yaml!("region: eu\nimage: {tag: v1}"), yaml!("replicas: 3"), yaml!("debug: true"));
+    let (new_base, new_prod) = (yaml!("region: eu\nreplicas: 3\nimage: {tag: v2}"), yaml!("region: ~"));
+    let old = DiffResult::new(
+        Some(Cow::Borrowed(&old_base)),
+        vec![Some(Cow::Borrowed(&old_prod)), Some(Cow::Borrowed(&old_dev))],
+    )
+    .with_names(["prod.yaml", "dev.yaml"]);
+    let new = DiffResult::new(Some(Cow::Borrowed(&new_base)), vec![Some(Cow::Borrowed(&new_prod)), None])
+        .with_names(["prod.yaml", "staging.yaml"]);
+
+    let delta = old.delta(&new);
+    assert_eq!(delta.base.added, ["replicas"]);
+    assert_eq!(delta.base.changed, ["image.tag"]);
+    assert!(delta.base.removed.is_empty());
+    let names: Vec<&str> = delta.diffs.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["prod.yaml", "dev.yaml"]);
+    assert_eq!(delta.diffs[0].1.added, ["region"]);
+    assert_eq!(delta.diffs[0].1.removed, ["replicas"]);
+    assert_eq!(delta.diffs[1].1.removed, ["debug"]);
+
+    let expected = yaml!(
+        "base: {added: [replicas], changed: [image.tag]}\n\
+         diffs:\n  prod.yaml: {added: [region], removed: [replicas]}\n  dev.yaml: {removed: [debug]}"
+    );
+    assert!(yabe::deep_equal::deep_equal(&delta.to_yaml(), &expected));
+    assert!(old.delta(&old).is_empty());
+}
@@ -0,0 +1,135 @@
+use yabe::schema::{
+    collection_scalar_conflicts, common_schema, coverage, schema_deviations, schema_diff, schema_of, type_conflicts, type_name,
+    union_schema, ConflictingValue, DeviationKind, StrictTypes,
+};
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+#[test]
+fn test_schema_of_replaces_leaves_with_types() {
+    let doc = yaml!("a: 1\nb:\n  c: text\n  d: [1, 2]\n  e: ~");
+    assert_eq!(schema_of(&doc), yaml!("a: int\nb:\n  c: string\n  d: array\n  e: \"null\""));
+    assert_eq!(type_name(&Yaml::Real("1.5".into())), "real");
+}
+
+#[test]
+fn test_equal_shapes_ignore_values() {
+    let a = yaml!("a: 1\nb:\n  c: x");
+    let b = yaml!("a: 2\nb:\n  c: y");
+    let objs = vec![&a, &b];
+    let (schema, deviations) = schema_diff(&objs, 1.0);
+    assert_eq!(schema, Some(yaml!("a: int\nb:\n  c: string")));
+    assert!(deviations.iter().all(|d| d.is_empty()));
+}
+
+#[test]
+fn test_missing_section_is_reported_once() {
+    let a = yaml!("a: 1\nsection:\n  x: 1\n  y: 2");
+    let b = yaml!("a: 1\nsection:\n  x: 3\n  y: 4");
+    let c = yaml!("a: 1");
+    let objs = vec![&a, &b, &c];
+    let (_, deviations) = schema_diff(&objs, 0.51);
+    assert!(deviations[0].is_empty());
+    assert!(deviations[1].is_empty());
+    assert_eq!(deviations[2].len(), 1);
+    assert_eq!(deviations[2][0].path, "section");
+    assert_eq!(deviations[2][0].kind, DeviationKind::Missing { expected: "hash".into() });
+}
+
+#[test]
+fn test_type_mismatch_and_unexpected_keys() {
+    let schema = yaml!("port: int\nname: string");
+    let doc = yaml!("port: \"80\"\nname: web\nextra:\n  k: v");
+    let deviations = schema_deviations(&doc, &schema);
+    assert_eq!(deviations.len(), 2);
+    assert_eq!(deviations[0].path, "port");
+    assert_eq!(
+        deviations[0].kind,
+        DeviationKind::TypeMismatch { expected: "int".into(), found: "string".into() }
+    );
+    assert_eq!(deviations[0].kind.to_string(), "string, expected int");
+    assert_eq!(deviations[1].path, "extra");
+    assert_eq!(deviations[1].kind, DeviationKind::Unexpected { found: "hash".into() });
+}
+
+#[test]
+fn test_common_schema_follows_quorum() {
+    let a = yaml!("a: 1\nb: x");
+    let b = yaml!("a: 2");
+    let c = yaml!("a: x");
+    let objs = vec![&a, &b, &c];
+    assert_eq!(common_schema(&objs, 0.51), Some(yaml!("a: int")));
+    assert_eq!(common_schema(&objs, 1.0), Some(yaml!("{}")));
+    assert_eq!(common_schema(&[], 0.51), None);
+}
+
+#[test]
+fn test_type_conflicts_continue_past_a_conflicting_subtree() {
+    let a = yaml!("port: 80\nresources:\n  memory: 512Mi\nenv:\n  debug: true\nname: web");
+    let b = yaml!("port: \"80\"\nresources: 512Mi\nenv:\n  debug: \"yes\"\nname: ~");
+    let c = yaml!("~");
+    let objs = vec![&a, &b, &c];
+
+    let conflicts = type_conflicts(&objs, StrictTypes::CollectAll);
+    let paths: Vec<&str> = conflicts.iter().map(|c| c.path.as_str()).collect();
+    assert_eq!(paths, ["port", "resources", "env.debug"]);
+    assert_eq!(conflicts[2].types, [Some("bool"), Some("string"), None]);
+    let names = vec!["a.yaml".to_string(), "b.yaml".to_string(), "c.yaml".to_string()];
+    assert_eq!(conflicts[0].describe(&names), "port: int in a.yaml; string in b.yaml");
+
+    let first = type_conflicts(&objs, StrictTypes::FailFast);
+    assert_eq!(first, conflicts[..1]);
+    assert!(type_conflicts(&[&a, &a], StrictTypes::CollectAll).is_empty());
+    assert_eq!("collect-all".parse::<StrictTypes>(), Ok(StrictTypes::CollectAll));
+}
+
+#[test]
+fn test_collection_scalar_conflicts_tolerate_other_type_differences() {
+    let a = yaml!("port: 80\nratio: 1\nresources: 512Mi\nhosts: [a.com, b.com]\nenv:\n  vars: {DEBUG: '1'}");
+    let b = yaml!("port: \"80\"\nratio: 1.5\nresources: {limits: {memory: 1Gi}, requests: {}}\nhosts: a.com\nenv:\n  vars: DEBUG=1");
+    let c = yaml!("resources: 512Mi\nhosts: {primary: a.com}\nenv: [x]");
+    let d = yaml!("~");
+    let objs = vec![&a, &b, &c, &d];
+
+    let conflicts = collection_scalar_conflicts(&objs);
+    let paths: Vec<&str> = conflicts.iter().map(|c| c.path.as_str()).collect();
+    // port and ratio differ in type only; env holds maps and an array but no scalar, so its maps are walked into
+    assert_eq!(paths, ["resources", "hosts", "env.vars"]);
+    assert_eq!(conflicts[1].values[1], (1, ConflictingValue::Scalar(yaml!("a.com"))));
+
+    let names: Vec<String> = ["a.yaml", "b.yaml", "c.yaml", "d.yaml"].iter().map(|name| name.to_string()).collect();
+    assert_eq!(conflicts[0].describe(&names), "resources: \"512Mi\" in a.yaml, c.yaml; map {limits, requests} in b.yaml");
+    assert_eq!(conflicts[1].describe(&names), "hosts: array of 2 in a.yaml; \"a.com\" in b.yaml; map {primary} in c.yaml");
+    assert!(collection_scalar_conflicts(&[&a, &d]).is_empty());
+    assert!(collection_scalar_conflicts(&[&yaml!("512Mi"), &yaml!("{a: 1}")])[0].describe(&names).starts_with("(root): "));
+}
+
+#[test]
+fn test_union_schema_has_every_key() {
+    let a = yaml!("a: 1\nm: {x: s}");
+    let b = yaml!("a: two\nm: 3\nn: {y: true}");
+    let c = yaml!("a: 2");
+    let union = union_schema(&[&a, &b, &c]).unwrap();
+    assert_eq!(union, yaml!("a: int\nm: {x: string}\nn: {y: bool}"));
+    assert_eq!(union_schema(&[]), None);
+}
+
+#[test]
+fn test_coverage_reports_missing_keys_and_overrides() {
+    let a = yaml!("a: 1\nm: {x: s, l: [1, 2]}");
+    let b = yaml!("a: 1\nm: {x: t, l: [1, 2]}\nn: {y: true}");
+    let c = yaml!("a: 2\nm: 3");
+    let (_, coverage) = coverage(&[&a, &b, &c]);
+
+    assert_eq!(coverage[0].missing, vec![("n".to_string(), "hash".to_string())]);
+    // Two values held by one document each: both override
+    assert_eq!(coverage[0].overrides, vec![("m.x".to_string(), yaml!("s"))]);
+    assert_eq!(coverage[1].overrides, vec![("m.x".to_string(), yaml!("t"))]);
+    assert!(coverage[1].missing.is_empty());
+    // A scalar where the others hold a map misses nothing below it
+    assert_eq!(coverage[2].missing, vec![("n".to_string(), "hash".to_string())]);
+    assert_eq!(
+        coverage[2].overrides,
+        vec![("a".to_string(), Yaml::Integer(2)), ("m".to_string(), Yaml::Integer(3))]
+    );
+}
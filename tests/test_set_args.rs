@@ -0,0 +1,57 @@
+use yabe::set_args::{escape_key, set_args, SetArg, SetFlag};
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+fn arguments(doc: &str) -> Vec<(SetFlag, String)> {
+    set_args(&yaml!(doc)).unwrap().iter().map(|arg| (arg.flag, arg.argument())).collect()
+}
+
+#[test]
+fn test_set_args_flags() {
+    let args = arguments("s: 'true'\nn: 3\nb: false\nx: null\nf: 1.50\nm: {}\nl: []");
+    assert_eq!(
+        args,
+        vec![
+            (SetFlag::SetString, "s=true".to_string()),
+            (SetFlag::Set, "n=3".to_string()),
+            (SetFlag::Set, "b=false".to_string()),
+            (SetFlag::Set, "x=null".to_string()),
+            (SetFlag::SetJson, "f=1.5".to_string()),
+            (SetFlag::SetJson, "m={}".to_string()),
+            (SetFlag::SetJson, "l=[]".to_string()),
+        ]
+    );
+    assert!(set_args(&yaml!("f: .inf")).is_err());
+}
+
+#[test]
+fn test_set_args_paths() {
+    let args = arguments("a:\n  - [1, {b: x}]\n  - c: {d: y}\n'k.e,y=[0]\\': 'v,a\\l'\n1: one");
+    let args: Vec<&str> = args.iter().map(|(_, arg)| arg.as_str()).collect();
+    assert_eq!(
+        args,
+        vec![
+            "a[0][0]=1",
+            "a[0][1].b=x",
+            "a[1].c.d=y",
+            "k\\.e\\,y\\=\\[0]\\\\=v\\,a\\\\l",
+            "1=one",
+        ]
+    );
+    assert_eq!(escape_key(&Yaml::String("".to_string())), Err("an empty key cannot be set".to_string()));
+    assert!(set_args(&yaml!("1: a\n'1': b")).is_err());
+    assert!(set_args(&yaml!("[1, 2]")).is_err());
+}
+
+#[test]
+fn test_set_arg_shell_quoting() {
+    let arg = |flag, path: &str, value: &str| SetArg {
+        flag,
+        path: path.to_string(),
+        value: value.to_string(),
+    };
+    assert_eq!(arg(SetFlag::Set, "image.tag", "v1").to_shell(), "--set image.tag=v1");
+    assert_eq!(arg(SetFlag::Set, "ports[0]", "80").to_shell(), "--set 'ports[0]=80'");
+    assert_eq!(arg(SetFlag::SetString, "msg", "it's").to_shell(), "--set-string 'msg=it'\\''s'");
+    assert_eq!(arg(SetFlag::SetJson, "m", "{}").to_string(), "--set-json 'm={}'");
+}
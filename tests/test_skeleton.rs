@@ -0,0 +1,95 @@
+use yabe::options::DiffOptions;
+use yabe::deep_equal::deep_equal;
+use yabe::skeleton::{default_value, is_placeholder, placeholder, template, two_phase};
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+#[test]
+fn test_skeleton_is_structurally_complete() {
+    let docs: Vec<Yaml> = [
+        "replicas: 3\nimage: {repository: nginx, tag: v3}\nresources: {limits: {cpu: 1, memory: 1Gi}}\nextra: 1",
+        "replicas: 2\nimage: {repository: nginx, tag: v2}\nresources: {limits: {cpu: 2, memory: 1Gi}}",
+        "replicas: 1\nimage: {repository: nginx, tag: v1}\nresources: {limits: {cpu: 3, memory: 2Gi}}",
+    ]
+    .iter()
+    .map(|s| yaml!(s))
+    .collect();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+    let options = DiffOptions { quorum: 0.6, ..DiffOptions::default() };
+
+    let result = two_phase(&objs, &options);
+    assert_eq!(
+        result.structure,
+        Some(yaml!("replicas: int\nimage: {repository: string, tag: string}\nresources: {limits: {cpu: int, memory: string}}"))
+    );
+    assert_eq!(
+        result.skeleton,
+        Some(yaml!(
+            "replicas: '<divergent int>'\nimage: {repository: nginx, tag: '<divergent string>'}\n\
+             resources: {limits: {cpu: '<divergent int>', memory: 1Gi}}"
+        ))
+    );
+    let paths: Vec<&str> = result.divergent.iter().map(|(path, _)| path.as_str()).collect();
+    assert_eq!(paths, ["replicas", "image.tag", "resources.limits.cpu"]);
+    assert_eq!(result.divergent[2].1, [Some(yaml!("1")), Some(yaml!("2")), Some(yaml!("3"))]);
+
+    assert!(is_placeholder(&placeholder("int")));
+    assert!(!is_placeholder(&yaml!("'<divergent'")));
+    assert!(!is_placeholder(&yaml!("1Gi")));
+}
+
+#[test]
+fn test_skeleton_placeholder_for_missing_and_mistyped_values() {
+    // Two of three files have `port` as an int, with different values; the third a string
+    let a = yaml!("port: 80\ntls: {enabled: true}");
+    let b = yaml!("port: 81\ntls: {enabled: true}");
+    let c = yaml!("port: http");
+    let options = DiffOptions { quorum: 0.6, ..DiffOptions::default() };
+
+    let result = two_phase(&[&a, &b, &c], &options);
+    // A key missing from a file counts against the value quorum, but not as a different type
+    assert_eq!(result.skeleton, Some(yaml!("port: '<divergent int>'\ntls: {enabled: '<divergent bool>'}")));
+    assert_eq!(
+        result.divergent,
+        [
+            ("port".to_string(), vec![Some(yaml!("80")), Some(yaml!("81")), Some(yaml!("http"))]),
+            ("tls.enabled".to_string(), vec![Some(yaml!("true")), Some(yaml!("true")), None]),
+        ]
+    );
+
+    // Without a shared structure there is no skeleton
+    let result = two_phase(&[&yaml!("a: 1"), &yaml!("b: 2")], &DiffOptions { quorum: 1.0, ..DiffOptions::default() });
+    assert_eq!(result.skeleton, Some(yaml!("{}")));
+    let result = two_phase(&[&yaml!("[1]"), &yaml!("a: 2")], &DiffOptions { quorum: 1.0, ..DiffOptions::default() });
+    assert!(result.skeleton.is_none() && result.divergent.is_empty());
+}
+
+#[test]
+fn test_template_has_every_key_with_defaults_of_its_type() {
+    let docs: Vec<Yaml> = [
+        "replicas: 3\nimage: {repository: nginx, tag: v3}\nports: [80]\nratio: 0.5\nextra: {debug: true}",
+        "replicas: 2\nimage: {repository: nginx, tag: v2}\nports: [80]\nratio: 0.7\nextra: {trace: ~}",
+        "replicas: 1\nimage: {repository: nginx}\nports: [443]\nratio: 0.9\nlabel: x",
+    ]
+    .iter()
+    .map(|s| yaml!(s))
+    .collect();
+    let refs: Vec<&Yaml> = docs.iter().collect();
+    let result = template(&refs, &DiffOptions { quorum: 0.6, ..DiffOptions::default() });
+
+    let expected = yaml!("replicas: 0\nimage: {repository: nginx, tag: ''}\nports: [80]\nratio: 0.0\nextra: {debug: false, trace: ~}\nlabel: ''");
+    assert!(deep_equal(result.template.as_ref().unwrap(), &expected));
+    let optional: Vec<(String, usize, usize)> =
+        result.optional.iter().map(|(keys, set, holding)| (keys.join("."), *set, *holding)).collect();
+    assert_eq!(
+        optional,
+        [
+            ("image.tag".to_string(), 2, 3),
+            ("extra".to_string(), 2, 3),
+            ("extra.debug".to_string(), 1, 2),
+            ("extra.trace".to_string(), 0, 2),
+            ("label".to_string(), 1, 3),
+        ]
+    );
+    assert_eq!(default_value("hash"), Yaml::Null);
+}
@@ -1,7 +1,9 @@
 use yaml_rust2::{YamlLoader, Yaml, YamlEmitter};
 use std::fs;
 use log::warn;
-use yabe::sorter::sort_yaml;
+use yabe::deep_equal::deep_equal;
+use yabe::sorter::{order_template, sort_yaml, template_from_paths, unlisted_keys, UnlistedKeys};
+use yabe::yaml;
 
 // Function to initialize test configuration
 pub fn init_test_config(config_path: &str) -> Yaml {
@@ -193,4 +195,40 @@ test: yaml"#;
     }
 
     assert_eq!(out_str, result);
-}
\ No newline at end of file
+}
+
+fn keys(doc: &Yaml) -> Vec<&str> {
+    doc.as_hash().unwrap().keys().map(|k| k.as_str().unwrap()).collect()
+}
+
+#[test]
+fn test_order_template() {
+    let config = yaml!("orderTemplate:\n  image:\n    repository: ~\n    tag: ~\n  replicas: ~\n  containers: [{name: ~, image: ~}]\n");
+    let doc = yaml!("zone: a\ncontainers:\n  - {image: nginx, name: app, args: []}\nextra: 1\nimage:\n  tag: v1\n  pullPolicy: Always\n  repository: nginx\nreplicas: 2\n");
+
+    let sorted = sort_yaml(&doc, &config);
+    assert_eq!(keys(&sorted), ["image", "replicas", "containers", "extra", "zone"]);
+    assert_eq!(keys(&sorted["image"]), ["repository", "tag", "pullPolicy"]);
+    assert_eq!(keys(&sorted["containers"][0]), ["name", "image", "args"]);
+    assert!(deep_equal(&sorted, &doc));
+}
+
+#[test]
+fn test_order_template_paths_and_first_seen() {
+    let config = yaml!("unlistedKeys: first-seen\norderTemplate:\n  - metadata.name\n  - spec\n  - metadata.labels\n");
+    let doc = yaml!("zone: a\nspec: {}\nextra: 1\nmetadata:\n  labels: {}\n  annotations: {}\n  name: x\n");
+
+    let sorted = sort_yaml(&doc, &config);
+    assert_eq!(keys(&sorted), ["metadata", "spec", "zone", "extra"]);
+    assert_eq!(keys(&sorted["metadata"]), ["name", "labels", "annotations"]);
+}
+
+#[test]
+fn test_order_template_errors() {
+    assert!(order_template(&yaml!("orderTemplate: [a, 1]")).is_err());
+    assert!(order_template(&yaml!("orderTemplate: [\"a[x]\"]")).is_err());
+    assert!(order_template(&yaml!("sortKey: name")).unwrap().is_none());
+    assert_eq!(template_from_paths(&["a.b", "c[0].d", "a.e"]), Ok(yaml!("a: {b: ~, e: ~}\nc: [{d: ~}]")));
+    assert!(unlisted_keys(&yaml!("unlistedKeys: random")).is_err());
+    assert_eq!("first-seen".parse::<UnlistedKeys>().unwrap().to_string(), "first-seen");
+}
@@ -0,0 +1,66 @@
+use std::borrow::Cow;
+
+use yabe::diff::{compute_diff_with_options, diff_and_common_with_options, LeafCounting};
+use yabe::options::DiffOptions;
+use yabe::stats::{run_stats, run_stats_with, RunStats};
+use yabe::yaml;
+use yaml_rust2::Yaml;
+
+#[test]
+fn test_run_stats_of_an_extraction() {
+    let docs: Vec<Yaml> = [
+        "replicas: 2\nimage: {repository: nginx, tag: v1}\nport: 80",
+        "replicas: 2\nimage: {repository: nginx, tag: v2}\nport: '80'",
+        "replicas: 2\nimage: {repository: nginx, tag: v1}",
+        "",
+    ]
+    .iter()
+    .map(|s| yaml!(s))
+    .collect();
+    let objs: Vec<&Yaml> = docs.iter().collect();
+    let (base, diffs) = diff_and_common_with_options(&objs, &DiffOptions::default()).into_parts();
+    let overrides: Vec<Option<&Yaml>> = diffs.iter().map(|diff| diff.as_deref()).collect();
+
+    let stats = run_stats(&objs, base.as_deref(), &overrides, 0);
+    assert_eq!(
+        stats,
+        RunStats {
+            files: 4,
+            empty_files: 1,
+            base_leaves: 3,
+            override_leaves: 3,
+            mean_override_leaves: Some(1.0),
+            max_override_leaves: 2,
+            files_without_overrides: 1,
+            mean_coverage: Some((0.75 + 0.5 + 1.0) / 3.0),
+            type_conflicts: 1,
+            groups: 0,
+            leaf_counting: LeafCounting::WholeArrays,
+        }
+    );
+}
+
+#[test]
+fn test_run_stats_json_has_a_stable_layout() {
+    let stats = run_stats(&[], None, &[], 0);
+    assert_eq!(
+        stats.to_json(),
+        "{\n  \"version\": 1,\n  \"files\": 0,\n  \"empty_files\": 0,\n  \"base_leaves\": 0,\n  \"override_leaves\": 0,\n  \"mean_override_leaves\": null,\n  \"max_override_leaves\": 0,\n  \"files_without_overrides\": 0,\n  \"mean_coverage\": null,\n  \"type_conflicts\": 0,\n  \"groups\": 0,\n  \"leaf_counting\": \"whole-arrays\"\n}\n"
+    );
+}
+
+#[test]
+fn test_run_stats_do_not_count_unchanged_slots() {
+    let base = yaml!("args: [-v, --debug, --color]\nreplicas: 2");
+    let docs = [yaml!("args: [-v, --trace, --color]\nreplicas: 2"), base.clone()];
+    let objs: Vec<&Yaml> = docs.iter().collect();
+    let options = DiffOptions::default();
+    let diffs: Vec<Option<Cow<Yaml>>> = docs.iter().map(|doc| compute_diff_with_options(doc, &base, &options)).collect();
+    let overrides: Vec<Option<&Yaml>> = diffs.iter().map(|diff| diff.as_deref()).collect();
+    assert_eq!(overrides[0], Some(&yaml!("args: [~, --trace, ~]")));
+
+    let stats = run_stats_with(&objs, Some(&base), &overrides, 0, LeafCounting::Elements);
+    assert_eq!((stats.base_leaves, stats.override_leaves, stats.max_override_leaves), (4, 1, 1));
+    assert_eq!(stats.mean_coverage, Some((0.75 + 1.0) / 2.0));
+    assert!(stats.to_json().ends_with("\"leaf_counting\": \"elements\"\n}\n"));
+}
@@ -0,0 +1,120 @@
+use yabe::deep_equal::deep_equal;
+use yabe::diff::{compute_diff_with_options, diff_and_common_with_options};
+use yabe::merge::merge_yaml_with_options;
+use yabe::options::{ArrayStrategy, DiffOptions};
+use yabe::yaml;
+
+fn strategic_options() -> DiffOptions {
+    DiffOptions {
+        array_strategy: ArrayStrategy::StrategicMerge { merge_key: "name".to_string() },
+        ..DiffOptions::default()
+    }
+}
+
+#[test]
+fn test_compute_diff_keyed_arrays() {
+    let helm_yaml = yaml!("env:\n  - name: A\n    value: 1\n  - name: B\n    value: 2\n  - name: C\n    value: 3");
+    let obj_yaml = yaml!("env:\n  - name: B\n    value: 2\n  - name: A\n    value: 5\n  - name: D\n    value: 4");
+    let options = strategic_options();
+
+    let diff = compute_diff_with_options(&obj_yaml, &helm_yaml, &options).unwrap();
+    let expected_diff = yaml!("env:\n  - name: A\n    value: 5\n  - name: D\n    value: 4\n  - name: C\n    $patch: delete");
+    assert!(deep_equal(&diff, &expected_diff));
+
+    let merged = merge_yaml_with_options(&helm_yaml, &diff, &options);
+    let expected_merged = yaml!("env:\n  - name: A\n    value: 5\n  - name: B\n    value: 2\n  - name: D\n    value: 4");
+    assert!(deep_equal(&merged, &expected_merged));
+}
+
+#[test]
+fn test_compute_diff_keyed_arrays_reordered() {
+    let helm_yaml = yaml!("env:\n  - name: A\n  - name: B");
+    let obj_yaml = yaml!("env:\n  - name: B\n  - name: A");
+
+    assert!(compute_diff_with_options(&obj_yaml, &helm_yaml, &strategic_options()).is_none());
+    assert!(compute_diff_with_options(&obj_yaml, &helm_yaml, &DiffOptions::default()).is_some());
+}
+
+#[test]
+fn test_unkeyed_arrays_fall_back_to_positional() {
+    let helm_yaml = yaml!("items:\n  - a\n  - b");
+    let obj_yaml = yaml!("items:\n  - a\n  - c");
+
+    let diff = compute_diff_with_options(&obj_yaml, &helm_yaml, &strategic_options()).unwrap();
+    let expected_diff = yaml!("items:\n  - null\n  - c");
+    assert!(deep_equal(&diff, &expected_diff));
+}
+
+#[test]
+fn test_diff_and_common_keyed_arrays() {
+    let yaml1 = yaml!("ports:\n  - name: http\n    port: 80\n  - name: metrics\n    port: 9090");
+    let yaml2 = yaml!("ports:\n  - name: metrics\n    port: 9090\n  - name: http\n    port: 8080");
+    let yaml3 = yaml!("ports:\n  - name: http\n    port: 80\n  - name: metrics\n    port: 9090");
+    let objs = vec![&yaml1, &yaml2, &yaml3];
+    let options = strategic_options();
+
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+
+    let expected_base = yaml!("ports:\n  - name: http\n    port: 80\n  - name: metrics\n    port: 9090");
+    assert!(deep_equal(base.as_ref().unwrap().as_ref(), &expected_base));
+    assert!(diffs[0].is_none());
+    assert!(diffs[2].is_none());
+    let expected_diff = yaml!("ports:\n  - name: http\n    port: 8080");
+    assert!(deep_equal(diffs[1].as_ref().unwrap().as_ref(), &expected_diff));
+}
+
+#[test]
+fn test_merge_replace_directive() {
+    let base = yaml!("env:\n  - name: A\n  - name: B");
+    let patch = yaml!("env:\n  - $patch: replace\n  - name: C");
+
+    let merged = merge_yaml_with_options(&base, &patch, &strategic_options());
+    assert!(deep_equal(&merged, &yaml!("env:\n  - name: C")));
+}
+
+#[test]
+fn test_composite_merge_key() {
+    let options = DiffOptions {
+        array_strategy: "strategic-merge:containerPort,protocol".parse().unwrap(),
+        ..DiffOptions::default()
+    };
+    let a = yaml!("ports:\n  - {containerPort: 53, protocol: TCP, name: dns-tcp}\n  - {containerPort: 53, protocol: UDP, name: dns}");
+    let b = yaml!("ports:\n  - {containerPort: 53, protocol: UDP, name: dns}\n  - {containerPort: 53, protocol: TCP, name: tcp}");
+    let c = yaml!("ports:\n  - {containerPort: 53, protocol: UDP, name: dns}");
+    let objs = vec![&a, &b, &c];
+
+    // Both elements share a name-less port number, but the protocol tells them apart
+    let (base, diffs) = diff_and_common_with_options(&objs, &options).into_parts();
+    let base = base.unwrap();
+    assert!(deep_equal(&base, &yaml!("ports:\n  - {containerPort: 53, protocol: UDP, name: dns}")));
+    assert!(deep_equal(
+        diffs[1].as_deref().unwrap(),
+        &yaml!("ports:\n  - {containerPort: 53, protocol: TCP, name: tcp}")
+    ));
+    assert!(diffs[2].is_none());
+    // Element order follows the base, so only the files listing UDP first merge back verbatim
+    for (obj, diff) in objs.iter().zip(&diffs).skip(1) {
+        let merged = match diff {
+            Some(diff) => merge_yaml_with_options(&base, diff, &options).into_owned(),
+            None => base.clone().into_owned(),
+        };
+        assert!(deep_equal(&merged, obj));
+    }
+
+    // Changed and deleted elements carry every field of the key
+    let diff = compute_diff_with_options(&c, &a, &options).unwrap();
+    assert!(deep_equal(&diff, &yaml!("ports:\n  - {containerPort: 53, protocol: TCP, $patch: delete}")));
+    let renamed = yaml!("ports:\n  - {containerPort: 53, protocol: TCP, name: dns-tcp}\n  - {containerPort: 53, protocol: UDP, name: udp}");
+    let diff = compute_diff_with_options(&renamed, &a, &options).unwrap();
+    assert!(deep_equal(&diff, &yaml!("ports:\n  - {containerPort: 53, protocol: UDP, name: udp}")));
+
+    // An element missing a field of the key, or two colliding elements, leave the array unkeyed
+    let missing = yaml!("ports:\n  - {containerPort: 53, protocol: UDP}\n  - {containerPort: 80}");
+    let colliding = yaml!("ports:\n  - {containerPort: 53, protocol: UDP}\n  - {containerPort: 53, protocol: UDP}");
+    let helm = yaml!("ports:\n  - {containerPort: 80}\n  - {containerPort: 53, protocol: UDP}");
+    for obj in [&missing, &colliding] {
+        let diff = compute_diff_with_options(obj, &helm, &options).unwrap();
+        assert!(diff["ports"][0]["containerPort"].as_i64().is_some(), "positional diff expected");
+    }
+    assert!("strategic-merge:name,".parse::<ArrayStrategy>().is_err());
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use yabe::diff::{diff_and_common_timed, diff_and_common_with_options};
+use yabe::options::DiffOptions;
+use yabe::timing::{timed, Timings};
+use yabe::yaml;
+
+#[test]
+fn test_phases_accumulate() {
+    let mut timings = Timings::default();
+    timings.record("parsing", Duration::from_millis(2));
+    timings.record("extraction", Duration::from_millis(5)).subtrees =
+        vec![("a".to_string(), Duration::from_millis(1)), ("b".to_string(), Duration::from_millis(3))];
+    timings.record("parsing", Duration::from_millis(1));
+
+    let names: Vec<&str> = timings.phases.iter().map(|phase| phase.name.as_str()).collect();
+    assert_eq!(names, ["parsing", "extraction"]);
+    assert_eq!(timings.phases[0].duration, Duration::from_millis(3));
+    assert_eq!(timings.total(), Duration::from_millis(8));
+
+    // The slowest subtrees come first, the others are counted
+    let report = timings.report(1);
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert!(lines[1].starts_with("extraction") && lines[1].ends_with("62.5%"));
+    assert!(lines[2].trim_start().starts_with("b "));
+    assert_eq!(lines[3], "  (1 more keys)");
+    assert!(lines[4].starts_with("total"));
+}
+
+#[test]
+fn test_timed_only_records_when_collecting() {
+    let mut off: Option<Timings> = None;
+    assert_eq!(timed(&mut off, "parsing", || 1), 1);
+    assert!(off.is_none());
+
+    let mut on = Some(Timings::default());
+    assert_eq!(timed(&mut on, "parsing", || 2), 2);
+    assert_eq!(on.unwrap().phases[0].name, "parsing");
+}
+
+#[test]
+fn test_timed_extraction_breaks_down_top_level_keys() {
+    let a = yaml!("name: web\nresources: {cpu: 1}\nreplicas: 1");
+    let b = yaml!("name: web\nresources: {cpu: 2}\nreplicas: 1");
+    let objs = [&a, &b];
+    let options = DiffOptions::default();
+
+    let (result, key_times) = diff_and_common_timed(&objs, &options);
+    assert_eq!(result, diff_and_common_with_options(&objs, &options));
+    let mut keys: Vec<&str> = key_times.iter().map(|(key, _)| key.as_str()).collect();
+    keys.sort_unstable();
+    assert_eq!(keys, ["name", "replicas", "resources"]);
+
+    // Only maps have keys to time
+    let (x, y) = (yaml!("[1, 2]"), yaml!("[1, 3]"));
+    assert!(diff_and_common_timed(&[&x, &y], &options).1.is_empty());
+}
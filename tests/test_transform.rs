@@ -0,0 +1,66 @@
+use std::borrow::Cow;
+
+use yabe::diff::diff_and_common_multiple;
+use yabe::result::DiffResult;
+use yabe::transform::{apply_transforms, AddMetadata, DiffTransform, SortKeys};
+use yabe::{yaml, Yaml};
+
+fn keys(doc: &Yaml) -> Vec<&str> {
+    doc.as_hash().unwrap().keys().filter_map(Yaml::as_str).collect()
+}
+
+#[test]
+fn test_transforms_run_in_order() {
+    let a = yaml!("name: web\nreplicas: 1");
+    let b = yaml!("name: web\nreplicas: 2");
+    let objs = [&a, &b];
+
+    let metadata = AddMetadata::new("generated", yaml!("true"));
+    let sort = SortKeys::new(yaml!("preOrder: [generated]"));
+
+    // Sorting last sees the metadata key
+    let mut result = diff_and_common_multiple(&objs, 1.0);
+    apply_transforms(&mut result, &[&metadata, &sort]);
+    assert_eq!(keys(result.base.as_deref().unwrap()), ["generated", "name"]);
+    assert_eq!(keys(result.diffs[0].as_deref().unwrap()), ["generated", "replicas"]);
+
+    // Sorting first does not, so the key is appended
+    let mut result = diff_and_common_multiple(&objs, 1.0);
+    apply_transforms(&mut result, &[&sort, &metadata]);
+    assert_eq!(keys(result.base.as_deref().unwrap()), ["name", "generated"]);
+}
+
+#[test]
+fn test_closures_compose_with_built_in_transforms() {
+    let a = yaml!("name: web\nreplicas: 1");
+    let b = yaml!("name: web\nreplicas: 2");
+    let objs = [&a, &b];
+
+    let metadata = AddMetadata::new("owner", yaml!("platform"));
+    let rename = |result: &mut DiffResult| {
+        for name in &mut result.names {
+            name.insert_str(0, "prod-");
+        }
+    };
+    let both = |result: &mut DiffResult| apply_transforms(result, &[&metadata, &rename]);
+
+    let mut result = diff_and_common_multiple(&objs, 1.0).with_names(["a", "b"]);
+    both.transform(&mut result);
+    assert_eq!(result.names, ["prod-a", "prod-b"]);
+    assert_eq!(result.diffs[1].as_deref(), Some(&yaml!("replicas: 2\nowner: platform")));
+}
+
+#[test]
+fn test_add_metadata_skips_missing_and_non_map_documents() {
+    let mut result = DiffResult::new(
+        Some(Cow::Owned(yaml!("owner: someone\nname: web"))),
+        vec![None, Some(Cow::Owned(yaml!("[1, 2]"))), Some(Cow::Owned(yaml!("replicas: 2")))],
+    );
+    AddMetadata::new("owner", yaml!("platform")).transform(&mut result);
+
+    // An existing key keeps its position
+    assert_eq!(result.base.as_deref(), Some(&yaml!("owner: platform\nname: web")));
+    assert_eq!(result.diffs[0], None);
+    assert_eq!(result.diffs[1].as_deref(), Some(&yaml!("[1, 2]")));
+    assert_eq!(result.diffs[2].as_deref(), Some(&yaml!("replicas: 2\nowner: platform")));
+}